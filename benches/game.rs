@@ -0,0 +1,92 @@
+//! How long the board and the bots take over the things they do most, run
+//! with `cargo bench`. For how well the bots play, see `netbattleship
+//! bench-sim`.
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use netbattleship::{
+	board::{Board, Cell, CellState},
+	bot::{self, Difficulty, Engine},
+	coord::Coord,
+	rules::Rules,
+	ship::ShipKind,
+	Game, Phase,
+};
+
+/// A board with the standard fleet laid out along column 0, one ship per
+/// row from A down.
+fn fleet(rules: &Rules) -> Board {
+	let mut board = Board::new(rules);
+	for (row, &ship) in (0..).zip(&rules.fleet) {
+		ship.place(&mut board, Coord::new(0, row), false);
+	}
+	board
+}
+
+/// A game halfway through, with every other square on the enemy's board
+/// fired at and a ship found but not yet sunk.
+fn midgame(rules: &Rules) -> Game {
+	let mut game = Game::new(rules.clone(), true);
+	game.phase = Phase::Playing;
+	let enemy = &mut game.board[usize::from(!game.you)];
+	for y in 0..rules.board_size {
+		for x in (y % 2..rules.board_size).step_by(2) {
+			enemy
+				.board
+				.insert(Coord::new(x, y), Cell::shot(CellState::Miss));
+		}
+	}
+	enemy
+		.board
+		.insert(Coord::new(4, 4), Cell::shot(CellState::Hit));
+	game
+}
+
+fn board(c: &mut Criterion) {
+	let rules = Rules::default();
+	c.bench_function("place the fleet", |b| {
+		b.iter(|| fleet(black_box(&rules)));
+	});
+	let empty = Board::new(&rules);
+	c.bench_function("check a placement", |b| {
+		b.iter(|| ShipKind::Carrier.can_place(black_box(&empty), Coord::new(3, 3), true));
+	});
+
+	let mut sunk = fleet(&rules);
+	for cell in sunk.board.values_mut() {
+		cell.state = CellState::Hit;
+	}
+	c.bench_function("detect a win", |b| b.iter(|| black_box(&sunk).all_sunk()));
+}
+
+fn bots(c: &mut Criterion) {
+	let rules = Rules::default();
+	let game = midgame(&rules);
+	for mut level in [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard] {
+		// Hard bots aim by the heatmap, so this is mostly working it out
+		c.bench_function(&format!("{level} bot aims"), |b| {
+			b.iter(|| level.aim(black_box(&game)));
+		});
+	}
+	let board = Board::new(&rules);
+	c.bench_function("hard bot places a ship", |b| {
+		b.iter(|| Difficulty::Hard.placement(black_box(&board), ShipKind::Carrier));
+	});
+}
+
+fn games(c: &mut Criterion) {
+	let rules = Rules::default();
+	for level in [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard] {
+		c.bench_function(&format!("{level} bot sinks a fleet"), |b| {
+			b.iter_batched(
+				|| (level, Difficulty::Easy),
+				|(mut hunter, mut placer)| bot::hunt(&mut hunter, &mut placer, &rules),
+				BatchSize::SmallInput,
+			);
+		});
+	}
+}
+
+criterion_group!(benches, board, bots, games);
+criterion_main!(benches);
@@ -0,0 +1,9 @@
+fn main() {
+	#[cfg(feature = "ffi")]
+	{
+		let crate_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+		cbindgen::generate(&crate_dir)
+			.expect("the ffi module can be described in C")
+			.write_to_file(format!("{crate_dir}/include/netbattleship.h"));
+	}
+}
@@ -0,0 +1,130 @@
+#ifndef NETBATTLESHIP_H
+#define NETBATTLESHIP_H
+
+/* Written by building netbattleship-core with the ffi feature; don't edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// With the radar rule, players earn a sweep each time they finish this many turns.
+#define TURNS_PER_SCAN 5
+
+#define MAX_BOARD_SIZE 10
+
+// One of the ships in a fleet.
+typedef enum NbShip {
+  NB_SHIP_CARRIER,
+  NB_SHIP_BATTLESHIP,
+  NB_SHIP_CRUISER,
+  NB_SHIP_SUBMARINE,
+  NB_SHIP_DESTROYER,
+  // Not a ship at all.
+  NB_SHIP_NONE,
+} NbShip;
+
+// What's been done to a square.
+typedef enum NbState {
+  NB_STATE_UNTOUCHED,
+  NB_STATE_HIT,
+  NB_STATE_MISS,
+  // Land, which can't hold a ship or be fired at.
+  NB_STATE_LAND,
+  // Off the edge of the board.
+  NB_STATE_OUTSIDE,
+} NbState;
+
+typedef struct NbGame NbGame;
+
+// How a shot went.
+typedef struct NbShot {
+  // Whether the shot was allowed at all. Nothing else counts if it
+  // wasn't: it's not the shooting phase, or the square is off the board,
+  // land or already fired at.
+  bool fired;
+  bool hit;
+  // The ship it sank, if any.
+  enum NbShip sunk;
+  // Whether that was the last of the fleet.
+  bool won;
+} NbShot;
+
+// One square of a board.
+typedef struct NbCell {
+  enum NbState state;
+  // The ship on the square, if any.
+  enum NbShip ship;
+} NbCell;
+
+// Starts a game by the rules in `rules_toml`, written like a rules file, or
+// the standard rules if it's null. Returns null if the rules can't be read
+// or played. Free it with [`nb_game_free`].
+//
+// # Safety
+// `rules_toml` must be null or a NUL-terminated string.
+struct NbGame *nb_game_new(const char *rules_toml);
+
+// Frees a game from [`nb_game_new`] or [`nb_game_load`]. Null is ignored.
+//
+// # Safety
+// `game` must be null or a game from this library that hasn't been freed.
+void nb_game_free(struct NbGame *game);
+
+// Places `player`'s `ship` with its top or left end at `x`, `y`, returning
+// whether it fit and is in their fleet but not yet on their board. The
+// shooting starts once both fleets are placed.
+//
+// # Safety
+// `game` must be a live game from this library.
+bool nb_place_ship(struct NbGame *game,
+                   bool player,
+                   enum NbShip ship,
+                   uint8_t x,
+                   uint8_t y,
+                   bool vertical);
+
+// Fires the next shot of whoever's turn it is at `x`, `y` on the other
+// player's board, passing the turn over once their shots are used up.
+//
+// # Safety
+// `game` must be a live game from this library.
+struct NbShot nb_fire(struct NbGame *game, uint8_t x, uint8_t y);
+
+// What's on `player`'s board at `x`, `y`, ships and all.
+//
+// # Safety
+// `game` must be a live game from this library.
+struct NbCell nb_cell(const struct NbGame *game, bool player, uint8_t x, uint8_t y);
+
+// Whose turn it is.
+//
+// # Safety
+// `game` must be a live game from this library.
+bool nb_turn(const struct NbGame *game);
+
+// The whole game as CBOR, the format save files use, setting `len` to how
+// many bytes it is. Free it with [`nb_bytes_free`]. Returns null if it
+// can't be written.
+//
+// # Safety
+// `game` must be a live game from this library, and `len` somewhere to
+// write a `size_t`.
+uint8_t *nb_game_save(const struct NbGame *game, size_t *len);
+
+// Reads back a game from [`nb_game_save`], or returns null if it isn't
+// one. Free it with [`nb_game_free`].
+//
+// # Safety
+// `bytes` must point to `len` readable bytes.
+struct NbGame *nb_game_load(const uint8_t *bytes, size_t len);
+
+// Frees bytes from [`nb_game_save`]. Null is ignored.
+//
+// # Safety
+// `bytes` and `len` must be as [`nb_game_save`] gave them, and not freed
+// yet.
+void nb_bytes_free(uint8_t *bytes, size_t len);
+
+#endif  /* NETBATTLESHIP_H */
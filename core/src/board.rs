@@ -0,0 +1,402 @@
+use crate::{
+	coord::Coord,
+	rules::{Rules, MAX_BOARD_SIZE},
+	ship::ShipKind,
+};
+use alloc::{
+	collections::{BTreeMap, BTreeSet},
+	vec::Vec,
+};
+use core::{fmt, iter, str::FromStr};
+use thiserror::Error;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// One square of a board: the ship on it, if we know of one, and whether
+/// it has been fired at.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Cell {
+	#[cfg_attr(feature = "serde", serde(deserialize_with = "crate::ship::legacy"))]
+	pub ship: Option<ShipKind>,
+	pub state: CellState,
+}
+
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum CellState {
+	#[default]
+	Untouched,
+	Hit,
+	Miss,
+	/// Fired at in a blind game, with the result kept secret.
+	Unknown,
+}
+
+impl Cell {
+	/// An untouched square holding `ship`.
+	#[must_use]
+	pub fn ship(ship: ShipKind) -> Self {
+		Self {
+			ship: Some(ship),
+			state: CellState::Untouched,
+		}
+	}
+
+	/// A square that was fired at, whose ship (if any) we don't know.
+	#[must_use]
+	pub fn shot(state: CellState) -> Self {
+		Self { ship: None, state }
+	}
+
+	/// How this square is drawn on a text board. Damaged ships show their
+	/// letter in lowercase, and misses look different on our own board
+	/// (where the enemy missed) than on theirs (where we did).
+	#[must_use]
+	pub fn symbol(&self, own: bool) -> char {
+		match (self.state, self.ship) {
+			(CellState::Untouched, None) => ' ',
+			(CellState::Untouched, Some(ship)) => ship.into(),
+			(CellState::Hit, None) => 'X',
+			(CellState::Hit, Some(ship)) => char::from(ship).to_ascii_lowercase(),
+			(CellState::Miss, _) if own => 'o',
+			(CellState::Miss, _) => '?',
+			(CellState::Unknown, _) => '~',
+		}
+	}
+}
+
+impl fmt::Display for Cell {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match (self.state, self.ship) {
+			(CellState::Untouched, None) => write!(f, "nothing"),
+			(CellState::Untouched, Some(ship)) => write!(f, "{ship:?}"),
+			(CellState::Hit, None) => write!(f, "a hit"),
+			(CellState::Hit, Some(ship)) => write!(f, "a damaged {ship:?}"),
+			(CellState::Miss, _) => write!(f, "a miss"),
+			(CellState::Unknown, _) => write!(f, "a shot with an unknown result"),
+		}
+	}
+}
+
+/// How the squares of a board fit together.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(
+	feature = "serde",
+	derive(Serialize, Deserialize),
+	serde(rename_all = "lowercase")
+)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum Topology {
+	/// Square cells in rows and columns, each touching the eight around it.
+	#[default]
+	Square,
+	/// Hexagonal cells in axial coordinates, each touching six others. `x`
+	/// runs along a row and `y` down and to the right, so the board is a
+	/// rhombus, and ships lie along either of those two axes.
+	Hex,
+}
+
+/// One player's waters: the ships on them, the shots taken at them and any land.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Board {
+	pub board: BTreeMap<Coord, Cell>,
+	size: u8,
+	/// Whether ships may not touch each other, even diagonally.
+	adjacency: bool,
+	#[cfg_attr(feature = "serde", serde(default))]
+	topology: Topology,
+	/// Squares of land, which can't hold a ship or be fired at.
+	#[cfg_attr(feature = "serde", serde(default))]
+	land: BTreeSet<Coord>,
+}
+
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutError {
+	#[error("a layout has between 1 and {MAX_BOARD_SIZE} lines")]
+	Size,
+	#[error("line {0} of the layout should be as many squares as the layout has lines")]
+	BadLine(usize),
+	#[error("{square:?} on line {line} isn't a ship letter, `.` or `#`")]
+	BadSquare { line: usize, square: char },
+	#[error("the {0:?} should be a straight line of {len} squares", len = .0.len())]
+	BadShip(ShipKind),
+}
+
+/// A board as text: a row to a line, with each ship's letter on its
+/// squares, `.` for water and `#` for land, like
+///
+/// ```text
+/// CCCCC.....
+/// ..........
+/// ..BBBB....
+/// ```
+///
+/// and so on for the rest of the rows. Only where the ships are is written,
+/// not what's been fired at, so players can keep layouts they like in a
+/// text file. Ship letters are read in either case.
+impl FromStr for Board {
+	type Err = LayoutError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let rows = s
+			.lines()
+			.map(str::trim)
+			.filter(|line| !line.is_empty())
+			.collect::<Vec<_>>();
+		let size = u8::try_from(rows.len()).map_err(|_| LayoutError::Size)?;
+		if !(1..=MAX_BOARD_SIZE).contains(&size) {
+			return Err(LayoutError::Size);
+		}
+		let mut board = Board {
+			board: BTreeMap::new(),
+			size,
+			adjacency: false,
+			topology: Topology::Square,
+			land: BTreeSet::new(),
+		};
+		for (y, row) in (0..size).zip(&rows) {
+			let line = usize::from(y) + 1;
+			if row.chars().count() != rows.len() {
+				return Err(LayoutError::BadLine(line));
+			}
+			for (x, square) in (0..size).zip(row.chars()) {
+				let pos = Coord::new(x, y);
+				let ship = ShipKind::into_iter()
+					.find(|&&ship| char::from(ship) == square.to_ascii_uppercase());
+				match (square, ship) {
+					('.', _) => {}
+					('#', _) => {
+						board.land.insert(pos);
+					}
+					(_, Some(&ship)) => {
+						board.board.insert(pos, Cell::ship(ship));
+					}
+					_ => return Err(LayoutError::BadSquare { line, square }),
+				}
+			}
+		}
+		for &ship in ShipKind::into_iter() {
+			let Some((first, last)) = board.span(ship) else {
+				continue;
+			};
+			let squares = ship
+				.footprint(first, first.x == last.x && first != last)
+				.collect::<BTreeSet<_>>();
+			let placed = board
+				.board
+				.iter()
+				.filter(|(_, cell)| cell.ship == Some(ship))
+				.map(|(&pos, _)| pos)
+				.collect::<BTreeSet<_>>();
+			if squares != placed {
+				return Err(LayoutError::BadShip(ship));
+			}
+		}
+		Ok(board)
+	}
+}
+
+/// Writes the board as a layout, in the format [`Board::from_str`] reads.
+impl fmt::Display for Board {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		for y in 0..self.size {
+			for x in 0..self.size {
+				let pos = Coord::new(x, y);
+				let square = match self.cell(pos).ship {
+					_ if self.land.contains(&pos) => '#',
+					Some(ship) => ship.into(),
+					None => '.',
+				};
+				write!(f, "{square}")?;
+			}
+			writeln!(f)?;
+		}
+		Ok(())
+	}
+}
+
+impl Default for Board {
+	fn default() -> Self {
+		Self::new(&Rules::default())
+	}
+}
+
+impl Board {
+	#[must_use]
+	pub fn new(rules: &Rules) -> Self {
+		Self {
+			board: BTreeMap::new(),
+			size: rules.board_size,
+			adjacency: rules.adjacency,
+			topology: rules.topology,
+			land: rules.land.clone(),
+		}
+	}
+
+	/// How many squares there are along each side. The shape of the board
+	/// comes from the rules it was made for, and can't change.
+	#[must_use]
+	pub fn size(&self) -> u8 {
+		self.size
+	}
+
+	/// Whether ships may not touch each other, even diagonally.
+	#[must_use]
+	pub fn adjacency(&self) -> bool {
+		self.adjacency
+	}
+
+	#[must_use]
+	pub fn topology(&self) -> Topology {
+		self.topology
+	}
+
+	/// Squares of land, which can't hold a ship or be fired at.
+	#[must_use]
+	pub fn land(&self) -> &BTreeSet<Coord> {
+		&self.land
+	}
+
+	/// Whether any part of `ship` hasn't been hit yet.
+	#[must_use]
+	pub fn contains(&self, ship: ShipKind) -> bool {
+		self.board
+			.values()
+			.any(|cell| cell.ship == Some(ship) && cell.state == CellState::Untouched)
+	}
+
+	/// Every square that's different on `other`, in order, with what's on it
+	/// here and there. Boards of other shapes are compared square by square
+	/// all the same.
+	#[must_use]
+	pub fn diff(&self, other: &Board) -> Vec<(Coord, Cell, Cell)> {
+		self.board
+			.keys()
+			.chain(other.board.keys())
+			.copied()
+			.collect::<BTreeSet<_>>()
+			.into_iter()
+			.map(|pos| (pos, self.cell(pos), other.cell(pos)))
+			.filter(|(_, ours, theirs)| ours != theirs)
+			.collect()
+	}
+
+	/// The ship a shot at `pos` would sink, if it's the last untouched
+	/// square of one.
+	#[must_use]
+	pub fn sinks(&self, pos: Coord) -> Option<ShipKind> {
+		let ship = self
+			.cell(pos)
+			.ship
+			.filter(|_| self.cell(pos).state == CellState::Untouched)?;
+		self.board
+			.iter()
+			.all(|(&at, cell)| {
+				at == pos || cell.ship != Some(ship) || cell.state != CellState::Untouched
+			})
+			.then_some(ship)
+	}
+
+	/// Whether every ship on the board has been hit in every square.
+	#[must_use]
+	pub fn all_sunk(&self) -> bool {
+		self.board
+			.values()
+			.all(|cell| cell.ship.is_none() || cell.state == CellState::Hit)
+	}
+
+	/// What's at `pos`, which is an untouched empty square if nothing was recorded.
+	#[must_use]
+	pub fn cell(&self, pos: Coord) -> Cell {
+		self.board.get(&pos).copied().unwrap_or_default()
+	}
+
+	#[must_use]
+	pub fn in_bounds(&self, pos: Coord) -> bool {
+		pos.x < self.size && pos.y < self.size
+	}
+
+	/// Whether `pos` is on the board and open water rather than land, so a
+	/// ship can go there and it can be fired at.
+	#[must_use]
+	pub fn is_water(&self, pos: Coord) -> bool {
+		self.in_bounds(pos) && !self.land.contains(&pos)
+	}
+
+	/// The cells of other ships that keep `ship` from going at `pos`: the ones
+	/// it would overlap and, with the adjacency rule, the ones it would touch.
+	///
+	/// Running off the board doesn't conflict with any cell, so an empty list
+	/// doesn't mean the ship fits; use [`ShipKind::can_place`] for that.
+	#[must_use]
+	pub fn placement_conflicts(&self, ship: ShipKind, pos: Coord, v: bool) -> Vec<Coord> {
+		let mut conflicts = Vec::new();
+		for cursor in ship.footprint(pos, v).filter(|&c| self.in_bounds(c)) {
+			let touching = self.adjacency.then(|| self.neighbours(cursor));
+			for cell in iter::once(cursor).chain(touching.into_iter().flatten()) {
+				if self.cell(cell).ship.is_some() && !conflicts.contains(&cell) {
+					conflicts.push(cell);
+				}
+			}
+		}
+		conflicts.sort();
+		conflicts
+	}
+
+	/// The first and last cells `ship` covers, if it's on the board.
+	#[must_use]
+	pub fn span(&self, ship: ShipKind) -> Option<(Coord, Coord)> {
+		let mut cells = self
+			.board
+			.iter()
+			.filter(|(_, cell)| cell.ship == Some(ship))
+			.map(|(&pos, _)| pos);
+		let first = cells.next()?;
+		Some((first, cells.next_back().unwrap_or(first)))
+	}
+
+	/// Where each ship on the board is, as its first square and whether it
+	/// runs down rather than across, in the order they'd be placed in.
+	#[must_use]
+	pub fn placements(&self) -> Vec<(ShipKind, Coord, bool)> {
+		ShipKind::into_iter()
+			.filter_map(|&ship| {
+				let (first, last) = self.span(ship)?;
+				Some((ship, first, first.x == last.x && first != last))
+			})
+			.collect()
+	}
+
+	/// How many cells in the 3x3 square centred on `pos` (or on a hex board,
+	/// `pos` and the six around it) hold a ship, whether or not it has been
+	/// hit.
+	#[must_use]
+	pub fn ship_cells_around(&self, pos: Coord) -> u8 {
+		let cells = core::iter::once(pos)
+			.chain(self.neighbours(pos))
+			.filter(|&cell| self.cell(cell).ship.is_some())
+			.count();
+		u8::try_from(cells).unwrap_or(u8::MAX)
+	}
+
+	/// The cells touching `pos`: up to eight on a square board, including
+	/// diagonals, and up to six on a hex board.
+	pub fn neighbours(&self, pos: Coord) -> impl Iterator<Item = Coord> + '_ {
+		let hex = self.topology == Topology::Hex;
+		(-1..=1)
+			.flat_map(|dy: i16| (-1..=1).map(move |dx: i16| (dx, dy)))
+			.filter(|&d| d != (0, 0))
+			// In axial coordinates, one of the diagonals isn't adjacent
+			.filter(move |&(dx, dy)| !hex || dx != dy)
+			.filter_map(move |(dx, dy)| {
+				let x = u8::try_from(i16::from(pos.x) + dx).ok()?;
+				let y = u8::try_from(i16::from(pos.y) + dy).ok()?;
+				Some(Coord::new(x, y))
+			})
+			.filter(|&n| self.in_bounds(n))
+	}
+}
@@ -0,0 +1,211 @@
+use alloc::{
+	format,
+	string::{String, ToString},
+	vec::Vec,
+};
+use core::{fmt, str::FromStr};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::rules::MAX_BOARD_SIZE;
+
+/// A cell on the board, addressed by column (`x`) and row (`y`).
+///
+/// Rendered in classic battleship notation, with the row as a letter and
+/// the column as a number, so `Coord { x: 5, y: 3 }` is "D5". Any
+/// [`Notation`] can be parsed, as well as "D-5", and [`Coord::written`]
+/// writes them all.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Coord {
+	pub x: u8,
+	pub y: u8,
+}
+
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseCoordError {
+	#[error("coordinates should be a row letter and a column number, like E5, 5E or E-5, or a row and column number, like 4,5")]
+	Malformed,
+	#[error("row is out of range")]
+	BadRow,
+	#[error("column is out of range")]
+	BadColumn,
+}
+
+/// A way of writing coordinates.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum Notation {
+	/// The row letter then the column number, like "D5".
+	#[default]
+	LetterFirst,
+	/// The column number then the row letter, like "5D".
+	NumberFirst,
+	/// The row number then the column number, both counted from 0 like the
+	/// columns, like "3,5".
+	Numeric,
+	/// Axial coordinates, as hex boards use them: the column as `q` and the
+	/// row as `r`, like "q5r3".
+	Axial,
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("unknown notation {0:?}, expected letter-first, number-first, numeric or axial")]
+pub struct ParseNotationError(String);
+
+impl FromStr for Notation {
+	type Err = ParseNotationError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.to_lowercase().as_str() {
+			"letter-first" | "a1" => Ok(Notation::LetterFirst),
+			"number-first" | "1a" => Ok(Notation::NumberFirst),
+			"numeric" | "0,1" => Ok(Notation::Numeric),
+			"axial" | "q1r0" => Ok(Notation::Axial),
+			_ => Err(ParseNotationError(s.to_string())),
+		}
+	}
+}
+
+impl fmt::Display for Notation {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			Notation::LetterFirst => "letter-first",
+			Notation::NumberFirst => "number-first",
+			Notation::Numeric => "numeric",
+			Notation::Axial => "axial",
+		})
+	}
+}
+
+/// A coordinate displayed in a particular [`Notation`]. Rows past `Z` are
+/// written by number, as [`Notation::Numeric`] does, since there's no letter
+/// for them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Written(pub Coord, pub Notation);
+
+impl fmt::Display for Written {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let Written(pos, notation) = *self;
+		let notation = match notation {
+			Notation::LetterFirst | Notation::NumberFirst if pos.y >= 26 => Notation::Numeric,
+			notation => notation,
+		};
+		match notation {
+			Notation::LetterFirst => write!(f, "{}{}", pos.row(), pos.x),
+			Notation::NumberFirst => write!(f, "{}{}", pos.x, pos.row()),
+			Notation::Numeric => write!(f, "{},{}", pos.y, pos.x),
+			Notation::Axial => write!(f, "q{}r{}", pos.x, pos.y),
+		}
+	}
+}
+
+const NATO: [&str; 26] = [
+	"Alfa", "Bravo", "Charlie", "Delta", "Echo", "Foxtrot", "Golf", "Hotel", "India", "Juliett",
+	"Kilo", "Lima", "Mike", "November", "Oscar", "Papa", "Quebec", "Romeo", "Sierra", "Tango",
+	"Uniform", "Victor", "Whiskey", "X-ray", "Yankee", "Zulu",
+];
+
+const DIGITS: [&str; 10] = [
+	"zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
+];
+
+impl Coord {
+	#[must_use]
+	pub const fn new(x: u8, y: u8) -> Self {
+		Self { x, y }
+	}
+
+	/// The row letter, `A` for the top row, or `?` for rows past `Z`.
+	#[must_use]
+	pub const fn row(&self) -> char {
+		match b'A'.checked_add(self.y) {
+			Some(row) if row <= b'Z' => row as char,
+			_ => '?',
+		}
+	}
+
+	/// The coordinate as it's written in `notation`.
+	#[must_use]
+	pub const fn written(self, notation: Notation) -> Written {
+		Written(self, notation)
+	}
+
+	/// How the coordinate should be read aloud, like "Delta five".
+	#[must_use]
+	pub fn spoken(&self) -> String {
+		let row = NATO.get(usize::from(self.y)).copied().unwrap_or("Unknown");
+		let col = self
+			.x
+			.to_string()
+			.bytes()
+			.map(|d| DIGITS[usize::from(d - b'0')])
+			.collect::<Vec<_>>()
+			.join(" ");
+		format!("{row} {col}")
+	}
+}
+
+impl fmt::Display for Coord {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		self.written(Notation::LetterFirst).fmt(f)
+	}
+}
+
+impl FromStr for Coord {
+	type Err = ParseCoordError;
+
+	/// Reads a coordinate in any [`Notation`], with or without a `-` between
+	/// the letter and number, ignoring case. Rows and columns past the
+	/// biggest board allowed are out of range.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		if let Some((q, r)) = s
+			.strip_prefix(['q', 'Q'])
+			.and_then(|rest| rest.split_once(['r', 'R']))
+		{
+			let x = number(q.trim()).ok_or(ParseCoordError::BadColumn)?;
+			let y = number(r.trim()).ok_or(ParseCoordError::BadRow)?;
+			return Ok(Coord::new(x, y));
+		}
+		if let Some((y, x)) = s.split_once(',') {
+			let y = number(y.trim()).ok_or(ParseCoordError::BadRow)?;
+			let x = number(x.trim()).ok_or(ParseCoordError::BadColumn)?;
+			return Ok(Coord::new(x, y));
+		}
+		let (letter, digits) = match s.chars().next() {
+			Some(first) if first.is_ascii_alphabetic() => (first, &s[1..]),
+			Some(first) if first.is_ascii_digit() => {
+				let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+				let mut letter = s[split..].trim_start_matches('-').chars();
+				match (letter.next(), letter.next()) {
+					(Some(row), None) => (row, &s[..split]),
+					_ => return Err(ParseCoordError::Malformed),
+				}
+			}
+			_ => return Err(ParseCoordError::Malformed),
+		};
+		let y = u8::try_from(letter.to_ascii_uppercase())
+			.ok()
+			.and_then(|row| row.checked_sub(b'A'))
+			.filter(|&y| y < MAX_BOARD_SIZE)
+			.ok_or(ParseCoordError::BadRow)?;
+		let digits = digits.strip_prefix('-').unwrap_or(digits);
+		if digits.is_empty() {
+			return Err(ParseCoordError::Malformed);
+		}
+		let x = number(digits).ok_or(ParseCoordError::BadColumn)?;
+		Ok(Coord::new(x, y))
+	}
+}
+
+/// A row or column number, if it's on the biggest board allowed.
+fn number(s: &str) -> Option<u8> {
+	if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+		return None;
+	}
+	s.parse().ok().filter(|&n| n < MAX_BOARD_SIZE)
+}
@@ -0,0 +1,311 @@
+//! The rules engine for C, and anything that can call C, like a Godot or
+//! Unity front end. Build the library with
+//! `cargo rustc -p netbattleship-core --features ffi --crate-type cdylib`
+//! (or `staticlib`), and include the `include/netbattleship.h` header that
+//! building with the feature writes.
+//!
+//! A game made here is played on one computer with both fleets known, so
+//! the front end can show either side or pass the table around. Players are
+//! `true` for the one who fires first and `false` for the other, as in
+//! [`Game`], and squares are columns and rows counted from 0.
+
+use alloc::boxed::Box;
+use core::{ffi::c_char, ptr, slice};
+use std::ffi::CStr;
+
+use crate::{
+	board::{Board, CellState},
+	coord::Coord,
+	history::Move,
+	rules::Rules,
+	ship::ShipKind,
+	Game, Phase,
+};
+
+/// One of the ships in a fleet.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NbShip {
+	Carrier,
+	Battleship,
+	Cruiser,
+	Submarine,
+	Destroyer,
+	/// Not a ship at all.
+	None,
+}
+
+impl From<Option<ShipKind>> for NbShip {
+	fn from(ship: Option<ShipKind>) -> Self {
+		match ship {
+			Some(ShipKind::Carrier) => NbShip::Carrier,
+			Some(ShipKind::Battleship) => NbShip::Battleship,
+			Some(ShipKind::Cruiser) => NbShip::Cruiser,
+			Some(ShipKind::Submarine) => NbShip::Submarine,
+			Some(ShipKind::Destroyer) => NbShip::Destroyer,
+			None => NbShip::None,
+		}
+	}
+}
+
+impl From<NbShip> for Option<ShipKind> {
+	fn from(ship: NbShip) -> Self {
+		match ship {
+			NbShip::Carrier => Some(ShipKind::Carrier),
+			NbShip::Battleship => Some(ShipKind::Battleship),
+			NbShip::Cruiser => Some(ShipKind::Cruiser),
+			NbShip::Submarine => Some(ShipKind::Submarine),
+			NbShip::Destroyer => Some(ShipKind::Destroyer),
+			NbShip::None => None,
+		}
+	}
+}
+
+/// What's been done to a square.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NbState {
+	Untouched,
+	Hit,
+	Miss,
+	/// Land, which can't hold a ship or be fired at.
+	Land,
+	/// Off the edge of the board.
+	Outside,
+}
+
+/// One square of a board.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NbCell {
+	pub state: NbState,
+	/// The ship on the square, if any.
+	pub ship: NbShip,
+}
+
+/// How a shot went.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NbShot {
+	/// Whether the shot was allowed at all. Nothing else counts if it
+	/// wasn't: it's not the shooting phase, or the square is off the board,
+	/// land or already fired at.
+	pub fired: bool,
+	pub hit: bool,
+	/// The ship it sank, if any.
+	pub sunk: NbShip,
+	/// Whether that was the last of the fleet.
+	pub won: bool,
+}
+
+impl NbShot {
+	const REFUSED: NbShot = NbShot {
+		fired: false,
+		hit: false,
+		sunk: NbShip::None,
+		won: false,
+	};
+}
+
+/// Starts a game by the rules in `rules_toml`, written like a rules file, or
+/// the standard rules if it's null. Returns null if the rules can't be read
+/// or played. Free it with [`nb_game_free`].
+///
+/// # Safety
+/// `rules_toml` must be null or a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn nb_game_new(rules_toml: *const c_char) -> *mut Game {
+	let rules = if rules_toml.is_null() {
+		Rules::default()
+	} else {
+		// SAFETY: the caller promises a NUL-terminated string
+		let Ok(text) = unsafe { CStr::from_ptr(rules_toml) }.to_str() else {
+			return ptr::null_mut();
+		};
+		match toml::from_str::<Rules>(text) {
+			Ok(rules) if rules.validate().is_ok() => rules,
+			_ => return ptr::null_mut(),
+		}
+	};
+	Box::into_raw(Box::new(Game::new(rules, true)))
+}
+
+/// Frees a game from [`nb_game_new`] or [`nb_game_load`]. Null is ignored.
+///
+/// # Safety
+/// `game` must be null or a game from this library that hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn nb_game_free(game: *mut Game) {
+	if !game.is_null() {
+		// SAFETY: the caller promises it came from Box::into_raw here
+		drop(unsafe { Box::from_raw(game) });
+	}
+}
+
+/// Places `player`'s `ship` with its top or left end at `x`, `y`, returning
+/// whether it fit and is in their fleet but not yet on their board. The
+/// shooting starts once both fleets are placed.
+///
+/// # Safety
+/// `game` must be a live game from this library.
+#[no_mangle]
+pub unsafe extern "C" fn nb_place_ship(
+	game: *mut Game,
+	player: bool,
+	ship: NbShip,
+	x: u8,
+	y: u8,
+	vertical: bool,
+) -> bool {
+	// SAFETY: the caller promises a live game
+	let game = unsafe { &mut *game };
+	let Some(ship) = Option::<ShipKind>::from(ship) else {
+		return false;
+	};
+	let pos = Coord::new(x, y);
+	let fleet = game.rules.fleet.clone();
+	let placed = |board: &Board, ship| board.board.values().any(|cell| cell.ship == Some(ship));
+	if player == game.you {
+		if !game.apply(Move::Place {
+			ship,
+			pos,
+			vertical,
+		}) {
+			return false;
+		}
+	} else {
+		// Only our own ships go in the log, as in a game over the network
+		let board = &mut game.board[usize::from(player)];
+		if !matches!(game.phase, Phase::Placing(_) | Phase::Ready)
+			|| !fleet.contains(&ship)
+			|| placed(board, ship)
+			|| !ship.place(board, pos, vertical)
+		{
+			return false;
+		}
+	}
+	let theirs = &game.board[usize::from(!game.you)];
+	if game.phase == Phase::Ready && fleet.iter().all(|&ship| placed(theirs, ship)) {
+		#[cfg(feature = "std")]
+		game.start_clock();
+		game.phase = Phase::Playing;
+	}
+	true
+}
+
+/// Fires the next shot of whoever's turn it is at `x`, `y` on the other
+/// player's board, passing the turn over once their shots are used up.
+///
+/// # Safety
+/// `game` must be a live game from this library.
+#[no_mangle]
+pub unsafe extern "C" fn nb_fire(game: *mut Game, x: u8, y: u8) -> NbShot {
+	// SAFETY: the caller promises a live game
+	let game = unsafe { &mut *game };
+	let by = game.turn;
+	let target = usize::from(!by);
+	let pos = Coord::new(x, y);
+	let board = &game.board[target];
+	let cell = board.cell(pos);
+	if game.phase != Phase::Playing || !board.is_water(pos) || cell.state != CellState::Untouched {
+		return NbShot::REFUSED;
+	}
+	let hit = cell.ship.is_some();
+	let sunk = board.sinks(pos);
+	game.apply(Move::Shot {
+		by,
+		pos,
+		hit: Some(hit),
+		sunk,
+	});
+	let won = matches!(game.phase, Phase::Done(_));
+	game.end_shot();
+	NbShot {
+		fired: true,
+		hit,
+		sunk: sunk.into(),
+		won,
+	}
+}
+
+/// What's on `player`'s board at `x`, `y`, ships and all.
+///
+/// # Safety
+/// `game` must be a live game from this library.
+#[no_mangle]
+pub unsafe extern "C" fn nb_cell(game: *const Game, player: bool, x: u8, y: u8) -> NbCell {
+	// SAFETY: the caller promises a live game
+	let game = unsafe { &*game };
+	let board = &game.board[usize::from(player)];
+	let pos = Coord::new(x, y);
+	let cell = board.cell(pos);
+	let state = match cell.state {
+		_ if !board.in_bounds(pos) => NbState::Outside,
+		_ if !board.is_water(pos) => NbState::Land,
+		CellState::Hit => NbState::Hit,
+		CellState::Miss | CellState::Unknown => NbState::Miss,
+		CellState::Untouched => NbState::Untouched,
+	};
+	NbCell {
+		state,
+		ship: cell.ship.into(),
+	}
+}
+
+/// Whose turn it is.
+///
+/// # Safety
+/// `game` must be a live game from this library.
+#[no_mangle]
+pub unsafe extern "C" fn nb_turn(game: *const Game) -> bool {
+	// SAFETY: the caller promises a live game
+	unsafe { &*game }.turn
+}
+
+/// The whole game as CBOR, the format save files use, setting `len` to how
+/// many bytes it is. Free it with [`nb_bytes_free`]. Returns null if it
+/// can't be written.
+///
+/// # Safety
+/// `game` must be a live game from this library, and `len` somewhere to
+/// write a `size_t`.
+#[no_mangle]
+pub unsafe extern "C" fn nb_game_save(game: *const Game, len: *mut usize) -> *mut u8 {
+	// SAFETY: the caller promises a live game
+	let game = unsafe { &*game };
+	let Ok(bytes) = serde_cbor::to_vec(game) else {
+		return ptr::null_mut();
+	};
+	let bytes = bytes.into_boxed_slice();
+	// SAFETY: the caller promises somewhere to write it
+	unsafe { *len = bytes.len() };
+	Box::into_raw(bytes).cast()
+}
+
+/// Reads back a game from [`nb_game_save`], or returns null if it isn't
+/// one. Free it with [`nb_game_free`].
+///
+/// # Safety
+/// `bytes` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn nb_game_load(bytes: *const u8, len: usize) -> *mut Game {
+	// SAFETY: the caller promises that many bytes
+	let bytes = unsafe { slice::from_raw_parts(bytes, len) };
+	match serde_cbor::from_slice::<Game>(bytes) {
+		Ok(game) => Box::into_raw(Box::new(game)),
+		Err(_) => ptr::null_mut(),
+	}
+}
+
+/// Frees bytes from [`nb_game_save`]. Null is ignored.
+///
+/// # Safety
+/// `bytes` and `len` must be as [`nb_game_save`] gave them, and not freed
+/// yet.
+#[no_mangle]
+pub unsafe extern "C" fn nb_bytes_free(bytes: *mut u8, len: usize) {
+	if !bytes.is_null() {
+		// SAFETY: the caller promises it's a boxed slice of this length
+		drop(unsafe { Box::from_raw(ptr::slice_from_raw_parts_mut(bytes, len)) });
+	}
+}
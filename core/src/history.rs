@@ -0,0 +1,168 @@
+//! Everything that happened in a game, in order, as far as one player knows,
+//! so the game can be replayed and checked afterwards.
+//!
+//! Moves go through [`Game::apply`], which adds them to [`Game::log`], so
+//! spectators, replays and other engines only need the moves, and
+//! [`Game::from_log`] plays them out again. The log is a record, not where
+//! the game's state is kept: the boards and the rest are stored beside it,
+//! and aren't checked against it. Fixing a board that fell out of sync with
+//! the enemy's changes it without a move, and the clocks aren't moves at
+//! all, so a game worked out from its log can differ from the one it came
+//! from.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	board::{Cell, CellState},
+	coord::Coord,
+	rules::Rules,
+	ship::ShipKind,
+	Game, Phase,
+};
+
+/// One step of a game. Players are named like [`Game::turn`](crate::Game::turn).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Move {
+	/// One of our own ships went onto our board. The enemy's placements are
+	/// never known.
+	Place {
+		ship: ShipKind,
+		pos: Coord,
+		vertical: bool,
+	},
+	/// `by` fired at the other player's board. `hit` is `None` if the blind
+	/// rule kept it from the shooter.
+	Shot {
+		by: bool,
+		pos: Coord,
+		hit: Option<bool>,
+		sunk: Option<ShipKind>,
+	},
+	/// `by` swept the other player's board with radar.
+	Scan { by: bool, center: Coord, count: u8 },
+	/// `by` moved their submarine instead of firing. Where it went is only
+	/// known for our own.
+	Submarine { by: bool, to: Option<(Coord, bool)> },
+}
+
+impl Game {
+	/// Makes `step` happen and adds it to the log, returning whether it
+	/// could. Only placing a ship or moving our submarine can fail, where
+	/// the ship doesn't fit or isn't one still to be placed.
+	///
+	/// Nothing is checked against the rules beyond that, and turns aren't
+	/// passed over, so a shot can be agreed on first; [`Game::end_shot`] does
+	/// that. The first move after placing starts the shooting, if that hasn't
+	/// already been started.
+	pub fn apply(&mut self, step: Move) -> bool {
+		let applied = match step {
+			Move::Place {
+				ship,
+				pos,
+				vertical,
+			} => self.place(ship, pos, vertical),
+			Move::Shot { by, pos, hit, sunk } => {
+				let target = usize::from(!by);
+				// Only a hit can sink, so even a blind shot is known to have hit then
+				let hit = hit.or(sunk.map(|_| true));
+				let board = &mut self.board[target];
+				let cell = board.cell(pos);
+				// A square that was hit stays hit, even when fired at again
+				if cell.state != CellState::Hit {
+					let state = match hit {
+						Some(true) => CellState::Hit,
+						Some(false) => CellState::Miss,
+						None => CellState::Unknown,
+					};
+					board.board.insert(pos, Cell { state, ..cell });
+				}
+				self.sunk[target].extend(sunk);
+				if self.surviving(!by) == 0 {
+					self.phase = Phase::Done(by == self.you);
+				}
+				true
+			}
+			Move::Scan { by, .. } => {
+				self.scans[usize::from(by)] += 1;
+				true
+			}
+			Move::Submarine { by, to } => {
+				let moved = match to {
+					Some((pos, vertical)) if by == self.you => self.resubmerge(pos, vertical),
+					_ => true,
+				};
+				self.moved[usize::from(by)] |= moved;
+				moved
+			}
+		};
+		if !applied {
+			return false;
+		}
+		if self.phase == Phase::Ready && !matches!(step, Move::Place { .. }) {
+			self.phase = Phase::Playing;
+		}
+		self.log.push(step);
+		true
+	}
+
+	/// The game `log` leads to, as seen by player `you`, or `None` if one of
+	/// its ships doesn't fit.
+	///
+	/// # Panics
+	/// Panics if the rules have an empty fleet.
+	#[must_use]
+	pub fn from_log(rules: Rules, you: bool, log: &[Move]) -> Option<Game> {
+		let mut game = Game::new(rules, you);
+		for &step in log {
+			if !game.apply(step) {
+				return None;
+			}
+			if matches!(step, Move::Shot { .. } | Move::Submarine { .. }) {
+				game.end_shot();
+			}
+		}
+		Some(game)
+	}
+
+	/// Places one of our ships still to be placed, moving on to the first of
+	/// the fleet left, or to being ready.
+	fn place(&mut self, ship: ShipKind, pos: Coord, vertical: bool) -> bool {
+		let board = &mut self.board[usize::from(self.you)];
+		let placed = |board: &crate::board::Board, ship| {
+			board.board.values().any(|cell| cell.ship == Some(ship))
+		};
+		if !matches!(self.phase, Phase::Placing(_))
+			|| !self.rules.fleet.contains(&ship)
+			|| placed(board, ship)
+			|| !ship.place(board, pos, vertical)
+		{
+			return false;
+		}
+		let board = &self.board[usize::from(self.you)];
+		self.phase = self
+			.rules
+			.fleet
+			.iter()
+			.find(|&&ship| !placed(board, ship))
+			.map_or(Phase::Ready, |&ship| Phase::Placing(ship));
+		true
+	}
+
+	/// Moves our submarine to `pos`, leaving it where it was if it doesn't
+	/// fit there.
+	fn resubmerge(&mut self, pos: Coord, vertical: bool) -> bool {
+		let board = &mut self.board[usize::from(self.you)];
+		let old = board.clone();
+		board
+			.board
+			.retain(|_, cell| cell.ship != Some(ShipKind::Submarine));
+		if ShipKind::Submarine.place(board, pos, vertical) {
+			true
+		} else {
+			*board = old;
+			false
+		}
+	}
+}
@@ -0,0 +1,229 @@
+//! The rules of battleship: boards, ships, the variants players can agree on
+//! and the state of a game in progress. Nothing here talks to the network.
+//!
+//! Without the default `std` feature this only needs `alloc`, so it works on
+//! embedded targets and in WASM.
+#![cfg_attr(not(feature = "std"), no_std)]
+#![warn(clippy::pedantic)]
+
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+use alloc::vec::Vec;
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::time::Instant;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use rules::Rules;
+use ship::ShipKind;
+
+pub mod board;
+pub mod coord;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod history;
+pub mod rules;
+pub mod ship;
+pub mod sync;
+
+#[derive(Default, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Game {
+	pub board: [board::Board; 2],
+	pub turn: bool,
+	pub you: bool,
+	pub phase: Phase,
+	pub rules: Rules,
+	/// The ships each player has lost, indexed like `board`.
+	pub sunk: [Vec<ShipKind>; 2],
+	/// Shots remaining in the current turn; only ever above one with salvos.
+	shots_left: u8,
+	/// When the current turn began, for enforcing the turn timer. Not saved;
+	/// a resumed turn starts over.
+	#[cfg(feature = "std")]
+	#[cfg_attr(feature = "serde", serde(skip))]
+	turn_started: Option<Instant>,
+	/// Time each player spent on their finished turns, indexed like `board`.
+	/// [`Game::clocks`] also counts the turn in progress.
+	time_used: [Duration; 2],
+	/// The turn in progress, counting both players' turns from 1.
+	pub turn_number: u32,
+	/// Turns each player has finished, indexed like `board`.
+	turns: [u32; 2],
+	/// Radar sweeps each player has used, indexed like `board`.
+	pub scans: [u32; 2],
+	/// Whether each player has moved their submarine, indexed like `board`.
+	pub moved: [bool; 2],
+	/// Every ship we placed and every move either player made, in order;
+	/// see [`Game::apply`]. A record of the game, kept beside the rest of
+	/// it rather than instead of it.
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub log: Vec<history::Move>,
+}
+
+/// With the radar rule, players earn a sweep each time they finish this many turns.
+pub const TURNS_PER_SCAN: u32 = 5;
+
+impl Game {
+	/// # Panics
+	/// Panics if the rules have an empty fleet.
+	#[must_use]
+	pub fn new(rules: Rules, you: bool) -> Self {
+		let board = board::Board::new(&rules);
+		let mut game = Self {
+			board: [board.clone(), board],
+			you,
+			turn: true,
+			turn_number: 1,
+			phase: Phase::Placing(rules.fleet[0]),
+			rules,
+			..Default::default()
+		};
+		game.shots_left = game.shots_per_turn(true);
+		game
+	}
+
+	/// How many of `player`'s ships are still afloat.
+	#[must_use]
+	pub fn surviving(&self, player: bool) -> usize {
+		self.rules.fleet.len() - self.sunk[usize::from(player)].len()
+	}
+
+	/// How many shots `player` may take in one turn.
+	#[must_use]
+	pub fn shots_per_turn(&self, player: bool) -> u8 {
+		if self.rules.salvo {
+			u8::try_from(self.surviving(player)).unwrap_or(u8::MAX)
+		} else {
+			1
+		}
+	}
+
+	/// Shots remaining in the current turn; only ever above one with salvos.
+	#[must_use]
+	pub fn shots_left(&self) -> u8 {
+		self.shots_left
+	}
+
+	/// How many turns `player` has finished.
+	#[must_use]
+	pub fn turns(&self, player: bool) -> u32 {
+		self.turns[usize::from(player)]
+	}
+
+	/// Uses up one shot, passing the turn over if none are left.
+	pub fn end_shot(&mut self) {
+		self.shots_left = self.shots_left.saturating_sub(1);
+		if self.shots_left == 0 {
+			self.turns[usize::from(self.turn)] += 1;
+			self.turn_number += 1;
+			#[cfg(feature = "std")]
+			self.stop_clock();
+			self.turn = !self.turn;
+			self.shots_left = self.shots_per_turn(self.turn);
+			#[cfg(feature = "std")]
+			if self.phase == Phase::Playing {
+				self.start_clock();
+			}
+		}
+	}
+
+	/// The squares that differ between each of our boards and `other`'s,
+	/// indexed like `board`; see [`board::Board::diff`]. Frontends can compare
+	/// snapshots to animate only what changed, and two copies of a game can
+	/// be checked square by square.
+	#[must_use]
+	pub fn diff(&self, other: &Game) -> [Vec<(coord::Coord, board::Cell, board::Cell)>; 2] {
+		[0, 1].map(|player| self.board[player].diff(&other.board[player]))
+	}
+
+	/// Whether `player` has earned a radar sweep they haven't used yet.
+	#[must_use]
+	pub fn scan_available(&self, player: bool) -> bool {
+		let player = usize::from(player);
+		self.rules.radar && self.turns[player] / TURNS_PER_SCAN > self.scans[player]
+	}
+
+	/// Whether `player` may still move their submarine. Only our own board
+	/// shows whether the submarine has been hit, so for the enemy this only
+	/// checks the rules and whether they've moved before.
+	#[must_use]
+	pub fn can_move_submarine(&self, player: bool) -> bool {
+		let index = usize::from(player);
+		if !self.rules.submarine_moves || self.moved[index] {
+			return false;
+		}
+		player != self.you || {
+			let cells = self.board[index]
+				.board
+				.values()
+				.filter(|c| {
+					c.ship == Some(ShipKind::Submarine) && c.state == board::CellState::Untouched
+				})
+				.count();
+			cells == usize::from(ShipKind::Submarine.len())
+		}
+	}
+
+	/// Starts timing the turn in progress, for the player taking it.
+	#[cfg(feature = "std")]
+	pub fn start_clock(&mut self) {
+		self.turn_started = Some(Instant::now());
+	}
+
+	/// Charges the turn in progress to the clock of the player taking it, and
+	/// stops timing until the next turn starts.
+	#[cfg(feature = "std")]
+	pub fn stop_clock(&mut self) {
+		if let Some(started) = self.turn_started.take() {
+			self.time_used[usize::from(self.turn)] += started.elapsed();
+		}
+	}
+
+	/// Time each player spent on their finished turns, indexed like `board`.
+	/// Without `std` nothing times turns, so this is only what a saved game
+	/// brought with it.
+	#[must_use]
+	pub fn time_used(&self) -> [Duration; 2] {
+		self.time_used
+	}
+
+	/// Time each player has spent on their turns, chess-clock style,
+	/// including the turn in progress. Indexed like `board`.
+	#[cfg(feature = "std")]
+	#[must_use]
+	pub fn clocks(&self) -> [Duration; 2] {
+		let mut clocks = self.time_used();
+		if let Some(started) = self.turn_started {
+			clocks[usize::from(self.turn)] += started.elapsed();
+		}
+		clocks
+	}
+
+	/// Whether the player whose turn it is has run out of time.
+	#[cfg(feature = "std")]
+	#[must_use]
+	pub fn turn_expired(&self) -> bool {
+		match (self.rules.turn_timer(), self.turn_started) {
+			(Some(limit), Some(started)) => started.elapsed() > limit,
+			_ => false,
+		}
+	}
+}
+
+#[derive(Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
+pub enum Phase {
+	#[default]
+	Connecting,
+	Placing(ShipKind),
+	/// Our fleet is placed, but the enemy is still placing theirs.
+	Ready,
+	Playing,
+	Done(bool),
+}
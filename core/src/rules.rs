@@ -0,0 +1,240 @@
+use alloc::{collections::BTreeSet, format, vec::Vec};
+use core::{fmt, time::Duration};
+#[cfg(feature = "toml")]
+use std::{fs, io, path::Path};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{board::Topology, coord::Coord, ship::ShipKind};
+
+/// The variant of battleship being played.
+///
+/// Both players must agree on the rules; they are compared during the
+/// handshake and the game refuses to start if they differ.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+	feature = "serde",
+	derive(Serialize, Deserialize),
+	serde(default, deny_unknown_fields)
+)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[allow(clippy::struct_excessive_bools)]
+pub struct Rules {
+	/// Width and height of each board.
+	pub board_size: u8,
+	/// The ships each player places, in placement order.
+	pub fleet: Vec<ShipKind>,
+	/// Whether each turn is a salvo of one shot per surviving ship.
+	pub salvo: bool,
+	/// How many seconds a player may take to fire before forfeiting.
+	pub turn_timer: Option<u64>,
+	/// Whether ships are forbidden from touching, even diagonally.
+	pub adjacency: bool,
+	/// Whether the shooter is only told about ships sinking, not about each hit.
+	pub blind: bool,
+	/// Whether players earn a radar sweep every few turns.
+	pub radar: bool,
+	/// Whether each player may move their unhit Submarine once per game instead of firing.
+	pub submarine_moves: bool,
+	/// Whether the boards are square or hexagonal grids.
+	pub topology: Topology,
+	/// Squares of land on both boards, which ships can't be placed on or
+	/// fired at. Usually drawn in a map; see [`Rules::with_map`].
+	pub land: BTreeSet<Coord>,
+}
+
+#[derive(Error, Debug)]
+pub enum RulesError {
+	#[cfg(feature = "toml")]
+	#[error("couldn't read rules file: {0}")]
+	Io(#[from] io::Error),
+	#[cfg(feature = "toml")]
+	#[error("couldn't parse rules file: {0}")]
+	Parse(#[from] toml::de::Error),
+	#[error("board size must be between 1 and {MAX_BOARD_SIZE}, not {0}")]
+	BoardSize(u8),
+	#[error("the fleet must contain at least one ship")]
+	EmptyFleet,
+	#[error("the fleet contains more than one {0:?}")]
+	DuplicateShip(ShipKind),
+	#[error("the {0:?} doesn't fit on the board")]
+	ShipTooLong(ShipKind),
+	#[error("the fleet doesn't fit on the board")]
+	FleetTooLarge,
+	#[error("the turn timer must be at least one second")]
+	TurnTimer,
+	#[error("submarines can only move if the fleet has a Submarine")]
+	NoSubmarine,
+	#[error("there's land at {0:?}, off the edge of the board")]
+	LandOffBoard(Coord),
+	#[error("line {0} of the map should be as many squares of `.` or `#` as the map has lines")]
+	BadMap(usize),
+}
+
+pub const MAX_BOARD_SIZE: u8 = 10;
+
+impl Default for Rules {
+	fn default() -> Self {
+		Self {
+			board_size: 10,
+			fleet: ShipKind::into_iter().copied().collect(),
+			salvo: false,
+			turn_timer: None,
+			adjacency: false,
+			blind: false,
+			radar: false,
+			submarine_moves: false,
+			topology: Topology::Square,
+			land: BTreeSet::new(),
+		}
+	}
+}
+
+impl Rules {
+	/// Reads and validates rules from a TOML file.
+	///
+	/// Missing keys take their default values, so a file containing only
+	/// `salvo = true` plays the standard game with salvos.
+	///
+	/// # Errors
+	/// Fails if the file can't be read or parsed, or describes an impossible game.
+	#[cfg(feature = "toml")]
+	pub fn load(path: impl AsRef<Path>) -> Result<Rules, RulesError> {
+		let rules: Rules = toml::from_str(&fs::read_to_string(path)?)?;
+		rules.validate()?;
+		Ok(rules)
+	}
+
+	/// Plays on the map in the text file at `path`; see [`Rules::with_map`].
+	///
+	/// # Errors
+	/// Fails if the file can't be read, or the map is malformed or leaves no
+	/// room for the fleet.
+	#[cfg(feature = "toml")]
+	pub fn load_map(self, path: impl AsRef<Path>) -> Result<Rules, RulesError> {
+		self.with_map(&fs::read_to_string(path)?)
+	}
+
+	/// Plays on `map`, which draws the board a row to a line, with `.` for
+	/// water and `#` for land. The board is as big as the map, which must be
+	/// square.
+	///
+	/// # Errors
+	/// Fails if the map is malformed or leaves no room for the fleet.
+	pub fn with_map(mut self, map: &str) -> Result<Rules, RulesError> {
+		let rows = map
+			.lines()
+			.map(str::trim)
+			.filter(|line| !line.is_empty())
+			.collect::<Vec<_>>();
+		let size = u8::try_from(rows.len()).map_err(|_| RulesError::BoardSize(u8::MAX))?;
+		self.land.clear();
+		for (y, row) in (0..size).zip(&rows) {
+			let bad = || RulesError::BadMap(usize::from(y) + 1);
+			if row.chars().count() != rows.len() {
+				return Err(bad());
+			}
+			for (x, square) in (0..size).zip(row.chars()) {
+				match square {
+					'.' => {}
+					'#' => {
+						self.land.insert(Coord::new(x, y));
+					}
+					_ => return Err(bad()),
+				}
+			}
+		}
+		self.board_size = size;
+		self.validate()?;
+		Ok(self)
+	}
+
+	/// Checks that a game with these rules can actually be played.
+	///
+	/// # Errors
+	/// Returns the first problem found.
+	pub fn validate(&self) -> Result<(), RulesError> {
+		if !(1..=MAX_BOARD_SIZE).contains(&self.board_size) {
+			return Err(RulesError::BoardSize(self.board_size));
+		}
+		if self.fleet.is_empty() {
+			return Err(RulesError::EmptyFleet);
+		}
+		for (i, ship) in self.fleet.iter().enumerate() {
+			if self.fleet[..i].contains(ship) {
+				return Err(RulesError::DuplicateShip(*ship));
+			}
+			if ship.len() > self.board_size {
+				return Err(RulesError::ShipTooLong(*ship));
+			}
+		}
+		if let Some(&land) = self
+			.land
+			.iter()
+			.find(|pos| pos.x >= self.board_size || pos.y >= self.board_size)
+		{
+			return Err(RulesError::LandOffBoard(land));
+		}
+		let cells: usize = self.fleet.iter().map(|s| usize::from(s.len())).sum();
+		if cells + self.land.len() > usize::from(self.board_size).pow(2) {
+			return Err(RulesError::FleetTooLarge);
+		}
+		if self.turn_timer == Some(0) {
+			return Err(RulesError::TurnTimer);
+		}
+		if self.submarine_moves && !self.fleet.contains(&ShipKind::Submarine) {
+			return Err(RulesError::NoSubmarine);
+		}
+		Ok(())
+	}
+
+	#[must_use]
+	pub fn turn_timer(&self) -> Option<Duration> {
+		self.turn_timer.map(Duration::from_secs)
+	}
+}
+
+impl fmt::Display for Rules {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let fleet = self
+			.fleet
+			.iter()
+			.map(|s| format!("{s:?}"))
+			.collect::<Vec<_>>()
+			.join(", ");
+		write!(
+			f,
+			"{size}x{size} {}board, fleet of {fleet}",
+			if self.topology == Topology::Hex {
+				"hex "
+			} else {
+				""
+			},
+			size = self.board_size
+		)?;
+		if self.salvo {
+			write!(f, ", salvos")?;
+		}
+		if let Some(secs) = self.turn_timer {
+			write!(f, ", {secs} second turns")?;
+		}
+		if self.adjacency {
+			write!(f, ", ships may not touch")?;
+		}
+		if self.blind {
+			write!(f, ", blind")?;
+		}
+		if self.radar {
+			write!(f, ", radar sweeps")?;
+		}
+		if self.submarine_moves {
+			write!(f, ", submarines may move")?;
+		}
+		if !self.land.is_empty() {
+			write!(f, ", {} squares of land", self.land.len())?;
+		}
+		Ok(())
+	}
+}
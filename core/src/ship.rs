@@ -0,0 +1,146 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize};
+
+use alloc::{collections::BTreeMap, format, string::String};
+
+use crate::{board::Cell, coord::Coord};
+
+/// What a player calls their ships, where they've renamed them.
+pub type ShipNames = BTreeMap<ShipKind, String>;
+
+/// A class of ship. Whether a square is empty or has been fired at is kept
+/// in its [`Cell`] instead.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[non_exhaustive]
+pub enum ShipKind {
+	Carrier,
+	Battleship,
+	Cruiser,
+	Submarine,
+	Destroyer,
+}
+
+impl From<ShipKind> for char {
+	fn from(s: ShipKind) -> Self {
+		match s {
+			ShipKind::Carrier => 'C',
+			ShipKind::Battleship => 'B',
+			ShipKind::Cruiser => 'R',
+			ShipKind::Submarine => 'S',
+			ShipKind::Destroyer => 'D',
+		}
+	}
+}
+
+impl ShipKind {
+	pub fn into_iter() -> core::slice::Iter<'static, ShipKind> {
+		[
+			ShipKind::Carrier,
+			ShipKind::Battleship,
+			ShipKind::Cruiser,
+			ShipKind::Submarine,
+			ShipKind::Destroyer,
+		]
+		.iter()
+	}
+
+	/// What this ship is called in `names`, or its class if it wasn't
+	/// renamed.
+	#[must_use]
+	pub fn called(self, names: &ShipNames) -> String {
+		names
+			.get(&self)
+			.cloned()
+			.unwrap_or_else(|| format!("{self:?}"))
+	}
+
+	/// How many squares the ship covers. Every ship covers at least one.
+	#[must_use]
+	#[allow(clippy::len_without_is_empty)]
+	pub const fn len(&self) -> u8 {
+		match self {
+			ShipKind::Carrier => 5,
+			ShipKind::Battleship => 4,
+			ShipKind::Cruiser | ShipKind::Submarine => 3,
+			ShipKind::Destroyer => 2,
+		}
+	}
+
+	/// The cells this ship would cover with its top-left section at `pos`.
+	///
+	/// Cells past the edge of a `u8` are left out, so check the length of the
+	/// result (or use [`ShipKind::can_place`]) before trusting it.
+	pub fn footprint(&self, pos: Coord, v: bool) -> impl Iterator<Item = Coord> {
+		(0..self.len()).map_while(move |i| {
+			Some(if v {
+				Coord::new(pos.x, pos.y.checked_add(i)?)
+			} else {
+				Coord::new(pos.x.checked_add(i)?, pos.y)
+			})
+		})
+	}
+
+	/// Whether this ship fits on the board at `pos` without overlapping (or,
+	/// with the adjacency rule, touching) another ship, or running aground.
+	#[must_use]
+	pub fn can_place(&self, board: &crate::board::Board, pos: Coord, v: bool) -> bool {
+		let start = if v { pos.y } else { pos.x };
+		if !board.in_bounds(pos) || self.len() > board.size() - start {
+			return false;
+		}
+
+		self.footprint(pos, v).all(|cursor| {
+			if board.board.contains_key(&cursor) || board.land().contains(&cursor) {
+				return false;
+			}
+			!board.adjacency()
+				|| board
+					.neighbours(cursor)
+					.all(|n| board.cell(n).ship.is_none())
+		})
+	}
+
+	pub fn place(&self, board: &mut crate::board::Board, pos: Coord, v: bool) -> bool {
+		if !self.can_place(board, pos, v) {
+			return false;
+		}
+
+		for cursor in self.footprint(pos, v) {
+			board.board.insert(cursor, Cell::ship(*self));
+		}
+
+		true
+	}
+}
+
+/// Reads where a ship might be, as written now or as older versions wrote
+/// it, when empty squares and shots were kinds of ship too. Those all read
+/// as no ship.
+///
+/// # Errors
+/// Fails if what's there isn't a ship or one of the old markers.
+#[cfg(feature = "serde")]
+pub fn legacy<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<ShipKind>, D::Error> {
+	#[derive(Deserialize)]
+	enum Legacy {
+		None,
+		Miss,
+		Hit,
+		Carrier,
+		Battleship,
+		Cruiser,
+		Submarine,
+		Destroyer,
+	}
+
+	Ok(match Option::<Legacy>::deserialize(deserializer)? {
+		None | Some(Legacy::None | Legacy::Miss | Legacy::Hit) => None,
+		Some(Legacy::Carrier) => Some(ShipKind::Carrier),
+		Some(Legacy::Battleship) => Some(ShipKind::Battleship),
+		Some(Legacy::Cruiser) => Some(ShipKind::Cruiser),
+		Some(Legacy::Submarine) => Some(ShipKind::Submarine),
+		Some(Legacy::Destroyer) => Some(ShipKind::Destroyer),
+	})
+}
@@ -0,0 +1,171 @@
+//! The part of a game both players can see, so they can check that their
+//! copies still agree, such as after reconnecting.
+
+use alloc::{collections::BTreeMap, vec::Vec};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	board::{Cell, CellState},
+	coord::Coord,
+	ship::ShipKind,
+	Game,
+};
+
+/// Everything about a game that both players should agree on: whose turn it
+/// is, where every shot landed and what has been used up or sunk. Ships that
+/// haven't been found are left out, so it's safe to send to the enemy.
+///
+/// Indexed like [`Game::board`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct PublicState {
+	pub turn_number: u32,
+	pub turn: bool,
+	/// Every square fired at on each board, in order, and what the shot did.
+	/// In blind games the shooter never finds out, so these are all
+	/// [`CellState::Unknown`].
+	pub shots: [Vec<(Coord, CellState)>; 2],
+	pub sunk: [Vec<ShipKind>; 2],
+	pub scans: [u32; 2],
+	pub moved: [bool; 2],
+}
+
+/// Where [FNV-1a](http://www.isthe.com/chongo/tech/comp/fnv/) hashes start.
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+impl PublicState {
+	/// A short fingerprint of this state, for checking that both players
+	/// agree without sending the whole thing. Unlike [`core::hash::Hash`],
+	/// it comes out the same on every platform and compiler.
+	#[must_use]
+	pub fn fingerprint(&self) -> u64 {
+		let mut hash = Fnv(FNV_OFFSET);
+		hash.write(&self.turn_number.to_le_bytes());
+		hash.write(&[u8::from(self.turn)]);
+		for shots in &self.shots {
+			hash.write(&u32::try_from(shots.len()).unwrap_or(u32::MAX).to_le_bytes());
+			for (pos, state) in shots {
+				hash.write(&[pos.x, pos.y, *state as u8]);
+			}
+		}
+		for sunk in &self.sunk {
+			hash.write(&u32::try_from(sunk.len()).unwrap_or(u32::MAX).to_le_bytes());
+			for ship in sunk {
+				hash.write(&[*ship as u8]);
+			}
+		}
+		for player in 0..2 {
+			hash.write(&self.scans[player].to_le_bytes());
+			hash.write(&[u8::from(self.moved[player])]);
+		}
+		hash.0
+	}
+}
+
+struct Fnv(u64);
+
+impl Fnv {
+	fn write(&mut self, bytes: &[u8]) {
+		for &byte in bytes {
+			self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(FNV_PRIME);
+		}
+	}
+}
+
+impl Game {
+	/// What both players can see of this game.
+	#[must_use]
+	pub fn public_state(&self) -> PublicState {
+		let shots = |player: usize| {
+			self.board[player]
+				.board
+				.iter()
+				.filter(|(_, cell)| cell.state != CellState::Untouched)
+				.map(|(pos, cell)| (*pos, self.public(cell.state)))
+				.collect()
+		};
+		PublicState {
+			turn_number: self.turn_number,
+			turn: self.turn,
+			shots: [shots(0), shots(1)],
+			sunk: self.sunk.clone(),
+			scans: self.scans,
+			moved: self.moved,
+		}
+	}
+
+	/// Brings our copy of what `player` knows best (their own board, the
+	/// ships they've lost and the sweeps and moves they've made) in line
+	/// with `theirs`, their own view of the game. Returns how many things
+	/// were out of date.
+	pub fn repair(&mut self, player: bool, theirs: &PublicState) -> usize {
+		let index = usize::from(player);
+		let blind = self.rules.blind;
+		let shots = theirs.shots[index]
+			.iter()
+			.copied()
+			.collect::<BTreeMap<_, _>>();
+		let board = &mut self.board[index];
+		let mut repaired = 0;
+
+		let stale = board
+			.board
+			.iter()
+			.filter(|(pos, cell)| cell.state != CellState::Untouched && !shots.contains_key(pos))
+			.map(|(pos, cell)| (*pos, *cell))
+			.collect::<Vec<_>>();
+		for (pos, cell) in stale {
+			match cell.ship {
+				Some(ship) => board.board.insert(pos, Cell::ship(ship)),
+				None => board.board.remove(&pos),
+			};
+			repaired += 1;
+		}
+
+		for (pos, shot) in shots {
+			let cell = board.cell(pos);
+			let known = match cell.state {
+				CellState::Untouched => CellState::Untouched,
+				_ if blind => CellState::Unknown,
+				known => known,
+			};
+			if known != shot {
+				board.board.insert(
+					pos,
+					Cell {
+						state: shot,
+						..cell
+					},
+				);
+				repaired += 1;
+			}
+		}
+
+		if self.sunk[index] != theirs.sunk[index] {
+			self.sunk[index].clone_from(&theirs.sunk[index]);
+			repaired += 1;
+		}
+		if self.scans[index] != theirs.scans[index] {
+			self.scans[index] = theirs.scans[index];
+			repaired += 1;
+		}
+		if self.moved[index] != theirs.moved[index] {
+			self.moved[index] = theirs.moved[index];
+			repaired += 1;
+		}
+		repaired
+	}
+
+	/// How a shot's result looks to both players.
+	fn public(&self, state: CellState) -> CellState {
+		if self.rules.blind {
+			CellState::Unknown
+		} else {
+			state
+		}
+	}
+}
@@ -0,0 +1,42 @@
+#![cfg(feature = "ffi")]
+
+use netbattleship_core::ffi::{
+	nb_bytes_free, nb_cell, nb_fire, nb_game_free, nb_game_load, nb_game_new, nb_game_save,
+	nb_place_ship, nb_turn, NbShip, NbState,
+};
+
+#[test]
+fn games_are_played_through_the_c_functions() {
+	unsafe {
+		let game = nb_game_new(c"board_size = 5\nfleet = [\"Destroyer\"]".as_ptr());
+		assert!(!game.is_null());
+		assert!(nb_game_new(c"board_size = 50".as_ptr()).is_null());
+		assert!(!nb_place_ship(game, true, NbShip::Carrier, 0, 0, false));
+		assert!(!nb_place_ship(game, true, NbShip::Destroyer, 4, 0, false));
+		for player in [true, false] {
+			assert!(nb_place_ship(game, player, NbShip::Destroyer, 0, 0, false));
+		}
+		assert!(!nb_place_ship(game, true, NbShip::Destroyer, 0, 2, false));
+
+		assert!(nb_turn(game));
+		assert!(nb_fire(game, 0, 0).hit);
+		assert!(!nb_turn(game));
+		assert!(!nb_fire(game, 4, 4).hit);
+		assert!(!nb_fire(game, 0, 0).fired);
+
+		let mut len = 0;
+		let bytes = nb_game_save(game, &mut len);
+		let copy = nb_game_load(bytes, len);
+		nb_bytes_free(bytes, len);
+		assert!(nb_game_load(b"nonsense".as_ptr(), 8).is_null());
+		let cell = nb_cell(copy, false, 0, 0);
+		assert_eq!((cell.state, cell.ship), (NbState::Hit, NbShip::Destroyer));
+		assert_eq!(nb_cell(copy, false, 5, 0).state, NbState::Outside);
+
+		let shot = nb_fire(copy, 1, 0);
+		assert_eq!((shot.sunk, shot.won), (NbShip::Destroyer, true));
+		assert!(!nb_fire(copy, 2, 0).fired);
+		nb_game_free(copy);
+		nb_game_free(game);
+	}
+}
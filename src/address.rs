@@ -0,0 +1,81 @@
+#[cfg(feature = "async")]
+use std::{io, net::SocketAddr};
+use std::{net::SocketAddrV4, str::FromStr};
+
+use thiserror::Error;
+#[cfg(feature = "async")]
+use tokio::net::lookup_host;
+
+use crate::invite::{Invite, InviteError, SCHEME};
+
+/// Where a player asked to connect to: an IPv4 address or invite code, which
+/// are ready to use, or a hostname that still has to be looked up.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Address {
+	Direct(SocketAddrV4),
+	Host(String, u16),
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum AddressError {
+	#[error("add the port after a colon, like {0}:7777")]
+	MissingPort(String),
+	#[error("the port should be a number from 0 to 65535")]
+	BadPort,
+	#[error("hostnames only contain letters, digits, dashes and dots")]
+	BadHost,
+	#[error("that's neither an address with a port nor a valid invite code ({0})")]
+	Invite(#[from] InviteError),
+}
+
+impl FromStr for Address {
+	type Err = AddressError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let s = s.trim();
+		if s.starts_with(SCHEME) {
+			return Ok(Address::Direct(s.parse::<Invite>()?.0));
+		}
+		if let Ok(addr) = s.parse() {
+			return Ok(Address::Direct(addr));
+		}
+		let Some((host, port)) = s.rsplit_once(':') else {
+			// Anything with a dot is meant as an address rather than an invite code
+			if s.contains('.') {
+				return Err(AddressError::MissingPort(s.to_string()));
+			}
+			return Ok(Address::Direct(s.parse::<Invite>()?.0));
+		};
+		let port = port.parse().map_err(|_| AddressError::BadPort)?;
+		let valid = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '.';
+		if host.is_empty() || !host.chars().all(valid) {
+			return Err(AddressError::BadHost);
+		}
+		Ok(Address::Host(host.to_string(), port))
+	}
+}
+
+impl Address {
+	/// Looks up the IPv4 address to connect to.
+	///
+	/// # Errors
+	/// Fails if a hostname can't be resolved or has no IPv4 address.
+	#[cfg(feature = "async")]
+	pub async fn resolve(&self) -> io::Result<SocketAddrV4> {
+		match self {
+			Address::Direct(addr) => Ok(*addr),
+			Address::Host(host, port) => lookup_host((host.as_str(), *port))
+				.await?
+				.find_map(|addr| match addr {
+					SocketAddr::V4(addr) => Some(addr),
+					SocketAddr::V6(_) => None,
+				})
+				.ok_or_else(|| {
+					io::Error::new(
+						io::ErrorKind::NotFound,
+						format!("{host} has no IPv4 address"),
+					)
+				}),
+		}
+	}
+}
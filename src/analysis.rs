@@ -0,0 +1,121 @@
+//! Looking back over a finished game: each of our shots is replayed from the
+//! log and rated by the heatmap hard bots fire by, against the best shot we
+//! could have taken with what we knew at the time.
+
+use std::fmt;
+
+use crate::{
+	board::{Cell, CellState},
+	bot::Heatmap,
+	coord::Coord,
+	history::Move,
+	Game, Phase,
+};
+
+/// How one of our shots compares to the best one we could have taken.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Grade {
+	/// The turn the shot was taken in, counting both players' turns from 1.
+	pub turn: u32,
+	pub fired: Coord,
+	/// How likely the heatmap rated a ship at `fired`, from 0 to 1.
+	pub chance: f64,
+	/// The square the heatmap rated most likely, and how likely.
+	pub best: Coord,
+	pub best_chance: f64,
+}
+
+impl Grade {
+	/// Whether the shot was as good as any we could have taken.
+	#[must_use]
+	pub fn is_best(&self) -> bool {
+		self.chance >= self.best_chance
+	}
+}
+
+impl fmt::Display for Grade {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let percent = |chance: f64| (chance * 100.0).round();
+		if self.is_best() {
+			write!(
+				f,
+				"turn {}: you fired {}, the best shot at {}%",
+				self.turn,
+				self.fired,
+				percent(self.chance)
+			)
+		} else {
+			write!(
+				f,
+				"turn {}: you fired {}, best was {} with {}% vs {}%",
+				self.turn,
+				self.fired,
+				self.best,
+				percent(self.best_chance),
+				percent(self.chance)
+			)
+		}
+	}
+}
+
+/// Grades every shot we fired in `game`, in order. Shots fired when nothing
+/// was left that could be hiding anywhere aren't graded.
+#[must_use]
+pub fn review(game: &Game) -> Vec<Grade> {
+	let mut seen = Game::new(game.rules.clone(), game.you);
+	seen.phase = Phase::Playing;
+	let enemy = usize::from(!game.you);
+	let mut grades = Vec::new();
+	for step in &game.log {
+		match *step {
+			Move::Shot { by, pos, hit, sunk } => {
+				if by == game.you {
+					grades.extend(grade(&seen, pos));
+					let board = &mut seen.board[enemy];
+					if board.cell(pos).state != CellState::Hit {
+						board.board.insert(
+							pos,
+							Cell::shot(match hit {
+								Some(true) => CellState::Hit,
+								Some(false) => CellState::Miss,
+								None => CellState::Unknown,
+							}),
+						);
+					}
+					seen.sunk[enemy].extend(sunk);
+				} else {
+					seen.sunk[usize::from(game.you)].extend(sunk);
+				}
+				seen.end_shot();
+			}
+			Move::Submarine { .. } => seen.end_shot(),
+			Move::Place { .. } | Move::Scan { .. } => {}
+		}
+	}
+	grades
+}
+
+/// Rates a shot at `pos` by what `game` knew of the enemy's board.
+fn grade(game: &Game, pos: Coord) -> Option<Grade> {
+	let board = &game.board[usize::from(!game.you)];
+	let heat = Heatmap::of(game);
+	let untouched = (0..board.size())
+		.flat_map(|y| (0..board.size()).map(move |x| Coord::new(x, y)))
+		.filter(|&pos| board.is_water(pos) && board.cell(pos).state == CellState::Untouched)
+		.collect::<Vec<_>>();
+	let total = untouched
+		.iter()
+		.map(|&pos| f64::from(heat.at(pos)))
+		.sum::<f64>();
+	let best = *untouched.iter().max_by_key(|&&pos| heat.at(pos))?;
+	if total == 0.0 {
+		return None;
+	}
+	Some(Grade {
+		turn: game.turn_number,
+		fired: pos,
+		chance: f64::from(heat.at(pos)) / total,
+		best,
+		best_chance: f64::from(heat.at(best)) / total,
+	})
+}
@@ -0,0 +1,373 @@
+//! A JSON API over HTTP for playing from other programs, like a mobile or
+//! Electron shell, which can then leave the networking and the rules to us.
+//! `netbattleship serve-api` offers it to this computer only:
+//!
+//! - `POST /games` with `{"address": "192.168.1.5:7777", "serve": false}`
+//!   connects to a game, or hosts one at the address with `"serve": true`,
+//!   and answers with its `id` once the other player is there. `rules`,
+//!   `name` and `password` can be given too, as in the other frontends.
+//! - `GET /games/{id}` is how the game stands: its phase, whose turn it is
+//!   and both boards, a string per row.
+//! - `POST /games/{id}/place` with `{"ship": "Carrier", "pos": "A0",
+//!   "vertical": false}` places a ship.
+//! - `POST /games/{id}/fire` with `{"pos": "E5"}` fires, answering with what
+//!   the shot did.
+//! - `GET /games/{id}/events` streams the game's
+//!   [`Event`](crate::event::Event)s as server-sent events, each a JSON
+//!   object like those from `--json-events`.
+//! - `DELETE /games/{id}` resigns the game and forgets it.
+//!
+//! The enemy's turns are waited for in the background, so there's nothing
+//! to poll for them. Failures answer with `{"error": "..."}`.
+//!
+//! Requests have to be for `localhost`, `127.0.0.1` or `[::1]` at the API's
+//! port, and from a page there if they come from a browser at all. Anything
+//! else is turned away, so a web page can't get at the API by pointing a
+//! hostname of its own at this computer.
+
+use std::{
+	collections::BTreeMap,
+	convert::Infallible,
+	io,
+	net::SocketAddr,
+	sync::{Arc, Mutex},
+};
+
+use axum::{
+	extract::{Path, Request, State},
+	http::{header, HeaderMap, StatusCode},
+	middleware::{self, Next},
+	response::{
+		sse::{self, KeepAlive, Sse},
+		IntoResponse, Response,
+	},
+	routing::{get, post},
+	Json, Router,
+};
+use futures_util::{stream, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::{net::TcpListener, sync::broadcast::error::RecvError};
+
+use crate::{
+	address::{Address, AddressError},
+	board::Board,
+	coord::{Coord, ParseCoordError},
+	flow::{GameFlow, GameFlowError},
+	protocol::TurnResults,
+	rules::Rules,
+	ship::ShipKind,
+	Phase,
+};
+
+#[derive(Error, Debug)]
+pub enum ApiError {
+	#[error("there's no game {0}")]
+	NoSuchGame(u64),
+	#[error("bad address: {0}")]
+	Address(#[from] AddressError),
+	#[error("couldn't find that address: {0}")]
+	Resolve(io::Error),
+	#[error("bad square: {0}")]
+	Coord(#[from] ParseCoordError),
+	#[error("{0}")]
+	Game(#[from] GameFlowError),
+	#[error("only this computer can use the API")]
+	NotLocal,
+}
+
+impl IntoResponse for ApiError {
+	fn into_response(self) -> Response {
+		let status = match self {
+			ApiError::NoSuchGame(_) => StatusCode::NOT_FOUND,
+			ApiError::Address(_) | ApiError::Coord(_) => StatusCode::BAD_REQUEST,
+			ApiError::Resolve(_) => StatusCode::BAD_GATEWAY,
+			ApiError::Game(_) => StatusCode::CONFLICT,
+			ApiError::NotLocal => StatusCode::FORBIDDEN,
+		};
+		let error = self.to_string();
+		(status, Json(serde_json::json!({ "error": error }))).into_response()
+	}
+}
+
+/// What `POST /games` is sent.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NewGame {
+	/// Where to connect, or to host with `serve`: an address, hostname or
+	/// invite code.
+	address: String,
+	#[serde(default)]
+	serve: bool,
+	#[serde(default)]
+	rules: Rules,
+	name: Option<String>,
+	password: Option<String>,
+}
+
+/// What `POST /games` answers.
+#[derive(Serialize)]
+struct Created {
+	id: u64,
+	/// What the other player asked to be called, if anything.
+	enemy: Option<String>,
+}
+
+/// What `POST /games/{id}/place` is sent.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Place {
+	ship: ShipKind,
+	pos: String,
+	#[serde(default)]
+	vertical: bool,
+}
+
+/// What `POST /games/{id}/fire` is sent.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Fire {
+	pos: String,
+}
+
+/// What `POST /games/{id}/fire` answers.
+#[derive(Serialize)]
+struct Shot {
+	/// Whether it hit, or null when the blind rule hides it.
+	hit: Option<bool>,
+	sunk: Option<ShipKind>,
+	won: bool,
+}
+
+impl From<TurnResults> for Shot {
+	fn from(results: TurnResults) -> Self {
+		Shot {
+			hit: (!results.hidden).then_some(results.hit),
+			sunk: results.sunk,
+			won: results.won,
+		}
+	}
+}
+
+/// What `GET /games/{id}` answers.
+#[derive(Serialize)]
+struct View {
+	phase: Phase,
+	your_turn: bool,
+	turn: u32,
+	/// Our board and what we've seen of theirs, drawn like `--style grid`
+	/// draws them: a string per row, `.` for water and `#` for land.
+	yours: Vec<String>,
+	theirs: Vec<String>,
+	/// The ships each side has lost.
+	sunk: [Vec<ShipKind>; 2],
+}
+
+/// The games being played through the API, by id.
+#[derive(Clone, Default)]
+struct Games {
+	games: Arc<Mutex<BTreeMap<u64, Arc<GameFlow>>>>,
+	next: Arc<Mutex<u64>>,
+}
+
+impl Games {
+	fn get(&self, id: u64) -> Result<Arc<GameFlow>, ApiError> {
+		self.games
+			.lock()
+			.expect("nothing panics holding the games")
+			.get(&id)
+			.cloned()
+			.ok_or(ApiError::NoSuchGame(id))
+	}
+
+	fn add(&self, game: Arc<GameFlow>) -> u64 {
+		let mut next = self.next.lock().expect("nothing panics holding the ids");
+		*next += 1;
+		self.games
+			.lock()
+			.expect("nothing panics holding the games")
+			.insert(*next, game);
+		*next
+	}
+}
+
+/// The API, to serve or try out on `port` of this computer.
+pub fn router(port: u16) -> Router {
+	Router::new()
+		.route("/games", post(create))
+		.route("/games/:id", get(view).delete(resign))
+		.route("/games/:id/place", post(place))
+		.route("/games/:id/fire", post(fire))
+		.route("/games/:id/events", get(events))
+		.with_state(Games::default())
+		.layer(middleware::from_fn_with_state(port, local_only))
+}
+
+/// Serves the API on `listener` until it fails.
+///
+/// # Errors
+/// Fails if the listener does.
+pub async fn serve(listener: TcpListener) -> io::Result<()> {
+	let port = listener.local_addr()?.port();
+	axum::serve(listener, router(port)).await
+}
+
+/// Turns away requests that aren't for this computer at `port`, or that
+/// come from a page anywhere else.
+async fn local_only(
+	State(port): State<u16>,
+	request: Request,
+	next: Next,
+) -> Result<Response, ApiError> {
+	if !is_local(request.headers(), port) {
+		return Err(ApiError::NotLocal);
+	}
+	Ok(next.run(request).await)
+}
+
+/// Whether `headers` say the request is for this computer at `port`, and
+/// from a page here if it's from a page at all.
+fn is_local(headers: &HeaderMap, port: u16) -> bool {
+	let local = |host: &str| {
+		["localhost", "127.0.0.1", "[::1]"].iter().any(|name| {
+			host.strip_prefix(name)
+				.is_some_and(|rest| rest == format!(":{port}") || (rest.is_empty() && port == 80))
+		})
+	};
+	let header = |name| headers.get(name).map(|value| value.to_str().ok());
+	let host = matches!(header(header::HOST), Some(Some(host)) if local(host));
+	let origin = match header(header::ORIGIN) {
+		None => true,
+		Some(origin) => origin
+			.and_then(|origin| origin.strip_prefix("http://"))
+			.is_some_and(local),
+	};
+	host && origin
+}
+
+async fn create(
+	State(games): State<Games>,
+	Json(new): Json<NewGame>,
+) -> Result<Json<Created>, ApiError> {
+	let addr = new
+		.address
+		.parse::<Address>()?
+		.resolve()
+		.await
+		.map_err(ApiError::Resolve)?;
+	let mut options = GameFlow::builder().rules(new.rules);
+	if let Some(name) = new.name {
+		options = options.name(name);
+	}
+	if let Some(password) = new.password {
+		options = options.password(password);
+	}
+	let game = Arc::new(options.connect(SocketAddr::from(addr), new.serve).await?);
+	let enemy = game.enemy_name().map(String::from);
+	tokio::spawn(wait_on_enemy(game.clone()));
+	let id = games.add(game);
+	Ok(Json(Created { id, enemy }))
+}
+
+/// Takes the enemy's side of `game`: waits for their fleet once ours is
+/// placed, then for each of their turns, until the game is over.
+async fn wait_on_enemy(game: Arc<GameFlow>) {
+	let mut snapshots = game.subscribe();
+	let placed = snapshots
+		.wait_for(|game| !matches!(game.phase, Phase::Connecting | Phase::Placing(_)))
+		.await
+		.is_ok();
+	if !placed || game.wait_for_fleet().await.is_err() {
+		return;
+	}
+	// Our own turns come back as they're fired
+	let turns = game.turns();
+	futures_util::pin_mut!(turns);
+	while let Some(Ok(_)) = turns.next().await {}
+}
+
+async fn view(State(games): State<Games>, Path(id): Path<u64>) -> Result<Json<View>, ApiError> {
+	let game = games.get(id)?.snapshot();
+	let us = usize::from(game.you);
+	Ok(Json(View {
+		phase: game.phase.clone(),
+		your_turn: game.turn == game.you,
+		turn: game.turn_number,
+		yours: rows(&game.board[us], true),
+		theirs: rows(&game.board[1 - us], false),
+		sunk: [game.sunk[us].clone(), game.sunk[1 - us].clone()],
+	}))
+}
+
+/// `board` a string per row, with our ships on it if it's `own`.
+fn rows(board: &Board, own: bool) -> Vec<String> {
+	(0..board.size())
+		.map(|y| {
+			(0..board.size())
+				.map(|x| {
+					let pos = Coord::new(x, y);
+					match board.cell(pos).symbol(own) {
+						_ if board.land().contains(&pos) => '#',
+						' ' => '.',
+						symbol => symbol,
+					}
+				})
+				.collect()
+		})
+		.collect()
+}
+
+async fn place(
+	State(games): State<Games>,
+	Path(id): Path<u64>,
+	Json(place): Json<Place>,
+) -> Result<StatusCode, ApiError> {
+	let pos = place.pos.parse()?;
+	games
+		.get(id)?
+		.place_ship(place.ship, pos, place.vertical)
+		.await?;
+	Ok(StatusCode::NO_CONTENT)
+}
+
+async fn fire(
+	State(games): State<Games>,
+	Path(id): Path<u64>,
+	Json(fire): Json<Fire>,
+) -> Result<Json<Shot>, ApiError> {
+	let pos = fire.pos.parse()?;
+	let results = games.get(id)?.fire(pos).await?;
+	Ok(Json(results.into()))
+}
+
+async fn resign(State(games): State<Games>, Path(id): Path<u64>) -> Result<StatusCode, ApiError> {
+	let game = games.get(id)?;
+	games
+		.games
+		.lock()
+		.expect("nothing panics holding the games")
+		.remove(&id);
+	game.resign().await?;
+	Ok(StatusCode::NO_CONTENT)
+}
+
+async fn events(
+	State(games): State<Games>,
+	Path(id): Path<u64>,
+) -> Result<Sse<impl Stream<Item = Result<sse::Event, Infallible>>>, ApiError> {
+	let events = games.get(id)?.events();
+	let stream = stream::unfold(events, |mut events| async move {
+		loop {
+			match events.recv().await {
+				Ok(event) => {
+					let data = String::from(event);
+					return Some((Ok(sse::Event::default().data(data)), events));
+				}
+				// A slow reader misses some, but can look at the game
+				Err(RecvError::Lagged(_)) => {}
+				Err(RecvError::Closed) => return None,
+			}
+		}
+	});
+	Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
@@ -0,0 +1,238 @@
+//! A record of every game played on this computer, kept only here, in a
+//! database under the user's config directory. Each finished game is stored
+//! with who it was against, how it went and where its replay was saved, for
+//! `netbattleship history` to look back through.
+
+use std::{
+	cmp::Ordering,
+	fmt::{self, Display, Formatter},
+	path::{Path, PathBuf},
+	thread,
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{config::Outcome, puzzle, rules::Rules};
+
+/// How many times to try opening the database while another copy of the game
+/// has it, a little apart, before giving up.
+const OPEN_ATTEMPTS: u32 = 20;
+const OPEN_RETRY: Duration = Duration::from_millis(50);
+
+#[derive(Error, Debug)]
+pub enum ArchiveError {
+	#[error("there's no config directory on this system")]
+	NoConfigDir,
+	#[error("couldn't use the game history: {0}")]
+	Db(#[from] sled::Error),
+	#[error("a game in the history couldn't be read: {0}")]
+	Encoding(#[from] serde_cbor::Error),
+}
+
+/// One finished game.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+	/// When the game ended, in seconds since the Unix epoch.
+	pub ended: u64,
+	/// What the enemy was called, which may be empty.
+	pub opponent: String,
+	pub outcome: Outcome,
+	pub rules: Rules,
+	/// How many turns were played.
+	pub turns: u32,
+	/// Where the game's replay was saved, if it was.
+	pub replay: Option<PathBuf>,
+}
+
+impl Entry {
+	/// A game against `opponent` by `rules` that just ended in `outcome`
+	/// after `turns` turns.
+	#[must_use]
+	pub fn new(opponent: String, outcome: Outcome, rules: Rules, turns: u32) -> Entry {
+		Entry {
+			ended: SystemTime::now()
+				.duration_since(UNIX_EPOCH)
+				.map_or(0, |since| since.as_secs()),
+			opponent,
+			outcome,
+			rules,
+			turns,
+			replay: None,
+		}
+	}
+}
+
+/// Which games to look back at. Everything left unset matches any game.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Filter {
+	/// Only games against someone called this, ignoring case.
+	pub opponent: Option<String>,
+	/// Only games that were won, or only ones that were lost or resigned.
+	pub won: Option<bool>,
+	/// Only games that ended on a day written starting like this, such as
+	/// `2024-03` for March 2024 or `2024-03-14` for one day of it, in UTC.
+	pub date: Option<String>,
+}
+
+impl Filter {
+	/// Whether `entry` is one of the games asked for.
+	#[must_use]
+	pub fn matches(&self, entry: &Entry) -> bool {
+		self.opponent
+			.as_ref()
+			.is_none_or(|name| name.eq_ignore_ascii_case(&entry.opponent))
+			&& self
+				.won
+				.is_none_or(|won| won == (entry.outcome == Outcome::Won))
+			&& self
+				.date
+				.as_ref()
+				.is_none_or(|date| puzzle::day(entry.ended).starts_with(date.as_str()))
+	}
+}
+
+/// How all our games against one opponent have gone.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Record {
+	pub opponent: String,
+	pub won: u32,
+	/// Games lost or resigned.
+	pub lost: u32,
+}
+
+impl Record {
+	/// Whether any games against them have finished.
+	#[must_use]
+	pub fn played(&self) -> bool {
+		self.won + self.lost > 0
+	}
+}
+
+impl Display for Record {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		let Record {
+			opponent,
+			won,
+			lost,
+		} = self;
+		match won.cmp(lost) {
+			Ordering::Greater => write!(f, "You lead {opponent} {won}\u{2013}{lost}"),
+			Ordering::Less => write!(f, "{opponent} leads you {lost}\u{2013}{won}"),
+			Ordering::Equal => write!(f, "You and {opponent} are level at {won}\u{2013}{lost}"),
+		}
+	}
+}
+
+/// The database of finished games.
+pub struct Archive {
+	db: sled::Db,
+}
+
+impl Archive {
+	/// Where the database lives, if the system has a config directory.
+	#[must_use]
+	pub fn path() -> Option<PathBuf> {
+		Some(dirs::config_dir()?.join("netbattleship").join("history"))
+	}
+
+	/// Opens the database under the config directory, creating it if it
+	/// isn't there yet.
+	///
+	/// # Errors
+	/// Fails if there's no config directory, or the database can't be opened.
+	pub fn open() -> Result<Archive, ArchiveError> {
+		Self::open_at(&Self::path().ok_or(ArchiveError::NoConfigDir)?)
+	}
+
+	/// Opens the database at `path`, creating it if it isn't there yet. Both
+	/// players on one computer finish at once, so this waits a moment for
+	/// the other to be done with it.
+	///
+	/// # Errors
+	/// Fails if the database can't be opened.
+	pub fn open_at(path: &Path) -> Result<Archive, ArchiveError> {
+		let mut tries = 1;
+		loop {
+			match sled::open(path) {
+				Ok(db) => return Ok(Archive { db }),
+				Err(sled::Error::Io(_)) if tries < OPEN_ATTEMPTS => {
+					tries += 1;
+					thread::sleep(OPEN_RETRY);
+				}
+				Err(e) => return Err(e.into()),
+			}
+		}
+	}
+
+	/// Keeps `entry`, returning the number it's kept under. Later games
+	/// always get higher numbers.
+	///
+	/// # Errors
+	/// Fails if the database can't be written.
+	pub fn add(&self, entry: &Entry) -> Result<u64, ArchiveError> {
+		let id = self.db.generate_id()?;
+		self.db
+			.insert(id.to_be_bytes(), serde_cbor::to_vec(entry)?)?;
+		self.db.flush()?;
+		Ok(id)
+	}
+
+	/// Every game `filter` matches, oldest first, with the number each is
+	/// kept under.
+	///
+	/// # Errors
+	/// Fails if the database can't be read.
+	pub fn find(&self, filter: &Filter) -> Result<Vec<(u64, Entry)>, ArchiveError> {
+		let mut found = Vec::new();
+		for kept in self.db.iter() {
+			let (key, value) = kept?;
+			let Ok(key) = <[u8; 8]>::try_from(&*key) else {
+				continue;
+			};
+			let entry: Entry = serde_cbor::from_slice(&value)?;
+			if filter.matches(&entry) {
+				found.push((u64::from_be_bytes(key), entry));
+			}
+		}
+		Ok(found)
+	}
+
+	/// How our games against `opponent` have gone, ignoring case in their
+	/// name.
+	///
+	/// # Errors
+	/// Fails if the database can't be read.
+	pub fn against(&self, opponent: &str) -> Result<Record, ArchiveError> {
+		let filter = Filter {
+			opponent: Some(opponent.to_string()),
+			..Filter::default()
+		};
+		let mut record = Record {
+			opponent: opponent.to_string(),
+			..Record::default()
+		};
+		for (_, entry) in self.find(&filter)? {
+			if entry.outcome == Outcome::Won {
+				record.won += 1;
+			} else {
+				record.lost += 1;
+			}
+		}
+		Ok(record)
+	}
+}
+
+/// How our games against `opponent` have gone, if they have a name and
+/// we've finished a game against them before.
+///
+/// # Errors
+/// Fails if the history can't be opened or read.
+pub fn head_to_head(opponent: &str) -> Result<Option<Record>, ArchiveError> {
+	if opponent.is_empty() {
+		return Ok(None);
+	}
+	let record = Archive::open()?.against(opponent)?;
+	Ok(record.played().then_some(record))
+}
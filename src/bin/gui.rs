@@ -1,56 +1,299 @@
-use std::{net::SocketAddrV4, sync::Arc, time::Duration};
+use std::{
+	future::Future,
+	net::{SocketAddr, SocketAddrV4},
+	path::Path,
+	sync::Arc,
+	time::{Duration, Instant},
+};
 
 use eframe::{
-	egui::{self, Button, Ui},
-	epaint::{Color32, Vec2},
+	egui::{
+		self, Align2, Button, FontId, Key, Rect, Response, RichText, Sense, Slider, Ui, Visuals,
+		WidgetInfo, WidgetType,
+	},
+	epaint::{Color32, Shape, Stroke, Vec2},
 	NativeOptions,
 };
 use netbattleship::{
-	flow::{GameFlow, TurnResults},
-	ship::Ship,
+	address::Address,
+	analysis::{self, Grade},
+	archive::{self, Archive, Entry, Record},
+	board::{Board, Cell, CellState, Topology},
+	config::{Config, Friend, Opponent, Outcome},
+	coord::{Coord, Notation},
+	flow::{
+		GameFlow, GameFlowBuilder, GameFlowError, Host, TurnResults, AIMING_IDLE, AIMING_INTERVAL,
+	},
+	hooks,
+	invite::{self, Invite},
+	layouts,
+	link::{Latency, Quality, PING_INTERVAL},
+	lobby::{self, Identity, Invitation, Listing, Presence},
+	nat::NatError,
+	render::image,
+	rules::Rules,
+	save::{Save, AUTOSAVE},
+	ship::ShipKind,
+	ui::error::{self, Failure, Recovery, Session},
 	Phase,
 };
+use structopt::StructOpt;
 use tokio::{runtime::Runtime, spawn, sync::RwLock, task::JoinHandle};
 
+#[derive(StructOpt)]
+struct Args {
+	/// Pick up the game left in the save slot after a crash or disconnect.
+	/// The other player has to continue too.
+	#[structopt(long = "continue")]
+	pub resume: bool,
+	/// The save slot to autosave to and continue from.
+	#[structopt(long, default_value = "autosave")]
+	pub slot: String,
+}
+
 fn main() {
+	let args = Args::from_args();
+	let mut app = App {
+		slot: args.slot,
+		..App::default()
+	};
+	let options = NativeOptions {
+		initial_window_size: app
+			.config
+			.window
+			.map(|[w, h]| Vec2::new(w.into(), h.into())),
+		..NativeOptions::default()
+	};
 	eframe::run_native(
 		"netbattleship",
-		NativeOptions::default(),
-		Box::new(|_| Box::new(App::default())),
+		options,
+		Box::new(move |cc| {
+			match app.config.dark {
+				Some(true) => cc.egui_ctx.set_visuals(Visuals::dark()),
+				Some(false) => cc.egui_ctx.set_visuals(Visuals::light()),
+				None => {}
+			}
+			if args.resume {
+				app.resume(&cc.egui_ctx);
+			}
+			Box::new(app)
+		}),
 	);
 }
 
+/// A background task, which fails if something breaks the game.
+type Task = JoinHandle<Result<(), GameFlowError>>;
+
+/// The games open in a lobby, or why they couldn't be listed.
+type Games = Result<Vec<Listing>, String>;
+
+/// Runs `task` in the background, repainting once it's done so whatever it
+/// changed shows up.
+fn background(
+	ctx: &egui::Context,
+	task: impl Future<Output = Result<(), GameFlowError>> + Send + 'static,
+) -> Task {
+	let ctx = ctx.clone();
+	spawn(async move {
+		let result = task.await;
+		ctx.request_repaint();
+		result
+	})
+}
+
+/// Checks our game against the enemy's copy, saying what had to be fixed.
+async fn resync(game: &GameFlow) -> Result<String, GameFlowError> {
+	Ok(match game.verify_sync().await? {
+		0 => "Your game matches your enemy's.".to_string(),
+		1 => "Fixed 1 thing that had drifted from your enemy's game.".to_string(),
+		n => format!("Fixed {n} things that had drifted from your enemy's game."),
+	})
+}
+
 pub struct App {
 	game: Arc<RwLock<Option<GameFlow>>>,
 	msg: Arc<RwLock<Vec<String>>>,
+	/// The invite to show as a QR code while we wait for a challenger.
+	qr: Arc<RwLock<Option<Invite>>>,
 	addr: String,
+	/// What to call the opponent in the recent list.
+	name: String,
+	/// The password both players have to give, which may be empty.
+	password: String,
 	serve: bool,
-	task: Option<JoinHandle<()>>,
+	config: Config,
+	/// The save slot games are autosaved to.
+	slot: String,
+	/// Where the game is being played, to save or reconnect it.
+	session: Arc<RwLock<Option<Session>>>,
+	/// Where to save a picture of the finished battle, as .png or .svg.
+	map: String,
+	/// What broke the game, while the player decides what to do about it.
+	failure: Option<Failure>,
+	/// The games open in the lobby when it was last asked, or why it
+	/// couldn't be.
+	games: Arc<RwLock<Option<Games>>>,
+	/// Keeps us online at the lobby, to be invited to games.
+	online: Option<JoinHandle<()>>,
+	/// Who the lobby knows us as, while we're online there.
+	me: Arc<RwLock<Option<Identity>>>,
+	/// Invitations that haven't been accepted or declined yet.
+	invitations: Arc<RwLock<Vec<Invitation>>>,
+	/// The friend being typed in, until they're added to the list.
+	new_friend: Friend,
+	task: Option<Task>,
+	/// Repaints whenever the game changes, once there is a game.
+	watcher: Option<JoinHandle<()>>,
 	runtime: Arc<Runtime>,
-	last_result: Arc<RwLock<Option<TurnResults>>>,
+	/// The most recent shot, and whether it landed on the enemy's board.
+	last_result: Arc<RwLock<Option<(bool, TurnResults)>>>,
 	vertical: bool,
+	/// The enemy's turn being waited out, and when it began.
+	waiting: Option<(u32, Instant)>,
+	/// The layout picked to place the rest of the fleet like.
+	layout: String,
+	/// The name to keep the fleet's layout under.
+	layout_name: String,
+	hovered: Option<(bool, Coord)>,
+	/// The square picked out with the arrow keys, for playing without a mouse.
+	cursor: Option<(bool, Coord)>,
+	/// Whether one of the board's squares had keyboard focus last frame, so
+	/// the arrow keys aren't stolen from text boxes.
+	board_focused: bool,
+	/// The scale on the slider, in percent, which is only applied once it's
+	/// let go of so the slider doesn't jump around under the pointer.
+	scale: u16,
+	/// Whether the screen has been touched, to make the squares big enough
+	/// for fingers.
+	touch: bool,
+	/// Whether the press still held down has already turned the ship, so
+	/// letting go doesn't count as a click too.
+	long_pressed: bool,
+	/// Whether the next click on the enemy's board is a radar sweep rather than a shot.
+	radar: bool,
+	/// Whether the next click on our own board moves the submarine there.
+	moving: bool,
+	/// How each of our shots rated, while the review is open after the game.
+	review: Option<Vec<Grade>>,
+	/// How our games against each of the recent opponents have gone, for
+	/// those we've finished one against.
+	records: Vec<Record>,
+	/// Whether the finished game has been kept in the history yet.
+	recorded: bool,
+	/// Whether to show how the game is going as our Discord status.
+	#[cfg(feature = "discord")]
+	discord: bool,
+	#[cfg(feature = "discord")]
+	presence: Option<netbattleship::presence::Presence>,
+	/// Reads announcements aloud, once it's been asked to.
+	#[cfg(feature = "speech")]
+	voice: Option<netbattleship::ui::speech::Voice>,
+	/// How many of the messages have been seen by [`App::announce`].
+	#[cfg(feature = "speech")]
+	spoken: usize,
 }
 
+const PREVIEW_FITS: Color32 = Color32::from_rgb(0, 120, 0);
+const PREVIEW_BLOCKED: Color32 = Color32::from_rgb(160, 0, 0);
+const CONFLICT: Color32 = Color32::from_rgb(255, 90, 0);
+const LAST_SHOT: Color32 = Color32::from_rgb(255, 200, 0);
+const UNKNOWN_SHOT: Color32 = Color32::from_rgb(70, 70, 90);
+const ENEMY_MISS: Color32 = Color32::from_rgb(30, 60, 110);
+const DAMAGED: Color32 = Color32::from_rgb(200, 40, 40);
+const LAND: Color32 = Color32::from_rgb(110, 90, 50);
+/// The smallest a board square gets, in points, for mice and for fingers.
+const MIN_CELL: f32 = 20.0;
+const MIN_TOUCH_CELL: f32 = 36.0;
+/// The biggest a board square gets, however much room there is.
+const MAX_CELL: f32 = 64.0;
+/// How long to hold a square on our board to turn the ship, in seconds.
+/// Anything over egui's 0.6 second limit doesn't count as a click anyway.
+const LONG_PRESS: f64 = 0.5;
+/// The range of the UI scale slider, in percent.
+const SCALES: std::ops::RangeInclusive<u16> = 50..=300;
+
+const GOOD_LINK: Color32 = Color32::from_rgb(60, 170, 60);
+const FAIR_LINK: Color32 = Color32::from_rgb(220, 170, 0);
+const POOR_LINK: Color32 = Color32::from_rgb(220, 50, 50);
+
 impl Default for App {
 	fn default() -> Self {
+		let config = Config::load().unwrap_or_default();
+		let records = records(&config.recent);
 		Self {
 			game: Default::default(),
 			msg: Default::default(),
-			addr: Default::default(),
+			qr: Default::default(),
+			addr: config.address.clone(),
+			name: Default::default(),
+			password: Default::default(),
 			serve: Default::default(),
+			config,
+			slot: AUTOSAVE.to_string(),
+			map: "battle.png".to_string(),
+			session: Default::default(),
+			failure: None,
+			games: Default::default(),
+			online: None,
+			me: Default::default(),
+			invitations: Default::default(),
+			new_friend: Friend::default(),
 			task: Default::default(),
+			watcher: None,
 			last_result: Default::default(),
 			vertical: false,
+			waiting: None,
+			layout: layouts::PRESETS[0].to_string(),
+			layout_name: Default::default(),
+			hovered: None,
+			cursor: None,
+			board_focused: false,
+			scale: 100,
+			touch: false,
+			long_pressed: false,
+			radar: false,
+			moving: false,
+			review: None,
+			records,
+			recorded: false,
+			#[cfg(feature = "discord")]
+			discord: true,
+			#[cfg(feature = "discord")]
+			presence: None,
+			#[cfg(feature = "speech")]
+			voice: None,
+			#[cfg(feature = "speech")]
+			spoken: 0,
 			runtime: Arc::new(Runtime::new().expect("Failed to open runtime!")),
 		}
 	}
 }
 
 impl eframe::App for App {
-	fn update(&mut self, ctx: &eframe::egui::Context, _frame: &mut eframe::Frame) {
-		ctx.request_repaint_after(Duration::from_secs(1));
+	/// Keeps the window's size for next time, leaving the rest of the config
+	/// file as it is now.
+	fn on_close_event(&mut self) -> bool {
+		let mut config = Config::load().unwrap_or_else(|_| self.config.clone());
+		config.window = self.config.window;
+		if let Err(e) = config.save() {
+			eprintln!("Couldn't save the window size: {}", e);
+		}
+		true
+	}
+
+	fn update(&mut self, ctx: &eframe::egui::Context, frame: &mut eframe::Frame) {
+		self.watch(ctx);
+		self.rescale(ctx, frame);
+		self.measure(ctx, frame);
+		#[cfg(feature = "speech")]
+		self.announce();
 
 		egui::TopBottomPanel::bottom("messages").show(ctx, |ui| {
+			if let Some(invite) = *self.runtime.block_on(self.qr.read()) {
+				ui.label(format!("Scan to join ({}):", invite.link()));
+				qr_code(ui, invite);
+				ui.separator();
+			}
 			for msg in self
 				.runtime
 				.block_on(self.msg.read())
@@ -65,9 +308,25 @@ impl eframe::App for App {
 		});
 
 		egui::TopBottomPanel::top("settings").show(ctx, |ui| {
-			egui::widgets::global_dark_light_mode_switch(ui);
+			ui.horizontal(|ui| {
+				self.theme_settings(ui);
+				self.scale_settings(ui);
+				self.notation_settings(ui);
+				#[cfg(feature = "discord")]
+				self.discord_settings(ui);
+				#[cfg(feature = "speech")]
+				self.speech_settings(ui);
+				self.runtime.block_on(self.latency(ui));
+				self.runtime.block_on(self.clocks(ui));
+				self.leave_button(ui, frame);
+			});
 		});
 
+		if self.failure.is_some() {
+			self.recovery(ctx, frame);
+			return;
+		}
+
 		egui::CentralPanel::default().show(ctx, |ui| {
 			// Clone the runtime for borrow checker reasons
 			let runtime = self.runtime.clone();
@@ -90,124 +349,1217 @@ impl eframe::App for App {
 						ui.label("Connecting...");
 					}
 					Phase::Placing(ship) => runtime.block_on(self.placing(ui, clicked, ship)),
+					Phase::Ready => self.ready(ui),
 					Phase::Playing => runtime.block_on(self.playing(ui, clicked)),
 					Phase::Done(won) => {
+						if !self.recorded {
+							self.record(won);
+						}
 						if won {
 							ui.heading("You won!");
 						} else {
 							ui.heading("You lost...");
 						}
+						ui.horizontal(|ui| {
+							ui.text_edit_singleline(&mut self.map);
+							if ui.button("Save battle map").clicked() {
+								self.export();
+							}
+						});
+						if ui.button("Review your shots").clicked() {
+							self.review = runtime
+								.block_on(self.game.read())
+								.as_ref()
+								.map(|game| analysis::review(&game.subscribe().borrow()));
+						}
 						if ui.button("Quit the game").clicked() {
-							panic!("User requested exit, not a bug.")
+							frame.close();
 						}
+						self.review_window(ui.ctx());
 					}
+					// Nothing to show for phases this version doesn't know
+					_ => {}
 				},
 			}
 		});
 		// Update Futures
 		if self.task.is_some() && self.task.as_ref().map(|t| t.is_finished()).unwrap_or(false) {
-			self.runtime
-				.block_on(self.task.take().unwrap())
-				.expect("Background Task Panicked");
+			#[cfg(feature = "discord")]
+			let reconnecting = self.failure.is_some();
+			self.failure = match self.runtime.block_on(self.task.take().unwrap()) {
+				Ok(Ok(())) => None,
+				// The game's over, and shows as won
+				Ok(Err(e @ GameFlowError::Resigned)) => {
+					let message = Failure::from(e).to_string();
+					self.runtime.block_on(self.msg.write()).push(message);
+					None
+				}
+				Ok(Err(e)) => Some(e.into()),
+				Err(e) => Some(e.into()),
+			};
+			// A reconnected game is a new one, so report on that instead
+			#[cfg(feature = "discord")]
+			if reconnecting && self.failure.is_none() {
+				self.presence = None;
+			}
 		}
 	}
 }
 
 impl App {
+	/// Shows how each of our shots compared to the best we could have taken,
+	/// over the boards, until it's closed.
+	fn review_window(&mut self, ctx: &egui::Context) {
+		let Some(grades) = &self.review else { return };
+		let mut open = true;
+		egui::Window::new("Shot review")
+			.open(&mut open)
+			.show(ctx, |ui| {
+				let best = grades.iter().filter(|grade| grade.is_best()).count();
+				ui.label(format!(
+					"{best} of your {} shots were the best you could have taken.",
+					grades.len()
+				));
+				ui.separator();
+				egui::ScrollArea::vertical().show(ui, |ui| {
+					for grade in grades {
+						if grade.is_best() {
+							ui.label(grade.to_string());
+						} else {
+							ui.colored_label(LAST_SHOT, grade.to_string());
+						}
+					}
+				});
+			});
+		if !open {
+			self.review = None;
+		}
+	}
+
 	pub async fn setup(&mut self, ui: &mut Ui) {
 		if self.game.read().await.is_none() && self.task.is_none() {
-			ui.label("Socket Address");
+			self.lobby_browser(ui).await;
+			if !self.config.recent.is_empty() {
+				ui.label("Recent opponents");
+				for opponent in &self.config.recent {
+					let mut label = if opponent.name.is_empty() {
+						opponent.address.clone()
+					} else {
+						format!("{} ({})", opponent.name, opponent.address)
+					};
+					let record = self
+						.records
+						.iter()
+						.find(|record| record.opponent == opponent.name);
+					if let Some(record) = record {
+						label = format!("{} \u{2014} {}", label, record);
+					}
+					if ui.link(label).clicked() {
+						self.addr = opponent.address.clone();
+						self.name = opponent.name.clone();
+						self.serve = false;
+					}
+				}
+				ui.separator();
+			}
+			ui.label("Address, hostname or invite code");
 			ui.text_edit_singleline(&mut self.addr);
-			ui.checkbox(&mut self.serve, "Hosting?");
-			if let Ok(addr) = self.addr.parse::<SocketAddrV4>() {
-				if ui.button("Go!").clicked() {
-					let addr = addr;
-					let serve = self.serve;
-					let game = self.game.clone();
-					let msg = self.msg.clone();
-					self.task = Some(spawn(async move {
-						if serve {
-							msg.write()
-								.await
-								.push(format!("Waiting for a challenger on {}...", addr))
+			ui.checkbox(
+				&mut self.serve,
+				"Hosting? (Leave the address empty to pick a free port.)",
+			);
+			if !self.serve {
+				ui.label("Opponent's name (optional)");
+				ui.text_edit_singleline(&mut self.name);
+			}
+			ui.label("Password (optional, and both players need the same one)");
+			ui.add(egui::TextEdit::singleline(&mut self.password).password(true));
+			let addr = if self.serve && self.addr.trim().is_empty() {
+				Ok(None)
+			} else {
+				self.addr.parse::<Address>().map(Some)
+			};
+			match addr {
+				Ok(addr) => {
+					if ui.button("Go!").clicked() {
+						self.connect(ui.ctx(), addr, None, None).await;
+					}
+				}
+				Err(e) if !self.addr.trim().is_empty() => {
+					ui.colored_label(Color32::from_rgb(255, 0, 0), e.to_string());
+				}
+				Err(_) => {}
+			}
+		}
+	}
+
+	/// The games waiting in the lobby, each with a button to join it, and a
+	/// button to host one of our own there.
+	async fn lobby_browser(&mut self, ui: &mut Ui) {
+		ui.horizontal(|ui| {
+			ui.label("Lobby server");
+			ui.text_edit_singleline(&mut self.config.lobby);
+		});
+		let lobby = match self.config.lobby.trim() {
+			"" => {
+				ui.separator();
+				return;
+			}
+			lobby => lobby.parse::<Address>(),
+		};
+		let lobby = match lobby {
+			Ok(lobby) => lobby,
+			Err(e) => {
+				ui.colored_label(Color32::from_rgb(255, 0, 0), e.to_string());
+				ui.separator();
+				return;
+			}
+		};
+		ui.horizontal(|ui| {
+			ui.label("Your name");
+			ui.text_edit_singleline(&mut self.config.name);
+		});
+		let (refresh, host) = ui
+			.horizontal(|ui| {
+				(
+					ui.button("Refresh").clicked(),
+					ui.button("Host new game").clicked(),
+				)
+			})
+			.inner;
+		let mut picked = None;
+		match &*self.games.read().await {
+			None => {
+				ui.label("Refresh to see who's waiting for a challenger.");
+			}
+			Some(Err(e)) => {
+				ui.colored_label(Color32::from_rgb(255, 0, 0), e);
+			}
+			Some(Ok(games)) if games.is_empty() => {
+				ui.label("Nobody is waiting for a challenger right now.");
+			}
+			Some(Ok(games)) => {
+				egui::Grid::new("lobby").striped(true).show(ui, |ui| {
+					for heading in ["Host", "Rules", "Latency", ""] {
+						ui.strong(heading);
+					}
+					ui.end_row();
+					for game in games {
+						ui.label(if game.name.is_empty() {
+							game.host.to_string()
 						} else {
-							msg.write().await.push(format!("Connecting to {}...", addr))
+							game.name.clone()
+						});
+						ui.label(game.rules.to_string());
+						ui.label(format!("{} ms", game.latency.as_millis()));
+						if ui.button("Join").clicked() {
+							picked = Some(game.clone());
 						}
-						let new_game = GameFlow::new(std::net::SocketAddr::V4(addr), serve).await;
+						ui.end_row();
+					}
+				});
+			}
+		}
+		ui.separator();
+		if refresh {
+			if let Err(e) = self.config.save() {
+				self.msg
+					.write()
+					.await
+					.push(format!("Couldn't save the lobby server: {}", e));
+			}
+			self.browse(ui.ctx(), lobby.clone());
+		}
+		if refresh || self.online.is_none() {
+			self.go_online(ui.ctx(), lobby.clone());
+		}
+		if host {
+			self.serve = true;
+			self.connect(ui.ctx(), None, Some(lobby), None).await;
+		} else if let Some(game) = picked {
+			self.addr = game.host.to_string();
+			self.name = game.name;
+			self.serve = false;
+			self.connect(
+				ui.ctx(),
+				Some(Address::Direct(game.host)),
+				Some(lobby),
+				None,
+			)
+			.await;
+		} else {
+			self.friends(ui, &lobby).await;
+			self.invitations_window(ui.ctx(), lobby).await;
+		}
+	}
 
-						match new_game {
-							Ok(new_game) => {
-								msg.write().await.push("Connected!".to_string());
-								*game.write().await = Some(new_game)
-							}
-							Err(e) => msg.write().await.push(format!("{}", e)),
-						}
-					}))
+	/// Our friend code, and the friends we can invite to a game of our own.
+	async fn friends(&mut self, ui: &mut Ui, lobby: &Address) {
+		let me = self.me.read().await.clone();
+		match &me {
+			Some(me) => {
+				ui.horizontal(|ui| {
+					ui.label("Your friend code");
+					ui.monospace(&me.code);
+				});
+				// The lobby may have handed out a new one
+				if self.config.identity.as_ref() != Some(me) {
+					self.config.identity = Some(me.clone());
+				}
+			}
+			None => {
+				ui.label("Not online at the lobby, so friends can't invite you.");
+			}
+		}
+		let mut invited = None;
+		let mut removed = None;
+		if !self.config.friends.is_empty() {
+			egui::Grid::new("friends").striped(true).show(ui, |ui| {
+				for (i, friend) in self.config.friends.iter().enumerate() {
+					ui.label(if friend.name.is_empty() {
+						&friend.code
+					} else {
+						&friend.name
+					});
+					ui.monospace(&friend.code);
+					if ui
+						.add_enabled(me.is_some(), Button::new("Invite"))
+						.clicked()
+					{
+						invited = Some(friend.code.clone());
+					}
+					if ui.button("Remove").clicked() {
+						removed = Some(i);
+					}
+					ui.end_row();
 				}
+			});
+		}
+		let added = ui
+			.horizontal(|ui| {
+				ui.label("Friend code");
+				ui.text_edit_singleline(&mut self.new_friend.code);
+				ui.label("Name");
+				ui.text_edit_singleline(&mut self.new_friend.name);
+				ui.add_enabled(
+					!self.new_friend.code.trim().is_empty(),
+					Button::new("Add friend"),
+				)
+				.clicked()
+			})
+			.inner;
+		ui.separator();
+		if added {
+			let mut friend = std::mem::take(&mut self.new_friend);
+			friend.code = lobby::friend_code(&friend.code);
+			friend.name = friend.name.trim().to_string();
+			self.config.friends.retain(|f| f.code != friend.code);
+			self.config.friends.push(friend);
+		} else if let Some(i) = removed {
+			self.config.friends.remove(i);
+		}
+		if added || removed.is_some() {
+			if let Err(e) = self.config.save() {
+				self.msg
+					.write()
+					.await
+					.push(format!("Couldn't save your friends: {}", e));
+			}
+		}
+		if let Some(friend) = invited {
+			self.serve = true;
+			self.connect(ui.ctx(), None, Some(lobby.clone()), Some(friend))
+				.await;
+		}
+	}
+
+	/// Asks whether to join each game we've been invited to, in turn.
+	async fn invitations_window(&mut self, ctx: &egui::Context, lobby: Address) {
+		let Some(invitation) = self.invitations.read().await.first().cloned() else {
+			return;
+		};
+		let from = if invitation.name.is_empty() {
+			invitation.from.clone()
+		} else {
+			format!("{} ({})", invitation.name, invitation.from)
+		};
+		let (accept, decline) = egui::Window::new("Invitation")
+			.collapsible(false)
+			.anchor(Align2::CENTER_CENTER, Vec2::ZERO)
+			.show(ctx, |ui| {
+				ui.label(format!("{from} invites you to a game."));
+				ui.label(invitation.rules.to_string());
+				ui.horizontal(|ui| {
+					(
+						ui.button("Accept").clicked(),
+						ui.button("Decline").clicked(),
+					)
+				})
+				.inner
+			})
+			.and_then(|window| window.inner)
+			.unwrap_or_default();
+		if accept || decline {
+			self.invitations.write().await.remove(0);
+		}
+		if accept {
+			self.addr = invitation.game.to_string();
+			self.name = invitation.name;
+			self.serve = false;
+			self.connect(
+				ctx,
+				Some(Address::Direct(invitation.game)),
+				Some(lobby),
+				None,
+			)
+			.await;
+		}
+	}
+
+	/// Goes online at `lobby` in the background, instead of wherever we were,
+	/// so friends can invite us to their games.
+	fn go_online(&mut self, ctx: &egui::Context, lobby: Address) {
+		if let Some(online) = self.online.take() {
+			online.abort();
+		}
+		let identity = self.config.identity.clone();
+		let me = self.me.clone();
+		let invitations = self.invitations.clone();
+		let msg = self.msg.clone();
+		let repaint = ctx.clone();
+		self.online = Some(spawn(async move {
+			let presence = match lobby.resolve().await {
+				Ok(lobby) => Presence::new(lobby.into(), identity)
+					.await
+					.map_err(|e| e.to_string()),
+				Err(e) => Err(format!("couldn't find the lobby: {}", e)),
+			};
+			let mut presence = match presence {
+				Ok(presence) => presence,
+				Err(e) => {
+					*me.write().await = None;
+					msg.write()
+						.await
+						.push(format!("Couldn't go online for invitations: {}", e));
+					repaint.request_repaint();
+					return;
+				}
+			};
+			if let Err(e) = Config::record_identity(presence.identity()) {
+				msg.write()
+					.await
+					.push(format!("Couldn't save your friend code: {}", e));
+			}
+			*me.write().await = Some(presence.identity().clone());
+			repaint.request_repaint();
+			while let Ok(invitation) = presence.invitation().await {
+				invitations.write().await.push(invitation);
+				repaint.request_repaint();
+			}
+			*me.write().await = None;
+			repaint.request_repaint();
+		}));
+	}
+
+	/// Asks `lobby` for its open games in the background, for
+	/// [`App::lobby_browser`] to list.
+	fn browse(&self, ctx: &egui::Context, lobby: Address) {
+		let games = self.games.clone();
+		let repaint = ctx.clone();
+		spawn(async move {
+			let listed = match lobby.resolve().await {
+				Ok(lobby) => lobby::browse(lobby.into()).await.map_err(|e| e.to_string()),
+				Err(e) => Err(format!("Couldn't find the lobby: {}", e)),
+			};
+			*games.write().await = Some(listed);
+			repaint.request_repaint();
+		});
+	}
+
+	/// Starts hosting or joining in the background, remembering who we
+	/// joined for next time. With a `lobby`, a host is listed there, or only
+	/// offered to `friend` if there is one, and a joiner is relayed through
+	/// it if the host can't be reached directly.
+	async fn connect(
+		&mut self,
+		ctx: &egui::Context,
+		addr: Option<Address>,
+		lobby: Option<Address>,
+		friend: Option<String>,
+	) {
+		let serve = self.serve;
+		self.config.address = self.addr.trim().to_string();
+		if !serve {
+			self.config.remember(Opponent {
+				address: self.addr.trim().to_string(),
+				name: self.name.trim().to_string(),
+			});
+		}
+		if let Err(e) = self.config.save() {
+			self.msg
+				.write()
+				.await
+				.push(format!("Couldn't save recent opponents: {}", e));
+		}
+		let game = self.game.clone();
+		let session = self.session.clone();
+		let msg = self.msg.clone();
+		let qr = self.qr.clone();
+		let slot = self.slot.clone();
+		let repaint = ctx.clone();
+		let name = self.config.name.trim().to_string();
+		// Hosts don't name their opponent until the handshake does
+		let opponent = if serve {
+			String::new()
+		} else {
+			self.name.trim().to_string()
+		};
+		let invite = friend.zip(self.me.read().await.clone());
+		let mut options = GameFlow::builder().ship_names(self.config.skin.names.clone());
+		if !name.is_empty() {
+			options = options.name(name.clone());
+		}
+		if !self.password.is_empty() {
+			options = options.password(self.password.clone());
+		}
+		let hooks = self.config.hooks.clone();
+		self.task = Some(background(ctx, async move {
+			let lobby = match lobby {
+				Some(lobby) => match lobby.resolve().await {
+					Ok(lobby) => Some(SocketAddr::from(lobby)),
+					Err(e) => {
+						msg.write()
+							.await
+							.push(format!("Couldn't find the lobby: {}", e));
+						return Ok(());
+					}
+				},
+				None => None,
+			};
+			let addr = match addr {
+				Some(addr) => match addr.resolve().await {
+					Ok(addr) => Some(addr),
+					Err(e) => {
+						msg.write()
+							.await
+							.push(format!("Couldn't find that address: {}", e));
+						return Ok(());
+					}
+				},
+				None => None,
+			};
+			let new_game = if serve {
+				let listing = lobby.map(|lobby| (lobby, name));
+				let hosted = Self::host(addr, listing, invite, &msg, &qr, &repaint, options).await;
+				*qr.write().await = None;
+				hosted
 			} else {
-				ui.colored_label(Color32::from_rgb(255, 0, 0), "Invalid address");
+				let addr = addr.expect("joining always has an address");
+				msg.write().await.push(format!("Connecting to {}...", addr));
+				repaint.request_repaint();
+				match lobby {
+					Some(lobby) => options.join_with_relay(addr, lobby).await,
+					None => options.connect(addr.into(), serve).await,
+				}
+				.map(|game| (game, addr.into()))
+			};
+
+			match new_game {
+				Ok((new_game, addr)) => {
+					let new_session = Session { addr, serve, slot };
+					new_session.autosave(&new_game);
+					msg.write().await.push("Connected!".to_string());
+					let opponent = if opponent.is_empty() {
+						new_game.enemy_name().unwrap_or_default()
+					} else {
+						&opponent
+					};
+					match archive::head_to_head(opponent) {
+						Ok(Some(record)) => msg.write().await.push(format!("{}.", record)),
+						Ok(None) => {}
+						Err(e) => eprintln!("Couldn't look back at your games: {}", e),
+					}
+					spawn(hooks::run(hooks, new_game.events()));
+					*game.write().await = Some(new_game);
+					*session.write().await = Some(new_session);
+				}
+				Err(e) => msg.write().await.push(format!("{}", e)),
+			}
+			Ok(())
+		}));
+	}
+
+	/// Reconnects to the game in our save slot, for `--continue`.
+	fn resume(&mut self, ctx: &egui::Context) {
+		let save = match Save::load(&self.slot) {
+			Ok(save) => save,
+			Err(e) => {
+				self.runtime
+					.block_on(self.msg.write())
+					.push(format!("Couldn't continue the saved game: {}", e));
+				return;
+			}
+		};
+		let game = self.game.clone();
+		let session = self.session.clone();
+		let msg = self.msg.clone();
+		let new_session = Session {
+			addr: save.addr,
+			serve: save.serve,
+			slot: self.slot.clone(),
+		};
+		let _runtime = self.runtime.enter();
+		let hooks = self.config.hooks.clone();
+		self.task = Some(background(ctx, async move {
+			match error::resume(save).await {
+				Ok(resumed) => {
+					new_session.autosave(&resumed);
+					msg.write()
+						.await
+						.push("Continued the saved game!".to_string());
+					spawn(hooks::run(hooks, resumed.events()));
+					*game.write().await = Some(resumed);
+					*session.write().await = Some(new_session);
+				}
+				Err(e) => msg.write().await.push(format!("{}", e)),
 			}
+			Ok(())
+		}));
+	}
+
+	/// Keeps the finished game in the history, against the opponent named
+	/// when joining or else the name they gave.
+	fn record(&mut self, won: bool) {
+		self.recorded = true;
+		let game = self.runtime.block_on(self.game.read());
+		let Some(game) = game.as_ref() else { return };
+		let opponent = if self.serve || self.name.trim().is_empty() {
+			game.enemy_name().unwrap_or_default()
+		} else {
+			self.name.trim()
+		};
+		let snapshot = game.snapshot();
+		let outcome = if won { Outcome::Won } else { Outcome::Lost };
+		let entry = Entry::new(
+			opponent.to_string(),
+			outcome,
+			snapshot.rules.clone(),
+			snapshot.turn_number,
+		);
+		if let Err(e) = Archive::open().and_then(|archive| archive.add(&entry)) {
+			let line = format!("Couldn't add the game to your history: {}", e);
+			self.runtime.block_on(self.msg.write()).push(line);
 		}
 	}
 
-	pub async fn board(&self, ui: &mut Ui) -> Option<(bool, u8, u8)> {
-		if let Some(game) = &*self.game.read().await {
-			let mut boards = Vec::with_capacity(2);
-			for team in [false, true] {
-				boards.push((team, game.board(team).await));
+	/// Saves a picture of both boards to the file the player chose.
+	fn export(&mut self) {
+		let game = self.runtime.block_on(self.game.read());
+		let Some(game) = game.as_ref() else { return };
+		let line = match image::export(&game.subscribe().borrow(), Path::new(&self.map)) {
+			Ok(()) => format!("Saved the battle map to {}.", self.map),
+			Err(e) => format!("Couldn't save the battle map: {}", e),
+		};
+		self.runtime.block_on(self.msg.write()).push(line);
+	}
+
+	/// Leaves a game that's still going, telling the enemy and closing the
+	/// window. Stops waiting for the enemy's shot first if need be.
+	fn leave_button(&mut self, ui: &mut Ui, frame: &mut eframe::Frame) {
+		let runtime = self.runtime.clone();
+		let game = runtime.block_on(self.game.read());
+		let Some(game) = game.as_ref() else { return };
+		if matches!(runtime.block_on(game.phase()), Phase::Done(_)) {
+			return;
+		}
+		if ui.button("Leave the game").clicked() {
+			// We're going either way
+			let _ = runtime.block_on(game.shutdown());
+			frame.close();
+		}
+	}
+
+	/// Explains what broke the game and offers to reconnect, save it or quit.
+	fn recovery(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+		let Some(failure) = &self.failure else { return };
+		let mut choice = None;
+		egui::CentralPanel::default().show(ctx, |ui| {
+			ui.heading("The game stopped");
+			ui.label(failure.to_string());
+			if self.task.is_some() {
+				ui.label("Waiting for your enemy to reconnect...");
+				return;
+			}
+			ui.horizontal(|ui| {
+				ui.label("Save slot");
+				ui.text_edit_singleline(&mut self.slot);
+			});
+			ui.horizontal(|ui| {
+				for recovery in Recovery::ALL {
+					if ui.button(recovery.to_string()).clicked() {
+						choice = Some(recovery);
+					}
+				}
+			});
+		});
+		let Some(choice) = choice else { return };
+		let runtime = self.runtime.clone();
+		let session = runtime.block_on(self.session.read()).clone();
+		let game = runtime.block_on(self.game.read());
+		let (Some(session), Some(game)) = (session, game.as_ref()) else {
+			// Nothing was connected, so there's nothing to save or reconnect
+			frame.close();
+			return;
+		};
+		let save = session.save(game);
+		match choice {
+			Recovery::Reconnect => {
+				let game = self.game.clone();
+				let msg = self.msg.clone();
+				let _runtime = runtime.enter();
+				let hooks = self.config.hooks.clone();
+				self.task = Some(background(ctx, async move {
+					let reconnected = error::resume(save).await?;
+					session.autosave(&reconnected);
+					// Only the player whose turn it is can check, the other is listening
+					let synced = if reconnected.my_turn().await {
+						Some(resync(&reconnected).await?)
+					} else {
+						None
+					};
+					spawn(hooks::run(hooks, reconnected.events()));
+					*game.write().await = Some(reconnected);
+					let mut msg = msg.write().await;
+					msg.push("Reconnected!".to_string());
+					msg.extend(synced);
+					Ok(())
+				}));
+			}
+			Recovery::Save => match save.store(&self.slot) {
+				Ok(()) => frame.close(),
+				Err(e) => runtime
+					.block_on(self.msg.write())
+					.push(format!("Couldn't save the game: {}", e)),
+			},
+			Recovery::Quit => frame.close(),
+		}
+	}
+
+	/// The toggle for sharing our status on Discord, which also starts
+	/// reporting once a game is connected.
+	#[cfg(feature = "discord")]
+	fn discord_settings(&mut self, ui: &mut Ui) {
+		use netbattleship::presence::{Presence, APPLICATION_ID_VAR};
+
+		let label = "Share status on Discord";
+		let Ok(id) = std::env::var(APPLICATION_ID_VAR) else {
+			ui.add_enabled(false, egui::Checkbox::new(&mut false, label))
+				.on_disabled_hover_text(format!("Set {} to use this.", APPLICATION_ID_VAR));
+			return;
+		};
+		if ui.checkbox(&mut self.discord, label).changed() {
+			if let Some(presence) = &self.presence {
+				presence.set_enabled(self.discord);
 			}
-			ui.horizontal_centered(|ui| {
+		}
+		if self.presence.is_none() {
+			if let Some(game) = self.runtime.block_on(self.game.read()).as_ref() {
+				let _runtime = self.runtime.enter();
+				self.presence = Some(Presence::start(game, id, self.discord));
+			}
+		}
+	}
+
+	/// Lets the player have announcements read aloud, remembering it in the
+	/// config file.
+	#[cfg(feature = "speech")]
+	fn speech_settings(&mut self, ui: &mut Ui) {
+		if ui
+			.checkbox(&mut self.config.speak, "Read announcements aloud")
+			.changed()
+		{
+			if let Err(e) = self.config.save() {
+				self.runtime
+					.block_on(self.msg.write())
+					.push(format!("Couldn't save the speech setting: {}", e));
+			}
+		}
+		let mut volume = self.config.volume.unwrap_or(100);
+		let slider = ui.add_enabled(
+			self.config.speak,
+			Slider::new(&mut volume, 0..=100).suffix("%").text("Volume"),
+		);
+		if slider.changed() {
+			self.config.volume = Some(volume);
+			if let Some(voice) = &mut self.voice {
+				if let Err(e) = voice.set_volume(f32::from(volume) / 100.0) {
+					eprintln!("Couldn't change the volume: {}", e);
+				}
+			}
+		}
+		if slider.drag_released() || (slider.changed() && !slider.dragged()) {
+			if let Err(e) = self.config.save() {
+				self.runtime
+					.block_on(self.msg.write())
+					.push(format!("Couldn't save the volume: {}", e));
+			}
+		}
+	}
+
+	/// Reads out the messages that came in since last frame, if the player
+	/// asked for them to be, after whatever is still being said.
+	#[cfg(feature = "speech")]
+	fn announce(&mut self) {
+		use netbattleship::ui::speech::Voice;
+
+		let msg = self.runtime.block_on(self.msg.read());
+		// The list only shrinks when it's been cleared, and then it's all new
+		let fresh = msg.get(self.spoken..).unwrap_or(&msg).join(" ");
+		self.spoken = msg.len();
+		drop(msg);
+		if !self.config.speak || fresh.is_empty() {
+			return;
+		}
+		if self.voice.is_none() {
+			match Voice::new(1.0) {
+				Ok(mut voice) => {
+					let volume = self.config.volume.unwrap_or(100);
+					if let Err(e) = voice.set_volume(f32::from(volume) / 100.0) {
+						eprintln!("Couldn't change the volume: {}", e);
+					}
+					self.voice = Some(voice);
+				}
+				Err(e) => {
+					self.config.speak = false;
+					self.runtime
+						.block_on(self.msg.write())
+						.push(format!("Couldn't start text-to-speech: {}", e));
+					return;
+				}
+			}
+		}
+		if let Some(voice) = &mut self.voice {
+			if let Err(e) = voice.say(&fresh, false) {
+				eprintln!("Speech failed with {}.", e);
+			}
+		}
+	}
+
+	/// Waits for a challenger at `addr`, or a free port, listing the game as
+	/// `name` in the lobby at `listing` if there is one. With an `invite`, the
+	/// game is only offered to that friend code there, as that identity.
+	/// The invite on our local network goes in `qr` to be shown until then.
+	async fn host(
+		addr: Option<SocketAddrV4>,
+		listing: Option<(SocketAddr, String)>,
+		invite: Option<(String, Identity)>,
+		msg: &RwLock<Vec<String>>,
+		qr: &RwLock<Option<Invite>>,
+		ctx: &egui::Context,
+		options: GameFlowBuilder,
+	) -> Result<(GameFlow, SocketAddr), GameFlowError> {
+		let mut host = Host::bind(addr.map(Into::into)).await?;
+		match (listing, invite) {
+			(Some((lobby, name)), Some((friend, from))) => {
+				host.invite(lobby, &from, name, &friend, Rules::default())
+					.await?;
+				msg.write().await.push(format!("Invited {}.", friend));
+			}
+			(Some((lobby, name)), None) => {
+				host.list_relay(lobby, name, Rules::default()).await?;
+			}
+			(None, _) => {}
+		}
+		let bound = host.listener().local_addr()?;
+		let mut msg_list = msg.write().await;
+		match host.port_mapping() {
+			Ok(mapping) => {
+				msg_list.push(format!("Forwarded {} through your router.", mapping.external()))
+			}
+			Err(NatError::Unsupported) => {}
+			Err(e) => msg_list.push(format!(
+				"Couldn't forward a port through your router ({}), so players outside your network may not be able to join.",
+				e
+			)),
+		}
+		msg_list.push("Waiting for a challenger. They can join with any of these:".to_string());
+		for addr in host.addrs() {
+			if let SocketAddr::V4(addr) = addr {
+				msg_list.push(format!("{} (invite code {})", addr, Invite(addr)));
+			}
+		}
+		drop(msg_list);
+		*qr.write().await = invite::lan_addr(&host.addrs()).map(Invite);
+		ctx.request_repaint();
+		Ok((options.accept(host).await?, bound))
+	}
+
+	pub async fn board(&mut self, ui: &mut Ui) -> Option<(bool, Coord)> {
+		let snapshot = match &*self.game.read().await {
+			Some(game) => game.snapshot(),
+			None => return None,
+		};
+		let boards =
+			[false, true].map(|team| (team, &snapshot.board[usize::from(snapshot.you ^ team)]));
+
+		let phase = snapshot.phase.clone();
+		let moved = self.keyboard(ui, boards[0].1.size(), &phase);
+		let pointer = self.hovered.or(self.cursor);
+
+		// Footprint of the ship being placed under the cursor, whether it fits,
+		// and the cells of other ships in its way
+		let preview = match (phase, pointer) {
+			(Phase::Placing(ship), Some((false, pos))) => Some((
+				ship.footprint(pos, self.vertical).collect::<Vec<_>>(),
+				ship.can_place(boards[0].1, pos, self.vertical),
+				boards[0].1.placement_conflicts(ship, pos, self.vertical),
+			)),
+			_ => None,
+		};
+
+		let last_shot = self
+			.last_result
+			.read()
+			.await
+			.as_ref()
+			.map(|(team, result)| (*team, result.aim));
+		let highlight = ui.visuals().selection.bg_fill;
+		let side = self.cell_size(ui, boards[0].1);
+		let pressed_for = {
+			let input = ui.input();
+			input
+				.pointer
+				.press_start_time()
+				.map(|start| input.time - start)
+		};
+		let mut held = false;
+
+		let mut hovered = None;
+		let mut focused = None;
+		let mut readout = [None, None];
+		let clicked = ui
+			.horizontal(|ui| {
 				let mut out = None;
 				for (team, board) in boards {
 					if team {
 						ui.separator();
 					}
-					for col in 0..10 {
-						let response = ui
-							.vertical(|ui| {
-								for cell in 0..10 {
-									if ui
-										.add(
-											Button::new(
-												char::from(
-													*board
-														.board
-														.get(&(col, cell))
-														.unwrap_or(&Ship::None),
-												)
-												.to_string(),
-											)
-											.min_size(Vec2::new(16.0, 0.0)),
+					// Our ships that have been hit, to outline every square of them
+					let damaged = board
+						.board
+						.values()
+						.filter(|c| c.state == CellState::Hit)
+						.filter_map(|c| c.ship)
+						.collect::<Vec<_>>();
+					// How to fill and outline a square
+					let look = |pos: Coord, cell: Cell| {
+						// Our own ships in the colours we picked for them
+						let skinned = cell
+							.ship
+							.filter(|_| !team)
+							.and_then(|ship| self.config.skin.color(ship))
+							.map(|[r, g, b]| Color32::from_rgb(r, g, b));
+						let fill = match &preview {
+							Some((cells, fits, _)) if !team && cells.contains(&pos) => {
+								Some(if *fits { PREVIEW_FITS } else { PREVIEW_BLOCKED })
+							}
+							_ if board.land().contains(&pos) => Some(LAND),
+							_ if pointer == Some((team, pos)) => Some(highlight),
+							_ if skinned.is_some() => skinned,
+							_ if cell.state == CellState::Unknown => Some(UNKNOWN_SHOT),
+							_ if cell.state == CellState::Miss && !team => Some(ENEMY_MISS),
+							_ => None,
+						};
+						let conflict = matches!(
+							&preview,
+							Some((_, _, conflicts)) if !team && conflicts.contains(&pos)
+						);
+						let stroke = if conflict {
+							Some(Stroke::new(2.0, CONFLICT))
+						} else if last_shot == Some((team, pos)) {
+							Some(Stroke::new(2.0, LAST_SHOT))
+						} else if cell.ship.is_some_and(|ship| damaged.contains(&ship)) {
+							Some(Stroke::new(1.5, DAMAGED))
+						} else {
+							None
+						};
+						(fill, stroke)
+					};
+					// What to do with a square once it's been drawn
+					let mut respond = |response: &Response, pos: Coord, label: String| {
+						response.widget_info(|| WidgetInfo::labeled(WidgetType::Button, &label));
+						if moved && self.cursor == Some((team, pos)) {
+							response.request_focus();
+						}
+						if response.has_focus() {
+							focused = Some((team, pos));
+						}
+						if self.cursor == Some((team, pos)) {
+							readout[1] = Some(label.clone());
+						}
+						if response.hovered() {
+							hovered = Some((team, pos));
+							readout[0] = Some(label);
+						}
+						// Holding a square on our board turns the ship instead
+						if !team
+							&& response.is_pointer_button_down_on()
+							&& pressed_for >= Some(LONG_PRESS)
+						{
+							held = true;
+						}
+						if response.clicked() && !self.long_pressed {
+							out = Some((team, pos));
+						}
+					};
+					ui.vertical(|ui| {
+						ui.label(if team { "Enemy" } else { "You" });
+						if board.topology() == Topology::Hex {
+							hex_board(ui, board, team, side, |ui, pos, cell, response| {
+								let (fill, stroke) = look(pos, cell);
+								let label = describe(team, pos, board, self.config.notation);
+								respond(response, pos, label);
+								let visuals = ui.style().interact(response);
+								let stroke = if response.has_focus() {
+									ui.visuals().selection.stroke
+								} else {
+									stroke.unwrap_or(visuals.bg_stroke)
+								};
+								(
+									self.config.skin.symbol(cell, !team),
+									fill.unwrap_or(visuals.bg_fill),
+									stroke,
+									visuals.text_color(),
+								)
+							});
+							return;
+						}
+						egui::Grid::new(("board", team))
+							.spacing(Vec2::splat(2.0))
+							.min_col_width(side)
+							.min_row_height(side)
+							.show(ui, |ui| {
+								ui.label("");
+								for col in 0..board.size() {
+									ui.label(col.to_string());
+								}
+								ui.end_row();
+								for row in 0..board.size() {
+									ui.label(Coord::new(0, row).row().to_string());
+									for col in 0..board.size() {
+										let pos = Coord::new(col, row);
+										let cell = board.cell(pos);
+										let symbol = RichText::new(
+											self.config.skin.symbol(cell, !team).to_string(),
 										)
-										.clicked()
-									{
-										out = Some((team, col, cell));
+										.size(side / 2.0);
+										let mut button =
+											Button::new(symbol).min_size(Vec2::splat(side));
+										let (fill, stroke) = look(pos, cell);
+										if let Some(fill) = fill {
+											button = button.fill(fill);
+										}
+										if let Some(stroke) = stroke {
+											button = button.stroke(stroke);
+										}
+										let label =
+											describe(team, pos, board, self.config.notation);
+										respond(&ui.add(button), pos, label);
 									}
+									ui.end_row();
 								}
-								None
-							})
-							.inner;
-						if response.is_some() {
-							return response;
-						}
-					}
+							});
+					});
 				}
 				out
 			})
-			.inner
-		} else {
-			None
+			.inner;
+
+		// What's under the mouse, or else the keyboard cursor
+		let [under_mouse, under_cursor] = readout;
+		ui.label(under_mouse.or(under_cursor).unwrap_or_else(|| {
+			"Arrow keys move around the boards, Enter picks a square and R turns the ship."
+				.to_string()
+		}));
+		self.hovered = hovered;
+		self.long_press(ui, held);
+		self.board_focused = focused.is_some();
+		if focused.is_some() {
+			self.cursor = focused;
 		}
+		clicked
 	}
 
-	pub async fn placing(&mut self, ui: &mut Ui, clicked: Option<(bool, u8, u8)>, ship: Ship) {
+	/// How big to draw each square so both boards fit the room there is,
+	/// but never too small to hit.
+	fn cell_size(&mut self, ui: &Ui, board: &Board) -> f32 {
+		self.touch |= ui.input().any_touches();
+		let min = if self.touch { MIN_TOUCH_CELL } else { MIN_CELL };
+		// Each board has a column and a row of labels too, and leaves about
+		// half the height for the controls underneath. Hex boards lean, so
+		// they're half again as wide
+		let squares = f32::from(board.size()) + 1.0;
+		let across = match board.topology() {
+			Topology::Square => squares,
+			Topology::Hex => squares + f32::from(board.size()) / 2.0,
+		};
+		let spacing = ui.spacing().item_spacing.x * 2.0 + 2.0 * across;
+		let wide = (ui.available_width() - spacing) / (across * 2.0);
+		let tall = ui.available_height() / 2.0 / (squares + 1.0);
+		wide.min(tall).clamp(min, MAX_CELL)
+	}
+
+	/// Turns the ship once a square on our board has been held down for
+	/// [`LONG_PRESS`], for touch screens with no R key or checkbox in reach.
+	fn long_press(&mut self, ui: &Ui, held: bool) {
+		if held && !self.long_pressed {
+			self.vertical = !self.vertical;
+			self.long_pressed = true;
+		}
+		if !ui.input().pointer.any_down() {
+			self.long_pressed = false;
+		} else if !self.long_pressed {
+			// Nothing else happens while it's held, so check back in time
+			ui.ctx()
+				.request_repaint_after(Duration::from_secs_f64(LONG_PRESS / 4.0));
+		}
+	}
+
+	/// The dark and light mode switch, remembering the choice in the config
+	/// file.
+	fn theme_settings(&mut self, ui: &mut Ui) {
+		let before = ui.visuals().dark_mode;
+		egui::widgets::global_dark_light_mode_switch(ui);
+		let dark = ui.ctx().style().visuals.dark_mode;
+		if dark != before {
+			self.config.dark = Some(dark);
+			if let Err(e) = self.config.save() {
+				self.runtime
+					.block_on(self.msg.write())
+					.push(format!("Couldn't save the colour scheme: {}", e));
+			}
+		}
+	}
+
+	/// The UI scale slider.
+	/// Lets the player pick how coordinates are written, remembering it in
+	/// the config file.
+	fn notation_settings(&mut self, ui: &mut Ui) {
+		let before = self.config.notation;
+		egui::ComboBox::from_label("Coordinates")
+			.selected_text(Coord::new(5, 4).written(before).to_string())
+			.show_ui(ui, |ui| {
+				for notation in [
+					Notation::LetterFirst,
+					Notation::NumberFirst,
+					Notation::Numeric,
+					Notation::Axial,
+				] {
+					let example = Coord::new(5, 4).written(notation).to_string();
+					ui.selectable_value(&mut self.config.notation, notation, example);
+				}
+			});
+		if self.config.notation != before {
+			if let Err(e) = self.config.save() {
+				self.runtime
+					.block_on(self.msg.write())
+					.push(format!("Couldn't save the coordinate notation: {}", e));
+			}
+		}
+	}
+
+	fn scale_settings(&mut self, ui: &mut Ui) {
+		let slider = ui.add(
+			Slider::new(&mut self.scale, SCALES)
+				.suffix("%")
+				.text("Scale"),
+		);
+		if slider.drag_released() || (slider.changed() && !slider.dragged()) {
+			self.config.scale = Some(self.scale);
+			if let Err(e) = self.config.save() {
+				self.runtime
+					.block_on(self.msg.write())
+					.push(format!("Couldn't save the UI scale: {}", e));
+			}
+		}
+	}
+
+	/// Draws everything at the scale picked in the settings, on top of the
+	/// screen's own, once the slider has been let go of.
+	fn rescale(&mut self, ctx: &egui::Context, frame: &eframe::Frame) {
+		let scale = self.config.scale.unwrap_or(100);
+		if ctx.memory().is_anything_being_dragged() && scale != self.scale {
+			return;
+		}
+		self.scale = scale;
+		let native = frame.info().native_pixels_per_point.unwrap_or(1.0);
+		let pixels_per_point = native * f32::from(scale) / 100.0;
+		if (ctx.pixels_per_point() - pixels_per_point).abs() > f32::EPSILON {
+			ctx.set_pixels_per_point(pixels_per_point);
+		}
+	}
+
+	/// Notes how big the window is, in the screen's own points rather than
+	/// the scaled ones, to open it at that size next time.
+	fn measure(&mut self, ctx: &egui::Context, frame: &eframe::Frame) {
+		let info = frame.info();
+		let native = info.native_pixels_per_point.unwrap_or(1.0);
+		let size = info.window_info.size * ctx.pixels_per_point() / native;
+		// Window sizes are well inside a u16
+		#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+		let size = [size.x.round() as u16, size.y.round() as u16];
+		if size != [0, 0] {
+			self.config.window = Some(size);
+		}
+	}
+
+	/// Moves the keyboard cursor with the arrow keys and turns the ship being
+	/// placed with R, unless something else (like a text box) has the
+	/// keyboard. Enter is left to the focused square's button, which takes
+	/// it as a click. Returns whether the cursor moved.
+	fn keyboard(&mut self, ui: &Ui, size: u8, phase: &Phase) -> bool {
+		if ui.memory().focus().is_some() && !self.board_focused {
+			return false;
+		}
+		let input = ui.input();
+		if input.key_pressed(Key::R) {
+			self.vertical = !self.vertical;
+		}
+		let dx = i16::from(input.key_pressed(Key::ArrowRight))
+			- i16::from(input.key_pressed(Key::ArrowLeft));
+		let dy = i16::from(input.key_pressed(Key::ArrowDown))
+			- i16::from(input.key_pressed(Key::ArrowUp));
+		drop(input);
+		if dx == 0 && dy == 0 {
+			return false;
+		}
+		let Some((team, pos)) = self.cursor else {
+			// Start where the next move is most likely to be
+			let enemy = *phase == Phase::Playing && !self.moving;
+			self.cursor = Some((enemy, Coord::new(0, 0)));
+			return true;
+		};
+		// Both boards side by side, as one wide grid
+		let size = i16::from(size);
+		let x = (i16::from(team) * size + i16::from(pos.x) + dx).clamp(0, size * 2 - 1);
+		let y = (i16::from(pos.y) + dy).clamp(0, size - 1);
+		let (Ok(col), Ok(row)) = (u8::try_from(x % size), u8::try_from(y)) else {
+			return false;
+		};
+		self.cursor = Some((x >= size, Coord::new(col, row)));
+		true
+	}
+
+	pub async fn placing(&mut self, ui: &mut Ui, clicked: Option<(bool, Coord)>, ship: ShipKind) {
 		// Name of ship
 		ui.heading(format!("Placing {:?}.", ship));
 		// Vertical Checkbox
 		ui.checkbox(
 			&mut self.vertical,
-			"Vertical? (Check before placing.)".to_owned(),
+			"Vertical? (Check before placing, or press R or hold a square.)".to_owned(),
 		);
+		self.use_layout(ui).await;
+		self.save_layout(ui).await;
 
 		if let Some(clicked) = clicked {
 			if clicked.0 {
@@ -220,7 +1572,7 @@ impl App {
 			let game = self.game.write().await;
 			let game = game.as_ref().unwrap();
 			if game
-				.place_ship(ship, (clicked.1, clicked.2), self.vertical)
+				.place_ship(ship, clicked.1, self.vertical)
 				.await
 				.is_ok()
 			{
@@ -234,17 +1586,206 @@ impl App {
 		}
 	}
 
-	pub async fn playing(&mut self, ui: &mut Ui, clicked: Option<(bool, u8, u8)>) {
-		let our_turn = self.game.read().await.as_ref().unwrap().my_turn().await;
+	/// Lets the player pick a layout to place the rest of the fleet like.
+	async fn use_layout(&mut self, ui: &mut Ui) {
+		let mut used = false;
+		ui.horizontal(|ui| {
+			egui::ComboBox::from_label("Layout")
+				.selected_text(self.layout.clone())
+				.show_ui(ui, |ui| {
+					for name in layouts::names() {
+						ui.selectable_value(&mut self.layout, name.clone(), name);
+					}
+				});
+			used = ui.button("Use").clicked();
+		});
+		if !used {
+			return;
+		}
+		let game = self.game.read().await;
+		let Some(game) = game.as_ref() else { return };
+		let snapshot = game.state.read().await.clone();
+		let board = &snapshot.board[usize::from(snapshot.you)];
+		let message = match layouts::placements(&self.layout, board, &snapshot.rules.fleet) {
+			Ok(placements) => {
+				for (ship, pos, v) in placements {
+					if game.place_ship(ship, pos, v).await.is_err() {
+						break;
+					}
+				}
+				"OK!".to_owned()
+			}
+			Err(e) => format!("Couldn't use that layout: {e}."),
+		};
+		self.msg.write().await.push(message);
+	}
+
+	/// Lets the player keep their fleet's layout to use in later games.
+	async fn save_layout(&mut self, ui: &mut Ui) {
+		let saved = ui
+			.horizontal(|ui| {
+				ui.label("Keep this layout as");
+				ui.text_edit_singleline(&mut self.layout_name);
+				ui.button("Save layout").clicked()
+			})
+			.inner;
+		if !saved {
+			return;
+		}
+		let game = self.game.read().await;
+		let Some(game) = game.as_ref() else { return };
+		let message = match layouts::save(&self.layout_name, &game.board(false).await) {
+			Ok(()) => format!("Saved your layout as {}.", self.layout_name),
+			Err(e) => format!("Couldn't save your layout: {e}."),
+		};
+		self.msg.write().await.push(message);
+	}
+
+	/// Waits in the background for the enemy to finish placing their fleet.
+	pub fn ready(&mut self, ui: &mut Ui) {
+		ui.heading("Your fleet is ready. Waiting for the enemy to place theirs...");
+		let runtime = self.runtime.clone();
+		let placing = runtime.block_on(async {
+			let game = self.game.read().await;
+			let game = game.as_ref()?;
+			let fleet = game.state.read().await.rules.fleet.len();
+			Some((game.enemy_placing(), fleet))
+		});
+		if let Some((placed @ 1.., fleet)) = placing {
+			ui.label(format!("Your enemy has placed {placed} of {fleet} ships."));
+		}
+		// Their progress doesn't change the game, so it wouldn't repaint
+		ui.ctx().request_repaint_after(Duration::from_secs(1));
+		runtime.block_on(self.save_layout(ui));
+		if self.task.is_none() {
+			let game = self.game.clone();
+			self.task = Some(background(ui.ctx(), async move {
+				game.read().await.as_ref().unwrap().wait_for_fleet().await
+			}));
+		}
+	}
+
+	/// Starts repainting whenever the game changes, once there is one, and
+	/// again for the new game after reconnecting.
+	fn watch(&mut self, ctx: &egui::Context) {
+		if self.watcher.as_ref().is_some_and(|w| !w.is_finished()) {
+			return;
+		}
+		let game = self.runtime.block_on(self.game.read());
+		let Some(game) = game.as_ref() else { return };
+		let mut snapshots = game.subscribe();
+		let ctx = ctx.clone();
+		self.watcher = Some(self.runtime.spawn(async move {
+			while snapshots.changed().await.is_ok() {
+				ctx.request_repaint();
+			}
+		}));
+	}
+
+	/// Shows the connection's latency, coloured by how playable it is.
+	pub async fn latency(&self, ui: &mut Ui) {
+		let game = self.game.read().await;
+		let Some(game) = game.as_ref() else { return };
+		// A fresh measurement comes in with every ping
+		ui.ctx().request_repaint_after(PING_INTERVAL);
+		ui.separator();
+		match game.latency() {
+			Some(rtt) => {
+				let color = match Quality::of(rtt) {
+					Quality::Good => GOOD_LINK,
+					Quality::Fair => FAIR_LINK,
+					Quality::Poor => POOR_LINK,
+				};
+				ui.colored_label(color, format!("Ping {}", Latency(rtt)));
+			}
+			None => {
+				ui.label("Ping not measured yet");
+			}
+		}
+	}
+
+	/// Shows how long each side has taken, once the shooting has started.
+	pub async fn clocks(&self, ui: &mut Ui) {
+		if let Some(game) = self.game.read().await.as_ref() {
+			let snapshot = game.snapshot();
+			let phase = &snapshot.phase;
+			if *phase == Phase::Playing {
+				// The clock of whoever is taking their turn keeps ticking
+				ui.ctx().request_repaint_after(Duration::from_secs(1));
+			}
+			if let Phase::Playing | Phase::Done(_) = phase {
+				ui.separator();
+				ui.label(game.clocks().await.to_string());
+			}
+		}
+	}
+
+	pub async fn playing(&mut self, ui: &mut Ui, clicked: Option<(bool, Coord)>) {
+		let (our_turn, turn) = {
+			let game = self.game.read().await;
+			let game = game.as_ref().unwrap();
+			let snapshot = game.snapshot();
+			(snapshot.turn == snapshot.you, snapshot.turn_number)
+		};
 		if our_turn {
-			ui.heading("Your Turn!");
+			ui.heading(format!("Turn {turn}. Your Turn!"));
 			if self.task.is_none() {
 				ui.label("Click on the enemy's board to fire.");
+				let game = self.game.read().await;
+				let active = {
+					let input = ui.input();
+					input.pointer.is_moving() || !input.keys_down.is_empty()
+				};
+				if active {
+					game.as_ref().unwrap().aiming().await;
+				}
+				if game.as_ref().unwrap().scan_available().await {
+					ui.checkbox(&mut self.radar, "Sweep with radar instead");
+				}
+				if ui.button("Check sync").clicked() {
+					let (game, msg) = (self.game.clone(), self.msg.clone());
+					self.task = Some(background(ui.ctx(), async move {
+						let game = game.read().await;
+						let line = resync(game.as_ref().unwrap()).await?;
+						msg.write().await.push(line);
+						Ok(())
+					}));
+					return;
+				}
+				if game.as_ref().unwrap().can_move_submarine().await {
+					ui.checkbox(
+						&mut self.moving,
+						"Move your submarine instead (click your own board)",
+					);
+					if self.moving {
+						ui.checkbox(&mut self.vertical, "Vertical?");
+					}
+				}
 			} else {
 				ui.label("Waiting on the enemy's response...");
 				return;
 			}
 			if let Some(clicked) = clicked {
+				if !clicked.0 && self.moving {
+					self.moving = false;
+					let (game, msg, vertical) =
+						(self.game.clone(), self.msg.clone(), self.vertical);
+					self.task = Some(background(ui.ctx(), async move {
+						let game = game.read().await;
+						let line = match game
+							.as_ref()
+							.unwrap()
+							.move_submarine(clicked.1, vertical)
+							.await
+						{
+							Ok(()) => format!("Your submarine slipped away to {}.", clicked.1),
+							Err(e) => format!("{}", e),
+						};
+						msg.write().await.push(line);
+						Ok(())
+					}));
+					return;
+				}
 				if !clicked.0 {
 					self.msg
 						.write()
@@ -253,23 +1794,47 @@ impl App {
 					return;
 				}
 
-				let pos = (clicked.1, clicked.2);
+				let pos = clicked.1;
+				let notation = self.config.notation;
 				let game = self.game.clone();
 				let last_result = self.last_result.clone();
 				let msg = self.msg.clone();
-				self.task = Some(spawn(async move {
+				if self.radar {
+					self.radar = false;
+					self.task = Some(background(ui.ctx(), async move {
+						let game = game.read().await;
+						let line = match game.as_ref().unwrap().scan(pos).await {
+							Ok(count) => format!(
+								"Radar found {count} ship cells around {}.",
+								pos.written(notation)
+							),
+							Err(e) => format!("{}", e),
+						};
+						msg.write().await.push(line);
+						Ok(())
+					}));
+					return;
+				}
+				self.task = Some(background(ui.ctx(), async move {
 					let game = game.read().await;
 					let results = game.as_ref().unwrap().fire(pos).await;
 
 					match results {
 						Ok(tr) => {
 							let mut msgs = vec![];
-							msgs.push(format!(
-								"You {} the enemy's ship at {}.",
-								if tr.hit.is_some() { "hit" } else { "missed" },
-								format_args!("({}, {})", tr.aim.0, tr.aim.1)
-							));
-							if tr.hit.is_some() {
+							if tr.hidden {
+								msgs.push(format!(
+									"Your shot at {} vanished into the fog.",
+									tr.aim.written(notation)
+								));
+							} else {
+								msgs.push(format!(
+									"You {} the enemy's ship at {}.",
+									if tr.hit { "hit" } else { "missed" },
+									tr.aim.written(notation)
+								));
+							}
+							if tr.hit {
 								msgs.push(format!(
 									"You {} the enemy's {}.",
 									if tr.sunk.is_some() {
@@ -277,44 +1842,71 @@ impl App {
 									} else {
 										"failed to sink"
 									},
-									tr.sunk
-										.filter(|s| !s.is_empty())
-										.map(|s| format!("{:?}", s))
-										.unwrap_or_else(|| "ship".to_string()),
+									tr.sunk_name.clone().unwrap_or_else(|| "ship".to_string()),
 								))
 							}
 							msg.write().await.append(&mut msgs);
-							*last_result.write().await = Some(tr)
+							*last_result.write().await = Some((true, tr))
 						}
-						Err(e) => msg.write().await.push(format!("{}", e)),
+						Err(GameFlowError::TimedOut) => msg
+							.write()
+							.await
+							.push("You took too long to fire, and lost the game.".to_string()),
+						Err(e) => return Err(e),
 					};
+					Ok(())
 				}))
 			}
 		} else {
-			ui.heading("The enemy's turn.");
+			ui.heading(format!("Turn {turn}. The enemy's turn."));
+			let since = match self.waiting {
+				Some((waiting, since)) if waiting == turn => since,
+				_ => self.waiting.insert((turn, Instant::now())).1,
+			};
+			// They've only just started if they haven't said anything yet
+			let aiming = since.elapsed() < AIMING_IDLE
+				|| self.game.read().await.as_ref().unwrap().enemy_aiming();
+			ui.label(if aiming {
+				"The enemy is aiming..."
+			} else {
+				"The enemy appears idle."
+			});
+			ui.ctx().request_repaint_after(AIMING_INTERVAL);
 			if self.task.is_none() {
+				let notation = self.config.notation;
+				let skin = self.config.skin.clone();
 				let game = self.game.clone();
 				let last_result = self.last_result.clone();
 				let msg = self.msg.clone();
-				self.task = Some(spawn(async move {
+				self.task = Some(background(ui.ctx(), async move {
 					let game = game.read().await;
-					let result = game.as_ref().unwrap().receive().await.unwrap();
+					let result = match game.as_ref().unwrap().receive().await {
+						Ok(result) => result,
+						Err(GameFlowError::TimedOut) => {
+							msg.write().await.push(
+								"The enemy took too long to fire, and lost the game.".to_owned(),
+							);
+							return Ok(());
+						}
+						Err(e) => return Err(e),
+					};
+					if result.moved {
+						msg.write()
+							.await
+							.push("The enemy's submarine has moved.".to_owned());
+						return Ok(());
+					}
 					let mut msgs = vec![];
 					msgs.push(format!(
 						"The enemy {} your {} at {}.",
-						if result.hit.is_some() {
-							"hit"
-						} else {
-							"missed"
-						},
+						if result.hit { "hit" } else { "missed" },
 						result
-							.hit
-							.filter(|s| !s.is_empty())
-							.map(|s| format!("{:?}", s))
+							.ship
+							.map(|s| skin.name(s))
 							.unwrap_or_else(|| "ships".to_string()),
-						format_args!("({}, {})", result.aim.0, result.aim.1)
+						result.aim.written(notation)
 					));
-					if result.hit.is_some() {
+					if result.hit {
 						msgs.push(format!(
 							"The enemy {} your {}.",
 							if result.sunk.is_some() {
@@ -324,16 +1916,135 @@ impl App {
 							},
 							result
 								.sunk
-								.filter(|s| !s.is_empty())
-								.map(|s| format!("{:?}", s))
+								.map(|s| skin.name(s))
 								.unwrap_or_else(|| "ship".to_string()),
 						))
 					}
 					msg.write().await.append(&mut msgs);
-					*last_result.write().await = Some(result);
+					*last_result.write().await = Some((false, result));
+					Ok(())
 				}))
 			}
 			ui.label("Waiting for the enemy to fire.");
 		}
 	}
 }
+
+/// Lays out a hex board as rows of hexagons, each half a hexagon further right
+/// than the one above, with column numbers along the top and row letters down
+/// the side. `paint` is told about each square once it can be clicked, and
+/// picks its symbol, fill, outline and text colour.
+fn hex_board(
+	ui: &mut Ui,
+	board: &Board,
+	team: bool,
+	side: f32,
+	mut paint: impl FnMut(&Ui, Coord, Cell, &Response) -> (char, Color32, Stroke, Color32),
+) {
+	let step = side + 2.0;
+	// Rows of pointy-topped hexagons overlap by a quarter of their height
+	let radius = step / 3f32.sqrt();
+	let row_step = radius * 1.5;
+	let size = f32::from(board.size());
+	let (rect, _) = ui.allocate_exact_size(
+		Vec2::new(
+			step * (size * 1.5 + 0.5),
+			step + radius * 2.0 + row_step * (size - 1.0),
+		),
+		Sense::hover(),
+	);
+	let center = |col: u8, row: u8| {
+		rect.min
+			+ Vec2::new(
+				step * (1.5 + f32::from(col) + f32::from(row) / 2.0),
+				step + radius + row_step * f32::from(row),
+			)
+	};
+	let font = FontId::proportional(side / 2.0);
+	let text = ui.visuals().text_color();
+	for col in 0..board.size() {
+		let above = center(col, 0) - Vec2::new(0.0, step);
+		ui.painter()
+			.text(above, Align2::CENTER_CENTER, col, font.clone(), text);
+	}
+	for row in 0..board.size() {
+		let before = center(0, row) - Vec2::new(step, 0.0);
+		let name = Coord::new(0, row).row();
+		ui.painter()
+			.text(before, Align2::CENTER_CENTER, name, font.clone(), text);
+		for col in 0..board.size() {
+			let pos = Coord::new(col, row);
+			let cell = board.cell(pos);
+			let middle = center(col, row);
+			let response = ui.interact(
+				Rect::from_center_size(middle, Vec2::splat(side)),
+				ui.id().with(("hex", team, pos)),
+				Sense::click(),
+			);
+			let (symbol, fill, stroke, color) = paint(ui, pos, cell, &response);
+			let corners = (0..6u8)
+				.map(|i| {
+					middle
+						+ Vec2::angled((f32::from(i) * 60.0 - 30.0).to_radians()) * (radius - 1.0)
+				})
+				.collect();
+			ui.painter()
+				.add(Shape::convex_polygon(corners, fill, stroke));
+			ui.painter()
+				.text(middle, Align2::CENTER_CENTER, symbol, font.clone(), color);
+		}
+	}
+}
+
+/// What a screen reader says for a square: which board and where, then
+/// what's on it, like "Enemy board D5, unknown".
+/// How our games against each of the `recent` opponents with a name have
+/// gone, leaving out those we haven't finished a game against.
+/// Draws `invite` as a QR code, dark on light whatever the theme so it
+/// scans, with the border scanners need.
+fn qr_code(ui: &mut Ui, invite: Invite) {
+	const SQUARE: f32 = 4.0;
+	const BORDER: usize = 4;
+	let rows = invite.qr();
+	#[allow(clippy::cast_precision_loss)]
+	let at = |squares: usize| squares as f32 * SQUARE;
+	let side = at(rows.len() + BORDER * 2);
+	let (rect, _) = ui.allocate_exact_size(Vec2::splat(side), Sense::hover());
+	ui.painter().rect_filled(rect, 0.0, Color32::WHITE);
+	for (y, row) in rows.iter().enumerate() {
+		for (x, _) in row.iter().enumerate().filter(|(_, &dark)| dark) {
+			let min = rect.min + Vec2::new(at(x + BORDER), at(y + BORDER));
+			ui.painter().rect_filled(
+				Rect::from_min_size(min, Vec2::splat(SQUARE)),
+				0.0,
+				Color32::BLACK,
+			);
+		}
+	}
+}
+
+fn records(recent: &[Opponent]) -> Vec<Record> {
+	let Ok(archive) = Archive::open() else {
+		return Vec::new();
+	};
+	recent
+		.iter()
+		.filter(|opponent| !opponent.name.is_empty())
+		.filter_map(|opponent| archive.against(&opponent.name).ok())
+		.filter(Record::played)
+		.collect()
+}
+
+fn describe(enemy: bool, pos: Coord, board: &Board, notation: Notation) -> String {
+	let cell = board.cell(pos);
+	let land = board.land().contains(&pos);
+	let board = if enemy { "Enemy board" } else { "Your board" };
+	let pos = pos.written(notation);
+	if land {
+		format!("{board} {pos}, land")
+	} else if enemy && cell.state == CellState::Untouched {
+		format!("{board} {pos}, unknown")
+	} else {
+		format!("{board} {pos}, {cell}")
+	}
+}
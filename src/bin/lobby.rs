@@ -0,0 +1,34 @@
+//! Lobby server for netbattleship
+//! Relays games between players who can't connect to each other directly
+
+use netbattleship::lobby;
+use std::{net::SocketAddr, path::PathBuf};
+use structopt::StructOpt;
+use tokio::net::TcpListener;
+
+#[derive(StructOpt)]
+struct Args {
+	/// The address to listen on.
+	#[structopt(default_value = "0.0.0.0:7737")]
+	pub bind: SocketAddr,
+	/// A TOML file to keep the friend codes handed out in, so they last
+	/// between runs.
+	#[structopt(long)]
+	pub friends: Option<PathBuf>,
+}
+
+#[tokio::main]
+async fn main() {
+	let args = Args::from_args();
+	let listener = TcpListener::bind(args.bind)
+		.await
+		.expect("Failed to listen");
+	let friends = match args.friends {
+		Some(path) => lobby::Friends::load(path).expect("Failed to read the friend codes"),
+		None => lobby::Friends::default(),
+	};
+	println!("Relaying games on {}", args.bind);
+	if let Err(e) = lobby::serve_with(listener, friends).await {
+		eprintln!("Lobby stopped: {e}");
+	}
+}
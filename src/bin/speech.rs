@@ -2,116 +2,623 @@
 //! Has the same arguments as the normal CLI
 
 use netbattleship::{
-	flow::GameFlow,
-	ui::{self, parse_coord},
+	archive::{self, Archive, Entry},
+	board::CellState,
+	config::{Config, Outcome},
+	coord::Coord,
+	event::{Event, Player},
+	flow::{GameFlow, GameFlowError, Host, AIMING_IDLE, AIMING_INTERVAL},
+	hooks,
+	invite::Invite,
+	layouts,
+	lobby::Route,
+	nat::NatError,
+	protocol::TurnResults,
+	render::{image, Style},
+	rules::Rules,
+	save::Save,
+	ship::ShipKind,
+	ui::{
+		command::{Command, ParseCommandError},
+		earcon::{Chime, Cue},
+		error::{self, Failure, Recovery, Session},
+		input::Input,
+		speech::Voice,
+	},
 	Phase,
 };
-use rustyline::Editor;
-use std::{net::SocketAddrV4, time::Duration};
-use structopt::StructOpt;
-use tokio::{
-	io::{stdout, AsyncWriteExt},
-	task::yield_now,
+use std::{
+	collections::VecDeque,
+	net::{SocketAddr, SocketAddrV4},
+	path::PathBuf,
+	process::exit,
+	str::FromStr,
+	time::Instant,
 };
-use tts::Tts;
+use structopt::StructOpt;
+use tokio::io::{stdout, AsyncWriteExt};
 
 #[derive(StructOpt)]
 struct Args {
-	/// The IP address of the other computer.
-	pub server: SocketAddrV4,
-	/// Whether to act as a server.
+	/// The IP address of the other computer, or the address to host on.
+	#[structopt(required_unless_one = &["serve", "join", "resume"])]
+	pub server: Option<SocketAddrV4>,
+	/// Whether to act as a server. Without an address, picks a free port and prints invite codes.
 	#[structopt(short, long)]
 	pub serve: bool,
+	/// Join a game using the invite code the host was given.
+	#[structopt(short, long, conflicts_with_all = &["server", "serve"])]
+	pub join: Option<Invite>,
 	/// Whether to call speech apis directly
 	#[structopt(short = "S", long)]
 	pub speak: bool,
 	/// How fast to talk
 	#[structopt(short = "p", long, default_value = "1.0")]
 	pub speed: f32,
+	/// A TOML file describing the variant to play. Both players must use the same rules.
+	#[structopt(long)]
+	pub rules: Option<PathBuf>,
+	/// A lobby server to relay the game through if the players can't connect directly.
+	#[structopt(long)]
+	pub lobby: Option<SocketAddr>,
+	/// How much to say: terse, normal or verbose.
+	#[structopt(long, default_value = "normal")]
+	pub verbosity: Verbosity,
+	/// Read each shot back and wait for enter before firing it.
+	#[structopt(short, long)]
+	pub confirm: bool,
+	/// Pick up the game left in the save slot after a crash or disconnect.
+	/// The other player has to continue too.
+	#[structopt(long = "continue", conflicts_with_all = &["join", "lobby"])]
+	pub resume: bool,
+	/// The save slot to autosave to and continue from.
+	#[structopt(long, default_value = "autosave")]
+	pub slot: String,
+	/// Play a short tone for hits, misses, sinkings and your turn, as set in the config file.
+	#[structopt(short, long)]
+	pub earcons: bool,
+	/// How the grid command draws the boards: grid, compact, ascii or unicode.
+	#[structopt(long, default_value = "grid")]
+	pub style: Style,
+}
+
+/// How much the speech client says.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Verbosity {
+	/// Just the facts, like "Hit." instead of "Your shot at Delta five hit the enemy."
+	Terse,
+	Normal,
+	/// Also the score after every shot.
+	Verbose,
 }
 
-async fn put(tts: &mut Option<Tts>, text: &str) {
-	println!("{}", text);
-	stdout().flush().await.expect("Broken pipe");
-	if let Some(tts) = tts {
-		if let Err(e) = tts.speak(text, true) {
-			eprintln!("Speech failed with {}.", e)
+impl FromStr for Verbosity {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.to_lowercase().as_str() {
+			"terse" => Ok(Verbosity::Terse),
+			"normal" => Ok(Verbosity::Normal),
+			"verbose" => Ok(Verbosity::Verbose),
+			_ => Err(format!(
+				"unknown verbosity {s:?}, expected terse, normal or verbose"
+			)),
+		}
+	}
+}
+
+/// How many announcements `repeat` can go back through.
+const HISTORY: usize = 5;
+
+/// Speaks and prints announcements, remembering the last few.
+struct Speaker {
+	voice: Option<Voice>,
+	chime: Option<Chime>,
+	verbosity: Verbosity,
+	history: VecDeque<String>,
+	/// How many announcements back the next `repeat` goes.
+	repeated: usize,
+}
+
+impl Speaker {
+	/// Announces `text`, remembering it for `repeat`.
+	async fn put(&mut self, text: &str) {
+		if self.history.len() == HISTORY {
+			self.history.pop_front();
+		}
+		self.history.push_back(text.to_string());
+		self.repeated = 0;
+		self.prompt(text).await;
+	}
+
+	/// Says `text` without remembering it, for prompts that come round again anyway.
+	async fn prompt(&mut self, text: &str) {
+		println!("{}", text);
+		stdout().flush().await.expect("Broken pipe");
+		if let Some(voice) = &mut self.voice {
+			if let Err(e) = voice.say(text, true) {
+				eprintln!("Speech failed with {}.", e)
+			}
+		}
+	}
+
+	/// Plays `cue`'s tone, if earcons are on.
+	fn cue(&self, cue: Cue) {
+		if let Some(chime) = &self.chime {
+			chime.play(cue);
+		}
+	}
+
+	/// Plays the tone for how a shot went, unless it vanished into the fog.
+	fn shot(&self, result: &TurnResults) {
+		match result {
+			TurnResults { hidden: true, .. } => {}
+			TurnResults { sunk: Some(_), .. } => self.cue(Cue::Sunk),
+			TurnResults { hit: true, .. } => self.cue(Cue::Hit),
+			TurnResults { .. } => self.cue(Cue::Miss),
+		}
+	}
+
+	/// Says the last announcement again, or the one before that if it was
+	/// just repeated, and so on back through the history.
+	async fn repeat(&mut self) {
+		let Some(index) = self.history.len().checked_sub(self.repeated + 1) else {
+			self.prompt("There's nothing further back to repeat.").await;
+			return;
+		};
+		let text = self.history[index].clone();
+		self.repeated += 1;
+		self.prompt(&text).await;
+	}
+
+	async fn wait(&mut self) {
+		if let Some(voice) = &mut self.voice {
+			voice.wait().await;
 		}
 	}
+
+	/// In verbose mode, says how many ships each side has left.
+	async fn score(&mut self, game: &GameFlow) {
+		if self.verbosity < Verbosity::Verbose {
+			return;
+		}
+		let (ours, theirs) = {
+			let state = game.state.read().await;
+			(state.surviving(state.you), state.surviving(!state.you))
+		};
+		self.put(&format!(
+			"You have {ours} ships left, and the enemy has {theirs}."
+		))
+		.await;
+		self.wait().await;
+	}
+}
+
+/// Reads a shot at `pos` back and waits for enter to fire it; anything else
+/// typed, including escape, calls it off. Returns `None` if input was closed.
+async fn confirm_shot(speaker: &mut Speaker, input: &mut Input, pos: Coord) -> Option<bool> {
+	speaker
+		.prompt(&format!(
+			"Fire at {}? Press enter to confirm or escape to cancel.",
+			pos.spoken()
+		))
+		.await;
+	let confirmed = input.next().await?.trim().is_empty();
+	if !confirmed {
+		speaker.put("Cancelled.").await;
+	}
+	Some(confirmed)
 }
 
-async fn wait_for_tts(tts: &mut Option<Tts>) {
-	let mut counter = 0;
-	while let Some(Ok(false)) = tts.as_mut().map(|v| v.is_speaking()) {
-		tokio::time::sleep(Duration::from_millis(1)).await;
-		if counter > 100 {
-			break;
+/// Hosts or joins a game, returning it and the address to reconnect to.
+async fn connect(
+	speaker: &mut Speaker,
+	args: &Args,
+	rules: Rules,
+) -> Result<(GameFlow, SocketAddr), GameFlowError> {
+	if args.serve {
+		let mut host = Host::bind(args.server.map(Into::into)).await?;
+		if let Some(lobby) = args.lobby {
+			if let Err(e) = host.register_relay(lobby).await {
+				speaker
+					.put(&format!("Couldn't offer the game through the relay ({e})."))
+					.await;
+			}
+		}
+		match host.port_mapping() {
+			Ok(mapping) => {
+				speaker
+					.put(&format!(
+						"Forwarded {} through your router.",
+						mapping.external()
+					))
+					.await;
+			}
+			Err(NatError::Unsupported) => {}
+			Err(e) => {
+				speaker.put(&format!("Couldn't forward a port through your router ({e}), so players outside your network may not be able to join.")).await;
+			}
 		}
-		counter += 1;
+		speaker
+			.put("Waiting for a challenger. They can join with any of these invite codes:")
+			.await;
+		for addr in host.addrs() {
+			if let SocketAddr::V4(addr) = addr {
+				speaker
+					.put(&format!("{} (address {addr})", Invite(addr)))
+					.await;
+			}
+		}
+		let addr = host.listener().local_addr()?;
+		Ok((host.accept(rules).await?, addr))
+	} else {
+		let addr = args.join.map_or(args.server, |invite| Some(invite.0));
+		let addr = addr.expect("structopt requires an address or invite");
+		speaker.put("Connecting...").await;
+		let game = match args.lobby {
+			Some(lobby) => GameFlow::join_with_relay(addr, lobby, rules).await,
+			None => GameFlow::new_with_rules(addr.into(), false, rules).await,
+		};
+		Ok((game?, addr.into()))
 	}
-	while let Some(Ok(true)) = tts.as_mut().map(|v| v.is_speaking()) {
-		yield_now().await;
+}
+
+/// What to call a ship out loud, if it's a ship at all.
+fn ship_name(ship: ShipKind) -> Option<&'static str> {
+	match ship {
+		ShipKind::Carrier => Some("aircraft carrier"),
+		ShipKind::Battleship => Some("battleship"),
+		ShipKind::Cruiser => Some("cruiser"),
+		ShipKind::Submarine => Some("submarine"),
+		ShipKind::Destroyer => Some("destroyer"),
+		_ => None,
 	}
 }
 
+async fn place(
+	speaker: &mut Speaker,
+	game: &GameFlow,
+	pos: Coord,
+	vertical: bool,
+) -> Result<(), GameFlowError> {
+	let Phase::Placing(ship) = game.phase().await else {
+		speaker.put("Cannot place a ship in this phase.").await;
+		return Ok(());
+	};
+	match game.place_ship(ship, pos, vertical).await {
+		Ok(()) => speaker.put("OK").await,
+		Err(GameFlowError::InvalidPlacement) => {
+			let board = game.board(false).await;
+			let mut blockers = Vec::new();
+			for cell in board.placement_conflicts(ship, pos, vertical) {
+				let Some(ship) = board.cell(cell).ship else {
+					continue;
+				};
+				if !blockers.contains(&ship) {
+					blockers.push(ship);
+				}
+			}
+			let blockers = blockers
+				.into_iter()
+				.filter_map(|ship| {
+					let (first, last) = board.span(ship)?;
+					let name = ship_name(ship)?;
+					Some(format!(
+						"your {name}, from {} to {}",
+						first.spoken(),
+						last.spoken()
+					))
+				})
+				.collect::<Vec<_>>();
+			if blockers.is_empty() {
+				speaker.put("That runs off the board.").await;
+			} else {
+				speaker
+					.put(&format!("Blocked by {}.", blockers.join(", and ")))
+					.await;
+			}
+		}
+		Err(e) => return Err(e),
+	}
+	Ok(())
+}
+
 #[tokio::main]
 async fn main() {
 	let args = Args::from_args();
-	let mut tts = if args.speak {
-		let tts = tts::Tts::default();
-		if let Err(e) = &tts {
+	let voice = if args.speak {
+		let voice = Voice::new(args.speed);
+		if let Err(e) = &voice {
 			eprintln!(
 				"Failed to connect to system TTS because {}, falling back to print line.",
 				e
 			);
 		}
-		tts.map(|mut tts| {
-			tts.set_rate(args.speed * tts.normal_rate())
-				.expect("Failed to set speech rate");
-			tts
-		})
-		.ok()
+		voice.ok()
+	} else {
+		None
+	};
+	let config = Config::load().unwrap_or_default();
+	let chime = if args.earcons {
+		Chime::new(config.earcons)
+			.map_err(|e| {
+				eprintln!(
+					"Failed to open the sound device because {}, so no tones.",
+					e
+				)
+			})
+			.ok()
 	} else {
 		None
 	};
-	let mut rl = Editor::<()>::new().expect("Failed to open prompt");
+	let mut speaker = Speaker {
+		voice,
+		chime,
+		verbosity: args.verbosity,
+		history: VecDeque::with_capacity(HISTORY),
+		repeated: 0,
+	};
+	let mut input = Input::editor();
+	let rules = match args.rules.as_ref().map(Rules::load).transpose() {
+		Ok(rules) => rules.unwrap_or_default(),
+		Err(e) => {
+			speaker.put(&format!("Bad rules: {e}")).await;
+			speaker.wait().await;
+			exit(1);
+		}
+	};
 
-	put(&mut tts, "Connecting...").await;
-	let game = GameFlow::new(std::net::SocketAddr::V4(args.server), args.serve)
-		.await
-		.expect("Failed to connect");
+	let connected = if args.resume {
+		let save = match Save::load(&args.slot) {
+			Ok(save) => save,
+			Err(e) => {
+				speaker
+					.put(&format!("Couldn't continue the saved game: {e}"))
+					.await;
+				speaker.wait().await;
+				exit(1);
+			}
+		};
+		let (addr, serve) = (save.addr, save.serve);
+		if serve {
+			speaker
+				.put("Waiting for your enemy to continue too...")
+				.await;
+		} else {
+			speaker.put("Reconnecting...").await;
+		}
+		error::resume(save).await.map(|game| (game, addr, serve))
+	} else {
+		connect(&mut speaker, &args, rules)
+			.await
+			.map(|(game, addr)| (game, addr, args.serve))
+	};
+	let (game, addr, serve) = match connected {
+		Ok(connected) => connected,
+		Err(GameFlowError::RulesMismatch { ours, theirs }) => {
+			speaker
+				.put(&format!(
+						"Your enemy is playing by different rules. Yours are {ours}. Theirs are {theirs}."
+					))
+				.await;
+			speaker.wait().await;
+			exit(1);
+		}
+		Err(GameFlowError::ResumeMismatch(ours, theirs)) => {
+			speaker
+				.put(&format!(
+					"You saved this game on turn {ours}, but your enemy saved it on turn {theirs}."
+				))
+				.await;
+			speaker.wait().await;
+			exit(1);
+		}
+		Err(e) => panic!("Failed to connect: {e}"),
+	};
+	let session = Session {
+		addr,
+		serve,
+		slot: args.slot.clone(),
+	};
+	session.autosave(&game);
+	match game.route() {
+		Route::Direct => speaker.put("Connected directly.").await,
+		Route::Relayed(_) => speaker.put("Connected through the relay.").await,
+	}
+	match archive::head_to_head(game.enemy_name().unwrap_or_default()) {
+		Ok(Some(record)) => speaker.put(&format!("{record}.")).await,
+		Ok(None) => {}
+		Err(e) => eprintln!("Couldn't look back at your games: {e}"),
+	}
+
+	if speaker.verbosity > Verbosity::Terse {
+		speaker.put("This is the speech version of net battleship. You can type 'help' in any mode to learn which commands are available.",
+		)
+		.await;
+	}
+	let mut last_enemy_shot: Option<Coord> = None;
+	let mut confirm = args.confirm;
+	let mut game = game;
+	loop {
+		tokio::spawn(hooks::run(config.hooks.clone(), game.events()));
+		let playing = play(
+			&mut speaker,
+			&mut input,
+			&game,
+			args.style,
+			&mut confirm,
+			&mut last_enemy_shot,
+		);
+		let result = tokio::select! {
+			result = error::catch(playing) => result,
+			_ = tokio::signal::ctrl_c() => Ok(Err(GameFlowError::Cancelled)),
+		};
+		let failure = match result {
+			Ok(Ok(())) => break,
+			Ok(Err(GameFlowError::Cancelled)) => {
+				resign(&mut speaker, &game, &session.slot).await;
+			}
+			// There's nobody left to reconnect to
+			Ok(Err(e @ (GameFlowError::Resigned | GameFlowError::Left))) => {
+				speaker.put(&Failure::from(e).to_string()).await;
+				break;
+			}
+			Ok(Err(e)) => Failure::from(e),
+			Err(failure) => failure,
+		};
+		// Catching Ctrl-C once stops it from interrupting by itself
+		game = tokio::select! {
+			game = recover(&mut speaker, &mut input, &session, &game, &failure) => game,
+			_ = tokio::signal::ctrl_c() => exit(130),
+		};
+	}
+	if let Phase::Done(won) = game.phase().await {
+		let outcome = if won { Outcome::Won } else { Outcome::Lost };
+		record(&mut speaker, &game, outcome).await;
+	}
+	speaker.wait().await;
+	if let Err(e) = Save::remove(&session.slot) {
+		eprintln!("Couldn't clear the autosave: {e}");
+	}
+}
 
-	put(
-		&mut tts,
-		"This is the speech version of net battleship. You can type 'help' in any mode to learn which commands are available.",
-	)
-	.await;
+/// Resigns the game when the player presses Ctrl-C, rather than just hanging
+/// up on the enemy: tells them, prints how the boards ended up, counts the
+/// loss and exits. Pressed once the game is over, it just leaves.
+async fn resign(speaker: &mut Speaker, game: &GameFlow, slot: &str) -> ! {
+	let outcome = match game.phase().await {
+		Phase::Done(true) => Outcome::Won,
+		Phase::Done(false) => Outcome::Lost,
+		_ => Outcome::Resigned,
+	};
+	// We're going either way
+	let _ = game.resign().await;
+	if outcome == Outcome::Resigned {
+		speaker.put("You resigned.").await;
+	}
+	println!("{}", game);
+	record(speaker, game, outcome).await;
+	speaker.wait().await;
+	// A resigned game is over, so there's nothing to continue
+	if let Err(e) = Save::remove(slot) {
+		eprintln!("Couldn't clear the autosave: {e}");
+	}
+	exit(130);
+}
 
+/// Counts a finished game in the stats kept in the config file, and keeps
+/// it in the history.
+async fn record(speaker: &mut Speaker, game: &GameFlow, outcome: Outcome) {
+	match Config::record(outcome) {
+		Ok(stats) => speaker.put(&format!("All your games: {stats}.")).await,
+		Err(e) => eprintln!("Couldn't update your stats: {e}"),
+	}
+	let snapshot = game.snapshot();
+	let opponent = game.enemy_name().unwrap_or_default().to_string();
+	let entry = Entry::new(
+		opponent,
+		outcome,
+		snapshot.rules.clone(),
+		snapshot.turn_number,
+	);
+	if let Err(e) = Archive::open().and_then(|archive| archive.add(&entry)) {
+		eprintln!("Couldn't add the game to your history: {e}");
+	}
+}
+
+/// Waits for the enemy to place their fleet, saying how far they've got
+/// whenever they place another ship.
+async fn wait_for_fleet(speaker: &mut Speaker, game: &GameFlow) -> Result<(), GameFlowError> {
+	let mut heard = game.events();
+	let fleet = game.state.read().await.rules.fleet.len();
+	if !game.state.read().await.enemy_placed() && game.enemy_placing() > 0 {
+		speaker
+			.put(&format!(
+				"The enemy has placed {} of {fleet} ships.",
+				game.enemy_placing()
+			))
+			.await;
+	}
+	let waiting = game.wait_for_fleet();
+	tokio::pin!(waiting);
 	loop {
-		wait_for_tts(&mut tts).await;
+		tokio::select! {
+			waited = &mut waiting => return waited,
+			Ok(event) = heard.recv() => {
+				if let Event::EnemyPlacing { placed, fleet } = event {
+					speaker
+						.put(&format!("The enemy has placed {placed} of {fleet} ships."))
+						.await;
+				}
+			}
+		}
+	}
+}
+
+/// Takes commands and announces the enemy's shots until the game is over or
+/// the player quits.
+async fn play(
+	speaker: &mut Speaker,
+	input: &mut Input,
+	game: &GameFlow,
+	style: Style,
+	confirm: &mut bool,
+	last_enemy_shot: &mut Option<Coord>,
+) -> Result<(), GameFlowError> {
+	let mut heard = game.events();
+	// The turn the your-turn tone was last played for
+	let mut cued = None;
+	loop {
+		speaker.wait().await;
+		while let Ok(event) = heard.try_recv() {
+			if let Event::Chat {
+				by: Player::Enemy,
+				text,
+			} = event
+			{
+				speaker.put(&format!("The enemy says: {text}")).await;
+			}
+		}
+		if game.phase().await == Phase::Ready {
+			speaker
+				.put("Your fleet is ready. Waiting for the enemy to place theirs.")
+				.await;
+			wait_for_fleet(speaker, game).await?;
+			continue;
+		}
 		if game.my_turn().await || game.phase().await != Phase::Playing {
-			put(
-				&mut tts,
-				&match game.state.read().await.phase {
+			let charged = game.scan_available().await;
+			let turn = game.turn_number().await;
+			if game.phase().await == Phase::Playing && cued != Some(turn) {
+				speaker.cue(Cue::YourTurn);
+				cued = Some(turn);
+			}
+			speaker
+				.prompt(&match game.state.read().await.phase {
 					netbattleship::Phase::Connecting => "Connecting...".to_string(),
+					netbattleship::Phase::Ready => "Waiting for the enemy...".to_string(),
 					netbattleship::Phase::Placing(s) => format!("Placing {:?}:", s),
-					netbattleship::Phase::Playing => "Your turn.".to_string(),
+					netbattleship::Phase::Playing if charged => {
+						format!("Turn {turn}. Your turn. Your radar is charged.")
+					}
+					netbattleship::Phase::Playing => format!("Turn {turn}. Your turn."),
 					netbattleship::Phase::Done(_) => "Done!".to_string(),
-				},
-			)
-			.await;
-			let readline = match rl.readline("") {
-				Ok(s) => s,
-				Err(e) => panic!("Reading failed with {}", e),
+					_ => "Waiting...".to_string(),
+				})
+				.await;
+			game.aiming().await;
+			let Some(readline) = input.next().await else {
+				return Ok(());
 			};
-			match readline.to_lowercase().as_str() {
-				"help" => {
-					put(
-							&mut tts,
-							&match game.state.read().await.phase {
+			match readline.parse::<Command>() {
+				Ok(Command::Help) => {
+					speaker.put(&match game.state.read().await.phase {
 								netbattleship::Phase::Connecting => {
 									"Commands are unavailable while connecting.".to_string()
 								}
+								netbattleship::Phase::Ready => {
+									"Commands are unavailable until the enemy has placed their ships.".to_string()
+								}
 								netbattleship::Phase::Placing(_) => {
 									[
 										"When placing a ship, you can take the following actions:",
@@ -120,7 +627,14 @@ async fn main() {
 										"2. Place a ship, by typing the letter P, followed by a letter from A to J, a number from 0 to 9, and optionally the letter V.",
 										"If V is omitted, the ship will be placed pointing right, in the increasing number direction.",
 										"If V is included, the ship will be placed pointing downwards, in the increasing letter direction.",
-										"3. Do nothing, to hear the prompt again, by pressing enter without typing anything."
+										"3. Place the rest of your ships randomly, by typing the word auto.",
+										"4. Place the rest of your ships like a layout, by typing the word layout followed by its name.",
+										"The built-in layouts are corners, diagonal and random-balanced.",
+										"5. Keep your fleet's layout to use in later games, by typing the words layout save followed by a name.",
+										"6. Hear what was last said again, by typing the word repeat. Type it again to go further back.",
+										"7. Print both boards as a grid for a braille display, by typing the word grid.",
+										"8. Do nothing, to hear the prompt again, by pressing enter without typing anything.",
+										"9. Leave the game, by typing the word quit."
 									].join("\n")
 								}
 								netbattleship::Phase::Playing => [
@@ -130,137 +644,385 @@ async fn main() {
 									"1. Query the board, by typing the letter Q, followed by a letter from A to J and a number from 0 to 9.",
 									"When querying the board, use a lowercase Q to query the enemy's board, and an uppercase Q to query your own.",
 									"2. Fire, by pressing the letter F, followed by a letter from A to J and a number from 0 to 9.",
-									"3. Do nothing, to hear the prompt again, by pressing enter without typing anything."
+									"3. Sweep with radar, once it's charged, by pressing the letter R, followed by a letter from A to J and a number from 0 to 9.",
+									"4. Move your submarine instead of firing, if the rules allow it, by pressing the letter M, followed by a letter from A to J, a number from 0 to 9, and optionally the letter V.",
+									"5. Hear what was last said again, by typing the word repeat. Type it again to go further back.",
+									"6. Hear where the enemy last fired, by typing the word where.",
+									"7. Turn reading shots back before firing on or off, by typing the word confirm.",
+									"When it's on, press enter to fire, or escape to cancel.",
+									"8. Hear how long each side has taken, by typing the word time.",
+									"9. Check that your game matches the enemy's, by typing the word sync.",
+									"10. Print both boards as a grid for a braille display, by typing the word grid.",
+									"11. Do nothing, to hear the prompt again, by pressing enter without typing anything.",
+									"12. Leave the game, by typing the word quit."
 								].join("\n"),
 								netbattleship::Phase::Done(_) => [
 									"After the game ends, you can take the following actions:",
 									"1. Query the board, by typing the letter Q, followed by a letter from A to J and a number from 0 to 9.",
 									"When querying the board, use a lowercase Q to query the enemy's board, and an uppercase Q to query your own.",
-									"2. Exit the game, by pressing control + c."
+									"2. Save a picture of the battle to share, by typing the word export, followed by a file name ending in .png or .svg.",
+									"3. Exit the game, by typing the word quit."
 								].join("\n"),
+								_ => "Commands are unavailable right now.".to_string(),
 							},
 						)
 						.await;
 				}
-				c if c.starts_with('p') => {
-					if let Phase::Placing(ship) = game.phase().await {
-						let coords = ui::parse_coord(&c[1..3]);
-						match coords {
-							Some(pos) => {
-								match game.place_ship(ship, pos, c.get(3..4) == Some("v")).await {
-									Ok(()) => put(&mut tts, "OK").await,
-									Err(e) => match e {
-										netbattleship::flow::GameFlowError::InvalidPlacement => {
-											put(&mut tts, "Placement out of bounds.").await
-										}
-										e => panic!("{}", e),
-									},
+				Ok(Command::Place { pos, vertical }) => {
+					place(speaker, game, pos, vertical).await?;
+				}
+				Ok(Command::Cell(pos)) if matches!(game.phase().await, Phase::Placing(_)) => {
+					place(speaker, game, pos, false).await?;
+				}
+				Ok(Command::Auto) => {
+					if let Phase::Placing(_) = game.phase().await {
+						match game.place_remaining().await {
+							Ok(_) => speaker.put("OK").await,
+							Err(_) => speaker.put("The rest of your fleet doesn't fit.").await,
+						}
+					} else {
+						speaker.put("Cannot place a ship in this phase.").await;
+					}
+				}
+				Ok(Command::Layout(name)) => {
+					let state = game.state.read().await.clone();
+					if let Phase::Placing(_) = state.phase {
+						let board = &state.board[usize::from(state.you)];
+						match layouts::placements(&name, board, &state.rules.fleet) {
+							Ok(placements) => {
+								for (ship, pos, v) in placements {
+									game.place_ship(ship, pos, v).await?;
 								}
+								speaker.put("OK").await;
+							}
+							Err(e) => {
+								speaker
+									.put(&format!("Couldn't use that layout: {e}."))
+									.await
 							}
-							None => put(&mut tts, "Bad coordinates").await,
 						}
 					} else {
-						put(&mut tts, "Cannot place a ship in this phase.").await;
+						speaker.put("Cannot place a ship in this phase.").await;
 					}
 				}
-				c if c.starts_with('q') => {
+				Ok(Command::SaveLayout(name)) => {
+					match layouts::save(&name, &game.board(false).await) {
+						Ok(()) => speaker.put(&format!("Saved your layout as {name}.")).await,
+						Err(e) => {
+							speaker
+								.put(&format!("Couldn't save your layout: {e}."))
+								.await
+						}
+					}
+				}
+				Ok(Command::Query { pos, own }) => {
 					if let Phase::Placing(_) | Phase::Playing | Phase::Done(_) = game.phase().await
 					{
-						let query_self = readline
-							.chars()
-							.next()
-							.map(|c| c.is_uppercase())
-							.unwrap_or(false);
-						let board = game.board(!query_self).await;
-						let coords = parse_coord(&c[1..3]);
-						match coords {
-							Some(pos) => {
-								put(
-									&mut tts,
-									match board
-										.board
-										.get(&pos)
-										.unwrap_or(&netbattleship::ship::Ship::None)
-									{
-										netbattleship::ship::Ship::None => "Empty.",
-										netbattleship::ship::Ship::Miss => "Missed shot.",
-										netbattleship::ship::Ship::Hit => "True shot.",
-										netbattleship::ship::Ship::Carrier => "Aircraft carrier.",
-										netbattleship::ship::Ship::Battleship => "Battleship.",
-										netbattleship::ship::Ship::Cruiser => "Cruiser.",
-										netbattleship::ship::Ship::Submarine => "Submarine.",
-										netbattleship::ship::Ship::Destroyer => "Destroyer.",
-									},
-								)
-								.await
+						let board = game.board(!own).await;
+						let cell = board.cell(pos);
+						let text = match (cell.state, cell.ship.and_then(ship_name)) {
+							_ if board.land().contains(&pos) => "Land.".to_string(),
+							(CellState::Untouched, None) => "Empty.".to_string(),
+							(CellState::Untouched, Some(name)) => {
+								format!("{}{}.", name[..1].to_uppercase(), &name[1..])
 							}
-							None => put(&mut tts, "Bad coordinates").await,
-						}
+							(CellState::Hit, None) => "True shot.".to_string(),
+							(CellState::Hit, Some(name)) => format!("Damaged {name}."),
+							(CellState::Miss, _) if own => "The enemy missed here.".to_string(),
+							(CellState::Miss, _) => "Missed shot.".to_string(),
+							(CellState::Unknown, _) => "Unknown shot.".to_string(),
+						};
+						speaker.put(&text).await;
 					} else {
-						put(&mut tts, "Cannot query the board in this phase.").await;
+						speaker.put("Cannot query the board in this phase.").await;
 					}
 				}
-				c if c.starts_with('f') => {
+				Ok(Command::Fire(pos) | Command::Cell(pos)) => {
 					if let Phase::Playing = game.phase().await {
 						if game.my_turn().await {
-							let coords = ui::parse_coord(&c[1..3]);
-							match coords {
-								Some(pos) => match game.fire(pos).await {
-									Ok(result) => {
-										if result.hit.is_some() {
-											put(&mut tts, "Your shot hit the enemy.").await;
-											wait_for_tts(&mut tts).await;
-										} else {
-											put(&mut tts, "Your shot hit the waves.").await;
-											wait_for_tts(&mut tts).await;
+							if *confirm {
+								match confirm_shot(speaker, input, pos).await {
+									Some(true) => {}
+									Some(false) => continue,
+									None => return Ok(()),
+								}
+							}
+							match game.fire(pos).await {
+								Ok(result) => {
+									speaker.shot(&result);
+									let aim = result.aim.spoken();
+									let terse = speaker.verbosity == Verbosity::Terse;
+									let message = match (result.hidden, result.hit) {
+										(true, _) if terse => "Lost in the fog.".to_string(),
+										(true, _) => {
+											format!("Your shot at {aim} vanished into the fog.")
 										}
-										if let Some(ship) = result.sunk {
-											put(
-												&mut tts,
-												&format!("You sunk the enemy {:?}!", ship),
-											)
-											.await;
-											wait_for_tts(&mut tts).await;
+										(false, true) if terse => "Hit.".to_string(),
+										(false, true) => {
+											format!("Your shot at {aim} hit the enemy.")
 										}
-										if result.won {
-											put(&mut tts, "You won the game!").await;
-											break;
+										(false, false) if terse => "Miss.".to_string(),
+										(false, false) => {
+											format!("Your shot at {aim} hit the waves.")
 										}
+									};
+									speaker.put(&message).await;
+									speaker.wait().await;
+									if let Some(name) = &result.sunk_name {
+										speaker.put(&format!("You sunk the enemy {}!", name)).await;
+										speaker.wait().await;
 									}
-									Err(e) => match e {
-										netbattleship::flow::GameFlowError::InvalidPlacement => {
-											put(&mut tts, "Placement out of bounds.").await
-										}
-										e => panic!("{}", e),
-									},
+									if result.won {
+										speaker.put("You won the game!").await;
+										return Ok(());
+									}
+									speaker.score(game).await;
+								}
+								Err(e) => match e {
+									GameFlowError::OutOfBounds(_) => {
+										speaker.put("That's off the board.").await
+									}
+									GameFlowError::Land(_) => speaker.put("That's land.").await,
+									GameFlowError::TimedOut => {
+										speaker
+											.put("You took too long to fire, and lost the game.")
+											.await;
+										return Ok(());
+									}
+									e => return Err(e),
 								},
-								None => put(&mut tts, "Bad coordinates").await,
 							}
 						}
 					} else {
-						put(&mut tts, "Cannot place a ship in this phase.").await;
+						speaker.put("Cannot fire in this phase.").await;
 					}
 				}
-
-				_ => put(&mut tts, "Unknown command.").await,
+				Ok(Command::Move { pos, vertical }) => {
+					match game.move_submarine(pos, vertical).await {
+						Ok(()) => speaker.put("Your submarine slipped away.").await,
+						Err(GameFlowError::CannotMove) => {
+							speaker.put("You can't move your submarine.").await;
+						}
+						Err(GameFlowError::InvalidPlacement) => {
+							speaker.put("Your submarine can't go there.").await;
+						}
+						Err(GameFlowError::OutOfOrder) => {
+							speaker
+								.put("Cannot move your submarine in this phase.")
+								.await;
+						}
+						Err(GameFlowError::TimedOut) => {
+							speaker.put("You took too long, and lost the game.").await;
+							return Ok(());
+						}
+						Err(e) => return Err(e),
+					}
+				}
+				Ok(Command::Scan(center)) => match game.scan(center).await {
+					Ok(count) => {
+						let center = center.spoken();
+						speaker
+							.put(&format!("Radar found {count} ship cells around {center}."))
+							.await;
+					}
+					Err(GameFlowError::NoScanAvailable) => {
+						speaker.put("Your radar isn't charged.").await;
+					}
+					Err(GameFlowError::OutOfBounds(_)) => {
+						speaker.put("That's off the board.").await;
+					}
+					Err(GameFlowError::OutOfOrder) => {
+						speaker.put("Cannot use radar in this phase.").await;
+					}
+					Err(e) => return Err(e),
+				},
+				Ok(Command::Sync) => match game.verify_sync().await {
+					Ok(0) => speaker.put("Your game matches the enemy's.").await,
+					Ok(n) => {
+						speaker
+							.put(&format!("Fixed {n} differences from the enemy's game."))
+							.await;
+					}
+					Err(GameFlowError::OutOfOrder) => {
+						speaker.put("Cannot check the game in this phase.").await;
+					}
+					Err(e) => return Err(e),
+				},
+				Ok(Command::Repeat) => speaker.repeat().await,
+				// Printed for a braille display, since reading it aloud would be no use
+				Ok(Command::Grid) => println!("{}", style.renderer().render(&game.snapshot())),
+				Ok(Command::Time) => speaker.put(&format!("{}.", game.clocks().await)).await,
+				Ok(Command::Confirm) => {
+					*confirm = !*confirm;
+					if *confirm {
+						speaker.put("Shots will be read back before firing.").await;
+					} else {
+						speaker.put("Shots will fire straight away.").await;
+					}
+				}
+				Ok(Command::Where) => match *last_enemy_shot {
+					Some(pos) => {
+						speaker
+							.put(&format!("The enemy last fired at {}.", pos.spoken()))
+							.await;
+					}
+					None => speaker.put("The enemy hasn't fired yet.").await,
+				},
+				Ok(Command::Chat(text)) => {
+					game.chat(&text).await;
+					speaker.put("Sent.").await;
+				}
+				Ok(Command::Export(path)) => {
+					let exported = image::export(&game.subscribe().borrow(), &path);
+					match exported {
+						Ok(()) => speaker.put("Saved the battle map.").await,
+						Err(e) => {
+							speaker
+								.put(&format!("Couldn't save the battle map: {e}"))
+								.await;
+						}
+					}
+				}
+				Ok(Command::Quit) => {
+					// We're going either way
+					let _ = game.shutdown().await;
+					return Ok(());
+				}
+				Ok(Command::Nothing) => {}
+				Err(ParseCommandError::Coord(_)) => speaker.put("Bad coordinates").await,
+				Err(ParseCommandError::Unknown) => speaker.put("Unknown command.").await,
 			}
 		} else {
-			put(&mut tts, "Enemy turn.").await;
-			let result = game.receive().await.unwrap();
-			if let Some(ship) = result.hit {
-				put(&mut tts, &format!("The enemy's shot hit your {:?}.", ship)).await;
-				wait_for_tts(&mut tts).await;
-			} else {
-				put(&mut tts, "The enemy's shot hit the waves.").await;
-				wait_for_tts(&mut tts).await;
+			speaker
+				.prompt(&format!("Turn {}. Enemy turn.", game.turn_number().await))
+				.await;
+			let result = match receive(speaker, game).await {
+				Ok(result) => result,
+				Err(GameFlowError::TimedOut) => {
+					speaker
+						.put("The enemy took too long to fire. You won the game!")
+						.await;
+					return Ok(());
+				}
+				Err(e) => return Err(e),
+			};
+			if result.moved {
+				speaker.put("The enemy's submarine has moved.").await;
+				speaker.wait().await;
+				continue;
 			}
-			if let Some(ship) = result.sunk {
-				put(&mut tts, &format!("The enemy sunk your {:?}.", ship)).await;
-				wait_for_tts(&mut tts).await;
+			*last_enemy_shot = Some(result.aim);
+			speaker.shot(&result);
+			let aim = result.aim.spoken();
+			let terse = speaker.verbosity == Verbosity::Terse;
+			let message = match result.ship {
+				Some(ship) if terse => format!("Enemy {aim}: hit your {:?}.", ship),
+				Some(ship) => format!("The enemy's shot at {aim} hit your {:?}.", ship),
+				None if terse => format!("Enemy {aim}: miss."),
+				None => format!("The enemy's shot at {aim} hit the waves."),
+			};
+			speaker.put(&message).await;
+			speaker.wait().await;
+			if let Some(name) = &result.sunk_name {
+				speaker.put(&format!("The enemy sunk your {}.", name)).await;
+				speaker.wait().await;
 			}
 			if result.won {
-				put(&mut tts, "You lost the game...").await;
+				speaker.put("You lost the game...").await;
+			} else {
+				speaker.score(game).await;
+			}
+		}
+	}
+}
+
+/// Waits for the enemy's shot, saying when they seem to stop aiming, and
+/// when they start again.
+async fn receive(speaker: &mut Speaker, game: &GameFlow) -> Result<TurnResults, GameFlowError> {
+	let receiving = game.receive();
+	tokio::pin!(receiving);
+	let waited = Instant::now();
+	let mut aiming = true;
+	let mut checks = tokio::time::interval(AIMING_INTERVAL);
+	loop {
+		tokio::select! {
+			result = &mut receiving => return result,
+			_ = checks.tick() => {
+				// They've only just started if they haven't said anything yet
+				let now = game.enemy_aiming() || waited.elapsed() < AIMING_IDLE;
+				if now != aiming {
+					aiming = now;
+					speaker
+						.put(if now { "The enemy is aiming." } else { "The enemy appears idle." })
+						.await;
+				}
+			}
+		}
+	}
+}
+
+/// Says why the game stopped and lets the player reconnect, save it or give
+/// up. Returns the reconnected game, since the other choices exit.
+async fn recover(
+	speaker: &mut Speaker,
+	input: &mut Input,
+	session: &Session,
+	game: &GameFlow,
+	failure: &Failure,
+) -> GameFlow {
+	speaker.put(&failure.to_string()).await;
+	loop {
+		speaker.wait().await;
+		speaker
+			.prompt("Type r to reconnect, s to save the game and quit, or q to quit.")
+			.await;
+		let Some(line) = input.next().await else {
+			exit(1)
+		};
+		match line.parse() {
+			Ok(Recovery::Reconnect) => {
+				if session.serve {
+					speaker.put("Waiting for your enemy to reconnect...").await;
+				} else {
+					speaker.put("Reconnecting...").await;
+				}
+				match session.reconnect(game).await {
+					Ok(game) => {
+						speaker.put("Reconnected.").await;
+						return game;
+					}
+					Err(e) => speaker.put(&Failure::from(e).to_string()).await,
+				}
+			}
+			Ok(Recovery::Save) => {
+				speaker
+					.prompt(&format!(
+						"Type a name to save the game as, or press enter to use {}.",
+						session.slot
+					))
+					.await;
+				let Some(slot) = input.next().await else {
+					exit(1)
+				};
+				let slot = match slot.trim() {
+					"" => session.slot.clone(),
+					slot => slot.to_string(),
+				};
+				match session.save(game).store(&slot) {
+					Ok(()) => {
+						speaker
+							.put(&format!(
+								"Saved as {slot}. To continue it later, start with --continue --slot {slot}."
+							))
+							.await;
+						speaker.wait().await;
+						exit(0);
+					}
+					Err(e) => speaker.put(&format!("Couldn't save the game: {e}")).await,
+				}
 			}
+			Ok(Recovery::Quit) => exit(1),
+			Err(()) => speaker.put("Unknown command.").await,
 		}
 	}
 }
@@ -0,0 +1,437 @@
+//! Computer players, which play for you with `--bot` or against you with
+//! `--practice`.
+//!
+//! Easy fires at random. Medium hunts at random until it hits something,
+//! then works along the ship until it sinks. Hard keeps a heatmap of every
+//! way the ships still afloat could fit around what it's seen, fires at the
+//! hottest square, and while hunting only looks at every nth square, since
+//! the shortest ship left can't slip between them. It also places its own
+//! ships away from where heatmaps like its own look first.
+//!
+//! Anything that plays by itself does so through [`Engine`], which
+//! [`taunt::Taunting`] wraps to give it something to say, and
+//! [`twitch::Twitch`] to let a stream's chat vote on its shots. [`practice`]
+//! sets one up as an opponent on this computer.
+
+pub mod past;
+pub mod taunt;
+#[cfg(feature = "async")]
+pub mod twitch;
+
+use std::{fmt, str::FromStr};
+
+use rand::seq::IndexedRandom;
+use serde::Deserialize;
+use thiserror::Error;
+
+#[cfg(feature = "async")]
+use crate::flow::{GameFlow, GameFlowError};
+use crate::{
+	board::{Board, Cell, CellState},
+	coord::Coord,
+	event::Event,
+	history::Move,
+	rules::Rules,
+	ship::ShipKind,
+	Game, Phase,
+};
+
+/// How much can be in flight between the two sides of a practice game.
+#[cfg(feature = "async")]
+const PRACTICE_BUFFER: usize = 4096;
+
+/// How many random spots hard bots try for each ship before taking the
+/// least likely to be found.
+const PLACEMENT_TRIES: usize = 16;
+
+/// How much more a placement counts for each hit it explains, so hard bots
+/// finish off ships they've found.
+const HIT_WEIGHT: u32 = 20;
+
+/// Something that plays a game by itself.
+pub trait Engine: Send {
+	/// Where to put `ship` on our `board`, or `None` if it doesn't fit
+	/// anywhere.
+	fn placement(&mut self, board: &Board, ship: ShipKind) -> Option<(Coord, bool)>;
+
+	/// Where to fire next in `game`, from our side of it.
+	fn aim(&mut self, game: &Game) -> Coord;
+
+	/// Something to say in chat about `event`, if anything. Says nothing by
+	/// default.
+	fn react(&mut self, _event: &Event) -> Option<String> {
+		None
+	}
+}
+
+impl<E: Engine + ?Sized> Engine for Box<E> {
+	fn placement(&mut self, board: &Board, ship: ShipKind) -> Option<(Coord, bool)> {
+		(**self).placement(board, ship)
+	}
+
+	fn aim(&mut self, game: &Game) -> Coord {
+		(**self).aim(game)
+	}
+
+	fn react(&mut self, event: &Event) -> Option<String> {
+		(**self).react(event)
+	}
+}
+
+/// How well a bot plays.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Difficulty {
+	Easy,
+	Medium,
+	Hard,
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("unknown difficulty {0:?}, expected easy, medium or hard")]
+pub struct ParseDifficultyError(String);
+
+impl FromStr for Difficulty {
+	type Err = ParseDifficultyError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.to_lowercase().as_str() {
+			"easy" => Ok(Difficulty::Easy),
+			"medium" => Ok(Difficulty::Medium),
+			"hard" => Ok(Difficulty::Hard),
+			_ => Err(ParseDifficultyError(s.to_string())),
+		}
+	}
+}
+
+impl fmt::Display for Difficulty {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			Difficulty::Easy => "easy",
+			Difficulty::Medium => "medium",
+			Difficulty::Hard => "hard",
+		})
+	}
+}
+
+impl Engine for Difficulty {
+	fn placement(&mut self, board: &Board, ship: ShipKind) -> Option<(Coord, bool)> {
+		let spots = cells(board)
+			.flat_map(|pos| [(pos, false), (pos, true)])
+			.filter(|&(pos, v)| ship.can_place(board, pos, v))
+			.collect::<Vec<_>>();
+		let mut rng = rand::rng();
+		match self {
+			Difficulty::Easy | Difficulty::Medium => spots.choose(&mut rng).copied(),
+			Difficulty::Hard => {
+				let heat = Heatmap::empty(board, &[ship]);
+				(0..PLACEMENT_TRIES)
+					.filter_map(|_| spots.choose(&mut rng).copied())
+					.min_by_key(|&(pos, v)| {
+						let found = ship
+							.footprint(pos, v)
+							.map(|cell| heat.at(cell))
+							.sum::<u32>();
+						// Ships touching are found together
+						let touching = ship
+							.footprint(pos, v)
+							.flat_map(|cell| board.neighbours(cell))
+							.filter(|&n| board.cell(n).ship.is_some())
+							.count();
+						found + u32::try_from(touching).unwrap_or(u32::MAX) * HIT_WEIGHT
+					})
+			}
+		}
+	}
+
+	fn aim(&mut self, game: &Game) -> Coord {
+		let board = &game.board[usize::from(!game.you)];
+		let untouched = cells(board)
+			.filter(|&pos| board.cell(pos).state == CellState::Untouched)
+			.collect::<Vec<_>>();
+		let mut rng = rand::rng();
+		let pick = match self {
+			Difficulty::Easy => None,
+			Difficulty::Medium => target(game, board).choose(&mut rng).copied(),
+			Difficulty::Hard => {
+				let afloat = afloat(game);
+				let hunting = open_hits(game, board) == 0;
+				let heat = Heatmap::of(game);
+				let smallest = afloat.iter().map(ShipKind::len).min().unwrap_or(1);
+				let parity = |pos: &Coord| (pos.x + pos.y).is_multiple_of(smallest);
+				let best = untouched
+					.iter()
+					.filter(|pos| !hunting || parity(pos))
+					.map(|&pos| heat.at(pos))
+					.max()
+					.unwrap_or(0);
+				let hottest = untouched
+					.iter()
+					.filter(|pos| !hunting || parity(pos))
+					.filter(|&&pos| heat.at(pos) == best)
+					.copied()
+					.collect::<Vec<_>>();
+				(best > 0)
+					.then(|| hottest.choose(&mut rng).copied())
+					.flatten()
+			}
+		};
+		pick.or_else(|| untouched.choose(&mut rng).copied())
+			.unwrap_or_default()
+	}
+}
+
+/// Starts a game against `engine` on this computer, by `rules`. We take
+/// the first shot, while the engine plays the other side in the background
+/// until the game is over.
+///
+/// # Errors
+/// Fails if the rules can't be played.
+#[cfg(feature = "async")]
+pub async fn practice(
+	rules: Rules,
+	engine: impl Engine + 'static,
+) -> Result<GameFlow, GameFlowError> {
+	let (ours, theirs) = tokio::io::duplex(PRACTICE_BUFFER);
+	let (us, them) = tokio::join!(
+		GameFlow::with_transport(ours, true, rules.clone()),
+		GameFlow::with_transport(theirs, false, rules),
+	);
+	let them = them?;
+	tokio::spawn(async move {
+		// If we hang up, there's nobody left to tell
+		let _ = them.play_with(engine).await;
+		let _ = them.done().await;
+	});
+	us
+}
+
+/// Plays `game` to the end with `engine`: places the fleet, then fires on
+/// our turns and waits out the enemy's, saying whatever the engine has to
+/// say in between.
+///
+/// # Errors
+/// Fails if the engine can't place a ship, or if the game breaks.
+#[cfg(feature = "async")]
+pub async fn play(game: &GameFlow, engine: &mut impl Engine) -> Result<(), GameFlowError> {
+	while let Phase::Placing(ship) = game.phase().await {
+		let board = game.board(false).await;
+		let (pos, v) = engine
+			.placement(&board, ship)
+			.ok_or(GameFlowError::InvalidPlacement)?;
+		game.place_ship(ship, pos, v).await?;
+	}
+	game.wait_for_fleet().await?;
+	let mut heard = game.events();
+	while game.phase().await == Phase::Playing {
+		if game.my_turn().await {
+			let aim = engine.aim(&game.subscribe().borrow());
+			game.fire(aim).await?;
+		} else {
+			game.receive().await?;
+		}
+		while let Ok(event) = heard.try_recv() {
+			// Once the game is over the enemy may already have hung up
+			if game.phase().await != Phase::Playing {
+				break;
+			}
+			if let Some(line) = engine.react(&event) {
+				game.chat(&line).await;
+			}
+		}
+	}
+	Ok(())
+}
+
+/// Has `hunter` fire at a fleet `placer` lays out by `rules` until it's all
+/// sunk, straight on the boards without any networking, returning how many
+/// shots that took. Bots are weighed against each other by how few shots
+/// they need.
+///
+/// # Panics
+/// Panics if `placer` can't fit the fleet.
+pub fn hunt(hunter: &mut impl Engine, placer: &mut impl Engine, rules: &Rules) -> usize {
+	let mut target = Board::new(rules);
+	for &ship in &rules.fleet {
+		let (pos, v) = placer
+			.placement(&target, ship)
+			.expect("the fleet fits the board");
+		ship.place(&mut target, pos, v);
+	}
+	let mut game = Game::new(rules.clone(), true);
+	game.phase = Phase::Playing;
+	let mut shots = 0;
+	while !target.all_sunk() {
+		let aim = hunter.aim(&game);
+		let cell = target.cell(aim);
+		let state = if cell.ship.is_some() {
+			CellState::Hit
+		} else {
+			CellState::Miss
+		};
+		let fresh = cell.state == CellState::Untouched;
+		target.board.insert(aim, Cell { state, ..cell });
+		let sunk = cell.ship.filter(|&ship| fresh && !target.contains(ship));
+		game.apply(Move::Shot {
+			by: game.you,
+			pos: aim,
+			hit: Some(state == CellState::Hit),
+			sunk,
+		});
+		shots += 1;
+	}
+	shots
+}
+
+/// Every square of `board` that isn't land.
+fn cells(board: &Board) -> impl Iterator<Item = Coord> + '_ {
+	let size = board.size();
+	(0..size)
+		.flat_map(move |y| (0..size).map(move |x| Coord::new(x, y)))
+		.filter(|&pos| board.is_water(pos))
+}
+
+/// The enemy's ships that haven't sunk yet.
+fn afloat(game: &Game) -> Vec<ShipKind> {
+	let sunk = &game.sunk[usize::from(!game.you)];
+	game.rules
+		.fleet
+		.iter()
+		.copied()
+		.filter(|ship| !sunk.contains(ship))
+		.collect()
+}
+
+/// How many hits on the enemy's `board` don't belong to a ship we've sunk.
+fn open_hits(game: &Game, board: &Board) -> usize {
+	let hits = board
+		.board
+		.values()
+		.filter(|cell| cell.state == CellState::Hit)
+		.count();
+	let sunk = game.sunk[usize::from(!game.you)]
+		.iter()
+		.map(|ship| usize::from(ship.len()))
+		.sum::<usize>();
+	hits.saturating_sub(sunk)
+}
+
+/// The squares to try next while finishing off a ship that's been hit: in
+/// line with two hits side by side if there are any, or else next to any
+/// hit. Empty while hunting.
+fn target(game: &Game, board: &Board) -> Vec<Coord> {
+	if open_hits(game, board) == 0 {
+		return Vec::new();
+	}
+	let hit = |pos: Coord| board.cell(pos).state == CellState::Hit;
+	let step = |pos: Coord, (dx, dy): (i16, i16)| {
+		let x = u8::try_from(i16::from(pos.x) + dx).ok()?;
+		let y = u8::try_from(i16::from(pos.y) + dy).ok()?;
+		Some(Coord::new(x, y)).filter(|&pos| board.is_water(pos))
+	};
+	let untouched =
+		|pos: Option<Coord>| pos.filter(|&pos| board.cell(pos).state == CellState::Untouched);
+	let directions = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+	let hits = board
+		.board
+		.iter()
+		.filter(|(_, cell)| cell.state == CellState::Hit)
+		.map(|(&pos, _)| pos)
+		.collect::<Vec<_>>();
+
+	// Carry on past the end of a line of hits
+	let mut along = Vec::new();
+	for &pos in &hits {
+		for direction in directions {
+			let behind = step(pos, (-direction.0, -direction.1));
+			if behind.is_some_and(hit) {
+				let mut end = pos;
+				while let Some(next) = step(end, direction).filter(|&next| hit(next)) {
+					end = next;
+				}
+				along.extend(untouched(step(end, direction)));
+			}
+		}
+	}
+	if !along.is_empty() {
+		return along;
+	}
+	hits.iter()
+		.flat_map(|&pos| directions.map(|direction| untouched(step(pos, direction))))
+		.flatten()
+		.collect()
+}
+
+/// How many ways the ships still afloat could cover each square.
+pub(crate) struct Heatmap {
+	size: u8,
+	heat: Vec<u32>,
+}
+
+impl Heatmap {
+	/// The heatmap hard bots fire by for the enemy's board in `game`, from
+	/// our side of it.
+	pub(crate) fn of(game: &Game) -> Self {
+		let board = &game.board[usize::from(!game.you)];
+		let targeting = open_hits(game, board) > 0;
+		Heatmap::new(board, &afloat(game), targeting)
+	}
+
+	/// The heatmap for an enemy `board` we've fired at. While `targeting`,
+	/// ships may lie across hits, and count for more the more they cover;
+	/// otherwise the hits are taken to be from ships already sunk.
+	fn new(board: &Board, afloat: &[ShipKind], targeting: bool) -> Self {
+		let mut map = Heatmap {
+			size: board.size(),
+			heat: vec![0; usize::from(board.size()).pow(2)],
+		};
+		for &ship in afloat {
+			for pos in cells(board) {
+				for v in [false, true] {
+					let footprint = ship.footprint(pos, v).collect::<Vec<_>>();
+					if footprint.len() != usize::from(ship.len())
+						|| !footprint.iter().all(|&cell| board.is_water(cell))
+					{
+						continue;
+					}
+					let states = footprint.iter().map(|&cell| board.cell(cell).state);
+					let mut hits = 0;
+					let mut fits = true;
+					for state in states {
+						match state {
+							CellState::Hit if targeting => hits += 1,
+							CellState::Untouched => {}
+							_ => fits = false,
+						}
+					}
+					if fits {
+						let weight = HIT_WEIGHT.pow(hits);
+						for cell in footprint {
+							if board.cell(cell).state == CellState::Untouched {
+								let i = map.index(cell);
+								map.heat[i] += weight;
+							}
+						}
+					}
+				}
+			}
+		}
+		map
+	}
+
+	/// The heatmap of a board nobody has fired at yet, which is where a
+	/// heatmap hunter looks first for `ships`.
+	fn empty(board: &Board, ships: &[ShipKind]) -> Self {
+		let mut blank = board.clone();
+		blank.board.clear();
+		Heatmap::new(&blank, ships, false)
+	}
+
+	fn index(&self, pos: Coord) -> usize {
+		usize::from(pos.y) * usize::from(self.size) + usize::from(pos.x)
+	}
+
+	pub(crate) fn at(&self, pos: Coord) -> u32 {
+		self.heat.get(self.index(pos)).copied().unwrap_or(0)
+	}
+}
@@ -0,0 +1,106 @@
+//! Practising against yourself: an engine that lays its ships out the way
+//! you did in games you saved replays of.
+
+use std::path::Path;
+
+use rand::seq::IndexedRandom;
+
+use crate::{
+	board::Board,
+	coord::Coord,
+	event::Event,
+	history::Move,
+	replay::{Replay, ReplayError},
+	rules::Rules,
+	ship::ShipKind,
+	Game,
+};
+
+use super::Engine;
+
+/// Where one ship went.
+type Placement = (ShipKind, Coord, bool);
+
+/// An engine that places its fleet as one of your past games did, picked at
+/// random, and otherwise plays like the one it wraps.
+pub struct PastYou<E> {
+	pub engine: E,
+	/// Every fleet you laid out, in the order you placed the ships.
+	layouts: Vec<Vec<Placement>>,
+	/// The layout used this game, once one has been picked.
+	layout: Option<Vec<Placement>>,
+}
+
+impl<E> PastYou<E> {
+	/// Collects the layouts from the replays in `paths` that were played by
+	/// `rules`, since a fleet from another variant wouldn't fit. Replays of
+	/// games that ended before every ship was placed are skipped too.
+	///
+	/// # Errors
+	/// Fails if any of the replays can't be read.
+	pub fn load(
+		engine: E,
+		rules: &Rules,
+		paths: impl IntoIterator<Item = impl AsRef<Path>>,
+	) -> Result<Self, ReplayError> {
+		let mut layouts = Vec::new();
+		for path in paths {
+			let replay = Replay::load(path.as_ref())?;
+			if replay.header.rules.board_size != rules.board_size
+				|| replay.header.rules.fleet != rules.fleet
+			{
+				continue;
+			}
+			let layout = replay
+				.moves
+				.iter()
+				.filter_map(|step| match *step {
+					Move::Place {
+						ship,
+						pos,
+						vertical,
+					} => Some((ship, pos, vertical)),
+					_ => None,
+				})
+				.collect::<Vec<_>>();
+			if layout.len() == rules.fleet.len() {
+				layouts.push(layout);
+			}
+		}
+		Ok(PastYou {
+			engine,
+			layouts,
+			layout: None,
+		})
+	}
+
+	/// How many of your past fleets there are to pick from.
+	#[must_use]
+	pub fn layouts(&self) -> usize {
+		self.layouts.len()
+	}
+}
+
+impl<E: Engine> Engine for PastYou<E> {
+	fn placement(&mut self, board: &Board, ship: ShipKind) -> Option<(Coord, bool)> {
+		if self.layout.is_none() {
+			self.layout = self.layouts.choose(&mut rand::rng()).cloned();
+		}
+		let past = self
+			.layout
+			.iter()
+			.flatten()
+			.find(|&&(placed, ..)| placed == ship)
+			.map(|&(_, pos, vertical)| (pos, vertical))
+			.filter(|&(pos, vertical)| ship.can_place(board, pos, vertical));
+		past.or_else(|| self.engine.placement(board, ship))
+	}
+
+	fn aim(&mut self, game: &Game) -> Coord {
+		self.engine.aim(game)
+	}
+
+	fn react(&mut self, event: &Event) -> Option<String> {
+		self.engine.react(event)
+	}
+}
@@ -0,0 +1,140 @@
+//! Things for a bot to say over chat as the shots land, from a TOML file of
+//! lines like:
+//!
+//! ```toml
+//! chance = 0.5
+//! hit = ["Found you!"]
+//! sunk = ["So long, {ship}."]
+//! enemy_miss = ["Not even close."]
+//! ```
+//!
+//! `{ship}` stands for the ship that was hit or sunk. Any list left out keeps
+//! the built-in lines, and an empty list keeps the bot quiet at that moment.
+
+use std::{fs, io, path::Path};
+
+use rand::{seq::IndexedRandom, RngExt};
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{
+	board::Board,
+	coord::Coord,
+	event::{Event, Player},
+	ship::ShipKind,
+	Game,
+};
+
+use super::Engine;
+
+/// What a bot says when.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Taunts {
+	/// How likely the bot is to say anything after a shot, from 0 to 1.
+	pub chance: f64,
+	/// When the bot hits one of the enemy's ships.
+	pub hit: Vec<String>,
+	/// When the bot misses.
+	pub miss: Vec<String>,
+	/// When the bot sinks one of the enemy's ships.
+	pub sunk: Vec<String>,
+	/// When the enemy hits one of the bot's ships.
+	pub enemy_hit: Vec<String>,
+	/// When the enemy misses.
+	pub enemy_miss: Vec<String>,
+	/// When the enemy sinks one of the bot's ships.
+	pub enemy_sunk: Vec<String>,
+}
+
+impl Default for Taunts {
+	fn default() -> Self {
+		let lines = |lines: &[&str]| lines.iter().map(ToString::to_string).collect();
+		Taunts {
+			chance: 0.5,
+			hit: lines(&[
+				"Found you!",
+				"Direct hit!",
+				"That one's going to leave a mark.",
+			]),
+			miss: lines(&["Just warming up.", "The sea can't hide you forever."]),
+			sunk: lines(&["So long, {ship}!", "Your {ship} sleeps with the fishes."]),
+			enemy_hit: lines(&["Lucky shot.", "My {ship}! You'll pay for that."]),
+			enemy_miss: lines(&["Not even close.", "Are you aiming with your eyes shut?"]),
+			enemy_sunk: lines(&["Not my {ship}!", "You'll regret that."]),
+		}
+	}
+}
+
+#[derive(Error, Debug)]
+pub enum TauntError {
+	#[error("couldn't read taunts file: {0}")]
+	Io(#[from] io::Error),
+	#[error("couldn't parse taunts file: {0}")]
+	Parse(#[from] toml::de::Error),
+}
+
+impl Taunts {
+	/// Reads taunts from a TOML file.
+	///
+	/// # Errors
+	/// Fails if the file can't be read or parsed.
+	pub fn load(path: impl AsRef<Path>) -> Result<Taunts, TauntError> {
+		Ok(toml::from_str(&fs::read_to_string(path)?)?)
+	}
+
+	/// A line to say about `event`, from the point of view of the player
+	/// whose events they are, or `None` if there's nothing to say or the bot
+	/// keeps quiet this time. Shots the blind rule hides are never remarked on.
+	#[must_use]
+	pub fn line(&self, event: &Event) -> Option<String> {
+		let Event::Result {
+			by,
+			hit: Some(hit),
+			ship,
+			sunk,
+			..
+		} = *event
+		else {
+			return None;
+		};
+		let lines = match (by, hit, sunk) {
+			(Player::You, _, Some(_)) => &self.sunk,
+			(Player::You, true, None) => &self.hit,
+			(Player::You, false, None) => &self.miss,
+			(Player::Enemy, _, Some(_)) => &self.enemy_sunk,
+			(Player::Enemy, true, None) => &self.enemy_hit,
+			(Player::Enemy, false, None) => &self.enemy_miss,
+		};
+		let mut rng = rand::rng();
+		if !rng.random_bool(self.chance.clamp(0.0, 1.0)) {
+			return None;
+		}
+		let line = lines.choose(&mut rng)?;
+		let ship = sunk
+			.or(ship)
+			.map_or("ship".to_string(), |ship| format!("{ship:?}"));
+		Some(line.replace("{ship}", &ship))
+	}
+}
+
+/// An engine that plays like the one it wraps, but taunts the enemy as the
+/// shots land.
+pub struct Taunting<E> {
+	pub engine: E,
+	pub taunts: Taunts,
+}
+
+impl<E: Engine> Engine for Taunting<E> {
+	fn placement(&mut self, board: &Board, ship: ShipKind) -> Option<(Coord, bool)> {
+		self.engine.placement(board, ship)
+	}
+
+	fn aim(&mut self, game: &Game) -> Coord {
+		self.engine.aim(game)
+	}
+
+	fn react(&mut self, event: &Event) -> Option<String> {
+		self.engine.react(event).or_else(|| self.taunts.line(event))
+	}
+}
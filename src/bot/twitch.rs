@@ -0,0 +1,228 @@
+//! Letting a stream's viewers play: an engine that fires wherever Twitch chat
+//! votes for on our turn.
+//!
+//! Chat is read over Twitch's IRC gateway by a task of its own. Any message
+//! that's just a square, like `B7`, is a vote for it, and each viewer's
+//! latest vote is the one that counts. When the countdown runs out the square
+//! with the most votes that can still be fired at is, ties going to whichever
+//! got there first. If nobody voted for anywhere that can be, or chat has gone
+//! away, the wrapped engine aims instead, and it places the fleet too.
+
+use std::{
+	collections::BTreeMap,
+	env, io,
+	sync::mpsc::{self, Receiver, RecvTimeoutError},
+	time::{Duration, Instant},
+};
+
+use tokio::{
+	io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+	net::{TcpStream, ToSocketAddrs},
+	sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+};
+
+use crate::{
+	board::{Board, CellState},
+	coord::Coord,
+	event::Event,
+	ship::ShipKind,
+	Game,
+};
+
+use super::Engine;
+
+/// Twitch's IRC gateway, without TLS.
+pub const TWITCH_IRC: &str = "irc.chat.twitch.tv:6667";
+
+/// The environment variables to log in to chat with, so the token stays off
+/// the command line.
+pub const NICK_VAR: &str = "NETBATTLESHIP_TWITCH_NICK";
+pub const TOKEN_VAR: &str = "NETBATTLESHIP_TWITCH_TOKEN";
+
+/// Who to read chat as. Without a login chat is only read, under one of the
+/// names Twitch keeps for anonymous readers, so nothing is posted to it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Login {
+	pub nick: String,
+	/// An OAuth token with permission to chat, with or without the `oauth:`
+	/// in front.
+	pub token: String,
+}
+
+impl Login {
+	/// The login in [`NICK_VAR`] and [`TOKEN_VAR`], if both are set.
+	#[must_use]
+	pub fn from_env() -> Option<Login> {
+		Some(Login {
+			nick: env::var(NICK_VAR).ok()?,
+			token: env::var(TOKEN_VAR).ok()?,
+		})
+	}
+}
+
+/// A vote for a square, and who cast it.
+type Vote = (String, Coord);
+
+/// An engine that fires where chat votes for, and otherwise plays like the
+/// one it wraps.
+pub struct Twitch<E> {
+	pub engine: E,
+	/// How long chat has to vote on each shot.
+	countdown: Duration,
+	votes: Receiver<Vote>,
+	/// Lines to post to chat, if we're logged in to post them.
+	say: UnboundedSender<String>,
+}
+
+impl<E> Twitch<E> {
+	/// Joins `channel`'s chat on the IRC server at `addr`, usually
+	/// [`TWITCH_IRC`], giving chat `countdown` to vote on each shot and
+	/// leaving everything else to `engine`. Reading chat carries on in a
+	/// task on the current runtime.
+	///
+	/// # Errors
+	/// Fails if the server can't be reached.
+	pub async fn connect(
+		addr: impl ToSocketAddrs,
+		channel: &str,
+		login: Option<Login>,
+		engine: E,
+		countdown: Duration,
+	) -> io::Result<Self> {
+		let mut stream = TcpStream::connect(addr).await?;
+		let channel = channel.trim_start_matches('#').to_lowercase();
+		let greeting = match &login {
+			Some(Login { nick, token }) => {
+				let token = token.trim_start_matches("oauth:");
+				format!("PASS oauth:{token}\r\nNICK {}\r\n", nick.to_lowercase())
+			}
+			None => format!("NICK justinfan{}\r\n", rand::random::<u32>() % 100_000),
+		};
+		stream
+			.write_all(format!("{greeting}JOIN #{channel}\r\n").as_bytes())
+			.await?;
+		let (votes, heard) = mpsc::channel();
+		let (say, said) = unbounded_channel();
+		let speaking = login.is_some().then_some(said);
+		tokio::spawn(async move {
+			// Losing chat only loses the votes, which the engine covers for
+			let _ = read(stream, &channel, &votes, speaking).await;
+		});
+		Ok(Twitch {
+			engine,
+			countdown,
+			votes: heard,
+			say,
+		})
+	}
+}
+
+impl<E: Engine> Engine for Twitch<E> {
+	fn placement(&mut self, board: &Board, ship: ShipKind) -> Option<(Coord, bool)> {
+		self.engine.placement(board, ship)
+	}
+
+	/// Waits out the countdown, counting chat's votes.
+	fn aim(&mut self, game: &Game) -> Coord {
+		let board = &game.board[usize::from(!game.you)];
+		let open =
+			|pos: Coord| board.is_water(pos) && board.cell(pos).state == CellState::Untouched;
+		// Votes from before our turn are for shots already taken
+		while self.votes.try_recv().is_ok() {}
+		let seconds = self.countdown.as_secs();
+		let _ = self.say.send(format!(
+			"Where should we fire? Vote with a square like B7, you have {seconds} seconds."
+		));
+		let deadline = Instant::now() + self.countdown;
+		let mut ballots: Vec<Vote> = Vec::new();
+		loop {
+			let left = deadline.saturating_duration_since(Instant::now());
+			match self.votes.recv_timeout(left) {
+				Ok((viewer, pos)) if open(pos) => {
+					ballots.retain(|(voter, _)| *voter != viewer);
+					ballots.push((viewer, pos));
+				}
+				Ok(_) => {}
+				Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => break,
+			}
+		}
+		let mut tally = BTreeMap::new();
+		for (_, pos) in &ballots {
+			*tally.entry(*pos).or_insert(0) += 1;
+		}
+		let most = tally.values().copied().max().unwrap_or(0);
+		let winner = ballots
+			.iter()
+			.map(|&(_, pos)| pos)
+			.find(|pos| tally[pos] == most);
+		if let Some(pos) = winner {
+			let _ = self.say.send(format!(
+				"Firing at {pos}, with {most} of {} votes.",
+				ballots.len()
+			));
+			pos
+		} else {
+			let pos = self.engine.aim(game);
+			let _ = self.say.send(format!("Nobody voted, so firing at {pos}."));
+			pos
+		}
+	}
+
+	fn react(&mut self, event: &Event) -> Option<String> {
+		self.engine.react(event)
+	}
+}
+
+/// Passes on the votes in `channel`'s chat until the server hangs up or the
+/// engine's gone, posting whatever comes through `said` if we can.
+async fn read(
+	stream: TcpStream,
+	channel: &str,
+	votes: &mpsc::Sender<Vote>,
+	mut said: Option<UnboundedReceiver<String>>,
+) -> io::Result<()> {
+	let (read, mut write) = stream.into_split();
+	let mut lines = BufReader::new(read).lines();
+	loop {
+		let saying = async {
+			match &mut said {
+				Some(said) => said.recv().await,
+				None => std::future::pending().await,
+			}
+		};
+		tokio::select! {
+			line = lines.next_line() => {
+				let Some(line) = line? else { return Ok(()) };
+				if let Some(server) = line.strip_prefix("PING") {
+					write.write_all(format!("PONG{server}\r\n").as_bytes()).await?;
+				} else if let Some(vote) = vote(&line) {
+					if votes.send(vote).is_err() {
+						return Ok(());
+					}
+				}
+			}
+			Some(text) = saying => {
+				write
+					.write_all(format!("PRIVMSG #{channel} :{text}\r\n").as_bytes())
+					.await?;
+			}
+		}
+	}
+}
+
+/// The vote in an IRC line, if it's a chat message that's just a square.
+fn vote(line: &str) -> Option<Vote> {
+	// Twitch only sends tags when asked to, but they'd come first
+	let line = match line.strip_prefix('@') {
+		Some(tagged) => tagged.split_once(' ')?.1,
+		None => line,
+	};
+	let (prefix, rest) = line.strip_prefix(':')?.split_once(' ')?;
+	let (command, rest) = rest.split_once(' ')?;
+	if command != "PRIVMSG" {
+		return None;
+	}
+	let (_, text) = rest.split_once(" :")?;
+	let viewer = prefix.split('!').next()?.to_string();
+	Some((viewer, text.trim().parse().ok()?))
+}
@@ -0,0 +1,156 @@
+//! Passing a game on to anyone watching as it's played: spectators connected
+//! over TCP, who get each event as a line of JSON like `--json-events`
+//! writes, and a replay file that's kept up to date after every move.
+
+use std::{net::SocketAddr, path::PathBuf, sync::Arc};
+
+use tokio::{
+	io::AsyncWriteExt,
+	net::{TcpListener, TcpStream},
+	sync::{broadcast::error::RecvError, Mutex},
+	task::JoinHandle,
+};
+
+use crate::{
+	event::{Event, Player},
+	flow::GameFlow,
+	replay::{Replay, ReplayError},
+	Phase,
+};
+
+/// Where a game is being passed on to. One broadcast can follow several
+/// games in turn, such as a game and its reconnections, keeping the same
+/// spectators and replay file.
+#[derive(Default)]
+pub struct Broadcast {
+	spectators: Arc<Mutex<Vec<TcpStream>>>,
+	listener: Option<JoinHandle<()>>,
+	/// The replay file, and what to call us and the enemy in it.
+	replay: Option<(PathBuf, String, String)>,
+}
+
+impl Broadcast {
+	/// Lets spectators watch by connecting to `addr`, from now on. Returns
+	/// the address they should connect to.
+	///
+	/// # Errors
+	/// Fails if `addr` can't be listened on.
+	pub async fn listen(&mut self, addr: SocketAddr) -> std::io::Result<SocketAddr> {
+		let listener = TcpListener::bind(addr).await?;
+		let local = listener.local_addr()?;
+		let spectators = self.spectators.clone();
+		if let Some(old) = self.listener.replace(tokio::spawn(async move {
+			while let Ok((socket, _)) = listener.accept().await {
+				let _ = socket.set_nodelay(true);
+				spectators.lock().await.push(socket);
+			}
+		})) {
+			old.abort();
+		}
+		Ok(local)
+	}
+
+	/// Keeps a replay of every game followed in `path`, calling the players
+	/// `you` and `enemy`.
+	pub fn record(&mut self, path: PathBuf, you: String, enemy: String) {
+		self.replay = Some((path, you, enemy));
+	}
+
+	/// How many spectators are watching.
+	pub async fn spectators(&self) -> usize {
+		self.spectators.lock().await.len()
+	}
+
+	/// Passes `game` on until it's over or dropped. The task finishes with
+	/// the first error writing the replay, if there was one; spectators
+	/// who hang up are just forgotten.
+	#[must_use]
+	pub fn follow(&self, game: &GameFlow) -> JoinHandle<Result<(), ReplayError>> {
+		let mut events = game.events();
+		let mut snapshots = game.subscribe();
+		let spectators = self.spectators.clone();
+		let replay = self.replay.clone();
+		tokio::spawn(async move {
+			let mut recorded = None;
+			let mut result = Ok(());
+			let mut done = false;
+			loop {
+				tokio::select! {
+					event = events.recv() => match event {
+						Ok(event) => {
+							done |= matches!(event, Event::Done { .. });
+							if let Some(event) = sanitize(event) {
+								send(&spectators, &event).await;
+							}
+						}
+						Err(RecvError::Lagged(_)) => {}
+						Err(RecvError::Closed) => break,
+					},
+					changed = snapshots.changed() => if changed.is_err() {
+						break;
+					},
+				}
+				// Every move lands in the log, so a longer log or a new
+				// phase means the replay is out of date
+				let game = snapshots.borrow().clone();
+				let progress = (game.log.len(), game.phase.clone());
+				if recorded.as_ref() != Some(&progress) {
+					if let (Some((path, you, enemy)), Ok(())) = (&replay, &result) {
+						result = Replay::new(&game, you.clone(), enemy.clone()).store(path);
+					}
+					recorded = Some(progress);
+				}
+				if done && matches!(game.phase, Phase::Done(_)) {
+					break;
+				}
+			}
+			result
+		})
+	}
+}
+
+impl Drop for Broadcast {
+	fn drop(&mut self) {
+		if let Some(listener) = &self.listener {
+			listener.abort();
+		}
+	}
+}
+
+/// What spectators may see of `event`. They could be passing it on to the
+/// enemy, so where our ships are and which one was hit stay secret, just as
+/// they are from the enemy.
+#[must_use]
+pub fn sanitize(event: Event) -> Option<Event> {
+	match event {
+		Event::Placing { .. } | Event::Placed { .. } => None,
+		Event::Result {
+			by: Player::Enemy,
+			pos,
+			hit,
+			sunk,
+			..
+		} => Some(Event::Result {
+			by: Player::Enemy,
+			pos,
+			hit,
+			ship: None,
+			sunk,
+		}),
+		event => Some(event),
+	}
+}
+
+/// Writes `event` to every spectator, forgetting any that have gone.
+async fn send(spectators: &Mutex<Vec<TcpStream>>, event: &Event) {
+	let mut line = String::from(event.clone());
+	line.push('\n');
+	let mut spectators = spectators.lock().await;
+	let mut watching = Vec::with_capacity(spectators.len());
+	for mut spectator in spectators.drain(..) {
+		if spectator.write_all(line.as_bytes()).await.is_ok() {
+			watching.push(spectator);
+		}
+	}
+	*spectators = watching;
+}
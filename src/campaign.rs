@@ -0,0 +1,297 @@
+//! Single-player campaigns: a directory of missions against the computer,
+//! each a TOML file like:
+//!
+//! ```toml
+//! name = "Harbour patrol"
+//! briefing = "Their fleet is hiding in the harbour, along the top rows."
+//! bot = "medium"
+//! after = "01-training"
+//! area = ["A0", "C9"]
+//! fleet = [{ ship = "Carrier", at = "A0" }, { ship = "Submarine", at = "B7", vertical = true }]
+//!
+//! [rules]
+//! salvo = true
+//! ```
+//!
+//! A mission is named after its file, without the `.toml`, and is locked
+//! until the mission it comes `after` has been won; missions without one are
+//! open from the start. The enemy places the ships listed in `fleet` where it
+//! says, and the rest at random, inside `area` if there is one. `rules` are
+//! the variant to play, and missing keys keep the standard ones.
+
+use std::{
+	collections::BTreeSet,
+	fs, io,
+	path::{Path, PathBuf},
+};
+
+use rand::seq::IndexedRandom;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{
+	board::Board,
+	bot::{Difficulty, Engine},
+	coord::{Coord, ParseCoordError},
+	event::Event,
+	rules::{Rules, RulesError},
+	ship::ShipKind,
+	Game,
+};
+
+/// Where one ship goes.
+type Placement = (ShipKind, Coord, bool);
+
+#[derive(Error, Debug)]
+pub enum CampaignError {
+	#[error("couldn't read the campaign: {0}")]
+	Io(#[from] io::Error),
+	#[error("couldn't parse {0}: {1}")]
+	Parse(PathBuf, toml::de::Error),
+	#[error("{0} can't be played: {1}")]
+	Rules(String, RulesError),
+	#[error("{0} has a bad square {1:?}: {2}")]
+	Coord(String, String, ParseCoordError),
+	#[error("{0} places a {1:?} where it doesn't fit, or that isn't in the fleet")]
+	Placement(String, ShipKind),
+	#[error("{0} comes after {1:?}, which isn't in the campaign")]
+	After(String, String),
+	#[error("there are no missions in {0}")]
+	Empty(PathBuf),
+}
+
+/// A mission as written in its file.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MissionFile {
+	name: String,
+	#[serde(default)]
+	briefing: String,
+	#[serde(default = "easy")]
+	bot: Difficulty,
+	after: Option<String>,
+	area: Option<[String; 2]>,
+	#[serde(default)]
+	fleet: Vec<ShipFile>,
+	#[serde(default)]
+	rules: Rules,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ShipFile {
+	ship: ShipKind,
+	at: String,
+	#[serde(default)]
+	vertical: bool,
+}
+
+fn easy() -> Difficulty {
+	Difficulty::Easy
+}
+
+/// One game of a campaign.
+#[derive(Clone, Debug)]
+pub struct Mission {
+	/// The mission's file name, without the `.toml`.
+	pub id: String,
+	pub name: String,
+	/// What to tell the player before the game starts.
+	pub briefing: String,
+	/// How well the enemy plays.
+	pub bot: Difficulty,
+	/// The mission that has to be won before this one can be played.
+	pub after: Option<String>,
+	/// The corners of the rectangle the enemy's ships all lie in, if they're
+	/// kept to one.
+	pub area: Option<(Coord, Coord)>,
+	/// The enemy ships with a fixed place.
+	pub fleet: Vec<Placement>,
+	pub rules: Rules,
+}
+
+/// A set of missions, played in the order they unlock.
+#[derive(Clone, Debug)]
+pub struct Campaign {
+	/// The campaign's directory name, which progress is kept under.
+	pub name: String,
+	/// Every mission, in file name order.
+	pub missions: Vec<Mission>,
+}
+
+impl Campaign {
+	/// Reads every `.toml` file in `dir` as a mission, checking that each can
+	/// be played and that whatever they come after exists.
+	///
+	/// # Errors
+	/// Fails if the directory or any mission can't be read, or a mission
+	/// doesn't make sense.
+	pub fn load(dir: impl AsRef<Path>) -> Result<Campaign, CampaignError> {
+		let dir = dir.as_ref();
+		let mut paths = fs::read_dir(dir)?
+			.map(|entry| entry.map(|entry| entry.path()))
+			.collect::<Result<Vec<_>, _>>()?;
+		paths.retain(|path| path.extension().is_some_and(|ext| ext == "toml"));
+		paths.sort();
+		let missions = paths
+			.iter()
+			.map(|path| Mission::load(path))
+			.collect::<Result<Vec<_>, _>>()?;
+		if missions.is_empty() {
+			return Err(CampaignError::Empty(dir.to_path_buf()));
+		}
+		for mission in &missions {
+			if let Some(after) = &mission.after {
+				if !missions.iter().any(|m| &m.id == after) {
+					return Err(CampaignError::After(mission.id.clone(), after.clone()));
+				}
+			}
+		}
+		let name = dir
+			.canonicalize()?
+			.file_name()
+			.map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+		Ok(Campaign { name, missions })
+	}
+
+	/// The mission called `id`, if there is one.
+	#[must_use]
+	pub fn mission(&self, id: &str) -> Option<&Mission> {
+		self.missions.iter().find(|mission| mission.id == id)
+	}
+
+	/// Whether `mission` can be played, given the missions already `won`.
+	#[must_use]
+	pub fn unlocked(mission: &Mission, won: &BTreeSet<String>) -> bool {
+		mission
+			.after
+			.as_ref()
+			.is_none_or(|after| won.contains(after))
+	}
+
+	/// The first mission that's open but hasn't been won yet, if any.
+	#[must_use]
+	pub fn next(&self, won: &BTreeSet<String>) -> Option<&Mission> {
+		self.missions
+			.iter()
+			.find(|mission| !won.contains(&mission.id) && Self::unlocked(mission, won))
+	}
+}
+
+impl Mission {
+	fn load(path: &Path) -> Result<Mission, CampaignError> {
+		let id = path
+			.file_stem()
+			.map_or_else(String::new, |stem| stem.to_string_lossy().into_owned());
+		let text = fs::read_to_string(path)?;
+		let file: MissionFile =
+			toml::from_str(&text).map_err(|e| CampaignError::Parse(path.to_path_buf(), e))?;
+		file.rules
+			.validate()
+			.map_err(|e| CampaignError::Rules(id.clone(), e))?;
+		let coord = |at: &str| {
+			at.parse::<Coord>()
+				.map_err(|e| CampaignError::Coord(id.clone(), at.to_string(), e))
+		};
+		let area = match &file.area {
+			Some([from, to]) => {
+				let (from, to) = (coord(from)?, coord(to)?);
+				Some((
+					Coord::new(from.x.min(to.x), from.y.min(to.y)),
+					Coord::new(from.x.max(to.x), from.y.max(to.y)),
+				))
+			}
+			None => None,
+		};
+		// The fixed ships have to fit together, in the fleet's order
+		let mut board = Board::new(&file.rules);
+		let mut fleet = Vec::new();
+		for &ship in &file.rules.fleet {
+			for placed in file.fleet.iter().filter(|placed| placed.ship == ship) {
+				let at = coord(&placed.at)?;
+				if !ship.place(&mut board, at, placed.vertical) {
+					return Err(CampaignError::Placement(id, ship));
+				}
+				fleet.push((ship, at, placed.vertical));
+			}
+		}
+		if let Some(stray) = file
+			.fleet
+			.iter()
+			.find(|placed| !file.rules.fleet.contains(&placed.ship))
+		{
+			return Err(CampaignError::Placement(id, stray.ship));
+		}
+		Ok(Mission {
+			id,
+			name: file.name,
+			briefing: file.briefing,
+			bot: file.bot,
+			after: file.after,
+			area,
+			fleet,
+			rules: file.rules,
+		})
+	}
+
+	/// The enemy to play this mission against.
+	#[must_use]
+	pub fn enemy(&self) -> Scripted<Difficulty> {
+		Scripted {
+			engine: self.bot,
+			fleet: self.fleet.clone(),
+			area: self.area,
+		}
+	}
+}
+
+/// An engine that places its fleet as a mission says, and otherwise plays
+/// like the one it wraps.
+pub struct Scripted<E> {
+	pub engine: E,
+	fleet: Vec<Placement>,
+	area: Option<(Coord, Coord)>,
+}
+
+impl<E> Scripted<E> {
+	/// Plays like `engine`, but places the ships in `fleet` where it says.
+	#[must_use]
+	pub fn new(engine: E, fleet: Vec<(ShipKind, Coord, bool)>) -> Self {
+		Scripted {
+			engine,
+			fleet,
+			area: None,
+		}
+	}
+}
+
+impl<E: Engine> Engine for Scripted<E> {
+	fn placement(&mut self, board: &Board, ship: ShipKind) -> Option<(Coord, bool)> {
+		if let Some(&(_, pos, v)) = self.fleet.iter().find(|&&(placed, ..)| placed == ship) {
+			return Some((pos, v));
+		}
+		let Some((from, to)) = self.area else {
+			return self.engine.placement(board, ship);
+		};
+		let inside =
+			|pos: Coord| (from.x..=to.x).contains(&pos.x) && (from.y..=to.y).contains(&pos.y);
+		let spots = (from.y..=to.y)
+			.flat_map(|y| (from.x..=to.x).map(move |x| Coord::new(x, y)))
+			.flat_map(|pos| [(pos, false), (pos, true)])
+			.filter(|&(pos, v)| ship.can_place(board, pos, v) && ship.footprint(pos, v).all(inside))
+			.collect::<Vec<_>>();
+		// A crowded area lets the ship out rather than leaving it unplaced
+		spots
+			.choose(&mut rand::rng())
+			.copied()
+			.or_else(|| self.engine.placement(board, ship))
+	}
+
+	fn aim(&mut self, game: &Game) -> Coord {
+		self.engine.aim(game)
+	}
+
+	fn react(&mut self, event: &Event) -> Option<String> {
+		self.engine.react(event)
+	}
+}
@@ -0,0 +1,235 @@
+use std::{
+	collections::{BTreeMap, BTreeSet},
+	fmt, fs, io,
+	path::PathBuf,
+};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{coord::Notation, hooks::Hook, lobby::Identity, skin::Skin, ui::earcon::Earcons};
+
+/// How many recent opponents are remembered.
+pub const MAX_RECENT: usize = 10;
+
+/// Settings and history kept between runs, in `netbattleship/config.toml`
+/// under the user's config directory.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+	/// The opponents played most recently, newest first.
+	pub recent: Vec<Opponent>,
+	/// How big the GUI is drawn, as a percentage of the screen's own scale.
+	/// Unset means 100%.
+	pub scale: Option<u16>,
+	/// Whether the GUI is dark rather than light. Unset leaves it to egui.
+	pub dark: Option<bool>,
+	/// How big the GUI's window was when it was last closed, in points.
+	pub window: Option<[u16; 2]>,
+	/// What was last typed in the GUI's address box, to start from next time.
+	pub address: String,
+	/// The lobby server the GUI lists open games from, as an address or
+	/// hostname with a port. Empty means none.
+	pub lobby: String,
+	/// What the GUI calls us to other players, which may be empty.
+	pub name: String,
+	/// Who the lobby server last knew us as, to keep the same friend code.
+	pub identity: Option<Identity>,
+	/// The players we can invite from the GUI.
+	pub friends: Vec<Friend>,
+	/// How coordinates are written for the player.
+	pub notation: Notation,
+	/// How the games played on this computer went.
+	pub stats: Stats,
+	/// The best score for each daily puzzle played, by date.
+	pub puzzles: BTreeMap<String, u32>,
+	/// The missions won in each campaign, by the campaign's directory name.
+	pub campaigns: BTreeMap<String, BTreeSet<String>>,
+	/// What our ships are called, and how they're drawn.
+	pub skin: Skin,
+	/// Whether the GUI reads its announcements aloud.
+	pub speak: bool,
+	/// How loud the GUI reads them, as a percentage of the voice's usual
+	/// volume. Unset means 100%.
+	pub volume: Option<u16>,
+	/// The tones the speech program plays with `--earcons`.
+	pub earcons: Earcons,
+	/// What to run when things happen in a game; see [`crate::hooks`].
+	pub hooks: Vec<Hook>,
+}
+
+/// Games won and lost, kept across runs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Stats {
+	pub wins: u32,
+	pub losses: u32,
+	/// How many of the losses were from resigning.
+	pub resigned: u32,
+}
+
+/// How a game ended for us.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Outcome {
+	Won,
+	Lost,
+	Resigned,
+}
+
+impl Stats {
+	/// Counts one more game that ended in `outcome`.
+	pub fn add(&mut self, outcome: Outcome) {
+		match outcome {
+			Outcome::Won => self.wins += 1,
+			Outcome::Lost => self.losses += 1,
+			Outcome::Resigned => {
+				self.losses += 1;
+				self.resigned += 1;
+			}
+		}
+	}
+}
+
+impl fmt::Display for Stats {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{} won, {} lost", self.wins, self.losses)?;
+		if self.resigned > 0 {
+			write!(f, " ({} resigned)", self.resigned)?;
+		}
+		Ok(())
+	}
+}
+
+/// Someone we've played before.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Opponent {
+	/// What was typed to reach them: an address, hostname or invite code.
+	pub address: String,
+	/// What the player calls them, which may be empty.
+	pub name: String,
+}
+
+/// Someone we can invite to a game through the lobby server.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Friend {
+	/// The friend code the lobby gave them.
+	pub code: String,
+	/// What the player calls them, which may be empty.
+	pub name: String,
+}
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+	#[error("there's no config directory on this system")]
+	NoConfigDir,
+	#[error("couldn't access the config file: {0}")]
+	Io(#[from] io::Error),
+	#[error("couldn't parse the config file: {0}")]
+	Parse(#[from] toml::de::Error),
+	#[error("couldn't write the config file: {0}")]
+	Write(#[from] toml::ser::Error),
+}
+
+impl Config {
+	/// Where the config file lives, if the system has a config directory.
+	#[must_use]
+	pub fn path() -> Option<PathBuf> {
+		Some(
+			dirs::config_dir()?
+				.join("netbattleship")
+				.join("config.toml"),
+		)
+	}
+
+	/// Reads the config file, or the defaults if there isn't one yet.
+	///
+	/// # Errors
+	/// Fails if the file exists but can't be read or parsed.
+	pub fn load() -> Result<Config, ConfigError> {
+		let path = Self::path().ok_or(ConfigError::NoConfigDir)?;
+		match fs::read_to_string(path) {
+			Ok(text) => Ok(toml::from_str(&text)?),
+			Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+			Err(e) => Err(e.into()),
+		}
+	}
+
+	/// Writes the config file, creating its directory if need be.
+	///
+	/// # Errors
+	/// Fails if the file can't be written.
+	pub fn save(&self) -> Result<(), ConfigError> {
+		let path = Self::path().ok_or(ConfigError::NoConfigDir)?;
+		if let Some(dir) = path.parent() {
+			fs::create_dir_all(dir)?;
+		}
+		fs::write(path, toml::to_string(self)?)?;
+		Ok(())
+	}
+
+	/// Counts a game that ended in `outcome` in the config file's stats,
+	/// leaving everything else as it was, and returns the new totals.
+	///
+	/// # Errors
+	/// Fails if the config file can't be read or written.
+	pub fn record(outcome: Outcome) -> Result<Stats, ConfigError> {
+		let mut config = Config::load()?;
+		config.stats.add(outcome);
+		config.save()?;
+		Ok(config.stats)
+	}
+
+	/// Keeps `score` for the puzzle on `date` in the config file if it beats
+	/// the best so far, leaving everything else as it was, and returns the
+	/// best before this one.
+	///
+	/// # Errors
+	/// Fails if the config file can't be read or written.
+	pub fn record_puzzle(date: &str, score: u32) -> Result<Option<u32>, ConfigError> {
+		let mut config = Config::load()?;
+		let best = config.puzzles.get(date).copied();
+		if best.is_none_or(|best| score > best) {
+			config.puzzles.insert(date.to_string(), score);
+			config.save()?;
+		}
+		Ok(best)
+	}
+
+	/// Counts `mission` of `campaign` as won in the config file, leaving
+	/// everything else as it was, and returns every mission won there so far.
+	///
+	/// # Errors
+	/// Fails if the config file can't be read or written.
+	pub fn record_mission(campaign: &str, mission: &str) -> Result<BTreeSet<String>, ConfigError> {
+		let mut config = Config::load()?;
+		let won = config.campaigns.entry(campaign.to_string()).or_default();
+		won.insert(mission.to_string());
+		let won = won.clone();
+		config.save()?;
+		Ok(won)
+	}
+
+	/// Keeps `identity` as who the lobby knows us as in the config file,
+	/// leaving everything else as it was.
+	///
+	/// # Errors
+	/// Fails if the config file can't be read or written.
+	pub fn record_identity(identity: &Identity) -> Result<(), ConfigError> {
+		let mut config = Config::load()?;
+		if config.identity.as_ref() != Some(identity) {
+			config.identity = Some(identity.clone());
+			config.save()?;
+		}
+		Ok(())
+	}
+
+	/// Puts `opponent` at the top of the recent list, replacing any older
+	/// entry with the same address and forgetting the oldest if it's full.
+	pub fn remember(&mut self, opponent: Opponent) {
+		self.recent.retain(|o| o.address != opponent.address);
+		self.recent.insert(0, opponent);
+		self.recent.truncate(MAX_RECENT);
+	}
+}
@@ -0,0 +1,80 @@
+use serde::{Serialize, Serializer};
+
+use crate::{coord::Coord, ship::ShipKind};
+
+/// Something that happened in a game, as announced by
+/// [`GameFlow::events`](crate::flow::GameFlow::events).
+///
+/// The CLI writes these one JSON object per line with `--json-events`. Every
+/// object has an `event` field naming the variant, and coordinates are
+/// written like `"E5"`.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+	/// The players are connected and about to place their ships.
+	Connected { relayed: bool },
+	/// It's time to place this ship.
+	Placing { ship: ShipKind },
+	/// One of our ships was placed.
+	Placed {
+		ship: ShipKind,
+		#[serde(serialize_with = "coord")]
+		pos: Coord,
+		vertical: bool,
+	},
+	/// Both fleets are placed and the shooting starts.
+	Playing,
+	/// The enemy has placed `placed` of the `fleet` ships they have to.
+	EnemyPlacing { placed: u8, fleet: u8 },
+	/// The enemy has placed their whole fleet too.
+	EnemyPlaced,
+	/// Someone fired a shot. Ours are only reported once the enemy has
+	/// taken them, so a shot they turn away as out of turn never is.
+	Fired {
+		by: Player,
+		#[serde(serialize_with = "coord")]
+		pos: Coord,
+	},
+	/// What a shot did. `hit` is null when the blind rule hides it, and
+	/// `ship` names the ship hit, which is only known for the enemy's shots.
+	Result {
+		by: Player,
+		#[serde(serialize_with = "coord")]
+		pos: Coord,
+		hit: Option<bool>,
+		ship: Option<ShipKind>,
+		sunk: Option<ShipKind>,
+	},
+	/// Our radar sweep around `center` found this many ship cells.
+	Scanned {
+		#[serde(serialize_with = "coord")]
+		center: Coord,
+		count: u8,
+	},
+	/// Someone moved their submarine instead of firing.
+	SubmarineMoved { by: Player },
+	/// Someone said something in chat.
+	Chat { by: Player, text: String },
+	/// The game is over.
+	Done { won: bool },
+}
+
+/// Which side of the table did something.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Player {
+	You,
+	Enemy,
+}
+
+// serde hands fields over by reference
+#[allow(clippy::trivially_copy_pass_by_ref)]
+fn coord<S: Serializer>(pos: &Coord, serializer: S) -> Result<S::Ok, S::Error> {
+	serializer.collect_str(pos)
+}
+
+impl From<Event> for String {
+	fn from(event: Event) -> Self {
+		serde_json::to_string(&event).expect("events always serialize")
+	}
+}
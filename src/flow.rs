@@ -1,52 +1,462 @@
-use std::{fmt, net::SocketAddr, sync::Arc};
+use std::{fmt, time::Duration};
+#[cfg(feature = "async")]
+use std::{
+	iter,
+	net::{SocketAddr, SocketAddrV4},
+	ops::{Deref, DerefMut},
+	sync::{Arc, Mutex},
+	time::Instant,
+};
 
+#[cfg(feature = "async")]
+use futures_util::{stream, Stream};
+use schemars::{schema::RootSchema, schema_for};
+use sha2::{Digest, Sha256};
 use thiserror::Error;
+#[cfg(feature = "async")]
 use tokio::{
-	io::AsyncWriteExt,
+	io::{AsyncReadExt, AsyncWriteExt},
 	net::{TcpListener, TcpStream},
-	sync::RwLock,
+	sync::{
+		broadcast::{
+			self,
+			error::{RecvError, TryRecvError},
+		},
+		watch, RwLock, RwLockWriteGuard,
+	},
+	time::{timeout, timeout_at},
 };
+#[cfg(feature = "async")]
+use tokio_util::sync::CancellationToken;
 
+#[cfg(feature = "async")]
 use crate::{
 	board::Board,
-	net::{read_from_async, write_to_async, Msg},
-	ship::Ship,
+	bot::{self, Engine},
+	event::{Event, Player},
+	invite,
+	link::Link,
+	lobby::{self, Identity, LobbyError, Registration, Route},
+	nat::{NatError, PortMapping},
+	net::{Codec, Compression, Profile, MAGIC},
+	protocol::{Done, StateMachine},
+	render::{self, BoardRenderer},
+	save::{self, Save},
+	ship::{ShipKind, ShipNames},
+	transport::Transport,
 	Game, Phase,
 };
+use crate::{
+	coord::Coord,
+	net::{Msg, NetError},
+	rules::{Rules, RulesError},
+};
+
+pub use crate::protocol::TurnResults;
+
+#[cfg(feature = "async")]
+mod accept;
+pub mod blocking;
+#[cfg(feature = "async")]
+mod builder;
+#[cfg(feature = "tls")]
+mod tls;
 
+#[cfg(feature = "async")]
+pub use accept::AcceptLimits;
+#[cfg(feature = "async")]
+pub use builder::GameFlowBuilder;
+#[cfg(feature = "tls")]
+pub use tls::Tls;
+
+#[cfg(feature = "async")]
 #[allow(clippy::module_name_repetitions)]
 pub struct GameFlow {
-	pub state: Arc<RwLock<Game>>,
-	pub socket: Arc<RwLock<TcpStream>>,
+	pub state: Arc<RwLock<StateMachine>>,
+	link: Link,
+	/// Kept so the router forwards the port until the game is over.
+	port_mapping: Option<PortMapping>,
+	route: Route,
+	/// What the other player asked to be called, if anything.
+	enemy: Profile,
+	events: broadcast::Sender<Event>,
+	/// Every turn's results, sent before the state lock is let go so
+	/// [`GameFlow::turns`] sees them in the same order as the turns.
+	results: broadcast::Sender<TurnResults>,
+	snapshots: watch::Sender<GameSnapshot>,
+	/// Cancelled to stop waiting on the other side, from
+	/// [`GameFlow::cancellation`] or [`GameFlow::shutdown`].
+	cancel: CancellationToken,
+	/// When we last told the enemy we were aiming.
+	aimed: Mutex<Option<Instant>>,
+}
+
+/// An immutable copy of the game state, cheap to clone and hold on to.
+#[cfg(feature = "async")]
+pub type GameSnapshot = Arc<Game>;
+
+/// A game waiting for a challenger to connect.
+#[cfg(feature = "async")]
+pub struct Host {
+	listener: TcpListener,
+	port_mapping: Result<PortMapping, NatError>,
+	relay: Option<Registration>,
 }
 
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum GameFlowError {
-	Network(#[from] tokio::io::Error),
+	Network(#[from] std::io::Error),
 	BadMessage(Msg),
 	InvalidPlacement,
 	OutOfOrder,
 	MalformedMessage(#[from] serde_cbor::Error),
 	Mismatch(u64, u64),
 	Busy(#[from] std::sync::TryLockError<()>),
+	InvalidRules(#[from] RulesError),
+	RulesMismatch {
+		ours: Box<Rules>,
+		theirs: Box<Rules>,
+	},
+	OutOfBounds(Coord),
+	/// The square is land, which can't be fired at.
+	Land(Coord),
+	TimedOut,
+	NoScanAvailable,
+	CannotMove,
+	#[cfg(feature = "async")]
+	Lobby(#[from] LobbyError),
+	Net(#[from] NetError),
+	/// The other side didn't start with [`MAGIC`], so it isn't a game.
+	NotAGame,
+	/// We and the other side saved the game at different turns.
+	ResumeMismatch(u32, u32),
+	/// We and the other side think the game is at different turns, so
+	/// there's no telling whose view is right.
+	OutOfSync(u32, u32),
+	/// The other side is on a different turn number from ours, so they
+	/// ignored our shot. Nothing changed, and [`GameFlow::verify_sync`] puts
+	/// the turn numbers right.
+	OutOfTurn(u32, u32),
+	/// Our game and the other side's stopped matching on this turn, going
+	/// by fingerprints of what both of us can see.
+	Desync {
+		turn: u32,
+		ours: u64,
+		theirs: u64,
+	},
+	/// We stopped waiting on the other side, through the game's
+	/// [`CancellationToken`].
+	Cancelled,
+	/// The other side said goodbye and hung up.
+	Left,
+	/// The other side resigned, so we've won.
+	Resigned,
+	/// The other side doesn't have the same password as us; see
+	/// [`GameFlowBuilder::password`].
+	WrongPassword,
 }
 
 impl fmt::Display for GameFlowError {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-		write!(f, "{:?}", self)
+		write!(f, "{self:?}")
+	}
+}
+
+/// The protocol version, sent in [`Msg::Hello`]. Both sides must speak the
+/// same one.
+pub const VERSION: u64 = 25;
+
+/// Extra time the waiting player allows beyond the turn timer, so that
+/// network latency doesn't make both sides disagree on who ran out of time.
+const TURN_TIMER_GRACE: Duration = Duration::from_secs(2);
+
+/// How long to try connecting straight to the host before asking the relay.
+#[cfg(feature = "async")]
+const DIRECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long the other side has to introduce itself once connected, so a
+/// silent connection can't keep a host from accepting real challengers.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The least time between telling the enemy we're aiming.
+pub const AIMING_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How long after last saying they were aiming the enemy seems idle.
+pub const AIMING_IDLE: Duration = Duration::from_secs(10);
+
+/// How many events a slow subscriber can fall behind before missing some.
+#[cfg(feature = "async")]
+const EVENT_BACKLOG: usize = 64;
+
+/// The most characters sent in one line of chat. Anything longer is cut off.
+pub const MAX_CHAT: usize = 200;
+
+/// The most characters in a player's name. Anything longer is cut off.
+pub const MAX_NAME: usize = 32;
+
+/// HMAC-SHA256 of the `parts` of a message, one after another, under `key`,
+/// as RFC 2104 has it.
+pub(crate) fn hmac(key: &[u8], parts: &[&[u8]]) -> [u8; 32] {
+	const BLOCK: usize = 64;
+	let mut padded = [0; BLOCK];
+	if key.len() > BLOCK {
+		padded[..32].copy_from_slice(&Sha256::digest(key));
+	} else {
+		padded[..key.len()].copy_from_slice(key);
+	}
+	let mut inner = Sha256::new();
+	inner.update(padded.map(|b| b ^ 0x36));
+	for part in parts {
+		inner.update(part);
 	}
+	let mut outer = Sha256::new();
+	outer.update(padded.map(|b| b ^ 0x5c));
+	outer.update(inner.finalize());
+	outer.finalize().into()
 }
 
-const VERSION: u64 = 1;
+/// Proves knowing `password` without sending it: an HMAC of the `host`'s
+/// and `guest`'s random nonces, labelled with which side is proving. Fresh
+/// nonces make an answer overheard once no use again, and the label keeps
+/// one side's answer from being passed back to it as the other's. A side
+/// without a password sends no nonce, which counts as all zeroes.
+fn answer(
+	password: &str,
+	by_host: bool,
+	host: Option<[u8; 16]>,
+	guest: Option<[u8; 16]>,
+) -> [u8; 32] {
+	let label: &[u8] = if by_host {
+		b"netbattleship host"
+	} else {
+		b"netbattleship guest"
+	};
+	hmac(
+		password.as_bytes(),
+		&[label, &host.unwrap_or_default(), &guest.unwrap_or_default()],
+	)
+}
+
+/// Whether two answers are the same, taking as long however early they
+/// differ.
+fn same(a: &[u8; 32], b: &[u8; 32]) -> bool {
+	a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
 
+/// Describes every message of this protocol version as JSON Schema, for
+/// writing a compatible client in another language.
+#[must_use]
+pub fn schema() -> RootSchema {
+	let mut schema = schema_for!(Msg);
+	let metadata = schema.schema.metadata();
+	metadata.title = Some(format!("netbattleship protocol version {VERSION}"));
+	metadata.description = Some(
+		"Both sides open with the 7 bytes \"NBSHIP\\0\", then exchange Hello, Challenge, \
+		 Response and Admitted where a password was asked for, Compression and Rules, \
+		 Resume when picking a saved game back up, and Profile, in that order. \
+		 Every message is a frame: a big-endian u32 length followed by that many bytes of \
+		 CBOR, deflated if compression was agreed and the length's top bit is set."
+			.to_string(),
+	);
+	schema
+}
+
+#[cfg(feature = "async")]
 #[allow(clippy::missing_errors_doc)]
 #[allow(clippy::missing_panics_doc)]
 impl GameFlow {
+	/// Hosts at `addr` if `serve` is set, or connects to the host there, with
+	/// the default rules and settings. See [`GameFlow::builder`] for more.
 	pub async fn new(addr: SocketAddr, serve: bool) -> Result<GameFlow, GameFlowError> {
-		let mut socket = Self::handshake(&addr, serve).await?;
+		Self::builder().connect(addr, serve).await
+	}
+
+	/// Starts setting up a game with more than the defaults.
+	#[must_use]
+	pub fn builder() -> GameFlowBuilder {
+		GameFlowBuilder::default()
+	}
+
+	/// Like [`GameFlow::new`], but plays a variant. The other player must
+	/// have chosen the same rules.
+	pub async fn new_with_rules(
+		addr: SocketAddr,
+		serve: bool,
+		rules: Rules,
+	) -> Result<GameFlow, GameFlowError> {
+		Self::builder().rules(rules).connect(addr, serve).await
+	}
+
+	/// Waits for a challenger on a listener the application bound itself, and
+	/// starts the game. The host takes the first shot.
+	///
+	/// The listener is only borrowed, so it can go on accepting challengers
+	/// after this game.
+	///
+	/// Connections that turn out not to be games, or that stall, are logged
+	/// and ignored, within the [`AcceptLimits`] of the default builder.
+	pub async fn host(listener: &TcpListener, rules: Rules) -> Result<GameFlow, GameFlowError> {
+		Self::builder().rules(rules).host(listener).await
+	}
 
-		write_to_async(&Msg::Hello(VERSION), &mut socket).await;
-		match read_from_async(&mut socket).await {
+	/// Starts a game over a connection to the host the application made itself.
+	pub async fn join(stream: TcpStream, rules: Rules) -> Result<GameFlow, GameFlowError> {
+		Self::builder().rules(rules).join(stream).await
+	}
+
+	/// Joins the game hosted at `addr`. If the host can't be reached directly,
+	/// asks the lobby server at `lobby` to relay the game instead; see
+	/// [`GameFlow::route`] for which one happened.
+	pub async fn join_with_relay(
+		addr: SocketAddrV4,
+		lobby: SocketAddr,
+		rules: Rules,
+	) -> Result<GameFlow, GameFlowError> {
+		Self::builder()
+			.rules(rules)
+			.join_with_relay(addr, lobby)
+			.await
+	}
+
+	/// Plays over a Unix domain socket at `path` instead of TCP.
+	#[cfg(unix)]
+	pub async fn new_unix(
+		path: impl AsRef<std::path::Path>,
+		serve: bool,
+	) -> Result<GameFlow, GameFlowError> {
+		Self::builder().unix(path, serve).await
+	}
+
+	/// Plays over a Windows named pipe instead of TCP.
+	#[cfg(windows)]
+	pub async fn new_named_pipe(name: &str, serve: bool) -> Result<GameFlow, GameFlowError> {
+		Self::builder().named_pipe(name, serve).await
+	}
+
+	/// Starts a game over an already connected transport.
+	///
+	/// Exactly one side of the connection must have `serve` set; that side
+	/// takes the first shot.
+	pub async fn with_transport(
+		socket: impl Transport + 'static,
+		serve: bool,
+		rules: Rules,
+	) -> Result<GameFlow, GameFlowError> {
+		Self::builder().rules(rules).transport(socket, serve).await
+	}
+
+	/// Picks a saved game back up over an already connected transport. The
+	/// other side must be resuming the same game, from the same turn.
+	pub async fn resume(
+		socket: impl Transport + 'static,
+		game: Game,
+	) -> Result<GameFlow, GameFlowError> {
+		Self::builder().resume(socket, game).await
+	}
+
+	/// Greets the other side and starts playing `game`, set up as `options`
+	/// say.
+	async fn open(
+		mut socket: Box<dyn Transport>,
+		mut game: Game,
+		resume: bool,
+		options: &GameFlowBuilder,
+	) -> Result<GameFlow, GameFlowError> {
+		#[cfg(feature = "tls")]
+		if let Some(tls) = &options.tls {
+			socket = timeout(options.handshake_timeout, tls.wrap(socket))
+				.await
+				.map_err(|_| GameFlowError::TimedOut)??;
+		}
+		let handshake = async {
+			let password = options.password.as_deref();
+			let codec = Self::greet(&mut socket, &game.rules, game.you, password).await?;
+			if resume {
+				Self::agree_on_turn(&mut socket, codec, game.turn_number).await?;
+			}
+			let profile = Self::introduce(&mut socket, codec, options.profile.clone()).await?;
+			Ok::<_, GameFlowError>((codec, profile))
+		};
+		let (codec, enemy) = timeout(options.handshake_timeout, handshake)
+			.await
+			.map_err(|_| GameFlowError::TimedOut)??;
+
+		if game.phase == Phase::Playing {
+			game.start_clock();
+		}
+		let link = Link::new(socket, codec);
+		let events = broadcast::channel(options.event_capacity).0;
+		// Chat arrives whenever it's sent, so it's announced as it comes
+		// rather than in turn. This stops when the link is dropped.
+		let mut chat = link.chat();
+		let announce = events.clone();
+		tokio::spawn(async move {
+			loop {
+				match chat.recv().await {
+					Ok(text) => {
+						let _ = announce.send(Event::Chat {
+							by: Player::Enemy,
+							text,
+						});
+					}
+					Err(broadcast::error::RecvError::Lagged(_)) => {}
+					Err(broadcast::error::RecvError::Closed) => break,
+				}
+			}
+		});
+		// So is how far they've got placing their fleet
+		let mut placing = link.placing();
+		let announce = events.clone();
+		let fleet = u8::try_from(game.rules.fleet.len()).unwrap_or(u8::MAX);
+		tokio::spawn(async move {
+			while placing.changed().await.is_ok() {
+				let placed = *placing.borrow_and_update();
+				let _ = announce.send(Event::EnemyPlacing { placed, fleet });
+			}
+		});
+		let you = game.you;
+		let mut state = if resume {
+			StateMachine::resumed(game.clone())
+		} else {
+			StateMachine::new(game.clone())
+		};
+		state.name_ships(you, options.profile.ships.clone());
+		state.name_ships(!you, enemy.ships.clone());
+		Ok(GameFlow {
+			snapshots: watch::channel(Arc::new(game)).0,
+			state: Arc::new(RwLock::new(state)),
+			link,
+			port_mapping: None,
+			route: Route::Direct,
+			enemy,
+			events,
+			results: broadcast::channel(options.event_capacity).0,
+			cancel: CancellationToken::new(),
+			aimed: Mutex::new(None),
+		})
+	}
+
+	/// Checks that the other side is a game, speaks our protocol version,
+	/// has our password and plays by our rules, and agrees how to compress
+	/// messages from then on.
+	async fn greet(
+		socket: &mut Box<dyn Transport>,
+		rules: &Rules,
+		serve: bool,
+		password: Option<&str>,
+	) -> Result<Codec, GameFlowError> {
+		socket.write_all(MAGIC).await?;
+		let mut preamble = [0; MAGIC.len()];
+		match socket.read_exact(&mut preamble).await {
+			Ok(_) if preamble == *MAGIC => {}
+			_ => return Err(GameFlowError::NotAGame),
+		}
+
+		// Nothing's compressed until it's agreed
+		let plain = Codec::default();
+		plain.write(&Msg::Hello(VERSION), socket).await?;
+		match plain.read(socket).await? {
 			Msg::Hello(other) => {
 				if other != VERSION {
 					return Err(GameFlowError::Mismatch(VERSION, other));
@@ -54,25 +464,182 @@ impl GameFlow {
 			}
 			m => return Err(GameFlowError::BadMessage(m)),
 		}
+		Self::check_password(socket, plain, serve, password).await?;
 
-		Ok(GameFlow {
-			state: Arc::new(RwLock::new(Game {
-				you: serve,
-				turn: true,
-				phase: Phase::Placing(*Ship::into_iter().next().unwrap()),
-				..Default::default()
-			})),
-			socket: Arc::new(RwLock::new(socket)),
-		})
+		let offer = Compression::SUPPORTED.to_vec();
+		plain.write(&Msg::Compression(offer), socket).await?;
+		let codec = match plain.read(socket).await? {
+			Msg::Compression(theirs) => Codec {
+				compression: Compression::agree(Compression::SUPPORTED, &theirs),
+				..Codec::default()
+			},
+			m => return Err(GameFlowError::BadMessage(m)),
+		};
+
+		codec.write(&Msg::Rules(rules.clone()), socket).await?;
+		match codec.read(socket).await? {
+			Msg::Rules(theirs) => {
+				if theirs != *rules {
+					return Err(GameFlowError::RulesMismatch {
+						ours: Box::new(rules.clone()),
+						theirs: Box::new(theirs),
+					});
+				}
+			}
+			m => return Err(GameFlowError::BadMessage(m)),
+		}
+		Ok(codec)
 	}
 
-	async fn handshake(addr: &SocketAddr, serve: bool) -> Result<TcpStream, GameFlowError> {
-		if serve {
-			let listen = TcpListener::bind(addr).await?;
-			Ok(listen.accept().await?.0)
+	/// Has the other side prove they know our password, if we have one, and
+	/// proves we know theirs, without either being sent. The guest proves
+	/// itself first, so the host answers for nobody it hasn't let in.
+	async fn check_password(
+		socket: &mut Box<dyn Transport>,
+		plain: Codec,
+		serve: bool,
+		password: Option<&str>,
+	) -> Result<(), GameFlowError> {
+		let ours = password.map(|_| rand::random::<[u8; 16]>());
+		plain.write(&Msg::Challenge(ours), socket).await?;
+		let theirs = match plain.read(socket).await? {
+			Msg::Challenge(theirs) => theirs,
+			m => return Err(GameFlowError::BadMessage(m)),
+		};
+		let (host, guest) = if serve {
+			(ours, theirs)
 		} else {
-			Ok(TcpStream::connect(addr).await?)
+			(theirs, ours)
+		};
+		for by_host in [false, true] {
+			// Only whoever sent a nonce asked for proof
+			if (if by_host { guest } else { host }).is_none() {
+				continue;
+			}
+			let expected = answer(password.unwrap_or_default(), by_host, host, guest);
+			if by_host == serve {
+				plain.write(&Msg::Response(expected), socket).await?;
+				match plain.read(socket).await? {
+					Msg::Admitted(true) => {}
+					Msg::Admitted(false) => return Err(GameFlowError::WrongPassword),
+					m => return Err(GameFlowError::BadMessage(m)),
+				}
+			} else {
+				let admitted = match plain.read(socket).await? {
+					Msg::Response(response) => same(&response, &expected),
+					m => return Err(GameFlowError::BadMessage(m)),
+				};
+				plain.write(&Msg::Admitted(admitted), socket).await?;
+				if !admitted {
+					return Err(GameFlowError::WrongPassword);
+				}
+			}
+		}
+		Ok(())
+	}
+
+	/// Checks that both sides are resuming from the same turn.
+	async fn agree_on_turn(
+		socket: &mut Box<dyn Transport>,
+		codec: Codec,
+		turn: u32,
+	) -> Result<(), GameFlowError> {
+		codec.write(&Msg::Resume(turn), socket).await?;
+		match codec.read(socket).await? {
+			Msg::Resume(theirs) if theirs == turn => Ok(()),
+			Msg::Resume(theirs) => Err(GameFlowError::ResumeMismatch(turn, theirs)),
+			m => Err(GameFlowError::BadMessage(m)),
+		}
+	}
+
+	/// Tells the other side what to call us and our ships, and hears what
+	/// to call theirs.
+	async fn introduce(
+		socket: &mut Box<dyn Transport>,
+		codec: Codec,
+		ours: Profile,
+	) -> Result<Profile, GameFlowError> {
+		codec.write(&Msg::Profile(ours), socket).await?;
+		match codec.read(socket).await? {
+			Msg::Profile(mut theirs) => {
+				let cut = |name: &mut String| *name = name.chars().take(MAX_NAME).collect();
+				theirs.name.iter_mut().for_each(cut);
+				theirs.ships.values_mut().for_each(cut);
+				Ok(theirs)
+			}
+			m => Err(GameFlowError::BadMessage(m)),
+		}
+	}
+
+	/// What the other player asked to be called, if they gave a name; see
+	/// [`GameFlowBuilder::name`].
+	#[must_use]
+	pub fn enemy_name(&self) -> Option<&str> {
+		self.enemy.name.as_deref()
+	}
+
+	/// What the other player calls their ships; see
+	/// [`GameFlowBuilder::ship_names`].
+	#[must_use]
+	pub fn enemy_ships(&self) -> &ShipNames {
+		&self.enemy.ships
+	}
+
+	/// Whether the game is connected straight to the other player or relayed
+	/// through a lobby server.
+	#[must_use]
+	pub fn route(&self) -> Route {
+		self.route
+	}
+
+	/// The rolling average time for a message to reach the other player and
+	/// back, measured with keepalive pings every
+	/// [`PING_INTERVAL`](crate::link::PING_INTERVAL). `None`
+	/// until the first ping has been answered.
+	#[must_use]
+	pub fn latency(&self) -> Option<Duration> {
+		self.link.latency()
+	}
+
+	/// How many ships the enemy has placed so far, as far as we've heard.
+	#[must_use]
+	pub fn enemy_placing(&self) -> u8 {
+		*self.link.placing().borrow()
+	}
+
+	/// Tells the enemy we're aiming, if it's our turn and we haven't in the
+	/// last [`AIMING_INTERVAL`]. Call it whenever the player does something
+	/// on their turn, so the enemy can tell they're not idle.
+	pub async fn aiming(&self) {
+		if self.phase().await != Phase::Playing || !self.my_turn().await {
+			return;
 		}
+		{
+			let Ok(mut aimed) = self.aimed.lock() else {
+				return;
+			};
+			if aimed.is_some_and(|aimed| aimed.elapsed() < AIMING_INTERVAL) {
+				return;
+			}
+			*aimed = Some(Instant::now());
+		}
+		// A broken connection shows up wherever we next wait on the enemy
+		let _ = self.link.try_send(&Msg::Aiming).await;
+	}
+
+	/// Whether the enemy has said they were aiming in the last
+	/// [`AIMING_IDLE`].
+	#[must_use]
+	pub fn enemy_aiming(&self) -> bool {
+		self.link
+			.aimed()
+			.is_some_and(|aimed| aimed.elapsed() < AIMING_IDLE)
+	}
+
+	/// How messages are compressed, as agreed with the other player.
+	#[must_use]
+	pub fn compression(&self) -> Compression {
+		self.link.codec().compression
 	}
 
 	pub async fn my_turn(&self) -> bool {
@@ -86,161 +653,355 @@ impl GameFlow {
 
 	pub async fn place_ship(
 		&self,
-		ship: Ship,
-		pos: (u8, u8),
+		ship: ShipKind,
+		pos: Coord,
 		v: bool,
 	) -> Result<(), GameFlowError> {
-		match self.phase().await {
-			Phase::Placing(s) if s == ship => {}
-			_ => return Err(GameFlowError::OutOfOrder),
+		self.step(|state| state.place(ship, pos, v)).await
+	}
+
+	/// Places every ship still waiting to be placed somewhere random,
+	/// returning where each one went.
+	pub async fn place_remaining(&self) -> Result<Vec<(ShipKind, Coord, bool)>, GameFlowError> {
+		self.step(StateMachine::place_remaining).await
+	}
+
+	pub async fn fire(&self, pos: Coord) -> Result<TurnResults, GameFlowError> {
+		self.still_open()?;
+		self.step(|state| state.fire(pos)).await?;
+		match self.exchange(None).await? {
+			Done::Turn(results) => Ok(results),
+			_ => unreachable!("a shot ends the turn"),
 		}
+	}
 
-		let mut state = self.state.write().await;
-		let you = state.you;
+	/// Moves our unhit submarine to `pos` instead of firing. The enemy learns
+	/// that it moved, but not where to. Only allowed once per game.
+	pub async fn move_submarine(&self, pos: Coord, v: bool) -> Result<(), GameFlowError> {
+		self.still_open()?;
+		self.step(|state| state.move_submarine(pos, v)).await
+	}
 
-		if ship.place(&mut state.board[usize::from(you)], pos, v) {
-			state.phase = Ship::into_iter()
-				.skip_while(|&&a| match state.phase {
-					Phase::Placing(b) => a != b,
-					_ => unreachable!(),
-				})
-				.skip(1)
-				.map(|v| Phase::Placing(*v))
-				.next()
-				.unwrap_or(Phase::Playing);
-			Ok(())
-		} else {
-			Err(GameFlowError::InvalidPlacement)
+	/// Whether we may still move our submarine.
+	pub async fn can_move_submarine(&self) -> bool {
+		let state = self.state.read().await;
+		state.can_move_submarine(state.you)
+	}
+
+	/// The turn in progress, counting both players' turns from 1.
+	pub async fn turn_number(&self) -> u32 {
+		self.state.read().await.turn_number
+	}
+
+	/// Whether we've earned a radar sweep we haven't used yet.
+	pub async fn scan_available(&self) -> bool {
+		let state = self.state.read().await;
+		state.scan_available(state.you)
+	}
+
+	/// How long each side has spent on their turns so far.
+	pub async fn clocks(&self) -> Clocks {
+		let state = self.state.read().await;
+		let clocks = state.clocks();
+		Clocks {
+			you: clocks[usize::from(state.you)],
+			enemy: clocks[usize::from(!state.you)],
 		}
 	}
 
-	pub async fn fire(&self, pos: (u8, u8)) -> Result<TurnResults, GameFlowError> {
-		if self.phase().await != Phase::Playing || !self.my_turn().await {
-			return Err(GameFlowError::OutOfOrder);
+	/// Sweeps the 3x3 square of the enemy's board around `center` with
+	/// radar, returning how many ship cells it holds. Doesn't use up a shot.
+	pub async fn scan(&self, center: Coord) -> Result<u8, GameFlowError> {
+		self.still_open()?;
+		self.step(|state| state.scan(center)).await?;
+		match self.exchange(None).await? {
+			Done::Scanned(count) => Ok(count),
+			_ => unreachable!("a radar sweep ends with its count"),
 		}
+	}
 
-		let mut socket = self.socket.write().await;
+	/// Checks that we and the enemy still agree on everything both of us can
+	/// see, such as after reconnecting. Whoever owns a board knows it best,
+	/// so we fix our copy of the enemy's side and they fix theirs of ours.
+	/// Returns how many things we had to fix. Doesn't use up a shot.
+	pub async fn verify_sync(&self) -> Result<usize, GameFlowError> {
+		self.still_open()?;
+		self.step(StateMachine::verify_sync).await?;
+		match self.exchange(None).await? {
+			Done::Synced(fixed) => Ok(fixed),
+			_ => unreachable!("a sync check ends with what it fixed"),
+		}
+	}
 
-		// Send the fire message
-		write_to_async(&Msg::Fire(pos.0, pos.1), &mut *socket).await;
-		// Did we hit?
-		let hit = match read_from_async(&mut *socket).await {
-			Msg::DidHit(b) => b,
-			m => return Err(GameFlowError::BadMessage(m)),
-		};
+	/// Waits for the enemy to take their turn, answering any radar sweeps and
+	/// sync checks on the way.
+	pub async fn receive(&self) -> Result<TurnResults, GameFlowError> {
+		self.still_open()?;
+		if self.phase().await == Phase::Ready {
+			self.wait_for_fleet().await?;
+		}
+		let limit = self.state.read().await.rules.turn_timer();
+		self.step(StateMachine::wait_for_enemy).await?;
+		let deadline = limit.map(|limit| tokio::time::Instant::now() + limit + TURN_TIMER_GRACE);
+		match self.exchange(deadline).await? {
+			Done::Turn(results) => Ok(results),
+			_ => unreachable!("the enemy's turn ends with a shot or a move"),
+		}
+	}
 
-		let mut state = self.state.write().await;
-		let you = state.you;
+	/// Waits for the enemy to finish placing their fleet, if they haven't.
+	pub async fn wait_for_fleet(&self) -> Result<(), GameFlowError> {
+		self.still_open()?;
+		if self.step(StateMachine::wait_for_fleet).await? {
+			match self.exchange(None).await? {
+				Done::Placed => {}
+				_ => unreachable!("placing ends with the whole fleet"),
+			}
+		}
+		Ok(())
+	}
 
-		// Place the hit or miss marker
-		state.board[usize::from(!you)]
-			.board
-			.insert(pos, if hit { Ship::Hit } else { Ship::Miss });
+	/// Lets `engine` play the rest of the game: it places whatever's left of
+	/// the fleet, fires on our turns and has its say in chat while we wait
+	/// out the enemy's, until the game is over. Events go out as usual.
+	/// See [`bot::play`].
+	pub async fn play_with(&self, mut engine: impl Engine) -> Result<(), GameFlowError> {
+		bot::play(self, &mut engine).await
+	}
 
-		// Did we sink?
-		let sunk = match read_from_async(&mut *socket).await {
-			Msg::Sunk(Ship::None) => None,
-			Msg::Sunk(s) => Some(s),
-			m => return Err(GameFlowError::BadMessage(m)),
-		};
-		// Did we win?
-		let won = match read_from_async(&mut *socket).await {
-			Msg::Finished => {
-				state.phase = Phase::Done(true);
-				true
-			}
-			Msg::NotFinished => false,
-			m => return Err(GameFlowError::BadMessage(m)),
-		};
-		state.turn = !state.turn;
-		Ok(TurnResults {
-			hit: Some(Ship::Hit).filter(|_| hit),
-			sunk,
-			won,
-			aim: pos,
-		})
+	/// Sends a line of chat to the other player, whoever's turn it is. Lines
+	/// longer than [`MAX_CHAT`] characters are cut short.
+	pub async fn chat(&self, text: &str) {
+		let text = text.chars().take(MAX_CHAT).collect::<String>();
+		// A broken connection shows up wherever we next wait on the enemy
+		let _ = self.link.try_send(&Msg::Chat(text.clone())).await;
+		self.emit(Event::Chat {
+			by: Player::You,
+			text,
+		});
 	}
 
-	pub async fn receive(&self) -> Result<TurnResults, GameFlowError> {
-		let you = {
-			let state = self.state.read().await;
-			state.you
-		};
+	pub async fn done(self) -> Result<(), GameFlowError> {
+		self.link.shutdown().await?;
+		Ok(())
+	}
+
+	/// Leaves the game: stops waiting on the other side, tells them we're
+	/// going with [`Msg::Goodbye`] and closes the connection. Anything still
+	/// waiting on them fails with [`GameFlowError::Cancelled`], and so does
+	/// everything tried afterwards.
+	pub async fn shutdown(&self) -> Result<(), GameFlowError> {
+		self.cancel.cancel();
+		self.link.try_send(&Msg::Goodbye).await?;
+		self.link.shutdown().await?;
+		Ok(())
+	}
 
-		if self.phase().await != Phase::Playing || self.my_turn().await {
-			return Err(GameFlowError::OutOfOrder);
+	/// Gives up the game, which the enemy wins: stops waiting on them like
+	/// [`GameFlow::shutdown`], but tells them we resigned. A game that's
+	/// already over is just left.
+	pub async fn resign(&self) -> Result<(), GameFlowError> {
+		self.cancel.cancel();
+		self.step(|state| Ok(state.resign())).await?;
+		self.link.shutdown().await?;
+		Ok(())
+	}
+
+	/// The token that stops anything waiting on the other side when
+	/// cancelled, such as [`GameFlow::receive`] or [`GameFlow::fire`], which
+	/// fail with [`GameFlowError::Cancelled`]. Waits can't be picked up where
+	/// they stopped, so once it's cancelled the game can only be left or
+	/// reconnected.
+	#[must_use]
+	pub fn cancellation(&self) -> CancellationToken {
+		self.cancel.clone()
+	}
+
+	/// Fails once the game has been cancelled, before anything is sent.
+	fn still_open(&self) -> Result<(), GameFlowError> {
+		if self.cancel.is_cancelled() {
+			Err(GameFlowError::Cancelled)
+		} else {
+			Ok(())
 		}
-		let aim = match read_from_async(&mut *self.socket.write().await).await {
-			Msg::Fire(x, y) => (x, y),
-			m => return Err(GameFlowError::BadMessage(m)),
-		};
+	}
 
-		let hit = self.board(false).await.board.get(&aim).copied();
-		let hit_ship = hit.filter(|v| !matches!(v, Ship::Hit | Ship::None | Ship::Miss));
-		write_to_async(
-			&Msg::DidHit(hit_ship.is_some()),
-			&mut *self.socket.write().await,
-		)
-		.await;
-		self.state.write().await.board[usize::from(you)]
-			.board
-			.insert(
-				aim,
-				if hit_ship.is_some() || matches!(hit, Some(Ship::Hit)) {
-					Ship::Hit
-				} else {
-					Ship::Miss
-				},
-			);
+	/// Waits for the other side's next message, unless the game is cancelled
+	/// first.
+	async fn recv(&self) -> Result<Msg, GameFlowError> {
+		tokio::select! {
+			biased;
+			() = self.cancel.cancelled() => Err(GameFlowError::Cancelled),
+			msg = self.link.recv() => Ok(msg?),
+		}
+	}
 
-		let sunk = {
-			let state = self.state.write().await;
-			hit_ship.filter(|hit| !state.board[usize::from(you)].contains(*hit))
-		};
-		write_to_async(
-			&Msg::Sunk(sunk.unwrap_or(Ship::None)),
-			&mut *self.socket.write().await,
-		)
-		.await;
-
-		let won = {
-			let state = self.state.read().await;
-			state.board[usize::from(you)]
-				.board
-				.iter()
-				.all(|(_, ship)| ship.is_empty())
+	/// Makes a move on the state machine, then sends whatever it has for the
+	/// other side and announces whatever happened, even if the move failed.
+	async fn step<T>(
+		&self,
+		step: impl FnOnce(&mut StateMachine) -> Result<T, GameFlowError>,
+	) -> Result<T, GameFlowError> {
+		let (result, outbox) = {
+			let mut state = self.write_state().await;
+			let result = step(&mut state);
+			while let Some(event) = state.poll_event() {
+				self.emit(event);
+			}
+			(
+				result,
+				iter::from_fn(|| state.poll_transmit()).collect::<Vec<_>>(),
+			)
 		};
-		write_to_async(
-			&if won { Msg::Finished } else { Msg::NotFinished },
-			&mut *self.socket.write().await,
-		)
-		.await;
-
-		if won {
-			let mut state = self.state.write().await;
-			state.phase = Phase::Done(false);
+		for msg in outbox {
+			self.link.try_send(&msg).await?;
 		}
-		{
-			let mut state = self.state.write().await;
-			state.turn = !state.turn;
+		result
+	}
+
+	/// Feeds the other side's messages to the state machine until what it's
+	/// waiting on is done. If the deadline passes first, they ran out of time.
+	async fn exchange(
+		&self,
+		deadline: Option<tokio::time::Instant>,
+	) -> Result<Done, GameFlowError> {
+		loop {
+			let msg = match deadline {
+				Some(deadline) => match timeout_at(deadline, self.recv()).await {
+					Ok(msg) => msg?,
+					Err(_) => return Err(self.step(|state| Ok(state.time_out())).await?),
+				},
+				None => self.recv().await?,
+			};
+			let done = self
+				.step(|state| {
+					let done = state.handle(msg)?;
+					if let Some(Done::Turn(results)) = &done {
+						let _ = self.results.send(results.clone());
+					}
+					Ok(done)
+				})
+				.await?;
+			if let Some(done) = done {
+				return Ok(done);
+			}
 		}
-		Ok(TurnResults {
-			aim,
-			hit: hit_ship,
-			sunk,
-			won,
+	}
+
+	/// Subscribes to everything that happens in the game from now on.
+	#[must_use]
+	pub fn events(&self) -> broadcast::Receiver<Event> {
+		self.events.subscribe()
+	}
+
+	/// Every turn's results from now until the game is over, ours and the
+	/// enemy's, in the order they happen. The stream waits for the enemy's
+	/// turns itself, so nothing else should call [`GameFlow::receive`];
+	/// ours turn up as [`GameFlow::fire`] returns them, wherever it's called.
+	/// It ends after the first error.
+	pub fn turns(&self) -> impl Stream<Item = Result<TurnResults, GameFlowError>> + '_ {
+		let results = self.results.subscribe();
+		stream::unfold(Some((self, results)), |open| async move {
+			let (flow, mut results) = open?;
+			loop {
+				let (mine, phase) = {
+					let state = flow.state.read().await;
+					(state.turn == state.you, state.phase.clone())
+				};
+				// Looked at after the state, so a turn that has passed is
+				// already here
+				match results.try_recv() {
+					Ok(turn) => return Some((Ok(turn), Some((flow, results)))),
+					Err(TryRecvError::Lagged(_)) => continue,
+					Err(TryRecvError::Closed) => return None,
+					Err(TryRecvError::Empty) => {}
+				}
+				match phase {
+					Phase::Done(_) => return None,
+					Phase::Placing(_) => {
+						let mut snapshots = flow.subscribe();
+						let placed = snapshots
+							.wait_for(|game| !matches!(game.phase, Phase::Placing(_)))
+							.await;
+						if placed.is_err() {
+							return None;
+						}
+					}
+					_ if mine => match results.recv().await {
+						Ok(turn) => return Some((Ok(turn), Some((flow, results)))),
+						Err(RecvError::Lagged(_)) => {}
+						Err(RecvError::Closed) => return None,
+					},
+					// The enemy's turn arrives with the others on the next
+					// time around
+					_ => {
+						if let Err(e) = flow.receive().await {
+							return Some((Err(e), None));
+						}
+					}
+				}
+			}
 		})
 	}
 
-	pub async fn done(self) -> Result<(), GameFlowError> {
-		self.socket.write().await.shutdown().await?;
-		Ok(())
+	/// Tells subscribers about `event`. Nobody listening is fine.
+	fn emit(&self, event: Event) {
+		let _ = self.events.send(event);
+	}
+
+	/// Watches the game state, which is marked changed after every change we
+	/// make to it. Changes made directly through `state` aren't seen.
+	#[must_use]
+	pub fn subscribe(&self) -> watch::Receiver<GameSnapshot> {
+		self.snapshots.subscribe()
+	}
+
+	/// The game as of our last change to it. Doesn't wait on the state lock
+	/// or copy anything, so it's cheap enough to take every frame.
+	#[must_use]
+	pub fn snapshot(&self) -> GameSnapshot {
+		self.snapshots.borrow().clone()
+	}
+
+	/// Keeps save `slot` up to date with the game, so it can be continued
+	/// after a crash: from a background task whenever the game changes, and
+	/// from a panic hook. The slot is emptied once the game is over.
+	///
+	/// `addr` and `serve` are saved so the game knows how to reconnect.
+	pub fn autosave(&self, slot: String, addr: SocketAddr, serve: bool) {
+		let mut snapshots = self.subscribe();
+		save::save_on_panic(slot.clone(), snapshots.clone(), addr, serve);
+		tokio::spawn(async move {
+			while snapshots.changed().await.is_ok() {
+				let game = (**snapshots.borrow_and_update()).clone();
+				let result = if let Phase::Done(_) = game.phase {
+					Save::remove(&slot)
+				} else {
+					Save { game, addr, serve }.store(&slot)
+				};
+				if let Err(e) = result {
+					eprintln!("Couldn't autosave the game: {e}");
+				}
+			}
+		});
 	}
 
-	pub async fn to_string(&self) -> String {
-		String::from(self.state.read().await.clone())
+	/// Locks the state for a change, publishing a snapshot afterwards.
+	async fn write_state(&self) -> StateWrite<'_> {
+		StateWrite {
+			state: self.state.write().await,
+			snapshots: &self.snapshots,
+		}
 	}
 
+	/// Draws the boards with the given renderer, from the latest snapshot.
+	#[must_use]
+	pub fn render(&self, renderer: &dyn BoardRenderer) -> String {
+		renderer.render(&self.snapshot())
+	}
+
+	/// A copy of one of the boards. To look at it without copying, borrow it
+	/// from a [`GameFlow::snapshot`] instead.
 	pub async fn board(&self, enemy: bool) -> Board {
 		let state = self.state.read().await;
 		let you = state.you;
@@ -248,9 +1009,205 @@ impl GameFlow {
 	}
 }
 
-pub struct TurnResults {
-	pub aim: (u8, u8),
-	pub hit: Option<Ship>,
-	pub sunk: Option<Ship>,
-	pub won: bool,
+/// A write lock on the game state that publishes a snapshot when released.
+#[cfg(feature = "async")]
+struct StateWrite<'a> {
+	state: RwLockWriteGuard<'a, StateMachine>,
+	snapshots: &'a watch::Sender<GameSnapshot>,
+}
+
+#[cfg(feature = "async")]
+impl Deref for StateWrite<'_> {
+	type Target = StateMachine;
+
+	fn deref(&self) -> &StateMachine {
+		&self.state
+	}
+}
+
+#[cfg(feature = "async")]
+impl DerefMut for StateWrite<'_> {
+	fn deref_mut(&mut self) -> &mut StateMachine {
+		&mut self.state
+	}
+}
+
+#[cfg(feature = "async")]
+impl Drop for StateWrite<'_> {
+	fn drop(&mut self) {
+		self.snapshots
+			.send_replace(Arc::new(Game::clone(&self.state)));
+	}
+}
+
+/// Time spent on each side's turns, like a chess clock.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Clocks {
+	pub you: Duration,
+	pub enemy: Duration,
+}
+
+#[cfg(feature = "async")]
+impl fmt::Display for GameFlow {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str(&self.render(&render::Ascii))
+	}
+}
+
+impl fmt::Display for Clocks {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let total = self.you + self.enemy;
+		write!(
+			f,
+			"You {}, enemy {}, total {}",
+			Clock(self.you),
+			Clock(self.enemy),
+			Clock(total)
+		)
+	}
+}
+
+/// Shows a duration as minutes and seconds, like `4:07`.
+pub struct Clock(pub Duration);
+
+impl fmt::Display for Clock {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let secs = self.0.as_secs();
+		write!(f, "{}:{:02}", secs / 60, secs % 60)
+	}
+}
+
+#[cfg(feature = "async")]
+#[allow(clippy::missing_errors_doc)]
+impl Host {
+	/// Starts listening for a challenger.
+	///
+	/// Without an address this binds a free port on every interface. With
+	/// the `upnp` feature, it also tries to forward the port through the
+	/// router; see [`Host::port_mapping`] for whether that worked.
+	pub async fn bind(addr: Option<SocketAddr>) -> Result<Host, GameFlowError> {
+		let listener = TcpListener::bind(
+			addr.unwrap_or(SocketAddrV4::new(std::net::Ipv4Addr::UNSPECIFIED, 0).into()),
+		)
+		.await?;
+		let bound = listener.local_addr()?;
+		let port_mapping = if bound.ip().is_loopback() {
+			Err(NatError::Unsupported)
+		} else {
+			PortMapping::open(bound.port()).await
+		};
+		Ok(Host {
+			listener,
+			port_mapping,
+			relay: None,
+		})
+	}
+
+	/// Also offers the game through the lobby server at `lobby`, for
+	/// challengers who can't connect directly. Whichever way a challenger
+	/// arrives first is used.
+	pub async fn register_relay(&mut self, lobby: SocketAddr) -> Result<(), GameFlowError> {
+		let port = self.listener.local_addr()?.port();
+		self.relay = Some(Registration::new(lobby, port).await?);
+		Ok(())
+	}
+
+	/// Like [`Host::register_relay`], but also lists the game in the lobby as
+	/// `name` playing by `rules`, for anyone browsing it to join.
+	pub async fn list_relay(
+		&mut self,
+		lobby: SocketAddr,
+		name: String,
+		rules: Rules,
+	) -> Result<(), GameFlowError> {
+		let port = self.listener.local_addr()?.port();
+		self.relay = Some(Registration::listed(lobby, port, name, rules).await?);
+		Ok(())
+	}
+
+	/// Invites the player with the friend code `friend` to play by `rules`,
+	/// as `from` calling ourselves `name`, through the lobby server at
+	/// `lobby`. The game is offered through its relay first if it isn't yet.
+	pub async fn invite(
+		&mut self,
+		lobby: SocketAddr,
+		from: &Identity,
+		name: String,
+		friend: &str,
+		rules: Rules,
+	) -> Result<(), GameFlowError> {
+		let relay = match self.relay.take() {
+			Some(relay) => relay,
+			None => Registration::new(lobby, self.listener.local_addr()?.port()).await?,
+		};
+		let public = relay.public();
+		self.relay = Some(relay);
+		lobby::invite(lobby, from, name, friend, rules, public).await?;
+		Ok(())
+	}
+
+	/// Each address a challenger might be able to reach the host on, most
+	/// widely reachable first.
+	#[must_use]
+	pub fn addrs(&self) -> Vec<SocketAddr> {
+		let Ok(bound) = self.listener.local_addr() else {
+			return vec![];
+		};
+		let mut addrs = Vec::new();
+		if let Ok(mapping) = &self.port_mapping {
+			addrs.push(mapping.external().into());
+		}
+		if let Some(relay) = &self.relay {
+			if !addrs.contains(&relay.public().into()) {
+				addrs.push(relay.public().into());
+			}
+		}
+		if bound.ip().is_unspecified() {
+			addrs.extend(
+				invite::local_addrs(bound.port())
+					.into_iter()
+					.map(SocketAddr::from),
+			);
+		} else {
+			addrs.push(bound);
+		}
+		addrs
+	}
+
+	/// The socket challengers connect to, for hosting games with [`GameFlow::host`].
+	#[must_use]
+	pub fn listener(&self) -> &TcpListener {
+		&self.listener
+	}
+
+	/// Whether the port was forwarded through the router, or why not.
+	pub fn port_mapping(&self) -> Result<&PortMapping, &NatError> {
+		self.port_mapping.as_ref()
+	}
+
+	/// Waits for a challenger and starts the game. The host takes the first shot.
+	///
+	/// Direct connections that turn out not to be games, or that stall, are
+	/// logged and ignored, within the [`AcceptLimits`] of the default builder.
+	pub async fn accept(self, rules: Rules) -> Result<GameFlow, GameFlowError> {
+		GameFlow::builder().rules(rules).accept(self).await
+	}
+
+	/// Waits for the other player of a saved game to connect, and picks it
+	/// back up; see [`GameFlow::resume`].
+	pub async fn resume(self, game: Game) -> Result<GameFlow, GameFlowError> {
+		game.rules.validate()?;
+		self.open(game, true, &GameFlow::builder()).await
+	}
+
+	async fn open(
+		self,
+		game: Game,
+		resume: bool,
+		options: &GameFlowBuilder,
+	) -> Result<GameFlow, GameFlowError> {
+		let mut game = accept::accept(&self.listener, self.relay, game, resume, options).await?;
+		game.port_mapping = self.port_mapping.ok();
+		Ok(game)
+	}
 }
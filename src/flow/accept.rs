@@ -0,0 +1,138 @@
+//! Accepting challengers without letting strangers wedge the host:
+//! handshakes run side by side, up to a limit, and an address that keeps
+//! connecting is dropped unheard for a while.
+
+use std::{
+	collections::HashMap,
+	future::pending,
+	net::{IpAddr, SocketAddr},
+	pin::pin,
+	time::{Duration, Instant},
+};
+
+use futures_util::{stream::FuturesUnordered, StreamExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::{
+	lobby::{Registration, Route},
+	Game,
+};
+
+use super::{GameFlow, GameFlowBuilder, GameFlowError};
+
+/// How a host keeps connections that aren't challengers from holding it up;
+/// see [`GameFlowBuilder::accept_limits`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AcceptLimits {
+	/// The most handshakes run at once. Connections beyond that wait in the
+	/// listener's backlog until one finishes.
+	pub handshakes: usize,
+	/// The most connections one address can make in `window` before the
+	/// rest are dropped.
+	pub attempts: u32,
+	pub window: Duration,
+}
+
+impl Default for AcceptLimits {
+	fn default() -> Self {
+		AcceptLimits {
+			handshakes: 8,
+			attempts: 5,
+			window: Duration::from_secs(10),
+		}
+	}
+}
+
+/// How many connections each address has made in the current window.
+#[derive(Default)]
+struct Attempts {
+	seen: HashMap<IpAddr, (Instant, u32)>,
+}
+
+impl Attempts {
+	/// Counts a connection from `ip`, and says whether it's within `limits`.
+	fn allow(&mut self, ip: IpAddr, limits: &AcceptLimits) -> bool {
+		let now = Instant::now();
+		self.seen
+			.retain(|_, (since, _)| now.duration_since(*since) < limits.window);
+		let (_, count) = self.seen.entry(ip).or_insert((now, 0));
+		*count += 1;
+		*count <= limits.attempts
+	}
+}
+
+/// Greets whoever's on the other end of `socket`, which arrived by `route`
+/// from `peer` if it came directly.
+async fn handshake(
+	socket: TcpStream,
+	peer: Option<SocketAddr>,
+	route: Route,
+	game: Game,
+	resume: bool,
+	options: &GameFlowBuilder,
+) -> (Result<GameFlow, GameFlowError>, Option<SocketAddr>, Route) {
+	let result = GameFlow::open(Box::new(socket), game, resume, options).await;
+	(result, peer, route)
+}
+
+/// Waits on `listener`, and on `relay` if there is one, for a challenger to
+/// play `game` with, as `options` say.
+///
+/// Direct connections that turn out not to be games, don't have the
+/// password, never introduce themselves or fail to greet us any other way
+/// are logged and skipped. The relay only pairs us once, so anything going
+/// wrong there is an error.
+pub(super) async fn accept(
+	listener: &TcpListener,
+	relay: Option<Registration>,
+	game: Game,
+	resume: bool,
+	options: &GameFlowBuilder,
+) -> Result<GameFlow, GameFlowError> {
+	let limits = options.accept_limits;
+	let mut attempts = Attempts::default();
+	let mut handshakes = FuturesUnordered::new();
+	let mut relayed = relay.is_some();
+	let mut paired = pin!(async move {
+		match relay {
+			Some(relay) => relay.paired().await,
+			None => pending().await,
+		}
+	});
+	loop {
+		tokio::select! {
+			direct = listener.accept(), if handshakes.len() < limits.handshakes.max(1) => {
+				let (socket, peer) = direct?;
+				if attempts.allow(peer.ip(), &limits) {
+					let game = game.clone();
+					handshakes.push(handshake(socket, Some(peer), Route::Direct, game, resume, options));
+				} else {
+					eprintln!("{peer}: connecting too often, dropping it");
+				}
+			}
+			pair = &mut paired, if relayed => {
+				relayed = false;
+				let (socket, route) = pair?;
+				handshakes.push(handshake(socket, None, route, game.clone(), resume, options));
+			}
+			Some((result, peer, route)) = handshakes.next(), if !handshakes.is_empty() => {
+				match (result, peer) {
+					(Err(e), Some(peer)) if route == Route::Direct => {
+						let stray = match e {
+							GameFlowError::NotAGame => "not a game, ignoring it".to_string(),
+							GameFlowError::WrongPassword => "wrong password, turning it away".to_string(),
+							GameFlowError::TimedOut => "never introduced itself, dropping it".to_string(),
+							e => format!("{e}, dropping it"),
+						};
+						eprintln!("{peer}: {stray}");
+					}
+					(result, _) => {
+						let mut game = result?;
+						game.route = route;
+						return Ok(game);
+					}
+				}
+			}
+		}
+	}
+}
@@ -0,0 +1,365 @@
+//! A game played with blocking calls on a [`std::net::TcpStream`], for
+//! programs that don't run an async runtime.
+//!
+//! It speaks the same protocol as the async [`GameFlow`](super::GameFlow),
+//! through the same [`StateMachine`], so either can play the other. It
+//! doesn't send keepalives or resume saved games, and chat only arrives while
+//! it's waiting on the other side.
+//!
+//! It's also the only `GameFlow` without the default `async` feature, for
+//! building the crate without tokio.
+
+use std::{
+	collections::VecDeque,
+	io::{self, ErrorKind, Read, Write},
+	net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs},
+	time::{Duration, Instant},
+};
+
+use crate::{
+	coord::Coord,
+	event::{Event, Player},
+	net::{Codec, Compression, Msg, NetError, Profile, MAGIC},
+	protocol::{Done, StateMachine},
+	rules::Rules,
+	ship::ShipKind,
+	Game, Phase,
+};
+
+use super::{
+	answer, same, GameFlowError, TurnResults, HANDSHAKE_TIMEOUT, MAX_CHAT, TURN_TIMER_GRACE,
+	VERSION,
+};
+
+/// One side of a game over a blocking connection.
+#[allow(clippy::module_name_repetitions)]
+pub struct GameFlow {
+	machine: StateMachine,
+	stream: TcpStream,
+	codec: Codec,
+	events: VecDeque<Event>,
+}
+
+#[allow(clippy::missing_errors_doc)]
+impl GameFlow {
+	/// Connects to the game hosted at `addr`.
+	pub fn join(addr: impl ToSocketAddrs, rules: Rules) -> Result<GameFlow, GameFlowError> {
+		Self::with_stream(TcpStream::connect(addr)?, false, rules)
+	}
+
+	/// Waits for a challenger on `listener` and starts the game. The host
+	/// takes the first shot. Connections that turn out not to be games are
+	/// ignored.
+	pub fn host(listener: &TcpListener, rules: &Rules) -> Result<GameFlow, GameFlowError> {
+		loop {
+			let (stream, _) = listener.accept()?;
+			match Self::with_stream(stream, true, rules.clone()) {
+				Err(GameFlowError::NotAGame | GameFlowError::WrongPassword) => {}
+				result => return result,
+			}
+		}
+	}
+
+	/// Starts a game over an already connected stream. Exactly one side must
+	/// have `serve` set; that side takes the first shot.
+	pub fn with_stream(
+		stream: TcpStream,
+		serve: bool,
+		rules: Rules,
+	) -> Result<GameFlow, GameFlowError> {
+		Self::with_password(stream, serve, rules, None)
+	}
+
+	/// Like [`GameFlow::with_stream`], but the other side has to know
+	/// `password` too; see [`super::GameFlowBuilder::password`].
+	pub fn with_password(
+		mut stream: TcpStream,
+		serve: bool,
+		rules: Rules,
+		password: Option<&str>,
+	) -> Result<GameFlow, GameFlowError> {
+		rules.validate()?;
+		stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+		let codec = greet(&mut stream, &rules, serve, password).map_err(timed_out)?;
+		stream.set_read_timeout(None)?;
+		Ok(GameFlow {
+			machine: StateMachine::new(Game::new(rules, serve)),
+			stream,
+			codec,
+			events: VecDeque::new(),
+		})
+	}
+
+	/// The game as it stands.
+	#[must_use]
+	pub fn game(&self) -> &Game {
+		&self.machine
+	}
+
+	#[must_use]
+	pub fn my_turn(&self) -> bool {
+		self.machine.turn == self.machine.you
+	}
+
+	#[must_use]
+	pub fn phase(&self) -> Phase {
+		self.machine.phase.clone()
+	}
+
+	/// The next thing that happened in the game, if any.
+	pub fn poll_event(&mut self) -> Option<Event> {
+		self.events.pop_front()
+	}
+
+	pub fn place_ship(&mut self, ship: ShipKind, pos: Coord, v: bool) -> Result<(), GameFlowError> {
+		self.step(|machine| machine.place(ship, pos, v))
+	}
+
+	pub fn fire(&mut self, pos: Coord) -> Result<TurnResults, GameFlowError> {
+		self.step(|machine| machine.fire(pos))?;
+		match self.exchange(None)? {
+			Done::Turn(results) => Ok(results),
+			_ => unreachable!("a shot ends the turn"),
+		}
+	}
+
+	/// Moves our unhit submarine to `pos` instead of firing. Only allowed
+	/// once per game.
+	pub fn move_submarine(&mut self, pos: Coord, v: bool) -> Result<(), GameFlowError> {
+		self.step(|machine| machine.move_submarine(pos, v))
+	}
+
+	/// Sweeps the 3x3 square of the enemy's board around `center` with
+	/// radar, returning how many ship cells it holds. Doesn't use up a shot.
+	pub fn scan(&mut self, center: Coord) -> Result<u8, GameFlowError> {
+		self.step(|machine| machine.scan(center))?;
+		match self.exchange(None)? {
+			Done::Scanned(count) => Ok(count),
+			_ => unreachable!("a radar sweep ends with its count"),
+		}
+	}
+
+	/// Checks that we and the enemy still agree on everything both of us can
+	/// see, returning how many things we had to fix.
+	pub fn verify_sync(&mut self) -> Result<usize, GameFlowError> {
+		self.step(StateMachine::verify_sync)?;
+		match self.exchange(None)? {
+			Done::Synced(fixed) => Ok(fixed),
+			_ => unreachable!("a sync check ends with what it fixed"),
+		}
+	}
+
+	/// Waits for the enemy to finish placing their fleet, if they haven't.
+	pub fn wait_for_fleet(&mut self) -> Result<(), GameFlowError> {
+		if self.step(StateMachine::wait_for_fleet)? {
+			match self.exchange(None)? {
+				Done::Placed => {}
+				_ => unreachable!("placing ends with the whole fleet"),
+			}
+		}
+		Ok(())
+	}
+
+	/// Waits for the enemy to take their turn.
+	pub fn receive(&mut self) -> Result<TurnResults, GameFlowError> {
+		if self.phase() == Phase::Ready {
+			self.wait_for_fleet()?;
+		}
+		self.step(StateMachine::wait_for_enemy)?;
+		let deadline = self
+			.machine
+			.rules
+			.turn_timer()
+			.map(|limit| Instant::now() + limit + TURN_TIMER_GRACE);
+		match self.exchange(deadline)? {
+			Done::Turn(results) => Ok(results),
+			_ => unreachable!("the enemy's turn ends with a shot or a move"),
+		}
+	}
+
+	/// Sends a line of chat to the other player. Lines longer than
+	/// [`MAX_CHAT`] characters are cut short.
+	pub fn chat(&mut self, text: &str) -> Result<(), GameFlowError> {
+		let text = text.chars().take(MAX_CHAT).collect::<String>();
+		self.codec
+			.write_blocking(&Msg::Chat(text.clone()), &mut self.stream)?;
+		self.events.push_back(Event::Chat {
+			by: Player::You,
+			text,
+		});
+		Ok(())
+	}
+
+	/// Gives up the game, which the enemy wins, and hangs up. A game that's
+	/// already over is just left.
+	pub fn resign(mut self) -> Result<(), GameFlowError> {
+		self.step(|machine| Ok(machine.resign()))?;
+		self.stream.shutdown(Shutdown::Both)?;
+		Ok(())
+	}
+
+	/// Makes a move on the state machine, then sends whatever it has for the
+	/// other side and collects whatever happened, even if the move failed.
+	fn step<T>(
+		&mut self,
+		step: impl FnOnce(&mut StateMachine) -> Result<T, GameFlowError>,
+	) -> Result<T, GameFlowError> {
+		let result = step(&mut self.machine);
+		self.events
+			.extend(std::iter::from_fn(|| self.machine.poll_event()));
+		while let Some(msg) = self.machine.poll_transmit() {
+			self.codec.write_blocking(&msg, &mut self.stream)?;
+		}
+		result
+	}
+
+	/// Feeds the other side's messages to the state machine until what it's
+	/// waiting on is done. If the deadline passes first, they ran out of time.
+	fn exchange(&mut self, deadline: Option<Instant>) -> Result<Done, GameFlowError> {
+		loop {
+			let left = deadline.map(|deadline| {
+				deadline
+					.saturating_duration_since(Instant::now())
+					.max(Duration::from_millis(1))
+			});
+			self.stream.set_read_timeout(left)?;
+			let msg = match self.codec.read_blocking(&mut self.stream) {
+				Ok(msg) => msg,
+				Err(NetError::Io(e)) if deadline.is_some() && is_timeout(&e) => {
+					self.stream.set_read_timeout(None)?;
+					return Err(self.step(|machine| Ok(machine.time_out()))?);
+				}
+				Err(e) => return Err(e.into()),
+			};
+			match msg {
+				Msg::Ping => self.codec.write_blocking(&Msg::Pong, &mut self.stream)?,
+				Msg::Pong => {}
+				Msg::Chat(text) => self.events.push_back(Event::Chat {
+					by: Player::Enemy,
+					text,
+				}),
+				msg => {
+					if let Some(done) = self.step(|machine| machine.handle(msg))? {
+						self.stream.set_read_timeout(None)?;
+						return Ok(done);
+					}
+				}
+			}
+		}
+	}
+}
+
+/// Whether a failed read only ran out of time.
+fn is_timeout(e: &io::Error) -> bool {
+	matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut)
+}
+
+/// Turns a handshake read that ran out of time into [`GameFlowError::TimedOut`].
+fn timed_out(e: GameFlowError) -> GameFlowError {
+	match e {
+		GameFlowError::Network(e) | GameFlowError::Net(NetError::Io(e)) if is_timeout(&e) => {
+			GameFlowError::TimedOut
+		}
+		e => e,
+	}
+}
+
+/// Has the other side prove they know our password, if we have one, and
+/// proves we know theirs, like the async handshake does.
+fn check_password(
+	stream: &mut TcpStream,
+	serve: bool,
+	password: Option<&str>,
+) -> Result<(), GameFlowError> {
+	let plain = Codec::default();
+	let ours = password.map(|_| rand::random::<[u8; 16]>());
+	plain.write_blocking(&Msg::Challenge(ours), stream)?;
+	let theirs = match plain.read_blocking(stream)? {
+		Msg::Challenge(theirs) => theirs,
+		m => return Err(GameFlowError::BadMessage(m)),
+	};
+	let (host, guest) = if serve {
+		(ours, theirs)
+	} else {
+		(theirs, ours)
+	};
+	// The guest goes first, so the host answers for nobody it hasn't let in
+	for by_host in [false, true] {
+		if (if by_host { guest } else { host }).is_none() {
+			continue;
+		}
+		let expected = answer(password.unwrap_or_default(), by_host, host, guest);
+		if by_host == serve {
+			plain.write_blocking(&Msg::Response(expected), stream)?;
+			match plain.read_blocking(stream)? {
+				Msg::Admitted(true) => {}
+				Msg::Admitted(false) => return Err(GameFlowError::WrongPassword),
+				m => return Err(GameFlowError::BadMessage(m)),
+			}
+		} else {
+			let admitted = match plain.read_blocking(stream)? {
+				Msg::Response(response) => same(&response, &expected),
+				m => return Err(GameFlowError::BadMessage(m)),
+			};
+			plain.write_blocking(&Msg::Admitted(admitted), stream)?;
+			if !admitted {
+				return Err(GameFlowError::WrongPassword);
+			}
+		}
+	}
+	Ok(())
+}
+
+/// Checks that the other side is a game, speaks our protocol version, has
+/// our password and plays by our rules, and agrees how to compress messages
+/// from then on.
+fn greet(
+	stream: &mut TcpStream,
+	rules: &Rules,
+	serve: bool,
+	password: Option<&str>,
+) -> Result<Codec, GameFlowError> {
+	stream.write_all(MAGIC)?;
+	let mut preamble = [0; MAGIC.len()];
+	match stream.read_exact(&mut preamble) {
+		Ok(()) if preamble == *MAGIC => {}
+		_ => return Err(GameFlowError::NotAGame),
+	}
+
+	let plain = Codec::default();
+	plain.write_blocking(&Msg::Hello(VERSION), stream)?;
+	match plain.read_blocking(stream)? {
+		Msg::Hello(other) if other == VERSION => {}
+		Msg::Hello(other) => return Err(GameFlowError::Mismatch(VERSION, other)),
+		m => return Err(GameFlowError::BadMessage(m)),
+	}
+	check_password(stream, serve, password)?;
+
+	plain.write_blocking(&Msg::Compression(Compression::SUPPORTED.to_vec()), stream)?;
+	let codec = match plain.read_blocking(stream)? {
+		Msg::Compression(theirs) => Codec {
+			compression: Compression::agree(Compression::SUPPORTED, &theirs),
+			..Codec::default()
+		},
+		m => return Err(GameFlowError::BadMessage(m)),
+	};
+
+	codec.write_blocking(&Msg::Rules(rules.clone()), stream)?;
+	match codec.read_blocking(stream)? {
+		Msg::Rules(theirs) if theirs == *rules => {}
+		Msg::Rules(theirs) => {
+			return Err(GameFlowError::RulesMismatch {
+				ours: Box::new(rules.clone()),
+				theirs: Box::new(theirs),
+			})
+		}
+		m => return Err(GameFlowError::BadMessage(m)),
+	}
+
+	// Names aren't shown here, so ours stays unsaid and theirs unheard
+	codec.write_blocking(&Msg::Profile(Profile::default()), stream)?;
+	match codec.read_blocking(stream)? {
+		Msg::Profile(_) => Ok(codec),
+		m => Err(GameFlowError::BadMessage(m)),
+	}
+}
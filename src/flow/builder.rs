@@ -0,0 +1,226 @@
+//! Setting up a [`GameFlow`] with more than the defaults.
+
+use std::{
+	net::{SocketAddr, SocketAddrV4},
+	time::Duration,
+};
+
+use tokio::{
+	net::{TcpListener, TcpStream},
+	time::timeout,
+};
+
+use crate::{
+	lobby::{self, Route},
+	net::Profile,
+	rules::Rules,
+	ship::ShipNames,
+	transport::{self, Transport},
+	Game,
+};
+
+use super::{
+	accept, AcceptLimits, GameFlow, GameFlowError, Host, DIRECT_TIMEOUT, EVENT_BACKLOG,
+	HANDSHAKE_TIMEOUT, MAX_NAME,
+};
+
+/// Sets up a [`GameFlow`] with more than the defaults, then connects it over
+/// whichever transport is chosen last.
+#[derive(Clone, Debug)]
+#[allow(clippy::module_name_repetitions)]
+pub struct GameFlowBuilder {
+	rules: Rules,
+	pub(super) handshake_timeout: Duration,
+	connect_timeout: Duration,
+	pub(super) event_capacity: usize,
+	pub(super) profile: Profile,
+	pub(super) password: Option<String>,
+	pub(super) accept_limits: AcceptLimits,
+	#[cfg(feature = "tls")]
+	pub(super) tls: Option<super::Tls>,
+}
+
+impl Default for GameFlowBuilder {
+	fn default() -> Self {
+		GameFlowBuilder {
+			rules: Rules::default(),
+			handshake_timeout: HANDSHAKE_TIMEOUT,
+			connect_timeout: DIRECT_TIMEOUT,
+			event_capacity: EVENT_BACKLOG,
+			profile: Profile::default(),
+			password: None,
+			accept_limits: AcceptLimits::default(),
+			#[cfg(feature = "tls")]
+			tls: None,
+		}
+	}
+}
+
+#[allow(clippy::missing_errors_doc)]
+impl GameFlowBuilder {
+	/// The variant to play. The other player must have chosen the same rules.
+	#[must_use]
+	pub fn rules(mut self, rules: Rules) -> Self {
+		self.rules = rules;
+		self
+	}
+
+	/// How long the other side has to introduce itself once connected.
+	#[must_use]
+	pub fn handshake_timeout(mut self, limit: Duration) -> Self {
+		self.handshake_timeout = limit;
+		self
+	}
+
+	/// How long [`GameFlowBuilder::join_with_relay`] tries the host directly
+	/// before asking the relay.
+	#[must_use]
+	pub fn connect_timeout(mut self, limit: Duration) -> Self {
+		self.connect_timeout = limit;
+		self
+	}
+
+	/// How many events a slow subscriber can fall behind before missing some.
+	/// At least one is always kept.
+	#[must_use]
+	pub fn event_capacity(mut self, capacity: usize) -> Self {
+		self.event_capacity = capacity.max(1);
+		self
+	}
+
+	/// What to call us to the other player; see [`GameFlow::enemy_name`].
+	/// Names longer than [`MAX_NAME`] characters are cut short.
+	#[must_use]
+	pub fn name(mut self, name: impl Into<String>) -> Self {
+		self.profile.name = Some(name.into().chars().take(MAX_NAME).collect());
+		self
+	}
+
+	/// What to call our ships, to us and to the other player; see
+	/// [`GameFlow::enemy_ships`] and [`crate::skin::Skin::names`]. Names
+	/// longer than [`MAX_NAME`] characters are cut short.
+	#[must_use]
+	pub fn ship_names(mut self, names: ShipNames) -> Self {
+		self.profile.ships = names
+			.into_iter()
+			.map(|(ship, name)| (ship, name.chars().take(MAX_NAME).collect()))
+			.collect();
+		self
+	}
+
+	/// A password the other side has to know too, so strangers can't take a
+	/// hosted game. It's never sent; each side proves it has it by hashing
+	/// it with random bytes from the other.
+	#[must_use]
+	pub fn password(mut self, password: impl Into<String>) -> Self {
+		self.password = Some(password.into());
+		self
+	}
+
+	/// How many challengers a host greets at once, and how often one address
+	/// can connect before it's dropped, so a port scanner can't wedge it.
+	#[must_use]
+	pub fn accept_limits(mut self, limits: AcceptLimits) -> Self {
+		self.accept_limits = limits;
+		self
+	}
+
+	/// Plays over TLS, taking the part `tls` says, once connected by
+	/// whatever transport. The other side has to take the other part. Only
+	/// with the `tls` feature.
+	#[cfg(feature = "tls")]
+	#[must_use]
+	pub fn tls(mut self, tls: super::Tls) -> Self {
+		self.tls = Some(tls);
+		self
+	}
+
+	/// Hosts at `addr` if `serve` is set, or connects to the host there.
+	pub async fn connect(self, addr: SocketAddr, serve: bool) -> Result<GameFlow, GameFlowError> {
+		if serve {
+			self.accept(Host::bind(Some(addr)).await?).await
+		} else {
+			self.join(TcpStream::connect(addr).await?).await
+		}
+	}
+
+	/// Waits for a challenger on `host` and starts the game; see
+	/// [`Host::accept`].
+	pub async fn accept(self, host: Host) -> Result<GameFlow, GameFlowError> {
+		self.rules.validate()?;
+		let game = Game::new(self.rules.clone(), true);
+		host.open(game, false, &self).await
+	}
+
+	/// Waits for a challenger on a listener the application bound itself; see
+	/// [`GameFlow::host`].
+	pub async fn host(self, listener: &TcpListener) -> Result<GameFlow, GameFlowError> {
+		self.rules.validate()?;
+		let game = Game::new(self.rules.clone(), true);
+		accept::accept(listener, None, game, false, &self).await
+	}
+
+	/// Starts a game over a connection to the host the application made itself.
+	pub async fn join(self, stream: TcpStream) -> Result<GameFlow, GameFlowError> {
+		self.transport(stream, false).await
+	}
+
+	/// Joins the game hosted at `addr`, relayed through the lobby server at
+	/// `lobby` if the host can't be reached directly; see
+	/// [`GameFlow::join_with_relay`].
+	pub async fn join_with_relay(
+		self,
+		addr: SocketAddrV4,
+		lobby: SocketAddr,
+	) -> Result<GameFlow, GameFlowError> {
+		let (socket, route) = match timeout(self.connect_timeout, TcpStream::connect(addr)).await {
+			Ok(Ok(socket)) => (socket, Route::Direct),
+			_ => (lobby::join(lobby, addr).await?, Route::Relayed(lobby)),
+		};
+		let mut game = self.join(socket).await?;
+		game.route = route;
+		Ok(game)
+	}
+
+	/// Plays over a Unix domain socket at `path` instead of TCP.
+	#[cfg(unix)]
+	pub async fn unix(
+		self,
+		path: impl AsRef<std::path::Path>,
+		serve: bool,
+	) -> Result<GameFlow, GameFlowError> {
+		let socket = transport::unix(path.as_ref(), serve).await?;
+		self.transport(socket, serve).await
+	}
+
+	/// Plays over a Windows named pipe instead of TCP.
+	#[cfg(windows)]
+	pub async fn named_pipe(self, name: &str, serve: bool) -> Result<GameFlow, GameFlowError> {
+		let socket = transport::named_pipe(name, serve).await?;
+		self.transport(socket, serve).await
+	}
+
+	/// Starts a game over an already connected transport. Exactly one side
+	/// must have `serve` set; that side takes the first shot.
+	pub async fn transport(
+		self,
+		socket: impl Transport + 'static,
+		serve: bool,
+	) -> Result<GameFlow, GameFlowError> {
+		self.rules.validate()?;
+		let game = Game::new(self.rules.clone(), serve);
+		GameFlow::open(Box::new(socket), game, false, &self).await
+	}
+
+	/// Picks a saved game back up over an already connected transport; see
+	/// [`GameFlow::resume`]. The game's own rules are played, whatever
+	/// [`GameFlowBuilder::rules`] says.
+	pub async fn resume(
+		self,
+		socket: impl Transport + 'static,
+		game: Game,
+	) -> Result<GameFlow, GameFlowError> {
+		game.rules.validate()?;
+		GameFlow::open(Box::new(socket), game, true, &self).await
+	}
+}
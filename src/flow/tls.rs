@@ -0,0 +1,42 @@
+//! Playing over TLS, with the `tls` feature, so nobody between the players
+//! can read the game or change it. Configs are made with [`rustls`], which
+//! the crate re-exports.
+//!
+//! [`rustls`]: crate::rustls
+
+use std::{io, sync::Arc};
+
+use tokio_rustls::{
+	rustls::{pki_types::ServerName, ClientConfig, ServerConfig},
+	TlsAcceptor, TlsConnector,
+};
+
+use crate::transport::Transport;
+
+/// Which end of TLS we take, and how. Either side of a game can serve it,
+/// but the other side has to take the client's part, and whoever hosts
+/// usually serves.
+#[derive(Clone, Debug)]
+pub enum Tls {
+	/// Serves TLS with the certificate `config` has.
+	Server(Arc<ServerConfig>),
+	/// Connects with `config`, checking the other side's certificate is for
+	/// the name given.
+	Client(Arc<ClientConfig>, ServerName<'static>),
+}
+
+impl Tls {
+	/// Shakes hands over `socket`, which from then on carries TLS.
+	pub(super) async fn wrap(&self, socket: Box<dyn Transport>) -> io::Result<Box<dyn Transport>> {
+		Ok(match self {
+			Tls::Server(config) => {
+				Box::new(TlsAcceptor::from(config.clone()).accept(socket).await?)
+			}
+			Tls::Client(config, name) => Box::new(
+				TlsConnector::from(config.clone())
+					.connect(name.clone(), socket)
+					.await?,
+			),
+		})
+	}
+}
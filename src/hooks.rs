@@ -0,0 +1,261 @@
+//! Running the player's own commands when things happen in a game, like
+//! flashing the lights on a hit. Hooks are listed in the config file:
+//!
+//! ```toml
+//! [[hooks]]
+//! on = "enemy_hit"
+//! run = ["hue-flash", "red", "{ship}"]
+//!
+//! [[hooks]]
+//! on = "win"
+//! url = "http://192.168.1.20:8123/api/webhook/battleship"
+//! ```
+//!
+//! A hook runs a program with arguments, without a shell, or posts to a
+//! webhook the event that set it off, as JSON like `--json-events` writes
+//! it with an `on` field added. Webhooks are plain HTTP, without TLS, being
+//! usually something on the same network.
+//!
+//! Arguments and webhook addresses can have `{on}` for what set the hook
+//! off, `{pos}` for the square shot at and `{ship}` for the ship hit or
+//! sunk. Those are empty when there's no such thing, or it isn't known.
+
+use std::{fmt, io, process::ExitStatus};
+#[cfg(feature = "async")]
+use std::{process::Stdio, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+#[cfg(feature = "async")]
+use tokio::{
+	io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+	net::TcpStream,
+	process::Command,
+	sync::broadcast::{error::RecvError, Receiver},
+	time::timeout,
+};
+
+use crate::event::{Event, Player};
+
+/// How long a webhook has to answer.
+#[cfg(feature = "async")]
+pub const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// What can set a hook off. A shot that sinks a ship sets off `hit` and
+/// `sunk` both, or `enemy_hit` and `enemy_sunk`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Trigger {
+	/// We hit one of the enemy's ships.
+	Hit,
+	/// We missed.
+	Miss,
+	/// We sank one of the enemy's ships.
+	Sunk,
+	/// The enemy hit one of our ships.
+	EnemyHit,
+	/// The enemy missed.
+	EnemyMiss,
+	/// The enemy sank one of our ships.
+	EnemySunk,
+	/// We won.
+	Win,
+	/// We lost.
+	Lose,
+}
+
+impl Trigger {
+	/// Everything `event` sets off. Shots the blind rule hides set off
+	/// nothing.
+	#[must_use]
+	pub fn of(event: &Event) -> Vec<Trigger> {
+		match *event {
+			Event::Result {
+				by,
+				hit: Some(hit),
+				sunk,
+				..
+			} => {
+				let ours = by == Player::You;
+				let mut triggers = vec![match (ours, hit) {
+					(true, true) => Trigger::Hit,
+					(true, false) => Trigger::Miss,
+					(false, true) => Trigger::EnemyHit,
+					(false, false) => Trigger::EnemyMiss,
+				}];
+				if sunk.is_some() {
+					triggers.push(if ours {
+						Trigger::Sunk
+					} else {
+						Trigger::EnemySunk
+					});
+				}
+				triggers
+			}
+			Event::Done { won: true } => vec![Trigger::Win],
+			Event::Done { won: false } => vec![Trigger::Lose],
+			_ => Vec::new(),
+		}
+	}
+}
+
+impl fmt::Display for Trigger {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			Trigger::Hit => "hit",
+			Trigger::Miss => "miss",
+			Trigger::Sunk => "sunk",
+			Trigger::EnemyHit => "enemy_hit",
+			Trigger::EnemyMiss => "enemy_miss",
+			Trigger::EnemySunk => "enemy_sunk",
+			Trigger::Win => "win",
+			Trigger::Lose => "lose",
+		})
+	}
+}
+
+/// Something to do when a game gets to `on`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hook {
+	pub on: Trigger,
+	/// A program to run and its arguments, if any.
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub run: Vec<String>,
+	/// A webhook to post to, like `http://192.168.1.20:8123/battleship`.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub url: Option<String>,
+}
+
+#[derive(Error, Debug)]
+pub enum HookError {
+	#[error("couldn't run it: {0}")]
+	Io(#[from] io::Error),
+	#[error("it failed with {0}")]
+	Failed(ExitStatus),
+	#[error("only http:// webhooks are supported")]
+	NotHttp,
+	#[error("the webhook didn't answer in time")]
+	TimedOut,
+	#[error("the webhook didn't answer in HTTP")]
+	BadResponse,
+	#[error("the webhook answered with status {0}")]
+	Status(u16),
+}
+
+#[cfg(feature = "async")]
+impl Hook {
+	/// Does what the hook says for `event`, which set off `trigger`.
+	///
+	/// # Errors
+	/// Fails if the program couldn't be run or didn't succeed, or the
+	/// webhook couldn't be reached or turned the event away.
+	pub async fn fire(&self, trigger: Trigger, event: &Event) -> Result<(), HookError> {
+		if let Some((program, args)) = self.run.split_first() {
+			let status = Command::new(fill(program, trigger, event))
+				.args(args.iter().map(|arg| fill(arg, trigger, event)))
+				// Stdout could be the board, or --json-events
+				.stdin(Stdio::null())
+				.stdout(Stdio::null())
+				.status()
+				.await?;
+			if !status.success() {
+				return Err(HookError::Failed(status));
+			}
+		}
+		if let Some(url) = &self.url {
+			let mut body = serde_json::to_value(event).map_err(io::Error::from)?;
+			body["on"] = trigger.to_string().into();
+			timeout(
+				WEBHOOK_TIMEOUT,
+				post(&fill(url, trigger, event), &body.to_string()),
+			)
+			.await
+			.map_err(|_| HookError::TimedOut)??;
+		}
+		Ok(())
+	}
+}
+
+/// Fills `template` in with what `event`, which set off `trigger`, was
+/// about.
+#[cfg(feature = "async")]
+fn fill(template: &str, trigger: Trigger, event: &Event) -> String {
+	let (pos, ship) = match *event {
+		Event::Result {
+			pos, ship, sunk, ..
+		} => (
+			pos.to_string(),
+			sunk.or(ship).map(|ship| format!("{ship:?}")),
+		),
+		_ => (String::new(), None),
+	};
+	template
+		.replace("{on}", &trigger.to_string())
+		.replace("{pos}", &pos)
+		.replace("{ship}", ship.as_deref().unwrap_or_default())
+}
+
+/// Posts `body` as JSON to the webhook at `url`, checking that it took it.
+#[cfg(feature = "async")]
+async fn post(url: &str, body: &str) -> Result<(), HookError> {
+	let rest = url.strip_prefix("http://").ok_or(HookError::NotHttp)?;
+	let (host, path) = match rest.split_once('/') {
+		Some((host, path)) => (host, format!("/{path}")),
+		None => (rest, "/".to_string()),
+	};
+	// A bare IPv6 address is all colons, but ends in its bracket
+	let addr = if host.ends_with(']') || !host.contains(':') {
+		format!("{host}:80")
+	} else {
+		host.to_string()
+	};
+	let mut stream = TcpStream::connect(addr).await?;
+	let request = format!(
+		"POST {path} HTTP/1.1\r\nHost: {host}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+		body.len()
+	);
+	stream.write_all(request.as_bytes()).await?;
+	let mut status = String::new();
+	BufReader::new(stream).read_line(&mut status).await?;
+	// Like `HTTP/1.1 204 No Content`
+	let code = status
+		.strip_prefix("HTTP/")
+		.and_then(|status| status.split_whitespace().nth(1))
+		.and_then(|code| code.parse().ok())
+		.ok_or(HookError::BadResponse)?;
+	if (200..300).contains(&code) {
+		Ok(())
+	} else {
+		Err(HookError::Status(code))
+	}
+}
+
+/// Sets off `hooks` as `events` come, until the game is over. Each hook
+/// runs on a task of its own, so a slow one holds nothing up, and any that
+/// fail are only reported.
+#[cfg(feature = "async")]
+pub async fn run(hooks: Vec<Hook>, mut events: Receiver<Event>) {
+	if hooks.is_empty() {
+		return;
+	}
+	loop {
+		let event = match events.recv().await {
+			Ok(event) => event,
+			Err(RecvError::Lagged(_)) => continue,
+			Err(RecvError::Closed) => break,
+		};
+		for trigger in Trigger::of(&event) {
+			for hook in hooks.iter().filter(|hook| hook.on == trigger) {
+				let (hook, event) = (hook.clone(), event.clone());
+				tokio::spawn(async move {
+					if let Err(e) = hook.fire(trigger, &event).await {
+						eprintln!("A hook on {trigger} failed: {e}");
+					}
+				});
+			}
+		}
+		if let Event::Done { .. } = event {
+			break;
+		}
+	}
+}
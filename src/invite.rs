@@ -0,0 +1,139 @@
+use std::{
+	fmt,
+	net::{Ipv4Addr, SocketAddr, SocketAddrV4},
+	str::FromStr,
+};
+
+use data_encoding::BASE32_NOPAD;
+use qrcode::{render::unicode::Dense1x2, Color, QrCode};
+use thiserror::Error;
+
+/// How an invite's link starts. The link is what its QR code holds, and
+/// joining takes it as well as the bare code, so whatever a phone scans it
+/// with can pass it on.
+pub const SCHEME: &str = "netbattleship://join/";
+
+/// A short code standing in for a host's address, so it can be pasted or
+/// read aloud instead of an IP and port.
+///
+/// The code is the base32 of the four address octets followed by the port,
+/// split in half with a dash for readability, like `P4AAA-ANV7U`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Invite(pub SocketAddrV4);
+
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InviteError {
+	#[error("invite codes only contain the letters A to Z and the digits 2 to 7")]
+	Malformed,
+	#[error("that invite code is the wrong length")]
+	WrongLength,
+}
+
+impl fmt::Display for Invite {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let mut bytes = self.0.ip().octets().to_vec();
+		bytes.extend_from_slice(&self.0.port().to_be_bytes());
+		let code = BASE32_NOPAD.encode(&bytes);
+		let (a, b) = code.split_at(code.len() / 2);
+		write!(f, "{a}-{b}")
+	}
+}
+
+impl Invite {
+	/// The invite as a link, like `netbattleship://join/P4AAA-ANV7U`.
+	#[must_use]
+	pub fn link(self) -> String {
+		format!("{SCHEME}{self}")
+	}
+
+	fn qr_code(self) -> QrCode {
+		QrCode::new(self.link()).expect("an invite link always fits in a QR code")
+	}
+
+	/// The link as a QR code, a row at a time, with `true` for the dark
+	/// squares. There's no border, so leave a light one of four squares
+	/// around it for scanners.
+	///
+	/// # Panics
+	/// Never, since the link is short.
+	#[must_use]
+	pub fn qr(self) -> Vec<Vec<bool>> {
+		let code = self.qr_code();
+		code.to_colors()
+			.chunks(code.width())
+			.map(|row| row.iter().map(|&color| color == Color::Dark).collect())
+			.collect()
+	}
+
+	/// The link as a QR code to print on a terminal, two rows of squares to
+	/// a line. It's drawn light on dark, since terminals mostly are, which
+	/// phones scan as well.
+	///
+	/// # Panics
+	/// Never, since the link is short.
+	#[must_use]
+	pub fn qr_text(self) -> String {
+		self.qr_code()
+			.render::<Dense1x2>()
+			.dark_color(Dense1x2::Light)
+			.light_color(Dense1x2::Dark)
+			.build()
+	}
+}
+
+impl FromStr for Invite {
+	type Err = InviteError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let s = s.trim();
+		let code = s
+			.strip_prefix(SCHEME)
+			.unwrap_or(s)
+			.chars()
+			.filter(|c| !c.is_whitespace() && *c != '-')
+			.map(|c| c.to_ascii_uppercase())
+			.collect::<String>();
+		if code.len() != BASE32_NOPAD.encode_len(6) {
+			return Err(InviteError::WrongLength);
+		}
+		let bytes = BASE32_NOPAD
+			.decode(code.as_bytes())
+			.map_err(|_| InviteError::Malformed)?;
+		let bytes: [u8; 6] = bytes.try_into().map_err(|_| InviteError::WrongLength)?;
+		Ok(Invite(SocketAddrV4::new(
+			Ipv4Addr::new(bytes[0], bytes[1], bytes[2], bytes[3]),
+			u16::from_be_bytes([bytes[4], bytes[5]]),
+		)))
+	}
+}
+
+/// The first of `addrs` on a private network, where a phone or computer
+/// scanning an invite's QR code most likely is, or failing that the first
+/// that isn't loopback.
+#[must_use]
+pub fn lan_addr(addrs: &[SocketAddr]) -> Option<SocketAddrV4> {
+	let v4 = || {
+		addrs.iter().filter_map(|addr| match addr {
+			SocketAddr::V4(addr) if !addr.ip().is_loopback() => Some(*addr),
+			_ => None,
+		})
+	};
+	v4().find(|addr| addr.ip().is_private())
+		.or_else(|| v4().next())
+}
+
+/// Every IPv4 address of this computer, with the given port. Non-loopback
+/// addresses come first, since those are the ones worth sharing.
+#[must_use]
+pub fn local_addrs(port: u16) -> Vec<SocketAddrV4> {
+	let mut addrs = if_addrs::get_if_addrs()
+		.unwrap_or_default()
+		.into_iter()
+		.filter_map(|i| match i.ip() {
+			std::net::IpAddr::V4(ip) => Some(SocketAddrV4::new(ip, port)),
+			std::net::IpAddr::V6(_) => None,
+		})
+		.collect::<Vec<_>>();
+	addrs.sort_by_key(|a| a.ip().is_loopback());
+	addrs
+}
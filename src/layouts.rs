@@ -0,0 +1,240 @@
+//! Ready-made ways to lay out a fleet: a few classic patterns built in, and
+//! the player's own, kept by name under the config directory as text files
+//! in the format [`Board`] reads and writes.
+//!
+//! Layouts are laid over whatever's on the board already, so ships placed
+//! by hand stay where they are and only the rest of the fleet is placed.
+
+use std::{fs, io, path::PathBuf};
+
+use rand::seq::IndexedRandom;
+use thiserror::Error;
+
+use crate::{
+	board::{Board, LayoutError},
+	coord::Coord,
+	ship::ShipKind,
+};
+
+/// The layouts built in, which can't be saved over.
+pub const PRESETS: [&str; 3] = ["corners", "diagonal", "random-balanced"];
+
+#[derive(Error, Debug)]
+pub enum LayoutsError {
+	#[error("there's no config directory on this system")]
+	NoConfigDir,
+	#[error("layout names only contain letters, digits, dashes and underscores, and aren't one of the built-in ones")]
+	BadName,
+	#[error("there's no layout called {0}")]
+	Unknown(String),
+	#[error("couldn't access the layout: {0}")]
+	Io(#[from] io::Error),
+	#[error("couldn't read the layout: {0}")]
+	Layout(#[from] LayoutError),
+	#[error("the layout is for a different board")]
+	DifferentBoard,
+	#[error("there's a {0:?} in the layout, which isn't in the fleet")]
+	NotInFleet(ShipKind),
+	#[error("the layout's {0:?} doesn't fit")]
+	DoesntFit(ShipKind),
+	#[error("there's no ship on the board to keep")]
+	Empty,
+}
+
+/// Where to put each of `fleet` not yet on `board` to lay it out like the
+/// layout called `name`, a preset or one of the player's own.
+///
+/// # Errors
+/// Fails if there's no such layout, or it doesn't fit around the ships
+/// already on the board.
+pub fn placements(
+	name: &str,
+	board: &Board,
+	fleet: &[ShipKind],
+) -> Result<Vec<(ShipKind, Coord, bool)>, LayoutsError> {
+	match name {
+		"corners" => arrange(board, fleet, |_, spots, footprint| {
+			least(spots, |spot| {
+				footprint(spot).map(|pos| corner(board, pos)).sum()
+			})
+		}),
+		"diagonal" => arrange(board, fleet, |_, spots, footprint| {
+			least(spots, |spot| {
+				footprint(spot)
+					.map(|pos| u32::from(pos.x.abs_diff(pos.y)))
+					.sum()
+			})
+		}),
+		"random-balanced" => arrange(board, fleet, |board, spots, footprint| {
+			// The quarter of the board with the fewest ship squares so far
+			let load = |spot| -> usize {
+				footprint(spot)
+					.map(|pos| {
+						board
+							.board
+							.iter()
+							.filter(|(&at, cell)| {
+								cell.ship.is_some() && quarter(board, at) == quarter(board, pos)
+							})
+							.count()
+					})
+					.sum()
+			};
+			let fewest = spots.iter().map(|&spot| load(spot)).min()?;
+			let balanced = spots
+				.iter()
+				.copied()
+				.filter(|&spot| load(spot) == fewest)
+				.collect::<Vec<_>>();
+			balanced.choose(&mut rand::rng()).copied()
+		}),
+		_ => {
+			let text = match fs::read_to_string(path(name)?) {
+				Err(e) if e.kind() == io::ErrorKind::NotFound => {
+					return Err(LayoutsError::Unknown(name.to_string()))
+				}
+				text => text?,
+			};
+			fit(&text.parse()?, board, fleet)
+		}
+	}
+}
+
+/// Where to put each of `fleet` not yet on `board` to match `layout`.
+///
+/// # Errors
+/// Fails if the layout is for another shape of board, has ships that
+/// aren't in the fleet, or doesn't fit around the ships already placed.
+pub fn fit(
+	layout: &Board,
+	board: &Board,
+	fleet: &[ShipKind],
+) -> Result<Vec<(ShipKind, Coord, bool)>, LayoutsError> {
+	if layout.size() != board.size() || layout.land() != board.land() {
+		return Err(LayoutsError::DifferentBoard);
+	}
+	let mut board = board.clone();
+	let mut placed = Vec::new();
+	for (ship, pos, v) in layout.placements() {
+		if !fleet.contains(&ship) {
+			return Err(LayoutsError::NotInFleet(ship));
+		}
+		if board.span(ship).is_some() {
+			continue;
+		}
+		if !ship.place(&mut board, pos, v) {
+			return Err(LayoutsError::DoesntFit(ship));
+		}
+		placed.push((ship, pos, v));
+	}
+	Ok(placed)
+}
+
+/// Keeps the ships on `board` as the layout called `name`, replacing any
+/// layout of the player's by that name.
+///
+/// # Errors
+/// Fails if the name is taken by a preset or isn't a valid file name, the
+/// board has no ships, or the file can't be written.
+pub fn save(name: &str, board: &Board) -> Result<(), LayoutsError> {
+	if board.placements().is_empty() {
+		return Err(LayoutsError::Empty);
+	}
+	let path = path(name)?;
+	if let Some(dir) = path.parent() {
+		fs::create_dir_all(dir)?;
+	}
+	fs::write(path, board.to_string())?;
+	Ok(())
+}
+
+/// The names of every layout, the presets first and then the player's own
+/// in alphabetical order.
+#[must_use]
+pub fn names() -> Vec<String> {
+	let mut saved = dir()
+		.and_then(|dir| fs::read_dir(dir).ok())
+		.into_iter()
+		.flatten()
+		.filter_map(|entry| {
+			let path = entry.ok()?.path();
+			let stem = path.file_stem()?.to_str()?;
+			(path.extension()? == "txt").then(|| stem.to_string())
+		})
+		.collect::<Vec<_>>();
+	saved.sort();
+	PRESETS
+		.iter()
+		.map(ToString::to_string)
+		.chain(saved)
+		.collect()
+}
+
+/// The directory the player's layouts are kept in, `netbattleship/layouts`
+/// under the user's config directory.
+#[must_use]
+pub fn dir() -> Option<PathBuf> {
+	Some(dirs::config_dir()?.join("netbattleship").join("layouts"))
+}
+
+fn path(name: &str) -> Result<PathBuf, LayoutsError> {
+	let valid = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+	if name.is_empty() || !name.chars().all(valid) || PRESETS.contains(&name) {
+		return Err(LayoutsError::BadName);
+	}
+	Ok(dir()
+		.ok_or(LayoutsError::NoConfigDir)?
+		.join(format!("{name}.txt")))
+}
+
+/// Places each of `fleet` not yet on `board` at the spot `pick` chooses out
+/// of those where it fits, given the board so far and the squares a spot
+/// covers.
+fn arrange(
+	board: &Board,
+	fleet: &[ShipKind],
+	mut pick: impl FnMut(
+		&Board,
+		&[(Coord, bool)],
+		&dyn Fn((Coord, bool)) -> Box<dyn Iterator<Item = Coord>>,
+	) -> Option<(Coord, bool)>,
+) -> Result<Vec<(ShipKind, Coord, bool)>, LayoutsError> {
+	let mut board = board.clone();
+	let mut placed = Vec::new();
+	for &ship in fleet {
+		if board.span(ship).is_some() {
+			continue;
+		}
+		let spots = (0..board.size())
+			.flat_map(|y| (0..board.size()).map(move |x| Coord::new(x, y)))
+			.flat_map(|pos| [(pos, false), (pos, true)])
+			.filter(|&(pos, v)| ship.can_place(&board, pos, v))
+			.collect::<Vec<_>>();
+		let footprint =
+			|(pos, v)| -> Box<dyn Iterator<Item = Coord>> { Box::new(ship.footprint(pos, v)) };
+		let (pos, v) = pick(&board, &spots, &footprint).ok_or(LayoutsError::DoesntFit(ship))?;
+		ship.place(&mut board, pos, v);
+		placed.push((ship, pos, v));
+	}
+	Ok(placed)
+}
+
+/// The first of `spots` that scores lowest.
+fn least(spots: &[(Coord, bool)], score: impl Fn((Coord, bool)) -> u32) -> Option<(Coord, bool)> {
+	spots.iter().copied().min_by_key(|&spot| score(spot))
+}
+
+/// How many squares `pos` is from the nearest corner of `board`, counting
+/// diagonal steps as one.
+fn corner(board: &Board, pos: Coord) -> u32 {
+	let last = board.size() - 1;
+	let x = pos.x.min(last - pos.x);
+	let y = pos.y.min(last - pos.y);
+	u32::from(x.max(y))
+}
+
+/// Which quarter of `board` `pos` is in.
+fn quarter(board: &Board, pos: Coord) -> (bool, bool) {
+	let half = board.size() / 2;
+	(pos.x < half, pos.y < half)
+}
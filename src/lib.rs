@@ -1,65 +1,46 @@
 #![warn(clippy::pedantic)]
 
-use ship::Ship;
-
-pub mod board;
-pub mod ship;
-
-#[derive(Default, Clone)]
-pub struct Game {
-	pub board: [board::Board; 2],
-	pub turn: bool,
-	pub you: bool,
-	pub phase: Phase,
-}
-
-impl From<Game> for String {
-	fn from(game: Game) -> Self {
-		let mut out = String::new();
-		out += " | YOU      | THEM     |\n";
-		out += " |0123456789|0123456789|\n";
-		for row in 0..10 {
-			out.push((b'A' + row) as char);
-			out += "|";
-			let mut left = String::new();
-			let mut right = String::new();
-			for col in 0..10 {
-				left.push(
-					game.board[usize::from(game.you)]
-						.board
-						.get(&(col, row))
-						.copied()
-						.unwrap_or_default()
-						.into(),
-				);
-				right.push(
-					game.board[1 ^ usize::from(game.you)]
-						.board
-						.get(&(col, row))
-						.copied()
-						.unwrap_or_default()
-						.into(),
-				);
-			}
-			out.push_str(&left);
-			out += "|";
-			out.push_str(&right);
-			out += "|\n";
-		}
-
-		out
-	}
-}
-
-#[derive(Default, Clone, PartialEq, Eq)]
-pub enum Phase {
-	#[default]
-	Connecting,
-	Placing(Ship),
-	Playing,
-	Done(bool),
-}
+pub use netbattleship_core::{
+	board, coord, history, rules, ship, sync, Game, Phase, TURNS_PER_SCAN,
+};
+#[cfg(feature = "tls")]
+pub use tokio_rustls::rustls;
 
+pub mod address;
+pub mod analysis;
+#[cfg(feature = "async")]
+pub mod api;
+pub mod archive;
+pub mod bot;
+#[cfg(feature = "async")]
+pub mod broadcast;
+pub mod campaign;
+pub mod config;
+pub mod event;
 pub mod flow;
+pub mod hooks;
+pub mod invite;
+pub mod layouts;
+#[cfg(feature = "async")]
+pub mod link;
+pub mod lobby;
+#[cfg(feature = "async")]
+pub mod manager;
+#[cfg(feature = "async")]
+pub mod nat;
 pub mod net;
+pub mod notify;
+pub mod pbm;
+pub mod prelude;
+#[cfg(feature = "discord")]
+pub mod presence;
+pub mod protocol;
+pub mod puzzle;
+pub mod render;
+pub mod replay;
+pub mod sandbox;
+pub mod save;
+pub mod skin;
+#[cfg(feature = "async")]
+pub mod transport;
 pub mod ui;
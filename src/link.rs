@@ -0,0 +1,298 @@
+use std::{
+	fmt,
+	sync::{Arc, Mutex},
+	time::{Duration, Instant},
+};
+
+use tokio::{
+	io::{self, AsyncWriteExt, ReadHalf, WriteHalf},
+	sync::{broadcast, mpsc, watch, Mutex as AsyncMutex},
+	task::JoinHandle,
+	time::interval,
+};
+
+use crate::{
+	net::{Codec, Msg, NetError},
+	transport::Transport,
+};
+
+/// How often each side pings the other to keep the connection alive and
+/// measure its latency.
+pub const PING_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How much each new round trip counts towards the rolling latency estimate,
+/// as a fraction 1/n.
+const SMOOTHING: u32 = 8;
+
+/// How many messages can arrive before the game gets round to them.
+const INBOX: usize = 16;
+
+/// How many lines of chat can arrive before a slow reader starts missing them.
+const CHAT_BACKLOG: usize = 32;
+
+/// A connection to the other player once the game has started.
+///
+/// Background tasks ping the other side regularly and read every message as
+/// it arrives, answering pings straight away and timing the answers to ours,
+/// so keepalives flow even while a player is thinking. Chat goes straight to
+/// whoever is listening to [`Link::chat`], as does how far the other side
+/// has got placing their fleet to [`Link::placing`]. When they were last
+/// aiming is kept for [`Link::aimed`], and everything else is passed on in
+/// order.
+pub struct Link {
+	codec: Codec,
+	writer: Arc<AsyncMutex<WriteHalf<Box<dyn Transport>>>>,
+	inbox: AsyncMutex<mpsc::Receiver<Result<Msg, NetError>>>,
+	rtt: Arc<Mutex<Rtt>>,
+	aside: Aside,
+	tasks: [JoinHandle<()>; 2],
+}
+
+/// What the other side sends that's handled as soon as it arrives, rather
+/// than in order with the game.
+#[derive(Clone)]
+struct Aside {
+	chat: broadcast::Sender<String>,
+	placing: watch::Sender<u8>,
+	aimed: Arc<Mutex<Option<Instant>>>,
+}
+
+/// The rolling round trip estimate, and when the ping in flight was sent.
+#[derive(Default)]
+struct Rtt {
+	estimate: Option<Duration>,
+	sent: Option<Instant>,
+}
+
+impl Rtt {
+	/// Folds in the round trip of the ping just answered, if one was in flight.
+	fn answered(&mut self) {
+		let Some(sent) = self.sent.take() else { return };
+		let sample = sent.elapsed();
+		self.estimate = Some(match self.estimate {
+			Some(estimate) => (estimate * (SMOOTHING - 1) + sample) / SMOOTHING,
+			None => sample,
+		});
+	}
+}
+
+impl Link {
+	/// Takes over `socket`, which must have finished its handshake, framing
+	/// messages with `codec` from now on.
+	#[must_use]
+	pub fn new(socket: Box<dyn Transport>, codec: Codec) -> Self {
+		let (reader, writer) = io::split(socket);
+		let writer = Arc::new(AsyncMutex::new(writer));
+		let rtt = Arc::new(Mutex::new(Rtt::default()));
+		let (sender, inbox) = mpsc::channel(INBOX);
+		let aside = Aside {
+			chat: broadcast::channel(CHAT_BACKLOG).0,
+			placing: watch::channel(0).0,
+			aimed: Arc::new(Mutex::new(None)),
+		};
+		let tasks = [
+			tokio::spawn(read(
+				reader,
+				codec,
+				sender,
+				aside.clone(),
+				writer.clone(),
+				rtt.clone(),
+			)),
+			tokio::spawn(ping(writer.clone(), codec, rtt.clone())),
+		];
+		Link {
+			codec,
+			writer,
+			inbox: AsyncMutex::new(inbox),
+			rtt,
+			aside,
+			tasks,
+		}
+	}
+
+	/// How messages are framed and compressed on this link.
+	#[must_use]
+	pub fn codec(&self) -> Codec {
+		self.codec
+	}
+
+	/// # Panics
+	/// Panics if the connection is closed.
+	pub async fn send(&self, msg: &Msg) {
+		self.try_send(msg).await.expect("bad write");
+	}
+
+	/// Like [`Link::send`], for when the connection may already be gone.
+	///
+	/// # Errors
+	/// Fails if the connection is closed.
+	pub async fn try_send(&self, msg: &Msg) -> io::Result<()> {
+		self.codec.write(msg, &mut *self.writer.lock().await).await
+	}
+
+	/// Waits for the next message that isn't a keepalive.
+	///
+	/// # Errors
+	/// Fails if the connection is closed or the other side sends something
+	/// that isn't a valid message.
+	pub async fn recv(&self) -> Result<Msg, NetError> {
+		match self.inbox.lock().await.recv().await {
+			Some(msg) => msg,
+			None => Err(NetError::Io(io::ErrorKind::UnexpectedEof.into())),
+		}
+	}
+
+	/// Subscribes to the lines of chat the other side sends from now on.
+	#[must_use]
+	pub fn chat(&self) -> broadcast::Receiver<String> {
+		self.aside.chat.subscribe()
+	}
+
+	/// Watches how many ships the other side has placed, from their
+	/// [`Msg::PlacingProgress`].
+	#[must_use]
+	pub fn placing(&self) -> watch::Receiver<u8> {
+		self.aside.placing.subscribe()
+	}
+
+	/// When the other side last said they were aiming, with
+	/// [`Msg::Aiming`], if they have.
+	#[must_use]
+	pub fn aimed(&self) -> Option<Instant> {
+		self.aside.aimed.lock().ok().and_then(|aimed| *aimed)
+	}
+
+	/// The rolling average round trip time, once a ping has been answered.
+	#[must_use]
+	pub fn latency(&self) -> Option<Duration> {
+		self.rtt.lock().ok().and_then(|rtt| rtt.estimate)
+	}
+
+	/// Closes our side of the connection.
+	///
+	/// # Errors
+	/// Fails if the connection couldn't be closed cleanly.
+	pub async fn shutdown(&self) -> io::Result<()> {
+		self.writer.lock().await.shutdown().await
+	}
+}
+
+impl Drop for Link {
+	fn drop(&mut self) {
+		for task in &self.tasks {
+			task.abort();
+		}
+	}
+}
+
+/// Reads messages until the connection closes, answering pings, handing chat
+/// and placing progress over, noting when they're aiming and passing
+/// everything else on.
+async fn read(
+	mut reader: ReadHalf<Box<dyn Transport>>,
+	codec: Codec,
+	inbox: mpsc::Sender<Result<Msg, NetError>>,
+	aside: Aside,
+	writer: Arc<AsyncMutex<WriteHalf<Box<dyn Transport>>>>,
+	rtt: Arc<Mutex<Rtt>>,
+) {
+	loop {
+		match codec.read(&mut reader).await {
+			Ok(Msg::Ping) => {
+				// If this fails, the next read reports the broken connection
+				let _ = codec.write(&Msg::Pong, &mut *writer.lock().await).await;
+			}
+			Ok(Msg::Pong) => {
+				if let Ok(mut rtt) = rtt.lock() {
+					rtt.answered();
+				}
+			}
+			Ok(Msg::Chat(text)) => {
+				// Nobody listening is fine
+				let _ = aside.chat.send(text);
+			}
+			Ok(Msg::PlacingProgress(placed)) => {
+				aside.placing.send_replace(placed);
+			}
+			Ok(Msg::Aiming) => {
+				if let Ok(mut aimed) = aside.aimed.lock() {
+					*aimed = Some(Instant::now());
+				}
+			}
+			Ok(msg) => {
+				if inbox.send(Ok(msg)).await.is_err() {
+					return;
+				}
+			}
+			Err(e) => {
+				let _ = inbox.send(Err(e)).await;
+				return;
+			}
+		}
+	}
+}
+
+/// Pings the other side every [`PING_INTERVAL`], unless the last ping is
+/// still unanswered.
+async fn ping(
+	writer: Arc<AsyncMutex<WriteHalf<Box<dyn Transport>>>>,
+	codec: Codec,
+	rtt: Arc<Mutex<Rtt>>,
+) {
+	let mut ticks = interval(PING_INTERVAL);
+	loop {
+		ticks.tick().await;
+		let mut writer = writer.lock().await;
+		{
+			let Ok(mut rtt) = rtt.lock() else { return };
+			if rtt.sent.is_some() {
+				continue;
+			}
+			rtt.sent = Some(Instant::now());
+		}
+		if codec.write(&Msg::Ping, &mut *writer).await.is_err() {
+			return;
+		}
+	}
+}
+
+/// A round trip time, displayed with how it rates, like "42 ms (good)".
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Latency(pub Duration);
+
+impl fmt::Display for Latency {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{} ms ({})", self.0.as_millis(), Quality::of(self.0))
+	}
+}
+
+/// How a connection's latency feels to play over.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Quality {
+	Good,
+	Fair,
+	Poor,
+}
+
+impl Quality {
+	/// Rates a round trip time.
+	#[must_use]
+	pub fn of(rtt: Duration) -> Self {
+		match rtt.as_millis() {
+			0..=149 => Quality::Good,
+			150..=399 => Quality::Fair,
+			_ => Quality::Poor,
+		}
+	}
+}
+
+impl fmt::Display for Quality {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			Quality::Good => "good",
+			Quality::Fair => "fair",
+			Quality::Poor => "poor",
+		})
+	}
+}
@@ -0,0 +1,696 @@
+use std::{
+	collections::BTreeMap,
+	fs, io,
+	net::{SocketAddr, SocketAddrV4},
+	path::PathBuf,
+	time::Duration,
+};
+#[cfg(feature = "async")]
+use std::{collections::HashMap, sync::Arc, time::Instant};
+
+#[cfg(feature = "async")]
+use data_encoding::BASE32_NOPAD;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+#[cfg(feature = "async")]
+use tokio::{
+	io::copy_bidirectional,
+	net::{TcpListener, TcpStream},
+	sync::{mpsc, oneshot, Mutex},
+	time::timeout,
+};
+
+#[cfg(feature = "async")]
+use crate::net::{read_from_async, Codec};
+use crate::{net::NetError, rules::Rules};
+
+/// How long a new connection has to say whether it hosts or joins.
+#[cfg(feature = "async")]
+const GREETING_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The most games a lobby keeps waiting from one IP address.
+pub const MAX_GAMES_PER_IP: usize = 8;
+
+/// Messages between a player and the lobby server, before the lobby starts
+/// relaying game traffic.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum LobbyMsg {
+	/// A host offers to be relayed to, using the port it is also listening on directly.
+	Host {
+		port: u16,
+	},
+	/// Like [`LobbyMsg::Host`], but also listed for anyone browsing the lobby.
+	Announce {
+		port: u16,
+		/// What the host calls themselves, which may be empty.
+		name: String,
+		rules: Rules,
+	},
+	/// The address joiners should use to reach a host through the relay.
+	Registered(SocketAddrV4),
+	/// A player wants to be relayed to the host registered as this address.
+	Join(SocketAddrV4),
+	/// Both players are connected; everything after this is game traffic.
+	Paired,
+	NoSuchGame,
+	/// Sent to a host that announced itself as soon as it's registered,
+	/// to time the way to it, and answered with [`LobbyMsg::Pong`].
+	Ping,
+	Pong,
+	/// Asks for every game the lobby lists.
+	List,
+	/// The games waiting for a challenger.
+	Games(Vec<Listing>),
+	/// A player stays connected to hear about invitations, as whoever they
+	/// were last time if they say so.
+	Online(Option<Identity>),
+	/// Who the lobby knows a player as from now on.
+	Welcome(Identity),
+	/// Invites the player with the friend code `to` to the game registered as
+	/// `game`, which must be the inviter's own.
+	Invite {
+		from: Identity,
+		/// What the inviter calls themselves, which may be empty.
+		name: String,
+		to: String,
+		rules: Rules,
+		game: SocketAddrV4,
+	},
+	/// Passed on to an online player that someone invited.
+	Invitation(Invitation),
+	/// The invitation was passed on.
+	Invited,
+	NotOnline,
+	/// The friend code doesn't go with the secret it was sent with.
+	NotYourCode,
+	/// The host's address already has as many games waiting as the lobby
+	/// keeps for one.
+	TooManyGames,
+}
+
+/// Who a player is to a lobby: the friend code others invite them by, and
+/// the secret that proves the code is theirs.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct Identity {
+	pub code: String,
+	pub secret: String,
+}
+
+impl Identity {
+	#[cfg(feature = "async")]
+	fn new() -> Identity {
+		Identity {
+			code: friend_code(&BASE32_NOPAD.encode(&rand::random::<[u8; 5]>())),
+			secret: BASE32_NOPAD.encode(&rand::random::<[u8; 20]>()),
+		}
+	}
+}
+
+/// `code` written the way the lobby hands friend codes out, like
+/// `ABCD-EFGH`, however it was typed.
+#[must_use]
+pub fn friend_code(code: &str) -> String {
+	let code = code
+		.chars()
+		.filter(|c| !c.is_whitespace() && *c != '-')
+		.map(|c| c.to_ascii_uppercase())
+		.collect::<String>();
+	match code.split_at_checked(code.len() / 2) {
+		Some((a, b)) if !code.is_empty() => format!("{a}-{b}"),
+		_ => code,
+	}
+}
+
+/// Someone asking us to join their game.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct Invitation {
+	/// The inviter's friend code.
+	pub from: String,
+	/// What the inviter calls themselves, which may be empty.
+	pub name: String,
+	pub rules: Rules,
+	/// Where to ask the lobby to join the game; see [`join`].
+	pub game: SocketAddrV4,
+}
+
+/// A game the lobby lists, waiting for a challenger.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct Listing {
+	/// Where to ask the lobby to join it; see [`join`].
+	pub host: SocketAddrV4,
+	/// What the host calls themselves, which may be empty.
+	pub name: String,
+	pub rules: Rules,
+	/// How long a message takes to reach the host and come back. From
+	/// [`browse`] this counts the way to the lobby too, as relayed games do.
+	pub latency: Duration,
+}
+
+#[derive(Error, Debug)]
+pub enum LobbyError {
+	#[error("couldn't reach the lobby: {0}")]
+	Network(#[from] io::Error),
+	#[error("the lobby connection broke: {0}")]
+	Net(#[from] NetError),
+	#[error("the lobby only relays IPv4 games")]
+	NotIpv4,
+	#[error("the lobby doesn't know about that game")]
+	NoSuchGame,
+	#[error("the lobby sent {0:?}, which makes no sense here")]
+	BadMessage(Box<LobbyMsg>),
+	#[error("they aren't online at the lobby")]
+	NotOnline,
+	#[error("the lobby didn't hand that friend code out to us")]
+	NotYourCode,
+	#[error("the lobby has too many games waiting from this address")]
+	TooManyGames,
+	#[error("couldn't keep the friend codes: {0}")]
+	Friends(io::Error),
+}
+
+/// How a game's connection reaches the other player.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Route {
+	Direct,
+	/// Through the relay at this lobby server.
+	Relayed(SocketAddr),
+}
+
+/// A host's registration with a lobby, waiting for a joiner to be relayed.
+#[cfg(feature = "async")]
+pub struct Registration {
+	stream: TcpStream,
+	lobby: SocketAddr,
+	public: SocketAddrV4,
+}
+
+#[cfg(feature = "async")]
+impl Registration {
+	/// Offers the host listening on `port` for relaying through `lobby`.
+	///
+	/// # Errors
+	/// Fails if the lobby can't be reached or answers nonsense.
+	pub async fn new(lobby: SocketAddr, port: u16) -> Result<Registration, LobbyError> {
+		Self::register(lobby, LobbyMsg::Host { port }).await
+	}
+
+	/// Offers the host listening on `port` for relaying through `lobby`, and
+	/// lists it there as `name` playing by `rules`.
+	///
+	/// # Errors
+	/// Fails if the lobby can't be reached or answers nonsense.
+	pub async fn listed(
+		lobby: SocketAddr,
+		port: u16,
+		name: String,
+		rules: Rules,
+	) -> Result<Registration, LobbyError> {
+		let mut registration =
+			Self::register(lobby, LobbyMsg::Announce { port, name, rules }).await?;
+		match read_from_async(&mut registration.stream).await? {
+			LobbyMsg::Ping => {
+				Codec::default()
+					.write(&LobbyMsg::Pong, &mut registration.stream)
+					.await?;
+				Ok(registration)
+			}
+			m => Err(LobbyError::BadMessage(Box::new(m))),
+		}
+	}
+
+	async fn register(lobby: SocketAddr, greeting: LobbyMsg) -> Result<Registration, LobbyError> {
+		let mut stream = TcpStream::connect(lobby).await?;
+		Codec::default().write(&greeting, &mut stream).await?;
+		match read_from_async(&mut stream).await? {
+			LobbyMsg::Registered(public) => Ok(Registration {
+				stream,
+				lobby,
+				public,
+			}),
+			LobbyMsg::TooManyGames => Err(LobbyError::TooManyGames),
+			m => Err(LobbyError::BadMessage(Box::new(m))),
+		}
+	}
+
+	/// The address a joiner should ask the lobby for; this is the host's
+	/// public address as the lobby sees it.
+	#[must_use]
+	pub fn public(&self) -> SocketAddrV4 {
+		self.public
+	}
+
+	/// Waits until a joiner is relayed through, returning the connection to them.
+	///
+	/// # Errors
+	/// Fails if the lobby goes away or answers nonsense.
+	pub async fn paired(mut self) -> Result<(TcpStream, Route), LobbyError> {
+		match read_from_async(&mut self.stream).await? {
+			LobbyMsg::Paired => Ok((self.stream, Route::Relayed(self.lobby))),
+			m => Err(LobbyError::BadMessage(Box::new(m))),
+		}
+	}
+}
+
+/// A player's connection to a lobby, waiting to be invited to games.
+#[cfg(feature = "async")]
+pub struct Presence {
+	stream: TcpStream,
+	identity: Identity,
+}
+
+#[cfg(feature = "async")]
+impl Presence {
+	/// Goes online at `lobby` as `identity`, or with a new friend code if
+	/// there isn't one or the lobby doesn't know it.
+	///
+	/// # Errors
+	/// Fails if the lobby can't be reached or answers nonsense.
+	pub async fn new(
+		lobby: SocketAddr,
+		identity: Option<Identity>,
+	) -> Result<Presence, LobbyError> {
+		let mut stream = TcpStream::connect(lobby).await?;
+		Codec::default()
+			.write(&LobbyMsg::Online(identity), &mut stream)
+			.await?;
+		match read_from_async(&mut stream).await? {
+			LobbyMsg::Welcome(identity) => Ok(Presence { stream, identity }),
+			m => Err(LobbyError::BadMessage(Box::new(m))),
+		}
+	}
+
+	/// Who the lobby knows us as, which should be kept to go online as next
+	/// time.
+	#[must_use]
+	pub fn identity(&self) -> &Identity {
+		&self.identity
+	}
+
+	/// Waits for someone to invite us to a game.
+	///
+	/// # Errors
+	/// Fails if the lobby goes away or answers nonsense.
+	pub async fn invitation(&mut self) -> Result<Invitation, LobbyError> {
+		match read_from_async(&mut self.stream).await? {
+			LobbyMsg::Invitation(invitation) => Ok(invitation),
+			m => Err(LobbyError::BadMessage(Box::new(m))),
+		}
+	}
+}
+
+/// Invites the player with the friend code `to` to our game by `rules`,
+/// registered with `lobby` as `game`, telling them we're `name`.
+///
+/// # Errors
+/// Fails if the lobby can't be reached, they aren't online, the game isn't
+/// registered or `from` isn't who the lobby knows us as.
+#[cfg(feature = "async")]
+pub async fn invite(
+	lobby: SocketAddr,
+	from: &Identity,
+	name: String,
+	to: &str,
+	rules: Rules,
+	game: SocketAddrV4,
+) -> Result<(), LobbyError> {
+	let mut stream = TcpStream::connect(lobby).await?;
+	let invite = LobbyMsg::Invite {
+		from: from.clone(),
+		name,
+		to: friend_code(to),
+		rules,
+		game,
+	};
+	Codec::default().write(&invite, &mut stream).await?;
+	match read_from_async(&mut stream).await? {
+		LobbyMsg::Invited => Ok(()),
+		LobbyMsg::NotOnline => Err(LobbyError::NotOnline),
+		LobbyMsg::NotYourCode => Err(LobbyError::NotYourCode),
+		LobbyMsg::NoSuchGame => Err(LobbyError::NoSuchGame),
+		m => Err(LobbyError::BadMessage(Box::new(m))),
+	}
+}
+
+/// Asks `lobby` to relay us to the host registered as `host`.
+///
+/// # Errors
+/// Fails if the lobby can't be reached or doesn't know the host.
+#[cfg(feature = "async")]
+pub async fn join(lobby: SocketAddr, host: SocketAddrV4) -> Result<TcpStream, LobbyError> {
+	let mut stream = TcpStream::connect(lobby).await?;
+	Codec::default()
+		.write(&LobbyMsg::Join(host), &mut stream)
+		.await?;
+	match read_from_async(&mut stream).await? {
+		LobbyMsg::Paired => Ok(stream),
+		LobbyMsg::NoSuchGame => Err(LobbyError::NoSuchGame),
+		m => Err(LobbyError::BadMessage(Box::new(m))),
+	}
+}
+
+/// Every game `lobby` lists, with the time to reach the lobby added to each
+/// one's latency.
+///
+/// # Errors
+/// Fails if the lobby can't be reached or answers nonsense.
+#[cfg(feature = "async")]
+pub async fn browse(lobby: SocketAddr) -> Result<Vec<Listing>, LobbyError> {
+	let mut stream = TcpStream::connect(lobby).await?;
+	let asked = Instant::now();
+	Codec::default().write(&LobbyMsg::List, &mut stream).await?;
+	match read_from_async(&mut stream).await? {
+		LobbyMsg::Games(mut games) => {
+			let trip = asked.elapsed();
+			for game in &mut games {
+				game.latency += trip;
+			}
+			Ok(games)
+		}
+		m => Err(LobbyError::BadMessage(Box::new(m))),
+	}
+}
+
+/// A host waiting for a joiner, and how it's listed if it is. The host's
+/// connection stays with the task watching it, which a joiner's is sent to.
+#[cfg(feature = "async")]
+struct Waiting {
+	join: oneshot::Sender<TcpStream>,
+	listing: Option<Listing>,
+}
+
+/// The friend codes a lobby has handed out, with the secret for each, kept
+/// in a TOML file if there is one so they last between runs.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Friends {
+	#[serde(skip)]
+	path: Option<PathBuf>,
+	codes: BTreeMap<String, String>,
+}
+
+impl Friends {
+	/// Reads the friend codes kept at `path`, or none if there's no file
+	/// there yet, and keeps new ones there.
+	///
+	/// # Errors
+	/// Fails if the file exists but can't be read or parsed.
+	pub fn load(path: PathBuf) -> Result<Friends, LobbyError> {
+		let mut friends: Friends = match fs::read_to_string(&path) {
+			Ok(text) => toml::from_str(&text)
+				.map_err(|e| LobbyError::Friends(io::Error::new(io::ErrorKind::InvalidData, e)))?,
+			Err(e) if e.kind() == io::ErrorKind::NotFound => Friends::default(),
+			Err(e) => return Err(LobbyError::Friends(e)),
+		};
+		friends.path = Some(path);
+		Ok(friends)
+	}
+
+	/// Whether the lobby handed `identity`'s code out with its secret.
+	#[cfg(feature = "async")]
+	fn owns(&self, identity: &Identity) -> bool {
+		self.codes.get(&identity.code) == Some(&identity.secret)
+	}
+
+	/// `identity` if it's one the lobby handed out, or a new one otherwise.
+	#[cfg(feature = "async")]
+	fn check_in(&mut self, identity: Option<Identity>) -> Result<Identity, LobbyError> {
+		if let Some(identity) = identity.filter(|identity| self.owns(identity)) {
+			return Ok(identity);
+		}
+		let mut identity = Identity::new();
+		while self.codes.contains_key(&identity.code) {
+			identity = Identity::new();
+		}
+		self.codes
+			.insert(identity.code.clone(), identity.secret.clone());
+		if let Some(path) = &self.path {
+			let text =
+				toml::to_string(self).map_err(|e| LobbyError::Friends(io::Error::other(e)))?;
+			fs::write(path, text).map_err(LobbyError::Friends)?;
+		}
+		Ok(identity)
+	}
+}
+
+/// Everything a lobby server keeps track of.
+#[cfg(feature = "async")]
+#[derive(Default)]
+struct Lobby {
+	hosts: Mutex<HashMap<SocketAddrV4, Waiting>>,
+	friends: Mutex<Friends>,
+	/// Where to pass invitations on to each player online, by friend code.
+	online: Mutex<HashMap<String, mpsc::UnboundedSender<Invitation>>>,
+}
+
+/// Runs a lobby server, pairing hosts with joiners and relaying between them.
+/// Friend codes are forgotten when it stops; see [`serve_with`] to keep them.
+///
+/// # Errors
+/// Returns only if accepting connections fails.
+#[cfg(feature = "async")]
+pub async fn serve(listener: TcpListener) -> Result<(), LobbyError> {
+	serve_with(listener, Friends::default()).await
+}
+
+/// Runs a lobby server like [`serve`], handing out friend codes from `friends`.
+///
+/// # Errors
+/// Returns only if accepting connections fails.
+#[cfg(feature = "async")]
+pub async fn serve_with(listener: TcpListener, friends: Friends) -> Result<(), LobbyError> {
+	let lobby = Arc::new(Lobby {
+		friends: Mutex::new(friends),
+		..Lobby::default()
+	});
+	loop {
+		let (stream, peer) = listener.accept().await?;
+		let lobby = lobby.clone();
+		tokio::spawn(async move {
+			if let Err(e) = handle(stream, peer, &lobby).await {
+				eprintln!("{peer}: {e}");
+			}
+		});
+	}
+}
+
+#[cfg(feature = "async")]
+async fn handle(mut stream: TcpStream, peer: SocketAddr, lobby: &Lobby) -> Result<(), LobbyError> {
+	let hosts = &lobby.hosts;
+	let SocketAddr::V4(peer) = peer else {
+		return Err(LobbyError::NotIpv4);
+	};
+	let greeting = timeout(GREETING_TIMEOUT, read_from_async(&mut stream))
+		.await
+		.map_err(|_| NetError::TimedOut)??;
+	match greeting {
+		LobbyMsg::Host { port } => {
+			let public = SocketAddrV4::new(*peer.ip(), port);
+			if !room_for(&*hosts.lock().await, public) {
+				Codec::default()
+					.write(&LobbyMsg::TooManyGames, &mut stream)
+					.await?;
+				return Err(LobbyError::TooManyGames);
+			}
+			Codec::default()
+				.write(&LobbyMsg::Registered(public), &mut stream)
+				.await?;
+			wait_for_joiner(stream, public, None, lobby).await
+		}
+		LobbyMsg::Announce { port, name, rules } => {
+			let public = SocketAddrV4::new(*peer.ip(), port);
+			if !room_for(&*hosts.lock().await, public) {
+				Codec::default()
+					.write(&LobbyMsg::TooManyGames, &mut stream)
+					.await?;
+				return Err(LobbyError::TooManyGames);
+			}
+			Codec::default()
+				.write(&LobbyMsg::Registered(public), &mut stream)
+				.await?;
+			let pinged = Instant::now();
+			Codec::default().write(&LobbyMsg::Ping, &mut stream).await?;
+			match timeout(GREETING_TIMEOUT, read_from_async(&mut stream))
+				.await
+				.map_err(|_| NetError::TimedOut)??
+			{
+				LobbyMsg::Pong => {}
+				m => return Err(LobbyError::BadMessage(Box::new(m))),
+			}
+			let listing = Listing {
+				host: public,
+				name,
+				rules,
+				latency: pinged.elapsed(),
+			};
+			wait_for_joiner(stream, public, Some(listing), lobby).await
+		}
+		LobbyMsg::List => {
+			let games = hosts
+				.lock()
+				.await
+				.values()
+				.filter_map(|waiting| waiting.listing.clone())
+				.collect();
+			Codec::default()
+				.write(&LobbyMsg::Games(games), &mut stream)
+				.await?;
+			Ok(())
+		}
+		LobbyMsg::Join(public) => relay(stream, public, lobby).await,
+		LobbyMsg::Online(identity) => {
+			let identity = lobby.friends.lock().await.check_in(identity)?;
+			Codec::default()
+				.write(&LobbyMsg::Welcome(identity.clone()), &mut stream)
+				.await?;
+			stay_online(stream, &identity.code, lobby).await;
+			Ok(())
+		}
+		LobbyMsg::Invite {
+			from,
+			name,
+			to,
+			rules,
+			game,
+		} => {
+			let invitation = Invitation {
+				from: from.code.clone(),
+				name,
+				rules,
+				game,
+			};
+			let reply = pass_on(lobby, peer, &from, &to, invitation).await;
+			Codec::default().write(&reply, &mut stream).await?;
+			Ok(())
+		}
+		m => Err(LobbyError::BadMessage(Box::new(m))),
+	}
+}
+
+/// Whether another game can wait as `public`: one already waiting there is
+/// replaced, but otherwise each IP address only gets so many.
+#[cfg(feature = "async")]
+fn room_for(hosts: &HashMap<SocketAddrV4, Waiting>, public: SocketAddrV4) -> bool {
+	hosts.contains_key(&public)
+		|| hosts.keys().filter(|host| host.ip() == public.ip()).count() < MAX_GAMES_PER_IP
+}
+
+/// Keeps the host on `stream` waiting as `public` until a joiner comes, then
+/// relays between them until either hangs up. A host that hangs up first, or
+/// says anything while it waits, stops waiting.
+#[cfg(feature = "async")]
+async fn wait_for_joiner(
+	mut stream: TcpStream,
+	public: SocketAddrV4,
+	listing: Option<Listing>,
+	lobby: &Lobby,
+) -> Result<(), LobbyError> {
+	let (join, mut claimed) = oneshot::channel();
+	lobby
+		.hosts
+		.lock()
+		.await
+		.insert(public, Waiting { join, listing });
+	let mut byte = [0; 1];
+	let joiner = tokio::select! {
+		biased;
+		// Gone when a joiner took the game, or another host replaced it
+		joiner = &mut claimed => joiner.ok(),
+		_ = stream.peek(&mut byte) => None,
+	};
+	drop(claimed);
+	let Some(mut joiner) = joiner else {
+		let mut hosts = lobby.hosts.lock().await;
+		if hosts
+			.get(&public)
+			.is_some_and(|waiting| waiting.join.is_closed())
+		{
+			hosts.remove(&public);
+		}
+		return Ok(());
+	};
+	let plain = Codec::default();
+	// A host that went away just now can't be paired with
+	if plain.write(&LobbyMsg::Paired, &mut stream).await.is_err() {
+		plain.write(&LobbyMsg::NoSuchGame, &mut joiner).await?;
+		return Err(LobbyError::NoSuchGame);
+	}
+	plain.write(&LobbyMsg::Paired, &mut joiner).await?;
+	copy_bidirectional(&mut stream, &mut joiner).await?;
+	Ok(())
+}
+
+/// Hands the joiner on `stream` to the host waiting as `public`, which
+/// relays between them from then on.
+#[cfg(feature = "async")]
+async fn relay(stream: TcpStream, public: SocketAddrV4, lobby: &Lobby) -> Result<(), LobbyError> {
+	let waiting = lobby.hosts.lock().await.remove(&public);
+	let mut stream = match waiting {
+		Some(waiting) => match waiting.join.send(stream) {
+			Ok(()) => return Ok(()),
+			// The host hung up without its task noticing yet
+			Err(stream) => stream,
+		},
+		None => stream,
+	};
+	Codec::default()
+		.write(&LobbyMsg::NoSuchGame, &mut stream)
+		.await?;
+	Err(LobbyError::NoSuchGame)
+}
+
+/// Passes `invitation` on to the player online as `to`, if it's really
+/// `from` at `peer` inviting them to their own game, and says how it went.
+#[cfg(feature = "async")]
+async fn pass_on(
+	lobby: &Lobby,
+	peer: SocketAddrV4,
+	from: &Identity,
+	to: &str,
+	invitation: Invitation,
+) -> LobbyMsg {
+	if !lobby.friends.lock().await.owns(from) {
+		return LobbyMsg::NotYourCode;
+	}
+	let game = invitation.game;
+	if game.ip() != peer.ip() || !lobby.hosts.lock().await.contains_key(&game) {
+		return LobbyMsg::NoSuchGame;
+	}
+	match lobby.online.lock().await.get(to) {
+		Some(online) if online.send(invitation).is_ok() => LobbyMsg::Invited,
+		_ => LobbyMsg::NotOnline,
+	}
+}
+
+/// Passes invitations for `code` on over `stream` until the player leaves,
+/// or goes online again somewhere else.
+#[cfg(feature = "async")]
+async fn stay_online(mut stream: TcpStream, code: &str, lobby: &Lobby) {
+	let (invitations, mut queue) = mpsc::unbounded_channel();
+	lobby
+		.online
+		.lock()
+		.await
+		.insert(code.to_string(), invitations);
+	loop {
+		tokio::select! {
+			invitation = queue.recv() => match invitation {
+				Some(invitation) => {
+					let invitation = LobbyMsg::Invitation(invitation);
+					if Codec::default().write(&invitation, &mut stream).await.is_err() {
+						break;
+					}
+				}
+				None => break,
+			},
+			// Nothing more is expected, so this is the player leaving
+			_ = read_from_async::<LobbyMsg, _>(&mut stream) => break,
+		}
+	}
+	drop(queue);
+	let mut online = lobby.online.lock().await;
+	if online
+		.get(code)
+		.is_some_and(mpsc::UnboundedSender::is_closed)
+	{
+		online.remove(code);
+	}
+}
@@ -1,84 +1,2445 @@
 #![warn(clippy::pedantic)]
-use netbattleship::flow::{GameFlow, GameFlowError};
-use netbattleship::ui::flush;
-use netbattleship::ui::parse_coord;
-use netbattleship::Phase;
-use std::io::stdin;
-use std::net::SocketAddrV4;
+use crossterm::cursor::MoveTo;
+use crossterm::event::{KeyCode, KeyModifiers};
+use crossterm::terminal::{Clear, ClearType};
+use netbattleship::analysis;
+use netbattleship::api;
+use netbattleship::archive::{self, Archive, Entry, Filter};
+use netbattleship::board::{Board, Cell};
+use netbattleship::bot::past::PastYou;
+use netbattleship::bot::taunt::{Taunting, Taunts};
+use netbattleship::bot::twitch::{Login, Twitch, NICK_VAR, TOKEN_VAR, TWITCH_IRC};
+use netbattleship::bot::{self, Difficulty, Engine};
+use netbattleship::broadcast::Broadcast;
+use netbattleship::campaign::{Campaign, Scripted};
+use netbattleship::config::{Config, Outcome};
+use netbattleship::coord::{Coord, Notation, Written};
+use netbattleship::event::{Event, Player};
+use netbattleship::flow::{
+	self, GameFlow, GameFlowBuilder, GameFlowError, GameSnapshot, Host, AIMING_IDLE,
+	AIMING_INTERVAL,
+};
+use netbattleship::history::Move;
+use netbattleship::hooks::{self, Hook};
+use netbattleship::invite::{self, Invite};
+use netbattleship::layouts;
+use netbattleship::link::Latency;
+use netbattleship::lobby::{Invitation, LobbyError, Presence, Route};
+use netbattleship::manager::{Finished, Manager};
+use netbattleship::nat::NatError;
+use netbattleship::notify;
+use netbattleship::pbm::{Correspondence, PbmError, Turn};
+use netbattleship::protocol::StateMachine;
+use netbattleship::puzzle::{self, Puzzle, PuzzleError};
+use netbattleship::render::{image, Style};
+use netbattleship::replay::{Replay, ReplayError};
+use netbattleship::rules::{Rules, RulesError};
+use netbattleship::sandbox::{self, Watched};
+use netbattleship::save::Save;
+use netbattleship::ship::ShipKind;
+use netbattleship::skin::Skin;
+use netbattleship::ui::cast;
+use netbattleship::ui::command::{Command, ParseCommandError};
+use netbattleship::ui::error::{self, Failure, Recovery, Session};
+use netbattleship::ui::fx::{self, Animation};
+use netbattleship::ui::input::Input;
+use netbattleship::ui::screen::Screen;
+use netbattleship::{Game, Phase};
+use std::fmt;
+use std::io::{stdin, stdout, IsTerminal, Write};
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::path::{Path, PathBuf};
+use std::process::exit;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use std::time::{Duration, Instant};
+use structopt::clap::AppSettings;
 use structopt::StructOpt;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast::{
+	error::{RecvError, TryRecvError},
+	Receiver,
+};
+use tokio::task::{JoinError, JoinHandle};
 
 #[derive(StructOpt)]
+#[structopt(setting = AppSettings::SubcommandsNegateReqs)]
+#[allow(clippy::struct_excessive_bools)]
 struct Args {
-	/// The IP address of the other computer.
-	pub server: SocketAddrV4,
-	/// Whether to act as a server.
+	/// The IP address of the other computer, or the address to host on.
+	#[structopt(
+		required_unless_one = &["serve", "join", "resume", "practice", "tutorial", "sandbox", "wait-for-invite"]
+	)]
+	pub server: Option<SocketAddrV4>,
+	/// Whether to act as a server. Without an address, picks a free port and prints invite codes.
 	#[structopt(short, long)]
 	pub serve: bool,
+	/// Join a game using the invite code the host was given, or the link in
+	/// its QR code.
+	#[structopt(short, long, conflicts_with_all = &["server", "serve"])]
+	pub join: Option<Invite>,
+	/// When hosting, also show the invite as a QR code, for a phone or another
+	/// computer on your network to scan and join with.
+	#[structopt(long, requires = "serve")]
+	pub qr: bool,
+	/// A TOML file describing the variant to play. Both players must use the same rules.
+	#[structopt(long)]
+	pub rules: Option<PathBuf>,
+	/// A text file mapping out land on the board, a row to a line with `.` for
+	/// water and `#` for land. The board is as big as the map.
+	#[structopt(long)]
+	pub map: Option<PathBuf>,
+	/// A text file laying out your fleet, a row to a line with each ship's
+	/// letter on its squares, `.` for water and `#` for land, to place it
+	/// that way at the start of every game. Ships left out are placed as usual.
+	#[structopt(long, conflicts_with = "bot")]
+	pub layout: Option<PathBuf>,
+	/// A lobby server to relay the game through if the players can't connect directly.
+	#[structopt(long)]
+	pub lobby: Option<SocketAddr>,
+	/// Invite the player with this friend code to the game, through --lobby.
+	#[structopt(long, requires_all = &["serve", "lobby"])]
+	pub invite: Option<String>,
+	/// Go online at --lobby, print your friend code, and join the first game
+	/// someone invites you to.
+	#[structopt(long, requires = "lobby", conflicts_with_all = &["server", "serve", "join"])]
+	pub wait_for_invite: bool,
+	/// After each game, wait for the next challenger instead of exiting.
+	#[structopt(long, requires = "serve", conflicts_with = "lobby")]
+	pub keep_serving: bool,
+	/// Host up to this many games at once, one per challenger, each played
+	/// by its own --bot, and keep taking challengers as games finish.
+	#[structopt(
+		long,
+		requires_all = &["serve", "bot"],
+		conflicts_with_all = &["keep-serving", "lobby", "resume", "spectators", "replay", "twitch"]
+	)]
+	pub max_games: Option<usize>,
+	/// How to write coordinates: letter-first (E5), number-first (5E),
+	/// numeric (4,5) or axial (q5r4). Defaults to the one in the config file.
+	#[structopt(long)]
+	pub notation: Option<Notation>,
+	/// How to draw the boards: ascii, unicode, compact or grid.
+	#[structopt(long, default_value = "ascii")]
+	pub style: Style,
+	/// Write each game event to stdout as a line of JSON, and everything else to stderr.
+	#[structopt(long)]
+	pub json_events: bool,
+	/// Pick up the game left in the save slot after a crash or disconnect.
+	/// The other player has to continue too.
+	#[structopt(long = "continue", conflicts_with_all = &["join", "keep-serving", "lobby"])]
+	pub resume: bool,
+	/// The save slot to autosave to and continue from.
+	#[structopt(long, default_value = "autosave")]
+	pub slot: String,
+	/// Let the computer play for you: easy, medium or hard.
+	#[structopt(long)]
+	pub bot: Option<Difficulty>,
+	/// Practise against the computer on this machine instead: easy, medium or hard.
+	#[structopt(long, conflicts_with_all = &["server", "serve", "join", "resume", "lobby"])]
+	pub practice: Option<Difficulty>,
+	/// Learn to play: a game against the computer, with a guide through
+	/// placing your fleet and your first two shots.
+	#[structopt(
+		long,
+		conflicts_with_all = &["server", "serve", "join", "resume", "lobby", "practice", "bot", "script"]
+	)]
+	pub tutorial: bool,
+	/// Try out the --rules, and the --map if there is one, by laying out a
+	/// fleet with them. Whenever either file changes it's read again, keeping
+	/// as much of the fleet as still fits.
+	#[structopt(
+		long,
+		requires = "rules",
+		conflicts_with_all = &["server", "serve", "join", "resume", "lobby", "practice", "tutorial", "bot", "layout"]
+	)]
+	pub sandbox: bool,
+	/// Replays of your own games, for the practice bot to lay its ships out
+	/// the way you did in one of them.
+	#[structopt(long, requires = "practice")]
+	pub past: Vec<PathBuf>,
+	/// Have the bot taunt your enemy over chat.
+	#[structopt(long, requires = "bot")]
+	pub taunt: bool,
+	/// A TOML file of lines for the bot to taunt with, instead of its own.
+	#[structopt(long, requires = "bot")]
+	pub taunts: Option<PathBuf>,
+	/// Let your stream's viewers choose your shots by voting in this Twitch
+	/// channel's chat. The --bot, or an easy one, places your ships and fires
+	/// when nobody votes. Set `NETBATTLESHIP_TWITCH_NICK` and
+	/// `NETBATTLESHIP_TWITCH_TOKEN` to post the votes in chat too.
+	#[structopt(long, conflicts_with_all = &["tutorial", "script", "cursor"])]
+	pub twitch: Option<String>,
+	/// How many seconds chat has to vote on each shot, with --twitch.
+	#[structopt(long, default_value = "20")]
+	pub vote_time: u64,
+	/// Play short animations between turns, on a terminal. Type anything to skip them.
+	#[structopt(long)]
+	pub effects: bool,
+	/// Place ships by steering them around the board with the arrow keys, on a terminal.
+	#[structopt(long, conflicts_with_all = &["bot", "json-events"])]
+	pub cursor: bool,
+	/// Play the commands in this file, one per line, without prompting. Exits
+	/// with 0 if you won, 2 if you lost, 3 if the game broke down and 4 if
+	/// the script ran out first.
+	#[structopt(long, conflicts_with_all = &["bot", "cursor", "keep-serving"])]
+	pub script: Option<PathBuf>,
+	/// When the game ends, save a picture of both boards to this .png or .svg file.
+	#[structopt(long)]
+	pub export: Option<PathBuf>,
+	/// Record everything shown on the terminal to this asciinema cast, to
+	/// play back or put on a web page.
+	#[structopt(long)]
+	pub record_cast: Option<PathBuf>,
+	/// Save a replay of the game to this .nbr file, kept up to date as it's played.
+	#[structopt(long)]
+	pub replay: Option<PathBuf>,
+	/// Let spectators watch by connecting to this address. They're sent
+	/// each move as a line of JSON, without where your ships are.
+	#[structopt(long, requires = "serve")]
+	pub spectators: Option<SocketAddr>,
+	/// What to call you in replays, and to the other player.
+	#[structopt(long)]
+	pub name: Option<String>,
+	/// What to call your enemy in replays.
+	#[structopt(long)]
+	pub opponent: Option<String>,
+	/// A password your enemy has to give too, so strangers can't take your
+	/// game. It's never sent over the network.
+	#[structopt(long)]
+	pub password: Option<String>,
+	#[structopt(subcommand)]
+	pub command: Option<Subcommand>,
+}
+
+#[derive(StructOpt)]
+enum Subcommand {
+	/// Work with replays saved with --replay.
+	Replay(ReplayCommand),
+	/// Have each bot sink fleets laid out by the hard bot, by --rules, and
+	/// report how many shots and how long each takes.
+	BenchSim {
+		/// How many fleets each bot sinks.
+		#[structopt(long, default_value = "200")]
+		games: u32,
+	},
+	/// Sink the day's fleet, the same for everyone, in as few of a limited
+	/// number of shots as you can.
+	Puzzle {
+		/// Play the puzzle for another day, given as YYYY-MM-DD.
+		#[structopt(long)]
+		date: Option<String>,
+	},
+	/// Play through a campaign of missions against the computer, from a
+	/// directory of mission files.
+	Campaign {
+		dir: PathBuf,
+		/// The mission to play, instead of the next one you haven't won.
+		#[structopt(long)]
+		mission: Option<String>,
+	},
+	/// Print a JSON Schema of every message this version sends over the
+	/// network, for writing a compatible client.
+	Protocol,
+	/// Let other programs on this computer play through a JSON API over
+	/// HTTP, with the game's events as server-sent events.
+	ServeApi {
+		/// The port to serve the API on, on localhost only.
+		#[structopt(long, default_value = "7780")]
+		port: u16,
+	},
+	/// Look back at the games played on this computer, like
+	/// `history --wins --against Bob --date 2024-03` for your wins against
+	/// Bob in March 2024.
+	History {
+		/// Only games against someone called this.
+		#[structopt(long)]
+		against: Option<String>,
+		/// Only games you won.
+		#[structopt(long, conflicts_with = "losses")]
+		wins: bool,
+		/// Only games you lost or resigned.
+		#[structopt(long)]
+		losses: bool,
+		/// Only games that ended in this year, month or day, as YYYY,
+		/// YYYY-MM or YYYY-MM-DD.
+		#[structopt(long)]
+		date: Option<String>,
+	},
+	/// Play by mail: a game a turn at a time, sending the files this writes
+	/// to your enemy however you like. They're signed with --password, which
+	/// your enemy has to give too.
+	Pbm(PbmCommand),
+}
+
+#[derive(StructOpt)]
+enum PbmCommand {
+	/// Start a game by --rules, writing the first letter with your first shot.
+	New {
+		/// Where to fire, instead of being asked.
+		#[structopt(long)]
+		fire: Option<Coord>,
+		/// Where to write the letter, instead of a file named after the game.
+		#[structopt(long)]
+		out: Option<PathBuf>,
+	},
+	/// Read a letter from your enemy, and write your reply.
+	Apply {
+		file: PathBuf,
+		/// Where to fire, instead of being asked.
+		#[structopt(long)]
+		fire: Option<Coord>,
+		/// Where to write the reply, instead of a file named after the game.
+		#[structopt(long)]
+		out: Option<PathBuf>,
+	},
+}
+
+#[derive(StructOpt)]
+enum ReplayCommand {
+	/// Check that every move in a replay was allowed and adds up.
+	Verify { file: PathBuf },
+}
+
+/// Set by `--json-events`, which keeps stdout for events alone.
+static JSON_EVENTS: AtomicBool = AtomicBool::new(false);
+
+/// Set by `--effects`, when stdout is a terminal to animate on.
+static EFFECTS: AtomicBool = AtomicBool::new(false);
+
+/// Set by `--script`, which plays without prompting.
+static SCRIPTED: AtomicBool = AtomicBool::new(false);
+
+/// How a game played with `--script` ended, as its exit code.
+#[derive(Clone, Copy)]
+enum Ending {
+	Won = 0,
+	Lost = 2,
+	/// The connection or the other side broke the game.
+	Broken = 3,
+	/// The script had no commands left while the game went on.
+	RanOut = 4,
+}
+
+impl Ending {
+	fn exit(self) -> ! {
+		exit(self as i32)
+	}
+}
+
+/// Set by `--notation`, or from the config file.
+static NOTATION: OnceLock<Notation> = OnceLock::new();
+
+/// The look of our fleet, from the config file.
+static SKIN: OnceLock<Skin> = OnceLock::new();
+
+/// What to run as the game goes, from the config file.
+static HOOKS: OnceLock<Vec<Hook>> = OnceLock::new();
+
+/// Where our ships go, from `--layout`.
+static LAYOUT: OnceLock<Vec<(ShipKind, Coord, bool)>> = OnceLock::new();
+
+/// Set by `--bot`, to play by itself.
+static BOT: Mutex<Option<Box<dyn Engine>>> = Mutex::new(None);
+
+/// Prints a line for the player, on stderr when stdout carries events.
+macro_rules! say {
+	($($arg:tt)*) => {
+		if JSON_EVENTS.load(Ordering::Relaxed) {
+			eprintln!($($arg)*);
+		} else {
+			writeln!(cast::stdout(), $($arg)*).expect("Broken pipe");
+		}
+	};
+}
+
+/// Like `say!`, but leaves the cursor on the line for the player's answer.
+/// Scripts don't need asking.
+macro_rules! ask {
+	($($arg:tt)*) => {
+		if !SCRIPTED.load(Ordering::Relaxed) {
+			if JSON_EVENTS.load(Ordering::Relaxed) {
+				eprint!($($arg)*);
+			} else {
+				let mut out = cast::stdout();
+				write!(out, $($arg)*).expect("Broken pipe");
+				out.flush().expect("Broken pipe");
+			}
+		}
+	};
+}
+
+/// The square prompts give as an example, E5 in the usual notation.
+const EXAMPLE: Coord = Coord::new(5, 4);
+
+/// How to show `pos` to the player, in the notation they prefer.
+fn at(pos: Coord) -> Written {
+	pos.written(NOTATION.get().copied().unwrap_or_default())
+}
+
+/// How our fleet looks, from the config file.
+fn skin() -> &'static Skin {
+	SKIN.get_or_init(Skin::default)
+}
+
+/// Draws `game`'s boards in `style`, with our fleet in its skin.
+fn render(game: &GameFlow, style: Style) -> String {
+	draw(&game.snapshot(), style)
+}
+
+/// Draws `game`'s boards in `style`, with our fleet in its skin and what the
+/// symbols mean underneath.
+fn draw(game: &Game, style: Style) -> String {
+	let renderer = style.renderer();
+	let boards = renderer.render_skinned(game, skin());
+	match renderer.legend() {
+		Some(legend) => format!("{boards}{legend}"),
+		None => boards,
+	}
+}
+
+/// Writes an event for whatever is driving us, if anything asked for them.
+fn emit(event: Event) {
+	if JSON_EVENTS.load(Ordering::Relaxed) {
+		println!("{}", String::from(event));
+	}
+}
+
+/// Games won and lost by a host that keeps serving.
+#[derive(Default)]
+struct Record {
+	wins: u32,
+	losses: u32,
+}
+
+impl fmt::Display for Record {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{} won, {} lost", self.wins, self.losses)
+	}
 }
 
 #[tokio::main]
 async fn main() {
 	let args = Args::from_args();
+	if let Some(Subcommand::Replay(ReplayCommand::Verify { file })) = &args.command {
+		exit(verify(file));
+	}
+	configure(&args);
+	stream(&args).await;
+	let rules = rules(&args);
+	let mut input = input(&args);
+	if offline(&args, &rules, &mut input).await {
+		return;
+	}
+	let broadcast = broadcast(&args).await;
+	if args.keep_serving {
+		keep_serving(&args, rules, &mut input, &broadcast).await;
+	}
+	if let (Some(max), Some(bot)) = (args.max_games, args.bot) {
+		serve_many(&args, rules, max, bot).await;
+	}
+
+	let connected = if let Some(level) = args.practice {
+		practice(&args, rules, level)
+			.await
+			.map(|game| (game, SocketAddr::from(([127, 0, 0, 1], 0)), true))
+	} else if args.resume {
+		let save = saved(&args.slot);
+		let (addr, serve) = (save.addr, save.serve);
+		resume(save).await.map(|game| (game, addr, serve))
+	} else if JSON_EVENTS.load(Ordering::Relaxed) {
+		// Whatever's driving us expects to hear it connect before placing
+		connect(&args, rules)
+			.await
+			.map(|(game, addr)| (game, addr, args.serve))
+	} else {
+		connect_placing(&args, rules, &mut input)
+			.await
+			.map(|(game, addr)| (game, addr, args.serve))
+	};
+	let (game, addr, serve) = match connected {
+		Ok(connected) => connected,
+		Err(GameFlowError::RulesMismatch { ours, theirs }) => {
+			report_mismatch(&ours, &theirs);
+			exit(1);
+		}
+		Err(e @ (GameFlowError::ResumeMismatch(..) | GameFlowError::WrongPassword)) => {
+			eprintln!("{}", Failure::from(e));
+			exit(1);
+		}
+		Err(e) => panic!("Failed to connect: {e}"),
+	};
+	match game.route() {
+		_ if args.practice.is_some() => {}
+		Route::Direct => say!("Connected directly."),
+		Route::Relayed(lobby) => say!("Connected through the relay at {lobby}."),
+	}
+	head_to_head(&args, &game);
+	let session = Session {
+		addr,
+		serve,
+		slot: args.slot.clone(),
+	};
+	// Practice games can't be picked up again without the bot
+	if args.practice.is_none() {
+		session.autosave(&game);
+	}
+	let mut game = game;
+	let mut resumed = args.resume;
+	let mut following;
+	loop {
+		following = broadcast.follow(&game);
+		let playing = error::catch(run(&game, args.style, &mut input, resumed));
+		let result = tokio::select! {
+			result = playing => result,
+			_ = tokio::signal::ctrl_c() => Ok(Err(GameFlowError::Cancelled)),
+		};
+		let failure = match result {
+			Ok(Ok(())) => break,
+			Ok(Err(GameFlowError::Cancelled)) => resign(&game, &args).await,
+			// There's nobody left to reconnect to
+			Ok(Err(e @ GameFlowError::Resigned)) => {
+				say!("{}", Failure::from(e));
+				break;
+			}
+			Ok(Err(GameFlowError::Left)) if !SCRIPTED.load(Ordering::Relaxed) => {
+				say!("{}", Failure::from(GameFlowError::Left));
+				break;
+			}
+			Ok(Err(e)) => Failure::from(e),
+			Err(failure) => failure,
+		};
+		// Nobody's there to choose what to do about it
+		if SCRIPTED.load(Ordering::Relaxed) {
+			say!("{failure}");
+			Ending::Broken.exit();
+		}
+		// Catching Ctrl-C once stops it from interrupting by itself
+		game = tokio::select! {
+			game = recover(&session, &game, &failure, &mut input) => game,
+			_ = tokio::signal::ctrl_c() => exit(130),
+		};
+		resumed = true;
+	}
+	finish(&args, &game, following).await;
+}
+
+/// Wraps up once the game is over: saves whatever was asked for, counts the
+/// game in the stats and clears the autosave.
+async fn finish(args: &Args, game: &GameFlow, following: JoinHandle<Result<(), ReplayError>>) {
+	if let Some(path) = &args.export {
+		export(game, path);
+	}
+	if let Some(path) = &args.replay {
+		report_replay(following.await, path);
+	}
+	if args.practice.is_none() {
+		if let Phase::Done(won) = game.phase().await {
+			record(args, game, if won { Outcome::Won } else { Outcome::Lost });
+		}
+	}
+	if let Err(e) = Save::remove(&args.slot) {
+		eprintln!("Couldn't clear the autosave: {e}");
+	}
+	if args.script.is_some() {
+		match game.phase().await {
+			Phase::Done(true) => Ending::Won.exit(),
+			Phase::Done(false) => Ending::Lost.exit(),
+			_ => Ending::Broken.exit(),
+		}
+	}
+}
+
+/// Resigns the game when the player presses Ctrl-C, rather than just hanging
+/// up on the enemy: tells them, shows how the boards ended up, counts the
+/// loss and exits. Pressed once the game is over, it just leaves.
+async fn resign(game: &GameFlow, args: &Args) -> ! {
+	let outcome = match game.phase().await {
+		Phase::Done(true) => Outcome::Won,
+		Phase::Done(false) => Outcome::Lost,
+		_ => Outcome::Resigned,
+	};
+	// We're going either way
+	let _ = game.resign().await;
+	say!();
+	if outcome == Outcome::Resigned {
+		say!("You resigned.");
+	}
+	say!("{}", render(game, args.style));
+	if args.practice.is_none() {
+		record(args, game, outcome);
+	}
+	// A resigned game is over, so there's nothing to continue
+	if let Err(e) = Save::remove(&args.slot) {
+		eprintln!("Couldn't clear the autosave: {e}");
+	}
+	exit(130);
+}
+
+/// What the enemy is called in the history: what `--opponent` says, or else
+/// what they asked to be called.
+fn opponent(args: &Args, game: &GameFlow) -> String {
+	args.opponent
+		.clone()
+		.or_else(|| game.enemy_name().map(String::from))
+		.unwrap_or_default()
+}
+
+/// Says how our earlier games against the enemy went, if there were any,
+/// unless they're the practice bot.
+fn head_to_head(args: &Args, game: &GameFlow) {
+	if args.practice.is_some() {
+		return;
+	}
+	match archive::head_to_head(&opponent(args, game)) {
+		Ok(Some(record)) => say!("{record}."),
+		Ok(None) => {}
+		Err(e) => eprintln!("Couldn't look back at your games: {e}"),
+	}
+}
+
+/// Counts a finished game in the stats kept in the config file, and keeps
+/// it in the history.
+fn record(args: &Args, game: &GameFlow, outcome: Outcome) {
+	match Config::record(outcome) {
+		Ok(stats) => say!("All your games: {stats}."),
+		Err(e) => eprintln!("Couldn't update your stats: {e}"),
+	}
+	let snapshot = game.snapshot();
+	let mut entry = Entry::new(
+		opponent(args, game),
+		outcome,
+		snapshot.rules.clone(),
+		snapshot.turn_number,
+	);
+	entry.replay = args
+		.replay
+		.as_ref()
+		.map(|path| path.canonicalize().unwrap_or_else(|_| path.clone()));
+	if let Err(e) = Archive::open().and_then(|archive| archive.add(&entry)) {
+		eprintln!("Couldn't add the game to your history: {e}");
+	}
+}
+
+/// The rules to play by; see [`load_rules`].
+fn rules(args: &Args) -> Rules {
+	match load_rules(args) {
+		Ok(rules) => rules,
+		Err(e) => {
+			eprintln!("Bad rules: {e}");
+			exit(1);
+		}
+	}
+}
+
+/// The rules to play by, from `--rules` or the standard ones, on the board
+/// from `--map` if there is one.
+fn load_rules(args: &Args) -> Result<Rules, RulesError> {
+	let rules = args
+		.rules
+		.as_ref()
+		.map(Rules::load)
+		.transpose()
+		.map(Option::unwrap_or_default);
+	match &args.map {
+		Some(map) => rules.and_then(|rules| rules.load_map(map)),
+		None => rules,
+	}
+}
+
+/// Where the player's commands come from.
+fn input(args: &Args) -> Input {
+	if let Some(path) = &args.script {
+		match std::fs::read_to_string(path) {
+			Ok(script) => Input::script(&script),
+			Err(e) => {
+				eprintln!("Couldn't read the script {}: {e}", path.display());
+				exit(1);
+			}
+		}
+	} else if args.cursor && stdin().is_terminal() && stdout().is_terminal() {
+		Input::terminal()
+	} else {
+		Input::stdin()
+	}
+}
+
+/// Sets up how the whole run behaves from `args`.
+fn configure(args: &Args) {
+	JSON_EVENTS.store(args.json_events, Ordering::Relaxed);
+	SCRIPTED.store(args.script.is_some(), Ordering::Relaxed);
+	let config = Config::load().unwrap_or_default();
+	let _ = NOTATION.set(args.notation.unwrap_or(config.notation));
+	let _ = SKIN.set(config.skin);
+	let _ = HOOKS.set(config.hooks);
+	EFFECTS.store(
+		fx::enabled(args.effects) && !args.json_events && args.script.is_none(),
+		Ordering::Relaxed,
+	);
+	if let Some(path) = &args.record_cast {
+		if let Err(e) = cast::start(path) {
+			eprintln!("Couldn't record to {}: {e}", path.display());
+			exit(1);
+		}
+	}
+	if let Some(path) = &args.layout {
+		let _ = LAYOUT.set(layout(path, &rules(args)));
+	}
+	if let Some(bot) = args.bot {
+		*BOT.lock().expect("nothing panics holding the bot") = Some(taunting(args, bot));
+	}
+}
+
+/// Reads the fleet layout at `path`, checking that it can be placed by
+/// `rules`. Exits if it can't.
+fn layout(path: &Path, rules: &Rules) -> Vec<(ShipKind, Coord, bool)> {
+	let bad = |problem: &dyn std::fmt::Display| -> ! {
+		eprintln!("Bad layout in {}: {problem}", path.display());
+		exit(1);
+	};
+	let text = std::fs::read_to_string(path).unwrap_or_else(|e| bad(&e));
+	let board = text.parse::<Board>().unwrap_or_else(|e| bad(&e));
+	if board.size() != rules.board_size || board.land() != &rules.land {
+		bad(&"it's for a different board than the rules");
+	}
+	let mut game = Game::new(rules.clone(), true);
+	let placements = board.placements();
+	for &(ship, pos, vertical) in &placements {
+		let step = Move::Place {
+			ship,
+			pos,
+			vertical,
+		};
+		if !game.apply(step) {
+			bad(&format!("the {ship:?} can't go there by the rules"));
+		}
+	}
+	placements
+}
+
+/// Wraps `engine` to taunt the enemy, if `--taunt` or `--taunts` asked it to.
+fn taunting(args: &Args, engine: impl Engine + 'static) -> Box<dyn Engine> {
+	if args.taunt || args.taunts.is_some() {
+		Box::new(Taunting {
+			engine,
+			taunts: taunts(args.taunts.as_deref()),
+		})
+	} else {
+		Box::new(engine)
+	}
+}
+
+/// Hands our shots over to the votes in Twitch chat, if `--twitch` asks to.
+/// Exits if chat can't be reached.
+async fn stream(args: &Args) {
+	let Some(channel) = &args.twitch else { return };
+	let engine = args.bot.unwrap_or(Difficulty::Easy);
+	let countdown = Duration::from_secs(args.vote_time);
+	let login = Login::from_env();
+	let posting = login.is_some();
+	match Twitch::connect(TWITCH_IRC, channel, login, engine, countdown).await {
+		Ok(twitch) => {
+			*BOT.lock().expect("nothing panics holding the bot") = Some(taunting(args, twitch));
+			say!("Chat in {channel} is choosing your shots.");
+			if !posting {
+				say!("Set {NICK_VAR} and {TOKEN_VAR} to post the votes in chat too.");
+			}
+		}
+		Err(e) => {
+			eprintln!("Couldn't join Twitch chat: {e}");
+			exit(1);
+		}
+	}
+}
+
+/// Tells the player why the game stopped and lets them reconnect, save it or
+/// give up. Returns the reconnected game, since the other choices exit.
+async fn recover(
+	session: &Session,
+	game: &GameFlow,
+	failure: &Failure,
+	input: &mut Input,
+) -> GameFlow {
+	say!("{failure}");
+	loop {
+		ask!("Type r to reconnect, s to save the game and quit, or q to quit: ");
+		let Some(line) = input.next().await else {
+			exit(1)
+		};
+		match line.parse() {
+			Ok(Recovery::Reconnect) => {
+				if session.serve {
+					say!("Waiting for your enemy to reconnect on {}...", session.addr);
+				} else {
+					say!("Reconnecting to {}...", session.addr);
+				}
+				match session.reconnect(game).await {
+					Ok(game) => {
+						say!("Reconnected!");
+						return game;
+					}
+					Err(e) => say!("{}", Failure::from(e)),
+				}
+			}
+			Ok(Recovery::Save) => {
+				ask!("Save to which slot? (Press enter for {}) ", session.slot);
+				let Some(slot) = input.next().await else {
+					exit(1)
+				};
+				let slot = match slot.trim() {
+					"" => session.slot.clone(),
+					slot => slot.to_string(),
+				};
+				match session.save(game).store(&slot) {
+					Ok(()) => {
+						say!("Saved. Continue it later with --continue --slot {slot}.");
+						exit(0);
+					}
+					Err(e) => say!("Couldn't save the game: {e}"),
+				}
+			}
+			Ok(Recovery::Quit) => exit(1),
+			Err(()) => {}
+		}
+	}
+}
+
+fn report_mismatch(ours: &Rules, theirs: &Rules) {
+	eprintln!("Your enemy is playing by different rules.");
+	eprintln!("Yours:  {ours}");
+	eprintln!("Theirs: {theirs}");
+}
+
+/// Hosts one game after another on the same port, keeping score.
+async fn keep_serving(args: &Args, rules: Rules, input: &mut Input, broadcast: &Broadcast) -> ! {
+	let host = match Host::bind(args.server.map(Into::into)).await {
+		Ok(host) => host,
+		Err(e) => panic!("Failed to listen: {e}"),
+	};
+	announce(&host, args.qr);
+	let mut record = Record::default();
+	loop {
+		let game = match introduce(args, rules.clone()).host(host.listener()).await {
+			Ok(game) => game,
+			Err(GameFlowError::RulesMismatch { ours, theirs }) => {
+				report_mismatch(&ours, &theirs);
+				continue;
+			}
+			Err(e) => {
+				eprintln!("Couldn't start a game: {e}");
+				continue;
+			}
+		};
+		let following = broadcast.follow(&game);
+		match error::catch(run(&game, args.style, input, false)).await {
+			Ok(Ok(())) => {}
+			Ok(Err(e)) => say!("{}", Failure::from(e)),
+			Err(failure) => say!("{failure}"),
+		}
+		match game.phase().await {
+			Phase::Done(true) => record.wins += 1,
+			Phase::Done(false) => record.losses += 1,
+			_ => {}
+		}
+		let _ = game.done().await;
+		if let Some(path) = &args.replay {
+			report_replay(following.await, path);
+		}
+		say!("So far you've {record}. Waiting for the next challenger...");
+	}
+}
+
+/// Hosts up to `max` games at once on the same port, each played by its own
+/// `bot`, keeping score as they finish.
+async fn serve_many(args: &Args, rules: Rules, max: usize, bot: Difficulty) -> ! {
+	let host = match Host::bind(args.server.map(Into::into)).await {
+		Ok(host) => host,
+		Err(e) => panic!("Failed to listen: {e}"),
+	};
+	announce(&host, args.qr);
+	let mut manager = Manager::new(max);
+	let mut results = manager.results().expect("nobody else took them");
+	tokio::spawn(async move {
+		let mut record = Record::default();
+		while let Some(Finished { id, enemy, outcome }) = results.recv().await {
+			let against = enemy.map_or_else(String::new, |name| format!(" against {name}"));
+			match outcome {
+				Ok(true) => {
+					record.wins += 1;
+					say!("Won game {id}{against}.");
+				}
+				Ok(false) => {
+					record.losses += 1;
+					say!("Lost game {id}{against}.");
+				}
+				Err(e) => say!("Game {id}{against} broke down: {}", Failure::from(e)),
+			}
+			say!("So far you've {record}.");
+		}
+	});
+	loop {
+		let engine = taunting(args, bot);
+		match manager
+			.host(host.listener(), introduce(args, rules.clone()), engine)
+			.await
+		{
+			Ok(id) => say!(
+				"Started game {id}, {} of {} running.",
+				manager.running(),
+				manager.max()
+			),
+			Err(GameFlowError::RulesMismatch { ours, theirs }) => report_mismatch(&ours, &theirs),
+			Err(e) => eprintln!("Couldn't start a game: {e}"),
+		}
+	}
+}
+
+/// Tells the host how challengers can reach them, with a QR code of the
+/// invite on their local network if `qr` is set.
+fn announce(host: &Host, qr: bool) {
+	match host.port_mapping() {
+		Ok(mapping) => say!("Forwarded {} through your router.", mapping.external()),
+		Err(NatError::Unsupported) => {}
+		Err(e) => say!(
+			"Couldn't forward a port through your router ({e}), so players outside your network may not be able to join."
+		),
+	}
+	say!("Waiting for a challenger. They can join with any of these:");
+	for addr in host.addrs() {
+		if let SocketAddr::V4(addr) = addr {
+			say!("  {addr:<21} invite code {}", Invite(addr));
+		}
+	}
+	if qr {
+		match invite::lan_addr(&host.addrs()) {
+			Some(addr) => say!(
+				"Or scan this to join at {addr}:\n{}",
+				Invite(addr).qr_text()
+			),
+			None => say!("There's no address on your network to make a QR code of."),
+		}
+	}
+}
+
+/// Sets up a game by `rules` that tells the other player our name and what
+/// we call our ships.
+fn introduce(args: &Args, rules: Rules) -> GameFlowBuilder {
+	let mut options = GameFlow::builder()
+		.rules(rules)
+		.ship_names(skin().names.clone());
+	if let Some(name) = &args.name {
+		options = options.name(name.clone());
+	}
+	if let Some(password) = &args.password {
+		options = options.password(password.clone());
+	}
+	options
+}
+
+/// Hosts or joins a game, returning it and the address to reconnect to.
+async fn connect(args: &Args, rules: Rules) -> Result<(GameFlow, SocketAddr), GameFlowError> {
+	if args.serve {
+		let mut host = Host::bind(args.server.map(Into::into)).await?;
+		if let Some(lobby) = args.lobby {
+			if let Err(e) = host.register_relay(lobby).await {
+				say!("Couldn't offer the game through the relay ({e}).");
+			}
+		}
+		if let (Some(friend), Some(lobby)) = (&args.invite, args.lobby) {
+			let name = args.name.clone().unwrap_or_default();
+			let invited = match go_online(lobby).await {
+				Ok(presence) => {
+					host.invite(lobby, presence.identity(), name, friend, rules.clone())
+						.await
+				}
+				Err(e) => Err(e.into()),
+			};
+			match invited {
+				Ok(()) => say!("Invited {friend}."),
+				Err(e) => say!("Couldn't invite {friend} ({e})."),
+			}
+		}
+		announce(&host, args.qr);
+		let addr = host.listener().local_addr()?;
+		Ok((introduce(args, rules).accept(host).await?, addr))
+	} else if let (true, Some(lobby)) = (args.wait_for_invite, args.lobby) {
+		let invitation = wait_for_invite(lobby).await?;
+		let game = introduce(args, rules)
+			.join_with_relay(invitation.game, lobby)
+			.await?;
+		Ok((game, invitation.game.into()))
+	} else {
+		let addr = args.join.map_or(args.server, |invite| Some(invite.0));
+		let addr = addr.expect("structopt requires an address or invite");
+		say!("Connecting to {addr}...");
+		let options = introduce(args, rules);
+		let game = match args.lobby {
+			Some(lobby) => options.join_with_relay(addr, lobby).await,
+			None => options.connect(addr.into(), false).await,
+		};
+		Ok((game?, addr.into()))
+	}
+}
+
+/// Goes online at `lobby` with the friend code from the config file,
+/// keeping the one it hands out if it doesn't know that one.
+async fn go_online(lobby: SocketAddr) -> Result<Presence, LobbyError> {
+	let identity = Config::load().ok().and_then(|config| config.identity);
+	let presence = Presence::new(lobby, identity).await?;
+	if let Err(e) = Config::record_identity(presence.identity()) {
+		eprintln!("Couldn't keep your friend code: {e}");
+	}
+	Ok(presence)
+}
+
+/// Waits at `lobby` for someone to invite us to a game, for --wait-for-invite.
+async fn wait_for_invite(lobby: SocketAddr) -> Result<Invitation, LobbyError> {
+	let mut presence = go_online(lobby).await?;
+	say!(
+		"Your friend code is {}. Waiting for an invitation...",
+		presence.identity().code
+	);
+	let invitation = presence.invitation().await?;
+	let from = if invitation.name.is_empty() {
+		&invitation.from
+	} else {
+		&invitation.name
+	};
+	say!(
+		"{from} invited you to a game of {}. Joining...",
+		invitation.rules
+	);
+	Ok(invitation)
+}
+
+/// Waits for the enemy to place their fleet, saying how far they've got
+/// whenever they place another ship.
+async fn wait_for_fleet(game: &GameFlow) -> Result<(), GameFlowError> {
+	if game.state.read().await.enemy_placed() {
+		return game.wait_for_fleet().await;
+	}
+	let mut heard = game.events();
+	let fleet = game.state.read().await.rules.fleet.len();
+	say!("Waiting for your enemy to place their ships...");
+	match game.enemy_placing() {
+		0 => {}
+		placed => say!("Your enemy has placed {placed} of {fleet} ships."),
+	}
+	let waiting = game.wait_for_fleet();
+	tokio::pin!(waiting);
+	loop {
+		tokio::select! {
+			waited = &mut waiting => return waited,
+			Ok(event) = heard.recv() => {
+				if let Event::EnemyPlacing { placed, fleet } = event {
+					say!("Your enemy has placed {placed} of {fleet} ships.");
+				}
+			}
+		}
+	}
+}
+
+/// Hosts or joins a game like [`connect`], letting the player place their
+/// ships while waiting. Ships placed by then are carried over, and the rest
+/// are placed once the game starts.
+async fn connect_placing(
+	args: &Args,
+	rules: Rules,
+	input: &mut Input,
+) -> Result<(GameFlow, SocketAddr), GameFlowError> {
+	let harbour = Harbour::new(Game::new(rules.clone(), args.serve));
+	let connecting = connect(args, rules);
+	tokio::pin!(connecting);
+	let connected = tokio::select! {
+		connected = &mut connecting => connected,
+		placed = place(&harbour, args.style, input) => {
+			match placed {
+				Ok(()) => {}
+				Err(GameFlowError::Cancelled) => exit(130),
+				Err(e) => return Err(e),
+			}
+			say!("Your fleet is ready. Waiting for your enemy to turn up...");
+			connecting.await
+		}
+	};
+	// Placing may have stopped partway through steering a ship
+	input.end_keys();
+	let (game, addr) = connected?;
+	for (ship, pos, v) in harbour.placed() {
+		game.place_ship(ship, pos, v).await?;
+	}
+	Ok((game, addr))
+}
+
+/// Starts a game against the computer at `level`, which lays out its ships
+/// like one of the `--past` replays if there are any.
+async fn practice(args: &Args, rules: Rules, level: Difficulty) -> Result<GameFlow, GameFlowError> {
+	if args.past.is_empty() {
+		return bot::practice(rules, level).await;
+	}
+	let past = match PastYou::load(level, &rules, &args.past) {
+		Ok(past) => past,
+		Err(e) => {
+			eprintln!("Couldn't read your past games: {e}");
+			exit(1);
+		}
+	};
+	match past.layouts() {
+		0 => say!(
+			"None of those games were played by these rules, so the bot will place its own ships."
+		),
+		1 => say!("The bot is laying its ships out the way you did in that game."),
+		n => say!("The bot is laying its ships out the way you did in one of {n} games."),
+	}
+	bot::practice(rules, past).await
+}
+
+/// The game saved in `slot`. Exits if it can't be read.
+fn saved(slot: &str) -> Save {
+	match Save::load(slot) {
+		Ok(save) => save,
+		Err(e) => {
+			eprintln!("Couldn't continue the game saved in {slot}: {e}");
+			exit(1);
+		}
+	}
+}
+
+/// Reconnects to the other player of a saved game, on the same address.
+async fn resume(save: Save) -> Result<GameFlow, GameFlowError> {
+	if save.serve {
+		let host = Host::bind(Some(save.addr)).await?;
+		announce(&host, false);
+		host.resume(save.game).await
+	} else {
+		say!("Reconnecting to {}...", save.addr);
+		GameFlow::resume(TcpStream::connect(save.addr).await?, save.game).await
+	}
+}
 
-	println!("Connecting...");
-	let game = GameFlow::new(std::net::SocketAddr::V4(args.server), args.serve)
+/// Plays `game` to the end. A `resumed` game is first checked against the
+/// enemy's copy, in case they drifted apart.
+async fn run(
+	game: &GameFlow,
+	style: Style,
+	input: &mut Input,
+	resumed: bool,
+) -> Result<(), GameFlowError> {
+	let events = forward_events(game).await;
+	tokio::spawn(print_chat(game.events()));
+	tokio::spawn(hooks::run(
+		HOOKS.get().cloned().unwrap_or_default(),
+		game.events(),
+	));
+	if let Phase::Placing(_) = game.phase().await {
+		say!("Ready! Now, place your ships.");
+		place(game, style, input).await?;
+	}
+	wait_for_fleet(game).await?;
+
+	say!("Ready to play! Choose your first target.");
+	effects(input, &[Animation::intro()]).await;
+	if resumed {
+		check_sync(game, false).await?;
+	}
+	play(game, style, input).await?;
+	if let Some(events) = events {
+		let _ = events.await;
+	}
+	let playing_ourselves = with_bot(|_| ()).is_none()
+		&& !JSON_EVENTS.load(Ordering::Relaxed)
+		&& !SCRIPTED.load(Ordering::Relaxed);
+	if playing_ourselves && matches!(game.phase().await, Phase::Done(_)) {
+		offer_review(game, input).await;
+	}
+	Ok(())
+}
+
+/// Offers to go back over each of our shots, rating it against the best
+/// shot we could have taken.
+async fn offer_review(game: &GameFlow, input: &mut Input) {
+	ask!("Type r to review your shots, or press enter to finish: ");
+	let wanted = input
+		.next()
 		.await
-		.expect("Failed to connect");
-	let mut stdin = stdin().lines().flatten();
-
-	println!("Ready! Now, place your ships.");
-	while let Phase::Placing(ship) = game.phase().await {
-		println!("{}", game.to_string().await);
-		print!("Place the top-left section of your {:?} (like E5): ", ship);
-		flush();
-		let Some(pos) = parse_coord(&stdin.next().expect("Broken pipe")) else { println!("Those coordinates were malformed, try again."); continue };
-		print!("Vertical (y)? ");
-		flush();
-		let v = stdin.next().expect("Broken pipe").starts_with('y');
+		.is_some_and(|line| line.trim().eq_ignore_ascii_case("r"));
+	if !wanted {
+		return;
+	}
+	let grades = analysis::review(&game.subscribe().borrow());
+	for grade in &grades {
+		say!("{grade}");
+	}
+	let best = grades.iter().filter(|grade| grade.is_best()).count();
+	say!(
+		"{best} of your {} shots were the best you could have taken.",
+		grades.len()
+	);
+}
+
+/// With `--json-events`, writes everything that happens in `game` to stdout
+/// until it's over.
+async fn forward_events(game: &GameFlow) -> Option<JoinHandle<()>> {
+	if !JSON_EVENTS.load(Ordering::Relaxed) {
+		return None;
+	}
+	let mut events = game.events();
+	emit(Event::Connected {
+		relayed: matches!(game.route(), Route::Relayed(_)),
+	});
+	if let Phase::Placing(ship) = game.phase().await {
+		emit(Event::Placing { ship });
+	}
+	Some(tokio::spawn(async move {
+		loop {
+			match events.recv().await {
+				Ok(event @ Event::Done { .. }) => {
+					emit(event);
+					break;
+				}
+				Ok(event) => emit(event),
+				Err(RecvError::Lagged(_)) => {}
+				Err(RecvError::Closed) => break,
+			}
+		}
+	}))
+}
+
+/// Shows chat from either side as it comes, until the game is over.
+async fn print_chat(mut events: Receiver<Event>) {
+	loop {
+		match events.recv().await {
+			Ok(Event::Chat {
+				by: Player::You,
+				text,
+			}) => say!("You say: {text}"),
+			Ok(Event::Chat {
+				by: Player::Enemy,
+				text,
+			}) => say!("Your enemy says: {text}"),
+			Ok(Event::Done { .. }) | Err(RecvError::Closed) => break,
+			Ok(_) | Err(RecvError::Lagged(_)) => {}
+		}
+	}
+}
+
+/// Runs `f` on the bot, if we're letting one play. The bot can take its
+/// time, like chat voting on a shot, so the rest of the game carries on
+/// meanwhile.
+fn with_bot<T>(f: impl FnOnce(&mut Box<dyn Engine>) -> T) -> Option<T> {
+	tokio::task::block_in_place(|| {
+		BOT.lock()
+			.expect("nothing panics holding the bot")
+			.as_mut()
+			.map(f)
+	})
+}
+
+/// Reads the bot's taunts from `path`, or uses its own if there's no file.
+/// Exits if the file can't be read.
+fn taunts(path: Option<&Path>) -> Taunts {
+	let Some(path) = path else {
+		return Taunts::default();
+	};
+	match Taunts::load(path) {
+		Ok(taunts) => taunts,
+		Err(e) => {
+			eprintln!("Bad taunts: {e}");
+			exit(1);
+		}
+	}
+}
+
+/// Lets the bot say what it thinks of everything that's happened since it
+/// was last asked, while the game is on.
+async fn banter(game: &GameFlow, events: &mut Receiver<Event>) {
+	loop {
+		let event = match events.try_recv() {
+			Ok(event) => event,
+			Err(TryRecvError::Lagged(_)) => continue,
+			Err(_) => break,
+		};
+		// Once the game is over the enemy may already have hung up
+		if game.phase().await != Phase::Playing {
+			break;
+		}
+		if let Some(Some(line)) = with_bot(|bot| bot.react(&event)) {
+			game.chat(&line).await;
+		}
+	}
+}
+
+/// Where our fleet is laid out: the game itself, or our own board while we
+/// wait for the enemy to connect.
+trait Fleet {
+	fn snapshot(&self) -> GameSnapshot;
+	async fn place_ship(&self, ship: ShipKind, pos: Coord, v: bool) -> Result<(), GameFlowError>;
+	async fn place_remaining(&self) -> Result<Vec<(ShipKind, Coord, bool)>, GameFlowError>;
+	/// Stops everything waiting on the enemy, when the player presses Ctrl-C.
+	fn cancel(&self);
+	/// Does whatever else the player asked for while placing.
+	async fn command(&self, command: Command);
+}
+
+impl Fleet for GameFlow {
+	fn snapshot(&self) -> GameSnapshot {
+		GameFlow::snapshot(self)
+	}
+
+	async fn place_ship(&self, ship: ShipKind, pos: Coord, v: bool) -> Result<(), GameFlowError> {
+		GameFlow::place_ship(self, ship, pos, v).await
+	}
+
+	async fn place_remaining(&self) -> Result<Vec<(ShipKind, Coord, bool)>, GameFlowError> {
+		GameFlow::place_remaining(self).await
+	}
+
+	fn cancel(&self) {
+		self.cancellation().cancel();
+	}
+
+	async fn command(&self, command: Command) {
+		other_command(self, command).await;
+	}
+}
+
+/// Our fleet, laid out before there's anyone to play against.
+struct Harbour(Mutex<StateMachine>);
+
+impl Harbour {
+	fn new(game: Game) -> Harbour {
+		Harbour(Mutex::new(StateMachine::new(game)))
+	}
+
+	fn lock(&self) -> MutexGuard<'_, StateMachine> {
+		self.0.lock().expect("nothing panics holding the harbour")
+	}
+
+	/// Every ship placed so far, in order.
+	fn placed(&self) -> Vec<(ShipKind, Coord, bool)> {
+		self.lock()
+			.log
+			.iter()
+			.filter_map(|action| match *action {
+				Move::Place {
+					ship,
+					pos,
+					vertical,
+				} => Some((ship, pos, vertical)),
+				_ => None,
+			})
+			.collect()
+	}
+}
+
+impl Fleet for Harbour {
+	fn snapshot(&self) -> GameSnapshot {
+		Arc::new(Game::clone(&self.lock()))
+	}
+
+	async fn place_ship(&self, ship: ShipKind, pos: Coord, v: bool) -> Result<(), GameFlowError> {
+		self.lock().place(ship, pos, v)
+	}
+
+	async fn place_remaining(&self) -> Result<Vec<(ShipKind, Coord, bool)>, GameFlowError> {
+		self.lock().place_remaining()
+	}
+
+	// There's nothing to wait on yet
+	fn cancel(&self) {}
+
+	async fn command(&self, command: Command) {
+		match command {
+			Command::Help => help(),
+			Command::Query { pos, own: true } => {
+				let state = self.lock();
+				say!(
+					"{}: {}",
+					at(pos),
+					state.board[usize::from(state.you)].cell(pos)
+				);
+			}
+			Command::Grid => {
+				say!("{}", draw(&self.snapshot(), Style::Grid));
+			}
+			Command::Quit => exit(0),
+			Command::Nothing => {}
+			_ => say!("That has to wait until your enemy is here."),
+		}
+	}
+}
+
+async fn place(game: &impl Fleet, style: Style, input: &mut Input) -> Result<(), GameFlowError> {
+	let snapshot = game.snapshot();
+	let ours = &snapshot.board[usize::from(snapshot.you)];
+	for &(ship, pos, v) in LAYOUT.get().into_iter().flatten() {
+		// Placing can pick up where it left off, with some of these placed
+		if ours.span(ship).is_none() {
+			game.place_ship(ship, pos, v).await?;
+		}
+	}
+	loop {
+		let snapshot = game.snapshot();
+		let Phase::Placing(ship) = snapshot.phase else {
+			break;
+		};
+		let board = &snapshot.board[usize::from(snapshot.you)];
+		if let Some(placement) = with_bot(|bot| bot.placement(board, ship)) {
+			let Some((pos, v)) = placement else {
+				return Err(GameFlowError::InvalidPlacement);
+			};
+			game.place_ship(ship, pos, v).await?;
+			continue;
+		}
+		let steered = if input.has_keys() {
+			steer(game, style, input, ship).await?
+		} else {
+			None
+		};
+		let (pos, v) = if let Some(steered) = steered {
+			steered
+		} else {
+			say!("{}", draw(&snapshot, style));
+			ask!(
+				"Place the top-left section of your {} (like {}): ",
+				skin().name(ship),
+				at(EXAMPLE)
+			);
+			match read_command(input).await {
+				Some(Command::Place { pos, vertical }) => (pos, vertical),
+				Some(Command::Cell(pos)) => {
+					ask!("Vertical (y)? ");
+					(
+						pos,
+						input
+							.next()
+							.await
+							.unwrap_or_else(|| ran_out())
+							.starts_with('y'),
+					)
+				}
+				Some(Command::Auto) => {
+					if game.place_remaining().await.is_err() {
+						say!("The rest of your fleet doesn't fit, place it yourself.");
+					}
+					continue;
+				}
+				Some(Command::Layout(name)) => {
+					match layouts::placements(&name, board, &snapshot.rules.fleet) {
+						Ok(placements) => {
+							for (ship, pos, v) in placements {
+								game.place_ship(ship, pos, v).await?;
+							}
+						}
+						Err(e) => say!("Couldn't use that layout: {e}."),
+					}
+					continue;
+				}
+				Some(Command::SaveLayout(name)) => {
+					save_layout(&name, board);
+					continue;
+				}
+				Some(command) => {
+					game.command(command).await;
+					continue;
+				}
+				None => continue,
+			}
+		};
 		match game.place_ship(ship, pos, v).await {
 			Ok(()) => {}
-			Err(GameFlowError::InvalidPlacement) => println!("Invalid placement, try again."),
-			Err(e) => panic!("{}", e),
+			Err(GameFlowError::InvalidPlacement) => {
+				say!("{}", blocked(board, ship, pos, v));
+			}
+			Err(e) => return Err(e),
+		}
+	}
+	Ok(())
+}
+
+/// Keeps the ships on `board` as the layout called `name`.
+fn save_layout(name: &str, board: &Board) {
+	match layouts::save(name, board) {
+		Ok(()) => say!("Saved your layout as {name}."),
+		Err(e) => say!("Couldn't save your layout: {e}."),
+	}
+}
+
+/// Lets the player steer `ship` into place with the arrow keys, turning it
+/// with space and placing it with enter, while the board shows where it would
+/// go. Returns `None` if they press escape to type a square instead.
+///
+/// Raw mode keeps Ctrl-C from interrupting, so it's caught here and cancels
+/// the game, which resigns it.
+async fn steer(
+	game: &impl Fleet,
+	style: Style,
+	input: &mut Input,
+	ship: ShipKind,
+) -> Result<Option<(Coord, bool)>, GameFlowError> {
+	let snapshot = game.snapshot();
+	let board = &snapshot.board[usize::from(snapshot.you)];
+	let last = board.size().saturating_sub(1);
+	let (mut pos, mut v) = (Coord::new(0, 0), false);
+	let mut screen = Screen::default();
+	let _ = crossterm::execute!(cast::stdout(), Clear(ClearType::All));
+	let placed = loop {
+		let fits = ship.can_place(board, pos, v);
+		let mut preview = Game::clone(&snapshot);
+		let ours = &mut preview.board[usize::from(preview.you)];
+		if fits {
+			ship.place(ours, pos, v);
+		} else {
+			for cell in ship.footprint(pos, v).filter(|&cell| board.in_bounds(cell)) {
+				ours.board.entry(cell).or_insert(Cell::ship(ship));
+			}
+		}
+		let status = if fits {
+			format!(
+				"Your {} at {}, {}.",
+				skin().name(ship),
+				at(pos),
+				if v { "down" } else { "across" }
+			)
+		} else {
+			blocked(board, ship, pos, v)
+		};
+		let footer = format!(
+			"{status}\nArrows move, space turns, enter places it, escape to type a square instead."
+		);
+		let mut out = cast::stdout();
+		// Only the lines that changed, so it doesn't flicker over slow links
+		for row in screen.update(&preview, &draw(&preview, style), &footer) {
+			let _ = crossterm::queue!(out, MoveTo(0, line_number(row)));
+			let _ = write!(out, "{}", screen.line(row));
+			let _ = crossterm::queue!(out, Clear(ClearType::UntilNewLine));
+		}
+		let _ = crossterm::queue!(out, MoveTo(0, line_number(screen.len())));
+		let _ = out.flush();
+		drop(out);
+
+		let Some(key) = input.key().await else {
+			break Ok(None);
+		};
+		match key.code {
+			KeyCode::Left => pos.x = pos.x.saturating_sub(1),
+			KeyCode::Right => pos.x = (pos.x + 1).min(last),
+			KeyCode::Up => pos.y = pos.y.saturating_sub(1),
+			KeyCode::Down => pos.y = (pos.y + 1).min(last),
+			KeyCode::Char(' ') => v = !v,
+			KeyCode::Enter if fits => break Ok(Some((pos, v))),
+			KeyCode::Esc => break Ok(None),
+			KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+				game.cancel();
+				break Err(GameFlowError::Cancelled);
+			}
+			_ => {}
+		}
+	};
+	input.end_keys();
+	placed
+}
+
+/// Where line `row` of the screen is, for moving the cursor there.
+fn line_number(row: usize) -> u16 {
+	u16::try_from(row).unwrap_or(u16::MAX)
+}
+
+/// Explains why `ship` can't go at `pos`, naming the ships in the way.
+fn blocked(board: &Board, ship: ShipKind, pos: Coord, v: bool) -> String {
+	let mut blockers = Vec::new();
+	for cell in board.placement_conflicts(ship, pos, v) {
+		let Some(ship) = board.cell(cell).ship else {
+			continue;
+		};
+		if !blockers.contains(&ship) {
+			blockers.push(ship);
 		}
 	}
+	let blockers = blockers
+		.into_iter()
+		.filter_map(|ship| {
+			let (first, last) = board.span(ship)?;
+			Some(format!(
+				"your {} at {}\u{2013}{}",
+				skin().name(ship),
+				at(first),
+				at(last)
+			))
+		})
+		.collect::<Vec<_>>();
+	if blockers.is_empty() {
+		"That runs off the board, try again.".to_string()
+	} else {
+		format!("Blocked by {}, try again.", blockers.join(" and "))
+	}
+}
 
-	println!("Ready to play! Choose your first target.");
+/// Makes sure we and the enemy see the same game, fixing our copy if not.
+/// Only the player whose turn it is can ask, since the other is listening.
+/// Unless `verbose`, says nothing when all is well.
+async fn check_sync(game: &GameFlow, verbose: bool) -> Result<(), GameFlowError> {
+	if game.phase().await != Phase::Playing || !game.my_turn().await {
+		return Ok(());
+	}
+	match game.verify_sync().await? {
+		0 if verbose => say!("Your game matches your enemy's."),
+		0 => {}
+		1 => say!("Fixed 1 thing that had drifted from your enemy's game."),
+		n => say!("Fixed {n} things that had drifted from your enemy's game."),
+	}
+	Ok(())
+}
+
+async fn play(game: &GameFlow, style: Style, input: &mut Input) -> Result<(), GameFlowError> {
+	let mut heard = game.events();
 	while matches!(game.phase().await, Phase::Playing) {
-		if game.my_turn().await {
-			println!("{}", game.to_string().await);
-			print!("Choose your target (like E5): ");
-			flush();
-			let Some(aim) = parse_coord(&stdin.next().expect("Broken pipe")) else { println!("Those coordinates were malformed, try again."); continue };
-			println!("Fire!!!");
-			let result = game.fire(aim).await.expect("Running fire code failed.");
-			if result.hit.is_some() {
-				println!("KABOOM!");
-			} else {
-				println!("Splash...");
+		let over = if game.my_turn().await {
+			your_turn(game, style, input).await?
+		} else {
+			enemy_turn(game, input).await?
+		};
+		if over {
+			break;
+		}
+		banter(game, &mut heard).await;
+	}
+	Ok(())
+}
+
+/// Handles one command on our turn, returning whether the game is over.
+async fn your_turn(
+	game: &GameFlow,
+	style: Style,
+	input: &mut Input,
+) -> Result<bool, GameFlowError> {
+	say!("{}", render(game, style));
+	if game.scan_available().await {
+		say!("Your radar is charged. Type r and a target (like r E5) to sweep around it.");
+	}
+	let turn = game.turn_number().await;
+	let snapshot = game.subscribe().borrow().clone();
+	let command = if let Some(aim) = with_bot(|bot| bot.aim(&snapshot)) {
+		say!("Turn {turn}{}.", ping(game));
+		Some(Command::Fire(aim))
+	} else {
+		ask!(
+			"Turn {turn}{}. Choose your target (like {}): ",
+			ping(game),
+			at(EXAMPLE)
+		);
+		read_aiming(game, input).await
+	};
+	let aim = match command {
+		Some(Command::Cell(aim) | Command::Fire(aim)) => aim,
+		Some(Command::Scan(center)) => {
+			match game.scan(center).await {
+				Ok(count) => say!("Radar found {count} ship cells around {}.", at(center)),
+				Err(GameFlowError::NoScanAvailable) => {
+					say!("You haven't earned a radar sweep yet.");
+				}
+				Err(GameFlowError::OutOfBounds(_)) => {
+					say!("That's off the board, try again.");
+				}
+				Err(e) => return Err(e),
 			}
-			if let Some(ship) = result.sunk {
-				println!("You sunk the enemy's {:?}.", ship);
+			return Ok(false);
+		}
+		Some(Command::Sync) => {
+			check_sync(game, true).await?;
+			return Ok(false);
+		}
+		Some(Command::Move { pos, vertical }) => {
+			match game.move_submarine(pos, vertical).await {
+				Ok(()) => say!("Your submarine slips away to {}.", at(pos)),
+				Err(GameFlowError::CannotMove) => {
+					say!("You can't move your submarine.");
+				}
+				Err(GameFlowError::InvalidPlacement) => {
+					say!("Your submarine can't go there, try again.");
+				}
+				Err(GameFlowError::TimedOut) => {
+					say!("You took too long. You lose...");
+					return Ok(true);
+				}
+				Err(e) => return Err(e),
 			}
-			if result.won {
-				println!("You win!!!");
-				break;
+			return Ok(false);
+		}
+		Some(command) => {
+			other_command(game, command).await;
+			return Ok(false);
+		}
+		None => return Ok(false),
+	};
+	say!("Firing at {}!!!", at(aim));
+	let result = match game.fire(aim).await {
+		Ok(result) => result,
+		Err(GameFlowError::OutOfBounds(_)) => {
+			say!("That's off the board, try again.");
+			return Ok(false);
+		}
+		Err(GameFlowError::Land(_)) => {
+			say!("That's land, try again.");
+			return Ok(false);
+		}
+		Err(GameFlowError::TimedOut) => {
+			say!("You took too long to fire. You lose...");
+			return Ok(true);
+		}
+		Err(GameFlowError::OutOfTurn(ours, theirs)) => {
+			say!("Your enemy is on turn {theirs}, not turn {ours}, so your shot didn't count.");
+			check_sync(game, true).await?;
+			return Ok(false);
+		}
+		Err(e) => return Err(e),
+	};
+	let hit = (!result.hidden).then_some(result.hit);
+	effects(input, &Animation::shot(hit, result.sunk.is_some())).await;
+	if result.hidden {
+		say!("Your shot disappears into the fog...");
+	} else if result.hit {
+		say!("KABOOM!");
+	} else {
+		say!("Splash...");
+	}
+	if let Some(name) = &result.sunk_name {
+		say!("You sunk {} {name}.", whose(game));
+	}
+	if result.won {
+		say!("You win!!!");
+		return Ok(true);
+	}
+	Ok(false)
+}
+
+/// Whose the enemy's ships are, by name if they gave one.
+fn whose(game: &GameFlow) -> String {
+	game.enemy_name()
+		.map_or_else(|| "the enemy's".to_string(), |name| format!("{name}'s"))
+}
+
+/// Reads a command on our turn, telling the enemy we're aiming as it's
+/// typed.
+async fn read_aiming(game: &GameFlow, input: &mut Input) -> Option<Command> {
+	game.aiming().await;
+	let Some(mut typing) = input.typing() else {
+		return read_command(input).await;
+	};
+	let reading = read_command(input);
+	tokio::pin!(reading);
+	loop {
+		tokio::select! {
+			command = &mut reading => return command,
+			changed = typing.changed() => {
+				if changed.is_err() {
+					return reading.await;
+				}
+				game.aiming().await;
+			}
+		}
+	}
+}
+
+/// Waits for one enemy shot, returning whether the game is over.
+async fn enemy_turn(game: &GameFlow, input: &mut Input) -> Result<bool, GameFlowError> {
+	let turn = game.turn_number().await;
+	say!(
+		"Turn {turn}{}. Waiting for your enemy to aim...",
+		ping(game)
+	);
+	let receive = game.receive();
+	tokio::pin!(receive);
+	// Only the first line typed while waiting can leave, the rest wait
+	// their turn
+	let mut watching = true;
+	let waited = Instant::now();
+	let mut aiming = true;
+	let mut checks = tokio::time::interval(AIMING_INTERVAL);
+	let received = loop {
+		tokio::select! {
+			result = &mut receive => break result,
+			_ = checks.tick() => {
+				// They've only just started if they haven't said anything yet
+				let now = game.enemy_aiming() || waited.elapsed() < AIMING_IDLE;
+				if now != aiming {
+					aiming = now;
+					say!("{}", if now { "Your enemy is aiming..." } else { "Your enemy appears idle." });
+				}
+			}
+			() = input.typed(), if watching => {
+				if matches!(input.peek().map(str::parse), Some(Ok(Command::Quit))) {
+					// We're going either way
+					let _ = game.shutdown().await;
+					exit(0);
+				}
+				watching = false;
 			}
+		}
+	};
+	let result = match received {
+		Ok(result) => result,
+		Err(GameFlowError::TimedOut) => {
+			say!("Your enemy took too long to fire. You win!!!");
+			return Ok(true);
+		}
+		Err(e) => return Err(e),
+	};
+	if !result.won {
+		notify::your_turn();
+	}
+	if result.moved {
+		say!("The enemy's submarine has moved somewhere else!");
+		return Ok(false);
+	}
+	say!("The enemy fired at {}.", at(result.aim));
+	effects(
+		input,
+		&Animation::shot(Some(result.hit), result.sunk.is_some()),
+	)
+	.await;
+	if let Some(ship) = result.ship {
+		say!("KABOOM! The enemy hit your {}!", skin().name(ship));
+	} else {
+		say!("Splash...");
+	}
+	if let Some(ship) = result.sunk {
+		say!("The enemy sunk your {}...", skin().name(ship));
+	}
+	if result.won {
+		say!("You lose...");
+		return Ok(true);
+	}
+	Ok(false)
+}
+
+/// With `--effects`, plays `animations` unless the player types something
+/// to skip them.
+async fn effects(input: &mut Input, animations: &[Animation]) {
+	if EFFECTS.load(Ordering::Relaxed) {
+		Animation::play_all(animations, input.typed()).await;
+	}
+}
+
+/// The connection's latency to show in prompts, like ", ping 42 ms (good)",
+/// once it's been measured.
+fn ping(game: &GameFlow) -> String {
+	game.latency()
+		.map(|rtt| format!(", ping {}", Latency(rtt)))
+		.unwrap_or_default()
+}
+
+/// Reads the next command, explaining what was wrong if it can't be understood.
+async fn read_command(input: &mut Input) -> Option<Command> {
+	let Some(line) = input.next().await else {
+		ran_out()
+	};
+	match line.parse() {
+		Ok(command) => Some(command),
+		Err(ParseCommandError::Coord(_)) => {
+			say!("Those coordinates were malformed, try again.");
+			None
+		}
+		Err(ParseCommandError::Unknown) => {
+			say!("Unknown command, type help to see what you can do.");
+			None
+		}
+	}
+}
+
+/// Gives up once input has been closed in the middle of the game.
+fn ran_out() -> ! {
+	if SCRIPTED.load(Ordering::Relaxed) {
+		say!("The script ran out of commands.");
+		Ending::RanOut.exit();
+	}
+	panic!("Broken pipe");
+}
+
+/// Saves a picture of the battle so far to `path`.
+fn export(game: &GameFlow, path: &Path) {
+	match image::export(&game.subscribe().borrow(), path) {
+		Ok(()) => say!("Saved the battle map to {}.", path.display()),
+		Err(e) => say!("Couldn't save the battle map: {e}"),
+	}
+}
+
+/// Sets up passing games on to spectators and a replay file, as asked.
+async fn broadcast(args: &Args) -> Broadcast {
+	let mut broadcast = Broadcast::default();
+	if let Some(path) = &args.replay {
+		broadcast.record(
+			path.clone(),
+			args.name.clone().unwrap_or_default(),
+			args.opponent.clone().unwrap_or_default(),
+		);
+	}
+	if let Some(addr) = args.spectators {
+		match broadcast.listen(addr).await {
+			Ok(addr) => say!("Spectators can watch by connecting to {addr}."),
+			Err(e) => say!("Couldn't let spectators watch ({e})."),
+		}
+	}
+	broadcast
+}
+
+/// Tells the player whether the replay in `path` was kept.
+fn report_replay(recorded: Result<Result<(), ReplayError>, JoinError>, path: &Path) {
+	match recorded {
+		Ok(Ok(())) => say!("Saved the replay to {}.", path.display()),
+		Ok(Err(e)) => say!("Couldn't save the replay: {e}"),
+		Err(_) => say!("Couldn't finish the replay."),
+	}
+}
+
+/// Runs the subcommands that don't play anyone, returning whether there was
+/// one.
+async fn offline(args: &Args, rules: &Rules, input: &mut Input) -> bool {
+	match &args.command {
+		Some(Subcommand::BenchSim { games }) => bench_sim(rules, *games),
+		Some(Subcommand::Puzzle { date }) => {
+			let puzzle = date.as_deref().map_or_else(Puzzle::today, Puzzle::new);
+			solve(puzzle, args.style, input).await;
+		}
+		Some(Subcommand::Campaign { dir, mission }) => {
+			campaign(dir, mission.as_deref(), args.style, input).await;
+		}
+		Some(Subcommand::Protocol) => {
+			let schema = serde_json::to_string_pretty(&flow::schema()).expect("bad schema");
+			println!("{schema}");
+		}
+		Some(Subcommand::History {
+			against,
+			wins,
+			losses,
+			date,
+		}) => history(&Filter {
+			opponent: against.clone(),
+			won: (*wins || *losses).then_some(*wins),
+			date: date.clone(),
+		}),
+		Some(Subcommand::ServeApi { port }) => serve_api(*port).await,
+		Some(Subcommand::Pbm(command)) => {
+			let password = args.password.as_deref().unwrap_or_default();
+			correspond(command, rules, password, args.style, input).await;
+		}
+		None if args.tutorial => tutorial(args.style, input).await,
+		None if args.sandbox => sandbox(args, input).await,
+		_ => return false,
+	}
+	true
+}
+
+/// Lays out a fleet by the `--rules`, starting again from as much of it as
+/// still fits whenever they change, until the player quits.
+async fn sandbox(args: &Args, input: &mut Input) -> ! {
+	let mut watched = Watched::new(args.rules.iter().chain(&args.map).cloned());
+	let mut rules = rules(args);
+	let mut placed = Vec::new();
+	say!("Playing by {rules}. Change the rules and they'll be read again.");
+	loop {
+		let harbour = Harbour::new(Game::new(rules.clone(), true));
+		for (ship, pos, v) in sandbox::refit(&placed, &rules) {
+			harbour
+				.place_ship(ship, pos, v)
+				.await
+				.expect("refitted ships fit");
+		}
+		tokio::select! {
+			() = watched.next_change() => {}
+			() = try_out(&harbour, args.style, input) => {}
+		}
+		// The change may have come partway through steering a ship
+		input.end_keys();
+		placed = harbour.placed();
+		match load_rules(args) {
+			Ok(changed) => {
+				rules = changed;
+				let kept = sandbox::refit(&placed, &rules).len();
+				say!(
+					"The rules changed to {rules}. Kept {kept} of your {} ships.",
+					placed.len()
+				);
+			}
+			Err(e) => say!("Bad rules, keeping the old ones: {e}"),
+		}
+	}
+}
+
+/// Has the player lay out their fleet in `harbour`, then look it over until
+/// they quit.
+async fn try_out(harbour: &Harbour, style: Style, input: &mut Input) -> ! {
+	match place(harbour, style, input).await {
+		Ok(()) => {}
+		Err(GameFlowError::Cancelled) => exit(130),
+		Err(e) => {
+			eprintln!("Couldn't place your fleet: {e}");
+			exit(1);
+		}
+	}
+	say!("{}", draw(&harbour.snapshot(), style));
+	say!("Your whole fleet fits. Change the rules to try again, or quit.");
+	loop {
+		if let Some(command) = read_command(input).await {
+			harbour.command(command).await;
+		}
+	}
+}
+
+/// Starts a game by mail or reads the latest letter of one, then writes our
+/// letter if it's our turn to. Exits if the game can't go on.
+async fn correspond(
+	command: &PbmCommand,
+	rules: &Rules,
+	password: &str,
+	style: Style,
+	input: &mut Input,
+) {
+	let (received, fire, out) = match command {
+		PbmCommand::New { fire, out } => (
+			Correspondence::start(rules.clone()).map(|c| (c, None)),
+			fire,
+			out,
+		),
+		PbmCommand::Apply { file, fire, out } => (
+			Correspondence::receive(file, password).map(|(c, turn)| (c, Some(turn))),
+			fire,
+			out,
+		),
+	};
+	let (mut correspondence, turn) = received.unwrap_or_else(|e| {
+		eprintln!("Couldn't play the letter: {e}");
+		exit(1);
+	});
+	if let Some(Turn {
+		shot: Some(pos), ..
+	}) = turn
+	{
+		let board = &correspondence.game.board[usize::from(correspondence.game.you)];
+		let hit = board.cell(pos).ship.is_some();
+		say!(
+			"They fired at {}: {}",
+			at(pos),
+			if hit { "a hit." } else { "a miss." }
+		);
+	}
+	say!("{}", draw(&correspondence.game, style));
+	match correspondence.game.phase {
+		Phase::Done(true) => say!("You win!"),
+		Phase::Done(false) => say!("You lose!"),
+		_ => {}
+	}
+	if correspondence.to_write() {
+		let letter = loop {
+			let aim = match fire {
+				Some(aim) => Some(*aim),
+				None if correspondence.to_fire() => Some(ask_aim(input).await),
+				None => None,
+			};
+			match correspondence.write(aim, password) {
+				Ok(letter) => break letter,
+				Err(PbmError::BadShot(_)) if fire.is_none() => {
+					say!("You can't fire there, try again.");
+				}
+				Err(e) => {
+					eprintln!("Couldn't write the letter: {e}");
+					exit(1);
+				}
+			}
+		};
+		let path = out.clone().unwrap_or_else(|| letter.file_name().into());
+		if let Err(e) = letter.write(&path) {
+			eprintln!("Couldn't write the letter: {e}");
+			exit(1);
+		}
+		say!("Send {} to your enemy.", path.display());
+	}
+	if let Err(e) = correspondence.store() {
+		eprintln!("Couldn't keep the game: {e}");
+		exit(1);
+	}
+}
+
+/// Asks where to fire until the player gives a square.
+async fn ask_aim(input: &mut Input) -> Coord {
+	loop {
+		ask!("Choose your target (like {}): ", at(EXAMPLE));
+		if let Some(Command::Cell(aim) | Command::Fire(aim)) = read_command(input).await {
+			return aim;
+		}
+		say!("Type a square to fire at it, like {}.", at(EXAMPLE));
+	}
+}
+
+/// Serves the game API on `port` until it's stopped. Exits if the port
+/// can't be had.
+async fn serve_api(port: u16) {
+	let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+	let listener = match TcpListener::bind(addr).await {
+		Ok(listener) => listener,
+		Err(e) => {
+			eprintln!("Couldn't serve the API on port {port}: {e}");
+			exit(1);
+		}
+	};
+	say!("Serving the API at http://{addr}");
+	if let Err(e) = api::serve(listener).await {
+		eprintln!("The API stopped: {e}");
+		exit(1);
+	}
+}
+
+/// Lists the games in the history that `filter` matches, oldest first, with
+/// the replay of each one that has one.
+fn history(filter: &Filter) {
+	let found = Archive::open().and_then(|archive| archive.find(filter));
+	let found = found.unwrap_or_else(|e| {
+		eprintln!("{e}");
+		exit(1);
+	});
+	if found.is_empty() {
+		say!("No games like that yet.");
+	}
+	for (_, entry) in found {
+		let outcome = match entry.outcome {
+			Outcome::Won => "won",
+			Outcome::Lost => "lost",
+			Outcome::Resigned => "resigned",
+		};
+		let against = match entry.opponent.as_str() {
+			"" => String::new(),
+			name => format!(" against {name}"),
+		};
+		let day = puzzle::day(entry.ended);
+		say!("{day}  {outcome}{against} in {} turns", entry.turns);
+		say!("            {}", entry.rules);
+		if let Some(replay) = entry.replay {
+			say!("            replay: {}", replay.display());
+		}
+	}
+}
+
+/// Lists a campaign's missions, then plays the one called `id`, or the next
+/// one to win, and keeps it if it's won.
+async fn campaign(dir: &Path, id: Option<&str>, style: Style, input: &mut Input) {
+	let campaign = Campaign::load(dir).unwrap_or_else(|e| {
+		eprintln!("Couldn't load the campaign: {e}");
+		exit(1);
+	});
+	let won = Config::load()
+		.map(|config| config.campaigns.get(&campaign.name).cloned())
+		.unwrap_or_default()
+		.unwrap_or_default();
+	for mission in &campaign.missions {
+		let status = if won.contains(&mission.id) {
+			"won"
+		} else if Campaign::unlocked(mission, &won) {
+			"open"
 		} else {
-			println!("Waiting for your enemy to aim...");
-			flush();
-			let result = game.receive().await.expect("Couldn't receive fire.");
-			if let Some(ship) = result.hit {
-				println!("KABOOM! The enemy hit your {:?}!", ship);
-			} else {
-				println!("Splash...");
+			"locked"
+		};
+		say!("  {:<16} {} ({status})", mission.id, mission.name);
+	}
+	let mission = match id.map(|id| (id, campaign.mission(id))) {
+		Some((_, Some(mission))) if Campaign::unlocked(mission, &won) => mission,
+		Some((id, Some(mission))) => {
+			let after = mission.after.as_deref().unwrap_or_default();
+			eprintln!("{id} is locked until you win {after}.");
+			exit(1);
+		}
+		Some((id, None)) => {
+			eprintln!("There's no mission called {id} in this campaign.");
+			exit(1);
+		}
+		None => {
+			let Some(mission) = campaign.next(&won) else {
+				say!("You've won every mission. Play one again with --mission.");
+				return;
+			};
+			mission
+		}
+	};
+	say!("\n{}", mission.name);
+	if !mission.briefing.is_empty() {
+		say!("{}", mission.briefing);
+	}
+	say!("Rules: {}", mission.rules);
+	let game = match bot::practice(mission.rules.clone(), mission.enemy()).await {
+		Ok(game) => game,
+		Err(e) => {
+			eprintln!("{}", Failure::from(e));
+			exit(1);
+		}
+	};
+	if let Err(e) = run(&game, style, input, false).await {
+		say!("{}", Failure::from(e));
+	}
+	if game.phase().await != Phase::Done(true) {
+		return;
+	}
+	match Config::record_mission(&campaign.name, &mission.id) {
+		Ok(won) => match campaign.next(&won) {
+			Some(next) => say!("Mission accomplished! Next up: {}.", next.name),
+			None => say!("Mission accomplished! That was the last one."),
+		},
+		Err(e) => eprintln!("Couldn't keep your progress: {e}"),
+	}
+}
+
+/// Where the tutorial's enemy hides its fleet, so the shots it suggests hit.
+const TUTORIAL_FLEET: [(ShipKind, Coord, bool); 5] = [
+	(ShipKind::Carrier, Coord::new(0, 0), false),
+	(ShipKind::Battleship, Coord::new(3, 4), false),
+	(ShipKind::Cruiser, Coord::new(8, 1), true),
+	(ShipKind::Submarine, Coord::new(1, 7), false),
+	(ShipKind::Destroyer, Coord::new(6, 8), false),
+];
+
+/// The squares the tutorial has the player fire at on their first two
+/// turns, both on the enemy's Battleship.
+const TUTORIAL_SHOTS: [Coord; 2] = [EXAMPLE, Coord::new(6, 4)];
+
+/// Walks a new player through a game against the easy bot: how squares are
+/// named, placing a fleet and two shots picked out for them, then leaves
+/// them to finish it.
+async fn tutorial(style: Style, input: &mut Input) {
+	say!(
+		"Welcome to netbattleship! You and your enemy each hide a fleet of ships on a \
+		 grid, then take turns firing at each other's. Sink the whole enemy fleet to win."
+	);
+	say!(
+		"Squares are named by their row's letter and their column's number, like {}.",
+		at(EXAMPLE)
+	);
+	let enemy = Scripted::new(Difficulty::Easy, TUTORIAL_FLEET.to_vec());
+	let game = match bot::practice(Rules::default(), enemy).await {
+		Ok(game) => game,
+		Err(e) => {
+			eprintln!("{}", Failure::from(e));
+			exit(1);
+		}
+	};
+	tokio::spawn(print_chat(game.events()));
+	if let Err(e) = coach(&game, style, input).await {
+		say!("{}", Failure::from(e));
+	}
+}
+
+/// Plays the tutorial's game, explaining each step.
+async fn coach(game: &GameFlow, style: Style, input: &mut Input) -> Result<(), GameFlowError> {
+	say!(
+		"\nFirst, hide your fleet on your own board, on the left under YOU. Each ship \
+		 goes in a straight line from the square you pick, across to the right or \
+		 down if you answer y. Type auto to have the rest placed for you."
+	);
+	place(game, style, input).await?;
+	game.wait_for_fleet().await?;
+	let lessons = [
+		format!(
+			"\nNow the battle begins. The enemy's board, on the right under THEM, starts \
+			 out empty because you can't see their ships. Psst: there's one at {}. \
+			 Type {} to fire at it.",
+			at(TUTORIAL_SHOTS[0]),
+			at(TUTORIAL_SHOTS[0])
+		),
+		format!(
+			"\nThe X on their board is your hit, and the enemy's misses on yours show \
+			 as o. Ships lie in straight lines, so after a hit try the squares next to \
+			 it. Fire at {} next.",
+			at(TUTORIAL_SHOTS[1])
+		),
+	];
+	for lesson in lessons {
+		say!("{lesson}");
+		while game.my_turn().await {
+			if your_turn(game, style, input).await? {
+				return Ok(());
+			}
+		}
+		if enemy_turn(game, input).await? {
+			return Ok(());
+		}
+	}
+	say!("\nThat's all there is to it. Sink the rest of their fleet to win!");
+	play(game, style, input).await
+}
+
+/// Plays a daily puzzle to the end, then keeps the score and prints a
+/// summary to share.
+async fn solve(mut puzzle: Puzzle, style: Style, input: &mut Input) {
+	say!(
+		"Daily puzzle for {}: sink the fleet in {} shots or fewer.",
+		puzzle.date,
+		puzzle::SHOTS
+	);
+	while !puzzle.over() {
+		say!("{}", draw(&puzzle.game, style));
+		ask!(
+			"{} shots left. Choose your target (like {}): ",
+			puzzle.shots_left(),
+			at(EXAMPLE)
+		);
+		let aim = match read_command(input).await {
+			Some(Command::Cell(aim) | Command::Fire(aim)) => aim,
+			Some(Command::Help) => {
+				say!("Type a square to fire at it, like {}.", at(EXAMPLE));
+				continue;
 			}
-			if let Some(ship) = result.sunk {
-				println!("The enemy sunk your {:?}...", ship);
+			Some(_) => {
+				say!("Only shots count in the puzzle.");
+				continue;
 			}
-			if result.won {
-				println!("You lose...");
-				break;
+			None => continue,
+		};
+		match puzzle.fire(aim) {
+			Ok(shot) => {
+				say!("{}", if shot.hit { "KABOOM!" } else { "Splash..." });
+				if let Some(ship) = shot.sunk {
+					say!("You sunk the {ship:?}.");
+				}
 			}
+			Err(PuzzleError::OutOfBounds(_)) => say!("That's off the board, try again."),
+			Err(PuzzleError::Over) => break,
+		}
+	}
+	say!("{}", draw(&puzzle.game, style));
+	if puzzle.solved() {
+		say!("Fleet sunk with {} shots to spare!", puzzle.score());
+	} else {
+		say!("Out of shots. Better luck tomorrow...");
+	}
+	match Config::record_puzzle(&puzzle.date, puzzle.score()) {
+		Ok(Some(best)) if best >= puzzle.score() => say!("Your best for this puzzle is {best}."),
+		Ok(Some(_)) => say!("That's your new best for this puzzle."),
+		Ok(None) => {}
+		Err(e) => eprintln!("Couldn't keep your score: {e}"),
+	}
+	say!("\n{}", puzzle.summary());
+}
+
+/// Weighs how well each bot plays against how fast, for `bench-sim`.
+fn bench_sim(rules: &Rules, games: u32) {
+	println!("Sinking {games} fleets with each bot, by {rules}");
+	for mut level in [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard] {
+		let start = Instant::now();
+		let shots = (0..games)
+			.map(|_| bot::hunt(&mut level, &mut Difficulty::Hard, rules))
+			.map(|shots| f64::from(u32::try_from(shots).unwrap_or(u32::MAX)))
+			.sum::<f64>();
+		let elapsed = start.elapsed();
+		println!(
+			"{level}: {:.1} shots and {:.2} ms a game",
+			shots / f64::from(games.max(1)),
+			elapsed.as_secs_f64() * 1000.0 / f64::from(games.max(1))
+		);
+	}
+}
+
+/// Checks the replay in `path`, saying what it's of and whether it adds up.
+/// Returns the exit code.
+fn verify(path: &Path) -> i32 {
+	let replay = match Replay::load(path) {
+		Ok(replay) => replay,
+		Err(e) => {
+			eprintln!("{e}");
+			return 2;
+		}
+	};
+	let header = &replay.header;
+	let name = |player: bool| match header.players[usize::from(player)].as_str() {
+		"" if player == header.you => "whoever saved it",
+		"" => "their enemy",
+		name => name,
+	};
+	println!(
+		"{} against {}, saved at {}",
+		name(header.you),
+		name(!header.you),
+		date(header.date)
+	);
+	println!("Rules: {}", header.rules);
+	match header.winner {
+		Some(winner) => println!("Won by {} in {} moves", name(winner), replay.moves.len()),
+		None => println!("Unfinished after {} moves", replay.moves.len()),
+	}
+	match replay.verify() {
+		Ok(()) => {
+			println!("Everything adds up.");
+			0
+		}
+		Err(e) => {
+			println!("This replay doesn't add up: {e}.");
+			1
+		}
+	}
+}
+
+/// Writes a Unix timestamp as a UTC date and time, like "2024-03-01 14:05 UTC".
+fn date(secs: u64) -> String {
+	let time = secs % 86_400;
+	format!(
+		"{} {:02}:{:02} UTC",
+		puzzle::day(secs),
+		time / 3600,
+		time % 3600 / 60
+	)
+}
+
+/// Lists the commands.
+fn help() {
+	say!(
+		"Commands:
+  E5          fire at E5, or place your ship there and then choose its direction
+  f E5        fire at E5
+  p E5 v      place your ship at E5, pointing down if followed by v
+  auto        place the rest of your fleet randomly
+  layout N    place the rest of your fleet like the layout called N: corners,
+              diagonal, random-balanced or one you saved
+  layout save N
+              keep your fleet's layout as N, to use in later games
+  m E5 v      move your unhit submarine to E5 instead of firing, once per game
+  r E5        sweep the cells around E5 with radar, once it's charged
+  q E5        describe E5 on the enemy's board, or on your own with Q E5
+  time        show how long each side has taken
+  sync        check that your game matches your enemy's, on your turn
+  grid        show both boards as a bare grid, for braille displays
+  export F    save a picture of both boards to F, a .png or .svg file
+  say TEXT    send your enemy a message
+  help        show this list
+  quit        leave the game"
+	);
+}
+
+/// Handles the commands that mean the same thing in every phase.
+async fn other_command(game: &GameFlow, command: Command) {
+	match command {
+		Command::Help => help(),
+		Command::Query { pos, own } => {
+			say!("{}: {}", at(pos), game.board(!own).await.cell(pos));
+		}
+		Command::Quit => {
+			// We're going either way
+			let _ = game.shutdown().await;
+			exit(0);
+		}
+		Command::Chat(text) => game.chat(&text).await,
+		Command::Export(path) => export(game, &path),
+		Command::SaveLayout(name) => save_layout(&name, &game.board(false).await),
+		Command::Time => say!("{}", game.clocks().await),
+		Command::Grid => say!("{}", render(game, Style::Grid)),
+		Command::Repeat | Command::Where | Command::Confirm => {
+			say!("That's only available in the speech version.");
+		}
+		Command::Nothing => {}
+		Command::Cell(_)
+		| Command::Place { .. }
+		| Command::Fire(_)
+		| Command::Scan(_)
+		| Command::Sync
+		| Command::Move { .. }
+		| Command::Auto
+		| Command::Layout(_) => {
+			say!("You can't do that right now.");
 		}
 	}
 }
@@ -0,0 +1,120 @@
+//! Hosting several games at once from one process, each against its own
+//! challenger and played by its own bot.
+//!
+//! Every game accepted gets a [`GameFlow`](crate::flow::GameFlow) of its own,
+//! run on a task of its own, so nothing about one game can leak into another.
+//! The manager only keeps count of how many are running, to stay under its
+//! limit, and reports each one as it finishes.
+
+use std::sync::Arc;
+
+use tokio::{
+	net::TcpListener,
+	sync::{mpsc, Semaphore},
+};
+
+use crate::{
+	bot::Engine,
+	flow::{GameFlowBuilder, GameFlowError},
+	Phase,
+};
+
+/// A game that was played to the end, or broke down.
+#[derive(Debug)]
+pub struct Finished {
+	/// Which game it was, counting from 1 in the order they started.
+	pub id: u64,
+	/// What the challenger asked to be called, if anything.
+	pub enemy: Option<String>,
+	/// Whether the bot won, or why the game couldn't be finished.
+	pub outcome: Result<bool, GameFlowError>,
+}
+
+/// Runs up to a set number of games side by side.
+pub struct Manager {
+	slots: Arc<Semaphore>,
+	max: usize,
+	started: u64,
+	finished: mpsc::UnboundedSender<Finished>,
+	results: Option<mpsc::UnboundedReceiver<Finished>>,
+}
+
+impl Manager {
+	/// Manages up to `max` games at once, and at least one.
+	#[must_use]
+	pub fn new(max: usize) -> Manager {
+		let max = max.max(1);
+		let (finished, results) = mpsc::unbounded_channel();
+		Manager {
+			slots: Arc::new(Semaphore::new(max)),
+			max,
+			started: 0,
+			finished,
+			results: Some(results),
+		}
+	}
+
+	/// The most games run at once.
+	#[must_use]
+	pub fn max(&self) -> usize {
+		self.max
+	}
+
+	/// How many games are running now.
+	#[must_use]
+	pub fn running(&self) -> usize {
+		self.max - self.slots.available_permits()
+	}
+
+	/// Takes the games as they finish. Only the first call gets them; any
+	/// after that get `None`.
+	pub fn results(&mut self) -> Option<mpsc::UnboundedReceiver<Finished>> {
+		self.results.take()
+	}
+
+	/// Waits until fewer than the most games are running, then for the next
+	/// challenger on `listener`, set up as `options` say, and has `engine`
+	/// play them on a task of its own. Returns the new game's id.
+	///
+	/// Other challengers still greeting the host when one gets in are turned
+	/// away, as with [`GameFlowBuilder::host`], and have to try again.
+	///
+	/// # Errors
+	/// Fails if no game could be started with the challenger; the manager
+	/// can carry on with the next one.
+	///
+	/// # Panics
+	/// Panics if the manager's limit was somehow closed.
+	pub async fn host(
+		&mut self,
+		listener: &TcpListener,
+		options: GameFlowBuilder,
+		engine: impl Engine + 'static,
+	) -> Result<u64, GameFlowError> {
+		let slot = self
+			.slots
+			.clone()
+			.acquire_owned()
+			.await
+			.expect("the limit is never closed");
+		let game = options.host(listener).await?;
+		self.started += 1;
+		let id = self.started;
+		let finished = self.finished.clone();
+		tokio::spawn(async move {
+			let enemy = game.enemy_name().map(String::from);
+			let played = game.play_with(engine).await;
+			let outcome = match (played, game.phase().await) {
+				(_, Phase::Done(won)) => Ok(won),
+				(Err(e), _) => Err(e),
+				(Ok(()), _) => Err(GameFlowError::Cancelled),
+			};
+			let _ = game.done().await;
+			// Let the next challenger in before saying this one's over
+			drop(slot);
+			// Nobody listening is fine
+			let _ = finished.send(Finished { id, enemy, outcome });
+		});
+		Ok(id)
+	}
+}
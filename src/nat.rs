@@ -0,0 +1,116 @@
+use std::net::SocketAddrV4;
+
+use thiserror::Error;
+
+/// A port forwarded through the local router by `UPnP`, so challengers
+/// outside the host's network can connect.
+///
+/// The mapping is removed again when this is dropped.
+pub struct PortMapping {
+	#[cfg(feature = "upnp")]
+	gateway: igd_next::Gateway,
+	external: SocketAddrV4,
+}
+
+#[derive(Error, Debug)]
+pub enum NatError {
+	#[error("this build doesn't support automatic port forwarding")]
+	Unsupported,
+	#[error("couldn't find a router that supports UPnP: {0}")]
+	NoGateway(String),
+	#[error("couldn't find this computer's address on the local network")]
+	NoLocalAddress,
+	#[error("the router refused to forward the port: {0}")]
+	Refused(String),
+}
+
+/// How long the router keeps the mapping if we never get to remove it.
+#[cfg(feature = "upnp")]
+const LEASE_SECS: u32 = 4 * 60 * 60;
+
+impl PortMapping {
+	/// Asks the router to forward `port` on its public address to the same
+	/// port on this computer.
+	///
+	/// # Errors
+	/// Fails if there's no `UPnP` router, it refuses, or this build was compiled
+	/// without the `upnp` feature.
+	#[cfg(feature = "upnp")]
+	pub async fn open(port: u16) -> Result<PortMapping, NatError> {
+		tokio::task::spawn_blocking(move || Self::open_blocking(port))
+			.await
+			.map_err(|e| NatError::Refused(e.to_string()))?
+	}
+
+	#[cfg(feature = "upnp")]
+	fn open_blocking(port: u16) -> Result<PortMapping, NatError> {
+		use igd_next::{search_gateway, PortMappingProtocol, SearchOptions};
+		use std::{
+			net::{IpAddr, SocketAddr, UdpSocket},
+			time::Duration,
+		};
+
+		let mut options = SearchOptions::default();
+		options.timeout = Some(Duration::from_secs(3));
+		let gateway = search_gateway(options).map_err(|e| NatError::NoGateway(e.to_string()))?;
+
+		// Find which of our addresses faces the router. Connecting a UDP
+		// socket doesn't send anything, it only picks a route.
+		let probe = UdpSocket::bind("0.0.0.0:0").map_err(|_| NatError::NoLocalAddress)?;
+		probe
+			.connect(gateway.addr)
+			.map_err(|_| NatError::NoLocalAddress)?;
+		let local = match probe.local_addr().map_err(|_| NatError::NoLocalAddress)? {
+			SocketAddr::V4(local) => SocketAddrV4::new(*local.ip(), port),
+			SocketAddr::V6(_) => return Err(NatError::NoLocalAddress),
+		};
+
+		let external_ip = match gateway
+			.get_external_ip()
+			.map_err(|e| NatError::Refused(e.to_string()))?
+		{
+			IpAddr::V4(ip) => ip,
+			IpAddr::V6(_) => return Err(NatError::Refused("public address is IPv6".into())),
+		};
+		gateway
+			.add_port(
+				PortMappingProtocol::TCP,
+				port,
+				local.into(),
+				LEASE_SECS,
+				"netbattleship",
+			)
+			.map_err(|e| NatError::Refused(e.to_string()))?;
+
+		Ok(PortMapping {
+			gateway,
+			external: SocketAddrV4::new(external_ip, port),
+		})
+	}
+
+	/// Asks the router to forward `port` on its public address to the same
+	/// port on this computer.
+	///
+	/// # Errors
+	/// Always fails, since this build was compiled without the `upnp` feature.
+	#[cfg(not(feature = "upnp"))]
+	#[allow(clippy::unused_async)]
+	pub async fn open(_port: u16) -> Result<PortMapping, NatError> {
+		Err(NatError::Unsupported)
+	}
+
+	/// The public address challengers can reach the host on.
+	#[must_use]
+	pub fn external(&self) -> SocketAddrV4 {
+		self.external
+	}
+}
+
+#[cfg(feature = "upnp")]
+impl Drop for PortMapping {
+	fn drop(&mut self) {
+		let _ = self
+			.gateway
+			.remove_port(igd_next::PortMappingProtocol::TCP, self.external.port());
+	}
+}
@@ -1,58 +1,387 @@
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use schemars::JsonSchema;
 use serde::de::DeserializeOwned;
 use serde::Deserialize;
 use serde::Serialize;
 use std::io::Read;
 use std::io::Write;
+use std::time::Duration;
+#[cfg(feature = "async")]
 use tokio::io::AsyncRead;
+#[cfg(feature = "async")]
 use tokio::io::AsyncReadExt;
+#[cfg(feature = "async")]
 use tokio::io::AsyncWrite;
+#[cfg(feature = "async")]
 use tokio::io::AsyncWriteExt;
+#[cfg(feature = "async")]
+use tokio::time::timeout;
 
-use crate::ship::Ship;
+use thiserror::Error;
 
+use crate::{
+	coord::Coord,
+	rules::Rules,
+	ship::{ShipKind, ShipNames},
+	sync::PublicState,
+};
+
+/// Encodes a value as a frame: a big-endian `u32` length followed by that
+/// many bytes of CBOR.
+fn frame<T: Serialize>(value: &T) -> Vec<u8> {
+	Codec::default().encode(value)
+}
+
+/// # Panics
+/// Panics if the value can't be serialized or the connection is closed.
 pub fn write_to<T: Serialize, W: Write>(value: &T, into: &mut W) {
-	let d = serde_cbor::to_vec(value).expect("bad ser");
-	into.write_all(&d).expect("bad write");
-	into.write_all(b"\n").expect("bad write");
+	into.write_all(&frame(value)).expect("bad write");
+}
+
+/// Sent by both sides before anything else, so that a browser or port
+/// scanner is turned away before any of its bytes are parsed as frames.
+pub const MAGIC: &[u8; 7] = b"NBSHIP\0";
+
+/// The largest frame accepted by default, so a hostile peer can't make us
+/// allocate whatever it likes. Real messages are a few hundred bytes at most.
+pub const MAX_FRAME_SIZE: u32 = 64 * 1024;
+
+/// How long the rest of a frame may take by default once its length has arrived.
+pub const FRAME_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// What a peer is allowed to send us.
+///
+/// There is no limit on how long a peer may wait before starting a frame,
+/// since players take their time, but a frame that has started must finish
+/// promptly.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Limits {
+	pub max_frame_size: u32,
+	pub frame_timeout: Duration,
 }
-pub async fn write_to_async<T: Serialize, W: AsyncWrite + AsyncWriteExt + Unpin>(
-	value: &T,
-	into: &mut W,
-) {
-	let d = serde_cbor::to_vec(value).expect("bad ser");
-	into.write_all(&d).await.expect("bad write");
-	into.write_all(b"\n").await.expect("bad write");
+
+impl Default for Limits {
+	fn default() -> Self {
+		Limits {
+			max_frame_size: MAX_FRAME_SIZE,
+			frame_timeout: FRAME_TIMEOUT,
+		}
+	}
 }
 
-/// # Panics
-/// Panics if the struct sent by the other player is not a valid `NetMsg` or the connection is closed.
-pub fn read_from<T: DeserializeOwned, R: Read>(from: &mut R) -> T {
-	let d: Vec<u8> = from.bytes().flatten().take_while(|c| *c != b'\n').collect();
-	serde_cbor::from_slice(&d).unwrap()
+#[derive(Error, Debug)]
+pub enum NetError {
+	#[error("the connection failed: {0}")]
+	Io(#[from] std::io::Error),
+	#[error("the other side sent something that isn't a valid message: {0}")]
+	Malformed(#[from] serde_cbor::Error),
+	#[error("the other side sent a {0} byte message, more than allowed")]
+	FrameTooLarge(u32),
+	#[error("the other side stalled in the middle of a message")]
+	TimedOut,
+	#[error("the other side sent a message that doesn't decompress: {0}")]
+	Decompress(std::io::Error),
 }
 
-/// # Panics
-/// Panics if the struct sent by the other player is not a valid `NetMsg` or the connection is closed.
+impl Limits {
+	/// Checks a frame's length prefix before anything is allocated for it.
+	fn frame_len(self, len: u32) -> Result<usize, NetError> {
+		if len > self.max_frame_size {
+			return Err(NetError::FrameTooLarge(len));
+		}
+		Ok(len as usize)
+	}
+}
+
+/// Ways a connection can compress its messages, from least to most
+/// preferred. Each side offers what it supports during the handshake, and
+/// both use the most preferred one they have in common.
+#[derive(
+	Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema,
+)]
+pub enum Compression {
+	#[default]
+	None,
+	/// Deflate, for messages big enough to be worth it.
+	Deflate,
+}
+
+impl Compression {
+	/// Everything this build can compress and decompress.
+	pub const SUPPORTED: &'static [Compression] = &[Compression::None, Compression::Deflate];
+
+	/// The most preferred compression both offers include. Every build
+	/// supports [`Compression::None`], so that's the fallback.
+	#[must_use]
+	pub fn agree(ours: &[Compression], theirs: &[Compression]) -> Compression {
+		ours.iter()
+			.filter(|c| theirs.contains(c))
+			.max()
+			.copied()
+			.unwrap_or_default()
+	}
+}
+
+/// Marks a compressed frame in the top bit of its length, on connections
+/// that agreed to compression.
+const COMPRESSED: u32 = 1 << 31;
+
+/// Messages smaller than this are sent as they are, since compressing them
+/// saves next to nothing.
+pub const COMPRESSION_THRESHOLD: usize = 128;
+
+/// How frames are written and read on a connection: the limits on what the
+/// other side may send, and the compression agreed in the handshake.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Codec {
+	pub limits: Limits,
+	pub compression: Compression,
+}
+
+impl Codec {
+	/// Encodes a value as a frame, compressing it if that was agreed and
+	/// makes it smaller.
+	///
+	/// # Panics
+	/// Panics if the value can't be serialized or is over 2 GiB.
+	#[must_use]
+	pub fn encode<T: Serialize>(&self, value: &T) -> Vec<u8> {
+		let mut d = serde_cbor::to_vec(value).expect("bad ser");
+		let mut flag = 0;
+		if self.compression == Compression::Deflate && d.len() >= COMPRESSION_THRESHOLD {
+			let mut encoder = DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+			encoder.write_all(&d).expect("deflating into memory");
+			let packed = encoder.finish().expect("deflating into memory");
+			if packed.len() < d.len() {
+				d = packed;
+				flag = COMPRESSED;
+			}
+		}
+		let len = u32::try_from(d.len())
+			.ok()
+			.filter(|len| len & COMPRESSED == 0)
+			.expect("message too large");
+		let mut frame = Vec::with_capacity(4 + d.len());
+		frame.extend_from_slice(&(len | flag).to_be_bytes());
+		frame.extend_from_slice(&d);
+		frame
+	}
+
+	/// Writes `value` as one frame.
+	///
+	/// # Errors
+	/// Fails if the connection is closed.
+	///
+	/// # Panics
+	/// Panics if the value can't be serialized.
+	#[cfg(feature = "async")]
+	pub async fn write<T: Serialize, W: AsyncWrite + AsyncWriteExt + Unpin>(
+		&self,
+		value: &T,
+		into: &mut W,
+	) -> std::io::Result<()> {
+		into.write_all(&self.encode(value)).await
+	}
+
+	/// Reads one frame.
+	///
+	/// # Errors
+	/// Fails if the connection is closed, or if the other side sends an
+	/// oversized frame, stalls partway through one, compresses one without
+	/// having agreed to, or sends one that doesn't decode as a `T`.
+	#[cfg(feature = "async")]
+	pub async fn read<T: DeserializeOwned, R: AsyncRead + AsyncReadExt + Unpin>(
+		&self,
+		from: &mut R,
+	) -> Result<T, NetError> {
+		let header = from.read_u32().await?;
+		let (len, compressed) = self.header(header);
+		let mut d = vec![0; self.limits.frame_len(len)?];
+		timeout(self.limits.frame_timeout, from.read_exact(&mut d))
+			.await
+			.map_err(|_| NetError::TimedOut)??;
+		if compressed {
+			d = self.inflate(&d)?;
+		}
+		Ok(serde_cbor::from_slice(&d)?)
+	}
+
+	/// Like [`Codec::write`], on a blocking writer.
+	///
+	/// # Errors
+	/// Fails if the connection is closed.
+	///
+	/// # Panics
+	/// Panics if the value can't be serialized.
+	pub fn write_blocking<T: Serialize, W: Write>(
+		&self,
+		value: &T,
+		into: &mut W,
+	) -> std::io::Result<()> {
+		into.write_all(&self.encode(value))
+	}
+
+	/// Like [`Codec::read`], on a blocking reader. Blocking reads can't time
+	/// out, so a stalled frame is only caught by the reader's own timeout.
+	///
+	/// # Errors
+	/// Fails if the connection is closed, or if the other side sends an
+	/// oversized frame, compresses one without having agreed to, or sends
+	/// one that doesn't decode as a `T`.
+	pub fn read_blocking<T: DeserializeOwned, R: Read>(&self, from: &mut R) -> Result<T, NetError> {
+		let mut header = [0; 4];
+		from.read_exact(&mut header)?;
+		let (len, compressed) = self.header(u32::from_be_bytes(header));
+		let mut d = vec![0; self.limits.frame_len(len)?];
+		from.read_exact(&mut d)?;
+		if compressed {
+			d = self.inflate(&d)?;
+		}
+		Ok(serde_cbor::from_slice(&d)?)
+	}
+
+	/// Splits a frame's length prefix into the length and whether the frame
+	/// is compressed. Without compression the top bit is just part of the
+	/// length, so such frames are refused as too large.
+	fn header(self, header: u32) -> (u32, bool) {
+		if self.compression == Compression::None {
+			(header, false)
+		} else {
+			(header & !COMPRESSED, header & COMPRESSED != 0)
+		}
+	}
+
+	/// Decompresses a frame body, refusing to grow it past the frame size limit.
+	fn inflate(self, packed: &[u8]) -> Result<Vec<u8>, NetError> {
+		let limit = self.limits.max_frame_size;
+		let mut d = Vec::new();
+		DeflateDecoder::new(packed)
+			.take(u64::from(limit) + 1)
+			.read_to_end(&mut d)
+			.map_err(NetError::Decompress)?;
+		if d.len() > limit as usize {
+			return Err(NetError::FrameTooLarge(limit.saturating_add(1)));
+		}
+		Ok(d)
+	}
+}
+
+/// Reads a frame with the default [`Limits`]. Blocking reads can't time out,
+/// so only the size limit applies.
+///
+/// # Errors
+/// Fails if the connection is closed, or if the other side sends an
+/// oversized frame or one that doesn't decode as a `T`.
+pub fn read_from<T: DeserializeOwned, R: Read>(from: &mut R) -> Result<T, NetError> {
+	Codec::default().read_blocking(from)
+}
+
+/// Reads a frame with the default [`Limits`].
+///
+/// # Errors
+/// See [`read_from_async_with`].
+#[cfg(feature = "async")]
 pub async fn read_from_async<T: DeserializeOwned, R: AsyncRead + AsyncReadExt + Unpin>(
 	from: &mut R,
-) -> T {
-	let mut d = vec![];
-	loop {
-		let b = from.read_u8().await.expect("bad read");
-		if b == b'\n' {
-			break;
-		}
-		d.push(b);
+) -> Result<T, NetError> {
+	read_from_async_with(from, Limits::default()).await
+}
+
+/// # Errors
+/// Fails if the connection is closed, or if the other side sends an
+/// oversized frame, stalls partway through one, or sends one that doesn't
+/// decode as a `T`.
+#[cfg(feature = "async")]
+pub async fn read_from_async_with<T: DeserializeOwned, R: AsyncRead + AsyncReadExt + Unpin>(
+	from: &mut R,
+	limits: Limits,
+) -> Result<T, NetError> {
+	Codec {
+		limits,
+		compression: Compression::None,
 	}
-	serde_cbor::from_slice(&d).unwrap()
+	.read(from)
+	.await
 }
 
-#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[derive(Clone, Serialize, Deserialize, JsonSchema, Debug, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum Msg {
 	Hello(u64),
-	NotFinished,
-	Finished,
-	DidHit(bool),
-	Fire(u8, u8),
-	Sunk(Ship),
+	/// Ends a turn with the fingerprint of the game the sender sees, from
+	/// [`PublicState::fingerprint`], so the shooter can check theirs.
+	NotFinished(u64),
+	Finished(u64),
+	/// Whether the shot taken on this turn number hit.
+	DidHit(bool, u32),
+	/// A shot, taken on this turn number. The answers to it echo the number
+	/// back, and a side on another turn answers [`Msg::WrongTurn`] instead.
+	Fire(Coord, u32),
+	/// What the shot taken on this turn number sank, if anything.
+	Sunk(
+		#[serde(deserialize_with = "crate::ship::legacy")] Option<ShipKind>,
+		u32,
+	),
+	Rules(Rules),
+	/// A radar sweep of the 3x3 square around a cell.
+	Scan(Coord),
+	/// How many ship cells the radar sweep found.
+	ScanResult(u8),
+	/// The sender moved their submarine instead of firing. Where is secret,
+	/// but the fingerprint of the game they see afterwards isn't.
+	Move(u64),
+	/// The sender is picking a saved game back up at this turn number.
+	Resume(u32),
+	/// The compression the sender supports, offered during the handshake.
+	Compression(Vec<Compression>),
+	/// A keepalive, answered with [`Msg::Pong`] whatever else is going on.
+	Ping,
+	Pong,
+	/// Asks the other side to compare the sender's view of the game with
+	/// theirs, answered with [`Msg::SyncState`].
+	SyncRequest(PublicState),
+	SyncState(PublicState),
+	/// A line of chat for the other player, passed on whatever else is
+	/// going on.
+	Chat(String),
+	/// The sender is leaving the game and about to hang up.
+	Goodbye,
+	/// The sender gives up, losing the game, and is about to hang up.
+	Resign,
+	/// The sender has placed their whole fleet, sent as soon as they have,
+	/// whatever else is going on. Nobody fires until both sides have said
+	/// they're ready.
+	Ready,
+	/// How many of their ships the sender has placed so far, sent after each
+	/// one and passed on whatever else is going on, so the other side isn't
+	/// left waiting in silence.
+	PlacingProgress(u8),
+	/// The sender is choosing where to shoot, sent every so often while they
+	/// do something on their turn and passed on whatever else is going on.
+	Aiming,
+	/// The sender ignored a shot because they're on this turn number, not the
+	/// one it was taken on.
+	WrongTurn(u32),
+	/// How the sender introduces themselves, sent once during the handshake.
+	Profile(Profile),
+	/// Random bytes for the other side to prove they know the sender's
+	/// password with, or none if the sender doesn't have one.
+	Challenge(Option<[u8; 16]>),
+	/// The answer to a [`Msg::Challenge`]: an HMAC-SHA256 under the password
+	/// of which side is answering and both sides' challenges. The guest
+	/// answers first, and the host only once the guest is admitted.
+	Response([u8; 32]),
+	/// Whether the [`Msg::Response`] proved the password, sent by the side
+	/// that asked for it.
+	Admitted(bool),
+}
+
+/// What a player would like to be called, and what they call their ships.
+#[derive(Clone, Serialize, Deserialize, JsonSchema, Debug, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct Profile {
+	pub name: Option<String>,
+	/// Ships they've renamed; see [`crate::skin::Skin`].
+	pub ships: ShipNames,
 }
@@ -0,0 +1,33 @@
+use std::io::{stderr, IsTerminal, Write};
+
+/// Lets the player know it's their turn, in case they looked away while the
+/// enemy was aiming. Rings the terminal bell, and with the `notify` feature
+/// also shows a desktop notification.
+pub fn your_turn() {
+	bell();
+	#[cfg(feature = "notify")]
+	desktop("Your turn", "The enemy has fired. It's your turn to shoot.");
+}
+
+/// Rings the terminal bell, if there's a terminal to ring.
+pub fn bell() {
+	let mut out = stderr();
+	if out.is_terminal() {
+		let _ = out.write_all(b"\x07");
+		let _ = out.flush();
+	}
+}
+
+/// Shows a desktop notification. Talking to the notification daemon can
+/// block, so it's done on its own thread, and failures are ignored.
+#[cfg(feature = "notify")]
+pub fn desktop(summary: &str, body: &str) {
+	let mut notification = notify_rust::Notification::new();
+	notification
+		.appname("netbattleship")
+		.summary(summary)
+		.body(body);
+	std::thread::spawn(move || {
+		let _ = notification.show();
+	});
+}
@@ -0,0 +1,393 @@
+//! Play by mail: a game played a turn at a time by sending files, by email
+//! or any messenger, instead of over a connection.
+//!
+//! Each [`Letter`] answers the shot in the last one and fires the next, and
+//! is signed with an HMAC of it under the players' password, so a letter
+//! that was changed on the way, or belongs to another game, is turned away.
+//! Letters are numbered, the first player's even and the second's odd, so
+//! one sent twice or skipped is caught too. Each side keeps its own
+//! [`Correspondence`] on disk in between, since only it knows where its
+//! fleet is.
+
+use std::{
+	fs, io,
+	path::{Path, PathBuf},
+};
+
+use rand::random;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+	board::CellState,
+	coord::{Coord, Notation},
+	flow::{hmac, GameFlowError},
+	history::Move,
+	protocol::StateMachine,
+	rules::Rules,
+	ship::ShipKind,
+	Game, Phase,
+};
+
+/// The extension letters are written with.
+pub const EXTENSION: &str = "nbm";
+
+/// What a shot did, as the player shot at tells it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Answer {
+	pub hit: bool,
+	/// The ship it sank, if any.
+	pub sunk: Option<ShipKind>,
+}
+
+/// One player's turn.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Turn {
+	/// Which game it's in, picked at random by whoever started it.
+	pub game: u64,
+	/// How many letters came before it in the game.
+	pub number: u32,
+	/// The rules of the game, in the first letter only.
+	pub rules: Option<Rules>,
+	/// What the shot in the last letter did.
+	pub answer: Option<Answer>,
+	/// Where this player fires, unless the game is over.
+	pub shot: Option<Coord>,
+}
+
+/// A turn signed with the players' password, as it's sent.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Letter {
+	pub turn: Turn,
+	signature: [u8; 32],
+}
+
+#[derive(Error, Debug)]
+pub enum PbmError {
+	#[error("there's no config directory on this system")]
+	NoConfigDir,
+	#[error("couldn't access the file: {0}")]
+	Io(#[from] io::Error),
+	#[error("couldn't read the file: {0}")]
+	Format(#[from] serde_cbor::Error),
+	#[error("the letter was changed after it was written, or the passwords differ")]
+	BadSignature,
+	#[error("that letter is from another game")]
+	WrongGame,
+	#[error("there's no game here for that letter; was the first one applied?")]
+	UnknownGame,
+	#[error("expected letter {expected} of the game, but this is letter {got}")]
+	OutOfOrder { expected: u32, got: u32 },
+	#[error("the letter doesn't answer our shot, or answers one we didn't fire")]
+	Unanswered,
+	#[error("the shot at {} isn't one that can be fired", .0.written(Notation::Numeric))]
+	BadShot(Coord),
+	#[error("it isn't their turn")]
+	NotTheirTurn,
+	#[error("it's their turn to write")]
+	Waiting,
+	#[error("it's our turn, so the letter needs a shot")]
+	NoShot,
+	#[error("the game is over")]
+	Over,
+	#[error("salvo, blind, radar and moving submarines can't be played by mail")]
+	Unsupported,
+	#[error(transparent)]
+	Flow(#[from] GameFlowError),
+}
+
+impl Letter {
+	/// Signs `turn` with `password`.
+	#[must_use]
+	pub fn sign(turn: Turn, password: &str) -> Letter {
+		let signature = signature(&turn, password);
+		Letter { turn, signature }
+	}
+
+	/// The turn in the letter, if it was signed with `password`.
+	///
+	/// # Errors
+	/// Fails if the signature doesn't match.
+	pub fn open(self, password: &str) -> Result<Turn, PbmError> {
+		let expected = signature(&self.turn, password);
+		// Takes as long however early they differ
+		let diff = expected
+			.iter()
+			.zip(&self.signature)
+			.fold(0, |diff, (a, b)| diff | (a ^ b));
+		if diff == 0 {
+			Ok(self.turn)
+		} else {
+			Err(PbmError::BadSignature)
+		}
+	}
+
+	/// Reads a letter from a file.
+	///
+	/// # Errors
+	/// Fails if the file can't be read or isn't a letter.
+	pub fn read(path: &Path) -> Result<Letter, PbmError> {
+		Ok(serde_cbor::from_slice(&fs::read(path)?)?)
+	}
+
+	/// Writes the letter to a file, to be sent.
+	///
+	/// # Errors
+	/// Fails if the file can't be written.
+	pub fn write(&self, path: &Path) -> Result<(), PbmError> {
+		fs::write(path, serde_cbor::to_vec(self)?)?;
+		Ok(())
+	}
+
+	/// What to call the file for this letter, like `00c0ffee00c0ffee-3.nbm`.
+	#[must_use]
+	pub fn file_name(&self) -> String {
+		format!("{:016x}-{}.{EXTENSION}", self.turn.game, self.turn.number)
+	}
+}
+
+/// An HMAC of `turn` under `password`.
+fn signature(turn: &Turn, password: &str) -> [u8; 32] {
+	let turn = serde_cbor::to_vec(turn).expect("a turn is always written");
+	hmac(password.as_bytes(), &[&turn])
+}
+
+/// One side of a game played by mail, kept between letters.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Correspondence {
+	pub id: u64,
+	/// Our view of the game, with our fleet and what we know of theirs.
+	pub game: Game,
+	/// The number of the next letter, whoever writes it.
+	pub next: u32,
+	/// Where we fired last, until we hear what it did.
+	aimed: Option<Coord>,
+	/// What their last shot did, to tell them in our next letter.
+	answer: Option<Answer>,
+}
+
+impl Correspondence {
+	/// Starts a game by `rules` with our fleet laid out at random. We fire
+	/// first, so the first letter is ours.
+	///
+	/// # Errors
+	/// Fails if the rules are impossible or can't be played by mail, or the
+	/// fleet doesn't fit.
+	pub fn start(rules: Rules) -> Result<Correspondence, PbmError> {
+		Self::new(random(), rules, true)
+	}
+
+	/// Joins the game the first letter of which is `turn`, with our fleet
+	/// laid out at random, and reads it.
+	///
+	/// # Errors
+	/// Fails if it isn't the first letter of a game that can be played by
+	/// mail, by rules that make a game, or if the letter can't be read; see [`Correspondence::read`].
+	pub fn accept(turn: &Turn) -> Result<Correspondence, PbmError> {
+		let (0, Some(rules)) = (turn.number, &turn.rules) else {
+			return Err(PbmError::UnknownGame);
+		};
+		let mut correspondence = Self::new(turn.game, rules.clone(), false)?;
+		correspondence.read(turn)?;
+		Ok(correspondence)
+	}
+
+	fn new(id: u64, rules: Rules, first: bool) -> Result<Correspondence, PbmError> {
+		rules.validate().map_err(GameFlowError::from)?;
+		if rules.salvo || rules.blind || rules.radar || rules.submarine_moves {
+			return Err(PbmError::Unsupported);
+		}
+		let mut machine = StateMachine::new(Game::new(rules, first));
+		machine.place_remaining()?;
+		Ok(Correspondence {
+			id,
+			game: Game::clone(&machine),
+			next: 0,
+			aimed: None,
+			answer: None,
+		})
+	}
+
+	/// Whether we went first.
+	#[must_use]
+	pub fn first(&self) -> bool {
+		self.game.you
+	}
+
+	/// Whether it's our turn to write a letter: we owe an answer, or a shot.
+	#[must_use]
+	pub fn to_write(&self) -> bool {
+		self.answer.is_some() || self.to_fire()
+	}
+
+	/// Whether it's our turn to fire.
+	#[must_use]
+	pub fn to_fire(&self) -> bool {
+		matches!(self.game.phase, Phase::Ready | Phase::Playing)
+			&& self.game.turn == self.game.you
+			&& self.aimed.is_none()
+	}
+
+	/// Reads their letter: what our last shot did, then their shot, which is
+	/// answered in our next letter. Nothing changes if it's refused.
+	///
+	/// # Errors
+	/// Fails if the letter is from another game or out of order, or its
+	/// answer or shot doesn't fit the game.
+	pub fn read(&mut self, turn: &Turn) -> Result<(), PbmError> {
+		if turn.game != self.id {
+			return Err(PbmError::WrongGame);
+		}
+		if turn.number != self.next || turn.number.is_multiple_of(2) == self.first() {
+			return Err(PbmError::OutOfOrder {
+				expected: self.next,
+				got: turn.number,
+			});
+		}
+		let mut game = self.game.clone();
+		let you = game.you;
+		match (self.aimed, turn.answer) {
+			(Some(pos), Some(Answer { hit, sunk })) => {
+				game.apply(Move::Shot {
+					by: you,
+					pos,
+					hit: Some(hit),
+					sunk,
+				});
+				game.end_shot();
+			}
+			(None, None) => {}
+			_ => return Err(PbmError::Unanswered),
+		}
+		let mut answer = None;
+		if let Some(pos) = turn.shot {
+			if !matches!(game.phase, Phase::Ready | Phase::Playing) {
+				return Err(PbmError::Over);
+			}
+			if game.turn == you {
+				return Err(PbmError::NotTheirTurn);
+			}
+			let board = &game.board[usize::from(you)];
+			let cell = board.cell(pos);
+			if !board.is_water(pos) || !matches!(cell.state, CellState::Untouched) {
+				return Err(PbmError::BadShot(pos));
+			}
+			let reply = Answer {
+				hit: cell.ship.is_some(),
+				sunk: board.sinks(pos),
+			};
+			game.apply(Move::Shot {
+				by: !you,
+				pos,
+				hit: Some(reply.hit),
+				sunk: reply.sunk,
+			});
+			game.end_shot();
+			answer = Some(reply);
+		}
+		self.game = game;
+		self.next += 1;
+		self.aimed = None;
+		self.answer = answer;
+		Ok(())
+	}
+
+	/// Writes our next letter, answering their last shot and firing at `aim`
+	/// if it's our turn to.
+	///
+	/// # Errors
+	/// Fails if there's nothing to write, or we're to fire but `aim` is
+	/// missing or can't be fired at.
+	pub fn write(&mut self, aim: Option<Coord>, password: &str) -> Result<Letter, PbmError> {
+		if !self.to_write() {
+			return Err(PbmError::Waiting);
+		}
+		let shot = if self.to_fire() {
+			let enemy = &self.game.board[usize::from(!self.game.you)];
+			match aim {
+				Some(pos)
+					if enemy.is_water(pos) && enemy.cell(pos).state == CellState::Untouched =>
+				{
+					Some(pos)
+				}
+				Some(pos) => return Err(PbmError::BadShot(pos)),
+				None => return Err(PbmError::NoShot),
+			}
+		} else {
+			None
+		};
+		let turn = Turn {
+			game: self.id,
+			number: self.next,
+			rules: (self.next == 0).then(|| self.game.rules.clone()),
+			answer: self.answer.take(),
+			shot,
+		};
+		self.next += 1;
+		self.aimed = shot;
+		Ok(Letter::sign(turn, password))
+	}
+
+	/// The directory games by mail are kept in, `netbattleship/pbm` under
+	/// the user's config directory.
+	#[must_use]
+	pub fn dir() -> Option<PathBuf> {
+		Some(dirs::config_dir()?.join("netbattleship").join("pbm"))
+	}
+
+	/// Where our side of game `id` is kept. Both sides of a game can be
+	/// played on one computer, so whether we went first is part of it.
+	fn path(id: u64, first: bool) -> Result<PathBuf, PbmError> {
+		let side = if first { "first" } else { "second" };
+		Ok(Self::dir()
+			.ok_or(PbmError::NoConfigDir)?
+			.join(format!("{id:016x}-{side}.cbor")))
+	}
+
+	/// Reads the letter in the file at `path`, signed with `password`, into
+	/// our side of its game, joining the game if it's the first letter.
+	/// Returns our side, ready for our reply, and their turn.
+	///
+	/// # Errors
+	/// Fails if the letter can't be read or opened, or doesn't fit the game.
+	pub fn receive(path: &Path, password: &str) -> Result<(Correspondence, Turn), PbmError> {
+		let turn = Letter::read(path)?.open(password)?;
+		let correspondence = match Self::load(&turn)? {
+			Some(mut correspondence) => {
+				correspondence.read(&turn)?;
+				correspondence
+			}
+			None => Self::accept(&turn)?,
+		};
+		Ok((correspondence, turn))
+	}
+
+	/// Our side of the game `turn` is in, if we've played it before.
+	///
+	/// # Errors
+	/// Fails if we have but it can't be read.
+	pub fn load(turn: &Turn) -> Result<Option<Correspondence>, PbmError> {
+		// The first player writes the even letters, so this came from them
+		let first = !turn.number.is_multiple_of(2);
+		match fs::read(Self::path(turn.game, first)?) {
+			Ok(bytes) => Ok(Some(serde_cbor::from_slice(&bytes)?)),
+			Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+			Err(e) => Err(e.into()),
+		}
+	}
+
+	/// Keeps our side of the game until the next letter.
+	///
+	/// # Errors
+	/// Fails if it can't be written.
+	pub fn store(&self) -> Result<(), PbmError> {
+		let path = Self::path(self.id, self.first())?;
+		if let Some(dir) = path.parent() {
+			fs::create_dir_all(dir)?;
+		}
+		let partial = path.with_extension("partial");
+		fs::write(&partial, serde_cbor::to_vec(self)?)?;
+		fs::rename(partial, path)?;
+		Ok(())
+	}
+}
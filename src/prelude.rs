@@ -0,0 +1,23 @@
+//! The types most bots and interfaces need, to bring in all at once:
+//!
+//! ```
+//! use netbattleship::prelude::*;
+//!
+//! let game = Game::new(Rules::default(), true);
+//! assert_eq!(game.board[0].size(), 10);
+//! ```
+
+#[cfg(feature = "async")]
+pub use crate::flow::{GameFlow, GameFlowBuilder};
+pub use crate::{
+	board::{Board, Cell, CellState},
+	bot::Engine,
+	coord::Coord,
+	event::{Event, Player},
+	flow::GameFlowError,
+	protocol::TurnResults,
+	render::BoardRenderer,
+	rules::Rules,
+	ship::ShipKind,
+	Game, Phase,
+};
@@ -0,0 +1,109 @@
+use discord_rich_presence::{activity::Activity, DiscordIpc, DiscordIpcClient};
+use tokio::{
+	sync::{broadcast::error::RecvError, watch},
+	task::spawn_blocking,
+};
+
+use crate::{flow::GameFlow, Game, Phase};
+
+/// The environment variable holding the ID of the Discord application to
+/// show the game as.
+pub const APPLICATION_ID_VAR: &str = "NETBATTLESHIP_DISCORD_ID";
+
+/// Keeps the player's Discord status up to date with a game, from a
+/// background task that wakes up whenever something happens.
+pub struct Presence {
+	enabled: watch::Sender<bool>,
+}
+
+impl Presence {
+	/// Starts reporting `game` as the Discord application `application_id`.
+	/// Nothing is shown until the presence is enabled. If Discord isn't
+	/// running, the status is quietly skipped and tried again later.
+	#[must_use]
+	pub fn start(game: &GameFlow, application_id: String, enabled: bool) -> Self {
+		let (sender, mut enabled) = watch::channel(enabled);
+		let mut events = game.events();
+		let state = game.state.clone();
+		tokio::spawn(async move {
+			let mut client = Some(DiscordIpcClient::new(application_id));
+			let mut connected = false;
+			loop {
+				let status = if *enabled.borrow_and_update() {
+					Some(status(&*state.read().await))
+				} else {
+					None
+				};
+				let Some(mut ipc) = client.take() else { break };
+				let result = spawn_blocking(move || {
+					let connected = show(&mut ipc, connected, status.as_deref());
+					(ipc, connected)
+				})
+				.await;
+				let Ok((ipc, now)) = result else { break };
+				client = Some(ipc);
+				connected = now;
+
+				tokio::select! {
+					event = events.recv() => {
+						if let Err(RecvError::Closed) = event {
+							break;
+						}
+					}
+					changed = enabled.changed() => {
+						if changed.is_err() {
+							break;
+						}
+					}
+				}
+			}
+			if let Some(mut ipc) = client {
+				if connected {
+					let _ = spawn_blocking(move || ipc.close()).await;
+				}
+			}
+		});
+		Presence { enabled: sender }
+	}
+
+	/// Shows or hides the status.
+	pub fn set_enabled(&self, enabled: bool) {
+		self.enabled.send_replace(enabled);
+	}
+}
+
+/// Sets or clears the status, connecting to Discord first if need be.
+/// Returns whether we're connected afterwards.
+fn show(client: &mut DiscordIpcClient, connected: bool, status: Option<&str>) -> bool {
+	if !connected && (status.is_none() || client.connect().is_err()) {
+		return false;
+	}
+	let result = match status {
+		Some(status) => client.set_activity(Activity::new().details(status)),
+		None => client.clear_activity(),
+	};
+	result.is_ok()
+}
+
+/// A line describing how the game is going, like "Turn 14 — winning 3 ships to 1".
+#[must_use]
+pub fn status(game: &Game) -> String {
+	match game.phase {
+		Phase::Connecting => "Waiting for a challenger".to_string(),
+		Phase::Placing(_) | Phase::Ready => "Placing ships".to_string(),
+		Phase::Playing => {
+			let turn = game.turn_number;
+			let ours = game.surviving(game.you);
+			let theirs = game.surviving(!game.you);
+			let standing = match ours.cmp(&theirs) {
+				std::cmp::Ordering::Greater => format!("winning {ours} ships to {theirs}"),
+				std::cmp::Ordering::Less => format!("losing {ours} ships to {theirs}"),
+				std::cmp::Ordering::Equal => format!("tied at {ours} ships each"),
+			};
+			format!("Turn {turn} \u{2014} {standing}")
+		}
+		Phase::Done(true) => "Won a game".to_string(),
+		Phase::Done(false) => "Lost a game".to_string(),
+		_ => "Playing".to_string(),
+	}
+}
@@ -0,0 +1,686 @@
+//! The rules of talking to the other player, without any talking.
+//!
+//! A [`StateMachine`] keeps one side's [`Game`] and knows what it's waiting
+//! to hear. Moves are made on it directly, messages from the other side are
+//! fed to [`StateMachine::handle`], and whatever it wants sent or announced
+//! in return is picked up with [`StateMachine::poll_transmit`] and
+//! [`StateMachine::poll_event`]. It never waits, reads or writes anything
+//! itself, so it can be driven synchronously; [`GameFlow`] drives it over a
+//! real connection.
+//!
+//! [`GameFlow`]: crate::flow::GameFlow
+
+use std::{
+	collections::VecDeque,
+	mem,
+	ops::{Deref, DerefMut},
+};
+
+use rand::seq::IndexedRandom;
+
+use crate::{
+	board::CellState,
+	coord::Coord,
+	event::{Event, Player},
+	flow::GameFlowError,
+	history::Move,
+	net::Msg,
+	ship::{ShipKind, ShipNames},
+	sync::PublicState,
+	Game, Phase,
+};
+
+/// One side of a game, as a state machine driven by moves and messages.
+///
+/// Derefs to the [`Game`] it keeps.
+#[allow(clippy::module_name_repetitions)]
+pub struct StateMachine {
+	game: Game,
+	waiting: Waiting,
+	/// Whether we've heard that the enemy placed their whole fleet.
+	enemy_placed: bool,
+	outbox: VecDeque<Msg>,
+	events: VecDeque<Event>,
+	/// What each player calls their ships, by their team.
+	names: [ShipNames; 2],
+}
+
+/// What we're waiting to hear from the other side.
+#[derive(Default)]
+enum Waiting {
+	/// Nothing; a message now is out of turn.
+	#[default]
+	Nothing,
+	/// The enemy to finish placing their fleet.
+	Fleet,
+	/// Whether our shot at `pos` hit.
+	Hit { pos: Coord },
+	/// Whether our shot at `pos` sank something. `hit` is `None` when the
+	/// blind rule keeps it from us.
+	Sunk { pos: Coord, hit: Option<bool> },
+	/// Whether our shot at `pos` won, and their fingerprint of the game.
+	Finished {
+		pos: Coord,
+		hit: Option<bool>,
+		sunk: Option<ShipKind>,
+	},
+	/// How many ship cells our radar sweep around `center` found.
+	Scan { center: Coord },
+	/// Their view of the game, to check against ours.
+	Sync { ours: Box<PublicState> },
+	/// Their turn, answering any radar sweeps and sync checks on the way.
+	Enemy,
+}
+
+/// An exchange with the other side that has finished.
+pub enum Done {
+	/// The enemy placed their whole fleet.
+	Placed,
+	/// A turn was taken, by us or by them.
+	Turn(TurnResults),
+	/// Our radar sweep found this many ship cells.
+	Scanned(u8),
+	/// We checked the game against theirs, fixing this many things.
+	Synced(usize),
+}
+
+#[derive(Clone, Debug)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct TurnResults {
+	pub aim: Coord,
+	pub hit: bool,
+	/// Which of our ships the enemy's shot hit. Our own shots only learn
+	/// what they sank, so this is `None` for them.
+	pub ship: Option<ShipKind>,
+	pub sunk: Option<ShipKind>,
+	/// What the sunk ship's owner calls it: its class, unless they renamed
+	/// it.
+	pub sunk_name: Option<String>,
+	pub won: bool,
+	/// Whether the blind rule kept the shooter from learning if the shot hit.
+	pub hidden: bool,
+	/// Whether the shooter moved their submarine instead of firing, in which
+	/// case the other fields besides `turn` mean nothing.
+	pub moved: bool,
+	/// The number of the turn the shot was taken in; see [`Game::turn_number`].
+	pub turn: u32,
+}
+
+/// Checks our fingerprint of the game at the end of `turn` against theirs.
+fn agree(turn: u32, ours: u64, theirs: u64) -> Result<(), GameFlowError> {
+	if ours == theirs {
+		Ok(())
+	} else {
+		Err(GameFlowError::Desync { turn, ours, theirs })
+	}
+}
+
+#[allow(clippy::missing_errors_doc)]
+impl StateMachine {
+	/// Takes over a new `game`, with nothing sent or heard yet. Ships can
+	/// be placed before there's anyone to talk to; the enemy is told once
+	/// the fleet is complete.
+	#[must_use]
+	pub fn new(game: Game) -> StateMachine {
+		StateMachine {
+			game,
+			waiting: Waiting::Nothing,
+			enemy_placed: false,
+			outbox: VecDeque::new(),
+			events: VecDeque::new(),
+			names: Default::default(),
+		}
+	}
+
+	/// Calls `player`'s ships by `names` in the results of turns; see
+	/// [`TurnResults::sunk_name`].
+	pub fn name_ships(&mut self, player: bool, names: ShipNames) {
+		self.names[usize::from(player)] = names;
+	}
+
+	/// Takes over a saved `game`. If we'd placed our fleet, the enemy is told
+	/// again, in case they missed it before the game was interrupted.
+	#[must_use]
+	pub fn resumed(game: Game) -> StateMachine {
+		let mut machine = StateMachine {
+			enemy_placed: matches!(game.phase, Phase::Playing | Phase::Done(_)),
+			..StateMachine::new(game)
+		};
+		match machine.phase {
+			Phase::Placing(_) => machine.send_progress(),
+			Phase::Ready | Phase::Playing => machine.send(Msg::Ready),
+			_ => {}
+		}
+		machine
+	}
+
+	/// Whether we've heard that the enemy placed their whole fleet.
+	#[must_use]
+	pub fn enemy_placed(&self) -> bool {
+		self.enemy_placed
+	}
+
+	/// The next message to send to the other side, if any.
+	pub fn poll_transmit(&mut self) -> Option<Msg> {
+		self.outbox.pop_front()
+	}
+
+	/// The next thing that happened in the game, if any.
+	pub fn poll_event(&mut self) -> Option<Event> {
+		self.events.pop_front()
+	}
+
+	/// Whether we're waiting to hear from the other side.
+	#[must_use]
+	pub fn is_waiting(&self) -> bool {
+		!matches!(self.waiting, Waiting::Nothing)
+	}
+
+	fn send(&mut self, msg: Msg) {
+		self.outbox.push_back(msg);
+	}
+
+	fn emit(&mut self, event: Event) {
+		self.events.push_back(event);
+	}
+
+	/// Fails unless it's our turn to shoot and nothing else is going on.
+	fn our_shot(&self) -> Result<(), GameFlowError> {
+		if self.phase != Phase::Playing || self.turn != self.you || self.is_waiting() {
+			return Err(GameFlowError::OutOfOrder);
+		}
+		Ok(())
+	}
+
+	/// Ends the game if we took too long over our turn.
+	fn check_clock(&mut self) -> Result<(), GameFlowError> {
+		if self.turn_expired() {
+			self.stop_clock();
+			self.phase = Phase::Done(false);
+			self.emit(Event::Done { won: false });
+			return Err(GameFlowError::TimedOut);
+		}
+		Ok(())
+	}
+
+	/// Places the ship due next on our board.
+	pub fn place(&mut self, ship: ShipKind, pos: Coord, v: bool) -> Result<(), GameFlowError> {
+		match self.phase {
+			Phase::Placing(s) if s == ship => {}
+			_ => return Err(GameFlowError::OutOfOrder),
+		}
+		if !self.apply(Move::Place {
+			ship,
+			pos,
+			vertical: v,
+		}) {
+			return Err(GameFlowError::InvalidPlacement);
+		}
+		self.emit(Event::Placed {
+			ship,
+			pos,
+			vertical: v,
+		});
+		self.send_progress();
+		if let Phase::Placing(ship) = self.phase {
+			self.emit(Event::Placing { ship });
+		} else {
+			self.send(Msg::Ready);
+			if self.enemy_placed {
+				self.start();
+			}
+		}
+		Ok(())
+	}
+
+	/// Tells the enemy how many of our ships are placed, if any are.
+	fn send_progress(&mut self) {
+		let board = &self.board[usize::from(self.you)];
+		let placed = self
+			.rules
+			.fleet
+			.iter()
+			.filter(|&&ship| board.span(ship).is_some())
+			.count();
+		if placed > 0 {
+			self.send(Msg::PlacingProgress(
+				u8::try_from(placed).unwrap_or(u8::MAX),
+			));
+		}
+	}
+
+	/// Starts the shooting, once both fleets are out.
+	fn start(&mut self) {
+		self.phase = Phase::Playing;
+		self.start_clock();
+		self.emit(Event::Playing);
+	}
+
+	/// Places every ship still waiting to be placed somewhere random,
+	/// returning where each one went.
+	pub fn place_remaining(&mut self) -> Result<Vec<(ShipKind, Coord, bool)>, GameFlowError> {
+		let mut placed = Vec::new();
+		while let Phase::Placing(ship) = self.phase {
+			let board = &self.board[usize::from(self.you)];
+			let spots = (0..board.size())
+				.flat_map(|y| (0..board.size()).map(move |x| Coord::new(x, y)))
+				.flat_map(|pos| [(pos, false), (pos, true)])
+				.filter(|&(pos, v)| ship.can_place(board, pos, v))
+				.collect::<Vec<_>>();
+			let &(pos, v) = spots
+				.choose(&mut rand::rng())
+				.ok_or(GameFlowError::InvalidPlacement)?;
+			self.place(ship, pos, v)?;
+			placed.push((ship, pos, v));
+		}
+		Ok(placed)
+	}
+
+	/// Starts waiting for the enemy to finish placing their fleet. Returns
+	/// `false` if they already have, and otherwise it's done once
+	/// [`StateMachine::handle`] has heard that they did.
+	pub fn wait_for_fleet(&mut self) -> Result<bool, GameFlowError> {
+		if self.is_waiting() {
+			return Err(GameFlowError::OutOfOrder);
+		}
+		if self.enemy_placed {
+			return Ok(false);
+		}
+		self.waiting = Waiting::Fleet;
+		Ok(true)
+	}
+
+	/// Fires at `pos` on the enemy's board. The turn is done once
+	/// [`StateMachine::handle`] has heard how it went, and the shot is only
+	/// reported as [`Event::Fired`] once the enemy has taken it, rather than
+	/// turned it away as out of turn.
+	pub fn fire(&mut self, pos: Coord) -> Result<(), GameFlowError> {
+		self.our_shot()?;
+		let board = &self.board[usize::from(!self.you)];
+		if !board.in_bounds(pos) {
+			return Err(GameFlowError::OutOfBounds(pos));
+		}
+		if !board.is_water(pos) {
+			return Err(GameFlowError::Land(pos));
+		}
+		self.check_clock()?;
+		let turn = self.turn_number;
+		self.send(Msg::Fire(pos, turn));
+		self.waiting = if self.rules.blind {
+			Waiting::Sunk { pos, hit: None }
+		} else {
+			Waiting::Hit { pos }
+		};
+		Ok(())
+	}
+
+	/// Moves our unhit submarine to `pos` instead of firing, which ends our
+	/// turn straight away. The enemy learns that it moved, but not where to.
+	pub fn move_submarine(&mut self, pos: Coord, v: bool) -> Result<(), GameFlowError> {
+		self.our_shot()?;
+		self.check_clock()?;
+		let you = self.you;
+		if !self.can_move_submarine(you) {
+			return Err(GameFlowError::CannotMove);
+		}
+		if !self.apply(Move::Submarine {
+			by: you,
+			to: Some((pos, v)),
+		}) {
+			return Err(GameFlowError::InvalidPlacement);
+		}
+		let fingerprint = self.public_state().fingerprint();
+		self.send(Msg::Move(fingerprint));
+		self.end_shot();
+		self.emit(Event::SubmarineMoved { by: Player::You });
+		Ok(())
+	}
+
+	/// Sweeps the 3x3 square of the enemy's board around `center` with
+	/// radar. Done once [`StateMachine::handle`] has heard the count.
+	pub fn scan(&mut self, center: Coord) -> Result<(), GameFlowError> {
+		self.our_shot()?;
+		if !self.board[usize::from(!self.you)].in_bounds(center) {
+			return Err(GameFlowError::OutOfBounds(center));
+		}
+		if !self.scan_available(self.you) {
+			return Err(GameFlowError::NoScanAvailable);
+		}
+		self.send(Msg::Scan(center));
+		self.waiting = Waiting::Scan { center };
+		Ok(())
+	}
+
+	/// Asks the enemy for their view of the game, to check against ours.
+	/// Done once [`StateMachine::handle`] has heard it.
+	pub fn verify_sync(&mut self) -> Result<(), GameFlowError> {
+		self.our_shot()?;
+		let ours = self.public_state();
+		self.send(Msg::SyncRequest(ours.clone()));
+		self.waiting = Waiting::Sync {
+			ours: Box::new(ours),
+		};
+		Ok(())
+	}
+
+	/// Starts waiting on the enemy's turn. Done once
+	/// [`StateMachine::handle`] has heard them fire or move.
+	pub fn wait_for_enemy(&mut self) -> Result<(), GameFlowError> {
+		if self.phase != Phase::Playing || self.turn == self.you || self.is_waiting() {
+			return Err(GameFlowError::OutOfOrder);
+		}
+		self.waiting = Waiting::Enemy;
+		Ok(())
+	}
+
+	/// The enemy ran out of time on their turn, so we've won.
+	pub fn time_out(&mut self) -> GameFlowError {
+		self.waiting = Waiting::Nothing;
+		self.stop_clock();
+		self.phase = Phase::Done(true);
+		self.emit(Event::Done { won: true });
+		GameFlowError::TimedOut
+	}
+
+	/// Gives up the game, which the enemy wins, telling them so. A game
+	/// that's already over is just left. Returns whether we resigned.
+	pub fn resign(&mut self) -> bool {
+		self.waiting = Waiting::Nothing;
+		let resigned = !matches!(self.phase, Phase::Done(_));
+		if resigned {
+			self.stop_clock();
+			self.phase = Phase::Done(false);
+			self.emit(Event::Done { won: false });
+		}
+		self.send(if resigned { Msg::Resign } else { Msg::Goodbye });
+		resigned
+	}
+
+	/// Takes in a message from the other side. Returns what finished, if
+	/// anything did; otherwise we're still waiting.
+	///
+	/// Changes are kept even when this fails, so a game that's gone out of
+	/// sync can still be looked at.
+	pub fn handle(&mut self, msg: Msg) -> Result<Option<Done>, GameFlowError> {
+		match (mem::take(&mut self.waiting), msg) {
+			(_, Msg::Goodbye) => Err(GameFlowError::Left),
+			(_, Msg::Resign) => {
+				self.stop_clock();
+				self.phase = Phase::Done(true);
+				self.emit(Event::Done { won: true });
+				Err(GameFlowError::Resigned)
+			}
+			(waiting, Msg::Ready) => {
+				if !self.enemy_placed {
+					self.enemy_placed = true;
+					self.emit(Event::EnemyPlaced);
+					if self.phase == Phase::Ready {
+						self.start();
+					}
+				}
+				if let Waiting::Fleet = waiting {
+					return Ok(Some(Done::Placed));
+				}
+				self.waiting = waiting;
+				Ok(None)
+			}
+			// Only a hint for the waiting player, with nothing to change
+			(waiting, Msg::Aiming) => {
+				self.waiting = waiting;
+				Ok(None)
+			}
+			(waiting, Msg::PlacingProgress(placed)) => {
+				let fleet = u8::try_from(self.rules.fleet.len()).unwrap_or(u8::MAX);
+				self.emit(Event::EnemyPlacing { placed, fleet });
+				self.waiting = waiting;
+				Ok(None)
+			}
+			(Waiting::Hit { .. } | Waiting::Sunk { .. }, Msg::WrongTurn(theirs)) => {
+				Err(GameFlowError::OutOfTurn(self.turn_number, theirs))
+			}
+			(Waiting::Hit { .. }, Msg::DidHit(_, turn) | Msg::Sunk(_, turn))
+			| (Waiting::Sunk { .. }, Msg::Sunk(_, turn))
+				if turn != self.turn_number =>
+			{
+				Err(GameFlowError::OutOfTurn(self.turn_number, turn))
+			}
+			(Waiting::Hit { pos }, Msg::DidHit(hit, _)) => {
+				self.emit(Event::Fired {
+					by: Player::You,
+					pos,
+				});
+				self.waiting = Waiting::Sunk {
+					pos,
+					hit: Some(hit),
+				};
+				Ok(None)
+			}
+			(Waiting::Sunk { pos, hit }, Msg::Sunk(sunk, _)) => {
+				// A blind shot has had no other answer to say it was taken
+				if hit.is_none() {
+					self.emit(Event::Fired {
+						by: Player::You,
+						pos,
+					});
+				}
+				self.waiting = Waiting::Finished { pos, hit, sunk };
+				Ok(None)
+			}
+			(Waiting::Finished { pos, hit, sunk }, Msg::Finished(theirs)) => {
+				self.shot_landed(pos, hit, sunk, true, theirs).map(Some)
+			}
+			(Waiting::Finished { pos, hit, sunk }, Msg::NotFinished(theirs)) => {
+				self.shot_landed(pos, hit, sunk, false, theirs).map(Some)
+			}
+			(Waiting::Scan { center }, Msg::ScanResult(count)) => {
+				let you = self.you;
+				self.apply(Move::Scan {
+					by: you,
+					center,
+					count,
+				});
+				self.emit(Event::Scanned { center, count });
+				Ok(Some(Done::Scanned(count)))
+			}
+			(Waiting::Sync { ours }, Msg::SyncState(theirs)) => {
+				Ok(Some(Done::Synced(self.resync(&ours, &theirs)?)))
+			}
+			(Waiting::Enemy, msg) => self.enemy_action(msg),
+			(_, m) => Err(GameFlowError::BadMessage(m)),
+		}
+	}
+
+	/// Records how our shot at `pos` went, once we've heard all of it.
+	fn shot_landed(
+		&mut self,
+		pos: Coord,
+		hit: Option<bool>,
+		sunk: Option<ShipKind>,
+		won: bool,
+		theirs: u64,
+	) -> Result<Done, GameFlowError> {
+		// Only a hit can sink, so even a blind shot is known to have hit then
+		let hit = hit.or(sunk.map(|_| true));
+		// Firing at a square that was already hit counts as a miss, but it
+		// stays hit, as it does for them
+		let you = self.you;
+		self.apply(Move::Shot {
+			by: you,
+			pos,
+			hit,
+			sunk,
+		});
+		// They know best whether that was the last of their fleet
+		if won {
+			self.phase = Phase::Done(true);
+		}
+		let ours = self.public_state().fingerprint();
+		let turn = self.turn_number;
+		self.end_shot();
+		self.emit(Event::Result {
+			by: Player::You,
+			pos,
+			hit,
+			ship: None,
+			sunk,
+		});
+		if won {
+			self.emit(Event::Done { won: true });
+		}
+		agree(turn, ours, theirs)?;
+		Ok(Done::Turn(TurnResults {
+			hit: hit == Some(true),
+			ship: None,
+			sunk,
+			sunk_name: sunk.map(|ship| ship.called(&self.names[usize::from(!self.you)])),
+			won,
+			aim: pos,
+			hidden: hit.is_none(),
+			moved: false,
+			turn,
+		}))
+	}
+
+	/// Takes in a message on the enemy's turn: their shot or submarine move,
+	/// which ends it, or a radar sweep or sync check to answer on the way.
+	fn enemy_action(&mut self, msg: Msg) -> Result<Option<Done>, GameFlowError> {
+		let you = self.you;
+		let turn = self.turn_number;
+		match msg {
+			Msg::Fire(_, theirs) if theirs != turn => {
+				self.send(Msg::WrongTurn(turn));
+				self.waiting = Waiting::Enemy;
+				Ok(None)
+			}
+			Msg::Fire(aim, _) if self.board[usize::from(you)].is_water(aim) => {
+				Ok(Some(Done::Turn(self.shot_at(aim))))
+			}
+			Msg::Move(theirs) if self.can_move_submarine(!you) => {
+				self.apply(Move::Submarine { by: !you, to: None });
+				let ours = self.public_state().fingerprint();
+				self.end_shot();
+				self.emit(Event::SubmarineMoved { by: Player::Enemy });
+				agree(turn, ours, theirs)?;
+				Ok(Some(Done::Turn(TurnResults {
+					aim: Coord::default(),
+					hit: false,
+					ship: None,
+					sunk: None,
+					sunk_name: None,
+					won: false,
+					hidden: false,
+					moved: true,
+					turn,
+				})))
+			}
+			Msg::Scan(center) if self.scan_available(!you) => {
+				let count = self.board[usize::from(you)].ship_cells_around(center);
+				self.apply(Move::Scan {
+					by: !you,
+					center,
+					count,
+				});
+				self.send(Msg::ScanResult(count));
+				self.waiting = Waiting::Enemy;
+				Ok(None)
+			}
+			Msg::SyncRequest(theirs) => {
+				let ours = self.public_state();
+				self.send(Msg::SyncState(ours.clone()));
+				self.resync(&ours, &theirs)?;
+				self.waiting = Waiting::Enemy;
+				Ok(None)
+			}
+			m => Err(GameFlowError::BadMessage(m)),
+		}
+	}
+
+	/// Works out how the enemy's shot at `aim` went and tells them.
+	fn shot_at(&mut self, aim: Coord) -> TurnResults {
+		let you = self.you;
+		let turn = self.turn_number;
+		self.emit(Event::Fired {
+			by: Player::Enemy,
+			pos: aim,
+		});
+		// Hitting a square that was already hit again counts as a miss
+		let cell = self.board[usize::from(you)].cell(aim);
+		let hit_ship = cell.ship.filter(|_| cell.state == CellState::Untouched);
+		if !self.rules.blind {
+			self.send(Msg::DidHit(hit_ship.is_some(), turn));
+		}
+		let sunk = self.board[usize::from(you)].sinks(aim);
+		self.apply(Move::Shot {
+			by: !you,
+			pos: aim,
+			hit: Some(hit_ship.is_some()),
+			sunk,
+		});
+		self.send(Msg::Sunk(sunk, turn));
+
+		let won = self.board[usize::from(you)].all_sunk();
+		let fingerprint = self.public_state().fingerprint();
+		self.send(if won {
+			Msg::Finished(fingerprint)
+		} else {
+			Msg::NotFinished(fingerprint)
+		});
+		if won {
+			self.phase = Phase::Done(false);
+		}
+		self.end_shot();
+		self.emit(Event::Result {
+			by: Player::Enemy,
+			pos: aim,
+			hit: Some(hit_ship.is_some()),
+			ship: hit_ship,
+			sunk,
+		});
+		if won {
+			self.emit(Event::Done { won: false });
+		}
+		TurnResults {
+			aim,
+			hit: hit_ship.is_some(),
+			ship: hit_ship,
+			sunk,
+			sunk_name: sunk.map(|ship| ship.called(&self.names[usize::from(you)])),
+			won,
+			hidden: false,
+			moved: false,
+			turn,
+		}
+	}
+
+	/// Fixes our copy of the enemy's side of the game to match theirs,
+	/// returning how many things we had to fix.
+	///
+	/// If we only disagree on the turn number, both sides move on to the
+	/// later one. Disagreeing on whose turn it is can't be fixed.
+	fn resync(&mut self, ours: &PublicState, theirs: &PublicState) -> Result<usize, GameFlowError> {
+		if ours.turn != theirs.turn {
+			return Err(GameFlowError::OutOfSync(
+				ours.turn_number,
+				theirs.turn_number,
+			));
+		}
+		let behind = usize::from(ours.turn_number < theirs.turn_number);
+		self.turn_number = ours.turn_number.max(theirs.turn_number);
+		let enemy = !self.you;
+		Ok(behind + self.repair(enemy, theirs))
+	}
+}
+
+impl Deref for StateMachine {
+	type Target = Game;
+
+	fn deref(&self) -> &Game {
+		&self.game
+	}
+}
+
+impl DerefMut for StateMachine {
+	fn deref_mut(&mut self) -> &mut Game {
+		&mut self.game
+	}
+}
@@ -0,0 +1,218 @@
+//! The daily challenge: the same hidden fleet for everyone on a given day,
+//! to sink with as few of a limited number of shots as possible.
+//!
+//! The fleet comes from a hash of the date alone, so players comparing scores
+//! are always shooting at the same ships.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use thiserror::Error;
+
+use crate::{
+	board::{Board, Cell, CellState},
+	coord::Coord,
+	history::Move,
+	rules::Rules,
+	ship::ShipKind,
+	Game, Phase,
+};
+
+/// How many shots a puzzle allows.
+pub const SHOTS: u32 = 50;
+
+/// How many shots make up a line of [`Puzzle::summary`].
+const SUMMARY_WIDTH: usize = 10;
+
+/// Where [FNV-1a](http://www.isthe.com/chongo/tech/comp/fnv/) hashes start.
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+#[derive(Error, Debug)]
+pub enum PuzzleError {
+	#[error("{0:?} is off the board")]
+	OutOfBounds(Coord),
+	#[error("the puzzle is already over")]
+	Over,
+}
+
+/// How one shot went.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Shot {
+	pub aim: Coord,
+	pub hit: bool,
+	pub sunk: Option<ShipKind>,
+}
+
+/// One day's puzzle, in progress.
+pub struct Puzzle {
+	/// The day it's for, as `YYYY-MM-DD`.
+	pub date: String,
+	/// What the player has found out, as the enemy's board of a game so it
+	/// can be drawn like one.
+	pub game: Game,
+	target: Board,
+	shots: Vec<Shot>,
+}
+
+impl Puzzle {
+	/// The puzzle for `date`. Any text gives a puzzle, but only `YYYY-MM-DD`
+	/// dates match everyone else's.
+	#[must_use]
+	pub fn new(date: &str) -> Puzzle {
+		let rules = Rules::default();
+		let mut rng = SplitMix(date.bytes().fold(FNV_OFFSET, |hash, byte| {
+			(hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+		}));
+		let mut target = Board::new(&rules);
+		for &ship in &rules.fleet {
+			let spots = (0..target.size())
+				.flat_map(|y| (0..target.size()).map(move |x| Coord::new(x, y)))
+				.flat_map(|pos| [(pos, false), (pos, true)])
+				.filter(|&(pos, v)| ship.can_place(&target, pos, v))
+				.collect::<Vec<_>>();
+			let pick = usize::try_from(rng.next() % spots.len() as u64).unwrap_or_default();
+			let (pos, v) = spots[pick];
+			ship.place(&mut target, pos, v);
+		}
+		let mut game = Game::new(rules, true);
+		game.phase = Phase::Playing;
+		Puzzle {
+			date: date.to_string(),
+			game,
+			target,
+			shots: Vec::new(),
+		}
+	}
+
+	/// The puzzle for today, going by UTC.
+	#[must_use]
+	pub fn today() -> Puzzle {
+		let now = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.unwrap_or_default()
+			.as_secs();
+		Puzzle::new(&day(now))
+	}
+
+	/// Fires at `aim`. Firing at a square that was already hit again counts
+	/// as a miss, and still uses up a shot.
+	///
+	/// # Errors
+	/// Fails if `aim` is off the board or the puzzle is over.
+	pub fn fire(&mut self, aim: Coord) -> Result<Shot, PuzzleError> {
+		if self.over() {
+			return Err(PuzzleError::Over);
+		}
+		if !self.target.in_bounds(aim) {
+			return Err(PuzzleError::OutOfBounds(aim));
+		}
+		let cell = self.target.cell(aim);
+		let hit = cell.ship.is_some() && cell.state == CellState::Untouched;
+		if hit {
+			self.target.board.insert(
+				aim,
+				Cell {
+					state: CellState::Hit,
+					..cell
+				},
+			);
+		}
+		let sunk = cell.ship.filter(|&ship| hit && !self.target.contains(ship));
+		self.game.apply(Move::Shot {
+			by: self.game.you,
+			pos: aim,
+			hit: Some(hit),
+			sunk,
+		});
+		let shot = Shot { aim, hit, sunk };
+		self.shots.push(shot);
+		if self.over() {
+			self.game.phase = Phase::Done(self.solved());
+		}
+		Ok(shot)
+	}
+
+	/// How many shots are left.
+	#[must_use]
+	pub fn shots_left(&self) -> u32 {
+		SHOTS.saturating_sub(u32::try_from(self.shots.len()).unwrap_or(u32::MAX))
+	}
+
+	/// Whether the whole fleet has been sunk.
+	#[must_use]
+	pub fn solved(&self) -> bool {
+		self.target.all_sunk()
+	}
+
+	/// Whether the fleet is sunk or the shots have run out.
+	#[must_use]
+	pub fn over(&self) -> bool {
+		self.solved() || self.shots_left() == 0
+	}
+
+	/// The shots left over once the fleet is sunk, or 0 if it never was.
+	#[must_use]
+	pub fn score(&self) -> u32 {
+		if self.solved() {
+			self.shots_left()
+		} else {
+			0
+		}
+	}
+
+	/// A summary to share that gives away how it went but not where the
+	/// ships were: every shot in order, 🟦 for a miss, 🟥 for a hit and 💥
+	/// for one that sank a ship.
+	#[must_use]
+	pub fn summary(&self) -> String {
+		let mut summary = format!(
+			"Netbattleship {} {}/{SHOTS}",
+			self.date,
+			if self.solved() {
+				self.score().to_string()
+			} else {
+				"X".to_string()
+			}
+		);
+		for line in self.shots.chunks(SUMMARY_WIDTH) {
+			summary.push('\n');
+			summary.extend(line.iter().map(|shot| match shot {
+				Shot { sunk: Some(_), .. } => '💥',
+				Shot { hit: true, .. } => '🟥',
+				Shot { .. } => '🟦',
+			}));
+		}
+		summary
+	}
+}
+
+/// The UTC day `secs` seconds after the Unix epoch falls on, as `YYYY-MM-DD`.
+#[must_use]
+pub fn day(secs: u64) -> String {
+	// Days to a civil date, after http://howardhinnant.github.io/date_algorithms.html
+	let days = secs / 86_400 + 719_468;
+	let era = days / 146_097;
+	let day_of_era = days % 146_097;
+	let year_of_era =
+		(day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+	let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+	let month = (5 * day_of_year + 2) / 153;
+	let day = day_of_year - (153 * month + 2) / 5 + 1;
+	let month = if month < 10 { month + 3 } else { month - 9 };
+	let year = era * 400 + year_of_era + u64::from(month <= 2);
+	format!("{year}-{month:02}-{day:02}")
+}
+
+/// A tiny random number generator that comes out the same everywhere, unlike
+/// `rand`'s, which may change between versions.
+struct SplitMix(u64);
+
+impl SplitMix {
+	fn next(&mut self) -> u64 {
+		self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+		let mut z = self.0;
+		z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+		z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+		z ^ (z >> 31)
+	}
+}
@@ -0,0 +1,272 @@
+use std::{fmt::Write, str::FromStr};
+
+use thiserror::Error;
+
+use crate::{
+	board::{Board, Cell, CellState, Topology},
+	coord::Coord,
+	skin::Skin,
+	Game, Phase,
+};
+
+pub mod image;
+
+/// A way of drawing a game's boards as text.
+pub trait BoardRenderer {
+	/// Draws `game`, with our own ships drawn as `skin` says.
+	fn render_skinned(&self, game: &Game, skin: &Skin) -> String;
+
+	fn render(&self, game: &Game) -> String {
+		self.render_skinned(game, &Skin::default())
+	}
+
+	/// A line explaining the symbols the boards are drawn with, to go under
+	/// them, if they need one.
+	fn legend(&self) -> Option<&'static str> {
+		None
+	}
+}
+
+/// What the letters on [`Ascii`] and [`Compact`] boards mean.
+const LETTERS: &str =
+	"X hit, ? miss, o their miss, other letters your ships (lowercase once hit), ~ unknown, # land";
+
+/// Both boards side by side in plain ASCII, one character per square.
+pub struct Ascii;
+
+/// Both boards side by side inside box-drawing borders, with emoji for
+/// ships and shots.
+pub struct Unicode;
+
+/// Only the board that matters right now: ours while placing ships or
+/// waiting for the enemy, theirs while aiming. Empty water is drawn as `.`
+/// so every square is read out by screen readers.
+pub struct Compact;
+
+/// Both boards as bare grids for braille displays: a title line each, then
+/// a line per row with one character per square, and no borders, labels or
+/// padding to read past. Empty water is drawn as `.` and land as `#`.
+pub struct Grid;
+
+/// Which renderer to use, as chosen on the command line.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Style {
+	#[default]
+	Ascii,
+	Unicode,
+	Compact,
+	Grid,
+}
+
+#[derive(Error, Debug)]
+#[error("unknown style {0:?}, expected ascii, unicode, compact or grid")]
+pub struct UnknownStyle(String);
+
+impl FromStr for Style {
+	type Err = UnknownStyle;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.to_lowercase().as_str() {
+			"ascii" => Ok(Style::Ascii),
+			"unicode" => Ok(Style::Unicode),
+			"compact" => Ok(Style::Compact),
+			"grid" => Ok(Style::Grid),
+			_ => Err(UnknownStyle(s.to_string())),
+		}
+	}
+}
+
+impl Style {
+	#[must_use]
+	pub fn renderer(self) -> &'static dyn BoardRenderer {
+		match self {
+			Style::Ascii => &Ascii,
+			Style::Unicode => &Unicode,
+			Style::Compact => &Compact,
+			Style::Grid => &Grid,
+		}
+	}
+}
+
+/// Draws one row of `board` a square at a time, with `land` for squares of
+/// land. On a hex board the squares are spaced out by `gap`, and each row
+/// starts half a square further right than the one above so it sits between
+/// its neighbours, then is padded to line up whatever follows.
+fn row(board: &Board, row: u8, gap: &str, land: &str, square: impl Fn(Cell) -> String) -> String {
+	let squares = (0..board.size()).map(|col| {
+		let pos = Coord::new(col, row);
+		if board.land().contains(&pos) {
+			land.to_string()
+		} else {
+			square(board.cell(pos))
+		}
+	});
+	if board.topology() == Topology::Square {
+		return squares.collect();
+	}
+	format!(
+		"{}{}{}",
+		" ".repeat(row.into()),
+		squares.collect::<Vec<_>>().join(gap),
+		" ".repeat(usize::from(board.size() - 1 - row))
+	)
+}
+
+/// How many columns a row drawn by [`row`] takes up, for squares `square`
+/// columns wide.
+fn width(board: &Board, square: usize, gap: &str) -> usize {
+	let size = usize::from(board.size());
+	match board.topology() {
+		Topology::Square => size * square,
+		Topology::Hex => size * square + (size - 1) * (gap.len() + 1),
+	}
+}
+
+/// Our board and the enemy's, in that order.
+fn boards(game: &Game) -> [&Board; 2] {
+	[
+		&game.board[usize::from(game.you)],
+		&game.board[usize::from(!game.you)],
+	]
+}
+
+impl BoardRenderer for Ascii {
+	fn render_skinned(&self, game: &Game, skin: &Skin) -> String {
+		let mut out = String::new();
+		let [ours, theirs] = boards(game);
+		let gap = if ours.topology() == Topology::Hex {
+			" "
+		} else {
+			""
+		};
+		let width = width(ours, 1, gap);
+		let columns = (0..ours.size())
+			.map(|c| c.to_string())
+			.collect::<Vec<_>>()
+			.join(gap);
+		let _ = writeln!(out, " |{:width$}|{:width$}|", "YOU", "THEM");
+		let _ = writeln!(out, " |{columns:width$}|{columns:width$}|");
+		for r in 0..ours.size() {
+			out.push(Coord::new(0, r).row());
+			out += "|";
+			out += &row(ours, r, gap, "#", |cell| {
+				skin.symbol(cell, true).to_string()
+			});
+			out += "|";
+			out += &row(theirs, r, gap, "#", |cell| cell.symbol(false).to_string());
+			out += "|\n";
+		}
+		out
+	}
+
+	fn legend(&self) -> Option<&'static str> {
+		Some(LETTERS)
+	}
+}
+
+impl Unicode {
+	/// The emoji for a square of land.
+	const LAND: &'static str = "🟫";
+
+	/// The emoji for a square. Each is two columns wide, so the column
+	/// headers are padded to match.
+	fn emoji(cell: Cell, own: bool) -> &'static str {
+		match cell.state {
+			CellState::Untouched if cell.ship.is_none() => "🌊",
+			CellState::Untouched => "🚢",
+			CellState::Hit if own => "🔥",
+			CellState::Hit => "💥",
+			CellState::Miss if own => "💦",
+			CellState::Miss => "⚪",
+			CellState::Unknown => "❔",
+		}
+	}
+}
+
+impl BoardRenderer for Unicode {
+	fn render_skinned(&self, game: &Game, _: &Skin) -> String {
+		let mut out = String::new();
+		let [ours, theirs] = boards(game);
+		let width = width(ours, 2, "");
+		let mut columns = String::new();
+		for c in 0..ours.size() {
+			let _ = write!(columns, "{c:<2}");
+		}
+		let border = "─".repeat(width);
+		let _ = writeln!(out, "  {:width$}   {:width$}", "YOU", "THEM");
+		let _ = writeln!(out, "  {columns:width$}   {columns:width$}");
+		let _ = writeln!(out, " ┌{border}┐ ┌{border}┐");
+		for r in 0..ours.size() {
+			let row_name = Coord::new(0, r).row();
+			let _ = write!(out, "{row_name}│");
+			out += &row(ours, r, "", Self::LAND, |cell| {
+				Self::emoji(cell, true).to_string()
+			});
+			let _ = write!(out, "│{row_name}│");
+			out += &row(theirs, r, "", Self::LAND, |cell| {
+				Self::emoji(cell, false).to_string()
+			});
+			out += "│\n";
+		}
+		let _ = writeln!(out, " └{border}┘ └{border}┘");
+		out
+	}
+
+	fn legend(&self) -> Option<&'static str> {
+		Some("💥 hit, ⚪ miss, 💦 their miss, 🚢 your ship, 🔥 your ship hit, ❔ unknown, 🟫 land")
+	}
+}
+
+impl BoardRenderer for Compact {
+	fn render_skinned(&self, game: &Game, skin: &Skin) -> String {
+		let own = matches!(game.phase, Phase::Placing(_)) || game.turn != game.you;
+		let board = boards(game)[usize::from(!own)];
+		let mut out = String::new();
+		let gap = if board.topology() == Topology::Hex {
+			" "
+		} else {
+			""
+		};
+		let columns = (0..board.size())
+			.map(|c| c.to_string())
+			.collect::<Vec<_>>()
+			.join(gap);
+		let _ = writeln!(out, "{}", if own { "YOU" } else { "THEM" });
+		let _ = writeln!(out, "  {columns}");
+		for r in 0..board.size() {
+			let _ = write!(out, "{} ", Coord::new(0, r).row());
+			let squares = row(board, r, gap, "#", |cell| match skin.symbol(cell, own) {
+				' ' => ".".to_string(),
+				c => c.to_string(),
+			});
+			out += squares.trim_end();
+			out += "\n";
+		}
+		out
+	}
+
+	fn legend(&self) -> Option<&'static str> {
+		Some(LETTERS)
+	}
+}
+
+impl BoardRenderer for Grid {
+	fn render_skinned(&self, game: &Game, skin: &Skin) -> String {
+		let mut out = String::new();
+		for (board, own) in boards(game).into_iter().zip([true, false]) {
+			let _ = writeln!(out, "{}", if own { "YOU" } else { "THEM" });
+			for r in 0..board.size() {
+				out.extend((0..board.size()).map(|c| {
+					let pos = Coord::new(c, r);
+					match skin.symbol(board.cell(pos), own) {
+						_ if board.land().contains(&pos) => '#',
+						' ' => '.',
+						c => c,
+					}
+				}));
+				out += "\n";
+			}
+		}
+		out
+	}
+}
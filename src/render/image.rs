@@ -0,0 +1,304 @@
+//! Pictures of both boards, every shot and how it landed, for sharing the
+//! battle once it's over. SVG keeps the row and column labels; PNG is drawn
+//! with [`tiny_skia`], which has no text, so it's just the boards.
+
+use std::{
+	fmt::Write as _,
+	fs, io,
+	path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+use tiny_skia::{Color, FillRule, Paint, PathBuilder, Pixmap, Rect, Transform};
+
+use crate::{
+	board::{Board, Cell, CellState, Topology},
+	coord::Coord,
+	Game,
+};
+
+use super::boards;
+
+/// The side of one square, in pixels.
+const CELL: f32 = 32.0;
+
+/// Room around the boards for their labels, and between them.
+const MARGIN: f32 = 40.0;
+
+type Rgb = (u8, u8, u8);
+
+const BACKGROUND: Rgb = (16, 24, 40);
+const WATER: Rgb = (30, 60, 110);
+const SHIP: Rgb = (140, 140, 150);
+const DAMAGED: Rgb = (200, 40, 40);
+const HIT: Rgb = (255, 90, 0);
+const MISS: Rgb = (235, 235, 235);
+const UNKNOWN: Rgb = (110, 110, 130);
+const LAND: Rgb = (110, 90, 50);
+const LABEL: Rgb = (220, 220, 220);
+
+/// What kind of picture to make.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+	Png,
+	Svg,
+}
+
+impl Format {
+	/// Picks the format matching a file's extension.
+	#[must_use]
+	pub fn of(path: &Path) -> Option<Self> {
+		let extension = path.extension()?.to_str()?.to_lowercase();
+		match extension.as_str() {
+			"png" => Some(Format::Png),
+			"svg" => Some(Format::Svg),
+			_ => None,
+		}
+	}
+}
+
+#[derive(Error, Debug)]
+pub enum ExportError {
+	#[error("can't tell what kind of picture {0:?} should be, name it .png or .svg")]
+	UnknownFormat(PathBuf),
+	#[error("the boards are too big to draw")]
+	TooBig,
+	#[error("couldn't encode the PNG: {0}")]
+	Png(String),
+	#[error(transparent)]
+	Io(#[from] io::Error),
+}
+
+/// Writes a picture of `game` to `path`, in the format its extension asks for.
+///
+/// # Errors
+/// Fails if the extension isn't `.png` or `.svg`, or the file can't be written.
+pub fn export(game: &Game, path: &Path) -> Result<(), ExportError> {
+	match Format::of(path) {
+		Some(Format::Png) => fs::write(path, png(game)?)?,
+		Some(Format::Svg) => fs::write(path, svg(game))?,
+		None => return Err(ExportError::UnknownFormat(path.to_path_buf())),
+	}
+	Ok(())
+}
+
+/// Something to draw, in pixels from the top left of the picture.
+enum Shape {
+	Square {
+		x: f32,
+		y: f32,
+		side: f32,
+		color: Rgb,
+	},
+	Dot {
+		x: f32,
+		y: f32,
+		radius: f32,
+		color: Rgb,
+	},
+	Label {
+		x: f32,
+		y: f32,
+		text: String,
+	},
+}
+
+/// How big the picture of `game` is.
+fn size(game: &Game) -> (f32, f32) {
+	let board = f32::from(game.rules.board_size) * CELL;
+	(MARGIN * 3.0 + width(game) * 2.0, MARGIN * 1.5 + board)
+}
+
+/// How wide each board is. Rows of a hex board each start half a square
+/// further right than the one above, so they're wider than they are tall.
+fn width(game: &Game) -> f32 {
+	let size = f32::from(game.rules.board_size);
+	match game.rules.topology {
+		Topology::Square => size * CELL,
+		Topology::Hex => (size * 1.5 - 0.5) * CELL,
+	}
+}
+
+/// Lays out both boards side by side, ours on the left.
+fn shapes(game: &Game) -> Vec<Shape> {
+	let mut shapes = Vec::new();
+	let board_width = width(game);
+	for (i, (board, title)) in boards(game).into_iter().zip(["You", "Them"]).enumerate() {
+		let own = i == 0;
+		let left = MARGIN + if own { 0.0 } else { board_width + MARGIN };
+		shapes.push(Shape::Label {
+			x: left + board_width / 2.0,
+			y: MARGIN / 2.0,
+			text: title.to_string(),
+		});
+		draw_board(&mut shapes, board, own, left);
+	}
+	shapes
+}
+
+/// Adds the squares of one board, with its labels, to `shapes`.
+fn draw_board(shapes: &mut Vec<Shape>, board: &Board, own: bool, left: f32) {
+	for i in 0..board.size() {
+		let offset = f32::from(i) * CELL + CELL / 2.0;
+		shapes.push(Shape::Label {
+			x: left + offset,
+			y: MARGIN - 6.0,
+			text: i.to_string(),
+		});
+		shapes.push(Shape::Label {
+			x: left - MARGIN / 3.0,
+			y: MARGIN + offset + 5.0,
+			text: Coord::new(0, i).row().to_string(),
+		});
+	}
+	for row in 0..board.size() {
+		for col in 0..board.size() {
+			let mut x = left + f32::from(col) * CELL;
+			if board.topology() == Topology::Hex {
+				x += f32::from(row) * CELL / 2.0;
+			}
+			let y = MARGIN + f32::from(row) * CELL;
+			let pos = Coord::new(col, row);
+			if board.land().contains(&pos) {
+				shapes.push(Shape::Square {
+					x: x + 1.0,
+					y: y + 1.0,
+					side: CELL - 2.0,
+					color: LAND,
+				});
+			} else {
+				draw_cell(shapes, board.cell(pos), own, x, y);
+			}
+		}
+	}
+}
+
+/// Adds one square, with whatever is on it, to `shapes`.
+fn draw_cell(shapes: &mut Vec<Shape>, cell: Cell, own: bool, x: f32, y: f32) {
+	shapes.push(Shape::Square {
+		x: x + 1.0,
+		y: y + 1.0,
+		side: CELL - 2.0,
+		color: WATER,
+	});
+	let inset = |color| Shape::Square {
+		x: x + 5.0,
+		y: y + 5.0,
+		side: CELL - 10.0,
+		color,
+	};
+	let dot = |color| Shape::Dot {
+		x: x + CELL / 2.0,
+		y: y + CELL / 2.0,
+		radius: CELL / 6.0,
+		color,
+	};
+	match cell.state {
+		CellState::Untouched if cell.ship.is_none() => {}
+		CellState::Untouched => shapes.push(inset(SHIP)),
+		CellState::Hit if own && cell.ship.is_some() => {
+			shapes.push(inset(DAMAGED));
+			shapes.push(dot(HIT));
+		}
+		CellState::Hit => shapes.push(inset(HIT)),
+		CellState::Miss => shapes.push(dot(MISS)),
+		CellState::Unknown => shapes.push(dot(UNKNOWN)),
+	}
+}
+
+/// Draws `game` as an SVG document.
+#[must_use]
+pub fn svg(game: &Game) -> String {
+	let (width, height) = size(game);
+	let mut out = String::new();
+	let _ = writeln!(
+		out,
+		r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+	);
+	let _ = writeln!(
+		out,
+		r#"<rect width="{width}" height="{height}" fill="{}"/>"#,
+		hex(BACKGROUND)
+	);
+	for shape in shapes(game) {
+		let _ = match shape {
+			Shape::Square { x, y, side, color } => writeln!(
+				out,
+				r#"<rect x="{x}" y="{y}" width="{side}" height="{side}" fill="{}"/>"#,
+				hex(color)
+			),
+			Shape::Dot {
+				x,
+				y,
+				radius,
+				color,
+			} => writeln!(
+				out,
+				r#"<circle cx="{x}" cy="{y}" r="{radius}" fill="{}"/>"#,
+				hex(color)
+			),
+			Shape::Label { x, y, text } => writeln!(
+				out,
+				r#"<text x="{x}" y="{y}" fill="{}" font-family="sans-serif" font-size="14" text-anchor="middle">{text}</text>"#,
+				hex(LABEL)
+			),
+		};
+	}
+	out += "</svg>\n";
+	out
+}
+
+/// Draws `game` as a PNG image.
+///
+/// # Errors
+/// Fails if the boards are too big to fit in an image, or it can't be encoded.
+pub fn png(game: &Game) -> Result<Vec<u8>, ExportError> {
+	let (width, height) = size(game);
+	#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+	let mut pixmap =
+		Pixmap::new(width.ceil() as u32, height.ceil() as u32).ok_or(ExportError::TooBig)?;
+	pixmap.fill(color(BACKGROUND));
+	for shape in shapes(game) {
+		let mut paint = Paint {
+			anti_alias: true,
+			..Paint::default()
+		};
+		match shape {
+			Shape::Square { x, y, side, color } => {
+				paint.set_color_rgba8(color.0, color.1, color.2, u8::MAX);
+				if let Some(rect) = Rect::from_xywh(x, y, side, side) {
+					pixmap.fill_rect(rect, &paint, Transform::identity(), None);
+				}
+			}
+			Shape::Dot {
+				x,
+				y,
+				radius,
+				color,
+			} => {
+				paint.set_color_rgba8(color.0, color.1, color.2, u8::MAX);
+				if let Some(circle) = PathBuilder::from_circle(x, y, radius) {
+					pixmap.fill_path(
+						&circle,
+						&paint,
+						FillRule::Winding,
+						Transform::identity(),
+						None,
+					);
+				}
+			}
+			Shape::Label { .. } => {}
+		}
+	}
+	pixmap
+		.encode_png()
+		.map_err(|e| ExportError::Png(e.to_string()))
+}
+
+fn color((r, g, b): Rgb) -> Color {
+	Color::from_rgba8(r, g, b, u8::MAX)
+}
+
+fn hex((r, g, b): Rgb) -> String {
+	format!("#{r:02x}{g:02x}{b:02x}")
+}
@@ -0,0 +1,316 @@
+//! Finished games written to `.nbr` files for sharing: a header saying who
+//! played, by which rules, when and who won, then every move as the player
+//! who saved it saw them. [`Replay::verify`] plays the moves back to check
+//! that they hang together, so organizers can trust a replay they're sent.
+
+use std::{
+	fs, io,
+	path::Path,
+	time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+	board::{Cell, CellState},
+	coord::Coord,
+	history::Move,
+	rules::{Rules, RulesError},
+	ship::ShipKind,
+	Game, Phase,
+};
+
+/// The extension replay files are saved with.
+pub const EXTENSION: &str = "nbr";
+
+/// The version of the replay format written by this build. Bumped whenever
+/// old replays can't be read the same way any more.
+pub const VERSION: u32 = 1;
+
+/// What a replay is of, without having to play it back.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Header {
+	pub version: u32,
+	pub rules: Rules,
+	/// What each player called themselves, indexed like [`Game::board`].
+	/// Either may be empty.
+	pub players: [String; 2],
+	/// Which player saved the replay. Only their ships are in it.
+	pub you: bool,
+	/// When the replay was saved, in seconds since the Unix epoch.
+	pub date: u64,
+	/// Who won, or `None` if the game never finished.
+	pub winner: Option<bool>,
+}
+
+/// A game saved to be watched again.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Replay {
+	pub header: Header,
+	pub moves: Vec<Move>,
+}
+
+#[derive(Error, Debug)]
+pub enum ReplayError {
+	#[error("couldn't access the replay: {0}")]
+	Io(#[from] io::Error),
+	#[error("couldn't read the replay: {0}")]
+	Format(#[from] serde_cbor::Error),
+	#[error("the replay is version {0}, but this build only reads version {VERSION}")]
+	Version(u32),
+	#[error("the replay's rules can't be played: {0}")]
+	Rules(#[from] RulesError),
+	#[error("move {0} places a ship out of order or where it doesn't fit")]
+	BadPlacement(usize),
+	#[error("move {0} is taken out of turn")]
+	OutOfTurn(usize),
+	#[error("move {0} is off the board, or fires at land")]
+	OutOfBounds(usize),
+	#[error("move {0} uses a radar sweep or submarine move that wasn't available")]
+	NotAllowed(usize),
+	#[error("move {0} has a result that doesn't match the board")]
+	WrongResult(usize),
+	#[error("move {0} sinks a ship that can't have sunk")]
+	WrongSunk(usize),
+	#[error("move {0} comes after the game was over")]
+	AfterEnd(usize),
+	#[error("the header says the game ended differently from how the moves play out")]
+	WrongOutcome,
+}
+
+impl Replay {
+	/// A replay of `game` so far, with the names the players go by.
+	#[must_use]
+	pub fn new(game: &Game, you: String, enemy: String) -> Self {
+		let players = if game.you { [enemy, you] } else { [you, enemy] };
+		let winner = match game.phase {
+			Phase::Done(won) => Some(if won { game.you } else { !game.you }),
+			_ => None,
+		};
+		Replay {
+			header: Header {
+				version: VERSION,
+				rules: game.rules.clone(),
+				players,
+				you: game.you,
+				date: SystemTime::now()
+					.duration_since(UNIX_EPOCH)
+					.map_or(0, |since| since.as_secs()),
+				winner,
+			},
+			moves: game.log.clone(),
+		}
+	}
+
+	/// Reads the replay in `path`.
+	///
+	/// # Errors
+	/// Fails if the file can't be read or isn't a replay.
+	pub fn load(path: &Path) -> Result<Replay, ReplayError> {
+		Ok(serde_cbor::from_slice(&fs::read(path)?)?)
+	}
+
+	/// Writes the replay to `path`. The file is written beside it first, so
+	/// anyone reading it while the game goes on never sees half of it.
+	///
+	/// # Errors
+	/// Fails if the file can't be written.
+	pub fn store(&self, path: &Path) -> Result<(), ReplayError> {
+		let partial = path.with_extension("partial");
+		fs::write(&partial, serde_cbor::to_vec(self)?)?;
+		fs::rename(partial, path)?;
+		Ok(())
+	}
+
+	/// Plays every move back by the rules in the header, checking that each
+	/// was allowed when it was made, that every result matches the ships of
+	/// the player who saved it, and that the game ends the way the header
+	/// says. The enemy's ships are never known, so shots at them can only be
+	/// checked against each other.
+	///
+	/// # Errors
+	/// Returns the first thing that doesn't add up.
+	pub fn verify(&self) -> Result<(), ReplayError> {
+		let header = &self.header;
+		if header.version != VERSION {
+			return Err(ReplayError::Version(header.version));
+		}
+		header.rules.validate()?;
+		let mut game = Game::new(header.rules.clone(), header.you);
+		let mut winner = None;
+		for (i, step) in self.moves.iter().enumerate() {
+			// Moves are counted from 1, like turns
+			let i = i + 1;
+			if winner.is_some() {
+				return Err(ReplayError::AfterEnd(i));
+			}
+			match *step {
+				Move::Place {
+					ship,
+					pos,
+					vertical,
+				} => place(&mut game, ship, pos, vertical).ok_or(ReplayError::BadPlacement(i))?,
+				Move::Shot { by, pos, hit, sunk } => {
+					check_turn(&game, by, i)?;
+					if !game.board[usize::from(!by)].is_water(pos) {
+						return Err(ReplayError::OutOfBounds(i));
+					}
+					if by == game.you {
+						our_shot(&mut game, pos, hit, sunk, i)?;
+					} else {
+						their_shot(&mut game, pos, hit, sunk, i)?;
+					}
+					if game.surviving(!by) == 0 {
+						winner = Some(by);
+					}
+					game.end_shot();
+				}
+				Move::Scan { by, center, count } => {
+					check_turn(&game, by, i)?;
+					if !game.board[usize::from(!by)].in_bounds(center) {
+						return Err(ReplayError::OutOfBounds(i));
+					}
+					if !game.scan_available(by) {
+						return Err(ReplayError::NotAllowed(i));
+					}
+					if by != game.you
+						&& game.board[usize::from(!by)].ship_cells_around(center) != count
+					{
+						return Err(ReplayError::WrongResult(i));
+					}
+					game.scans[usize::from(by)] += 1;
+				}
+				Move::Submarine { by, to } => {
+					check_turn(&game, by, i)?;
+					if !game.can_move_submarine(by) {
+						return Err(ReplayError::NotAllowed(i));
+					}
+					if by == game.you {
+						let (pos, vertical) = to.ok_or(ReplayError::BadPlacement(i))?;
+						move_submarine(&mut game, pos, vertical)
+							.ok_or(ReplayError::BadPlacement(i))?;
+					}
+					game.moved[usize::from(by)] = true;
+					game.end_shot();
+				}
+			}
+		}
+		match (winner, header.winner) {
+			(Some(winner), Some(claimed)) if winner == claimed => Ok(()),
+			(None, None) => Ok(()),
+			// Running out of time ends a game without a last shot
+			(None, Some(_))
+				if header.rules.turn_timer.is_some() && game.phase == Phase::Playing =>
+			{
+				Ok(())
+			}
+			_ => Err(ReplayError::WrongOutcome),
+		}
+	}
+}
+
+/// Places the next of our ships, moving on to the next one or to playing.
+fn place(game: &mut Game, ship: ShipKind, pos: Coord, vertical: bool) -> Option<()> {
+	if game.phase != Phase::Placing(ship)
+		|| !ship.place(&mut game.board[usize::from(game.you)], pos, vertical)
+	{
+		return None;
+	}
+	let fleet = &game.rules.fleet;
+	let next = fleet
+		.iter()
+		.position(|&s| s == ship)
+		.and_then(|i| fleet.get(i + 1));
+	game.phase = next.map_or(Phase::Playing, |&ship| Phase::Placing(ship));
+	Some(())
+}
+
+fn check_turn(game: &Game, by: bool, i: usize) -> Result<(), ReplayError> {
+	if game.phase == Phase::Playing && game.turn == by {
+		Ok(())
+	} else {
+		Err(ReplayError::OutOfTurn(i))
+	}
+}
+
+/// Checks our shot at the enemy against what we already knew of their board.
+fn our_shot(
+	game: &mut Game,
+	pos: Coord,
+	hit: Option<bool>,
+	sunk: Option<ShipKind>,
+	i: usize,
+) -> Result<(), ReplayError> {
+	let enemy = usize::from(!game.you);
+	let blind = game.rules.blind;
+	let before = game.board[enemy].cell(pos).state;
+	// Only sinking gives away a blind hit, and a square can't be hit twice
+	let result_known = hit.is_some() != (blind && sunk.is_none());
+	let hit_again = before == CellState::Hit && hit == Some(true);
+	let hit_a_miss = before == CellState::Miss && hit == Some(true);
+	if !result_known || hit_again || hit_a_miss {
+		return Err(ReplayError::WrongResult(i));
+	}
+	if let Some(ship) = sunk {
+		let sinkable = hit == Some(true)
+			&& game.rules.fleet.contains(&ship)
+			&& !game.sunk[enemy].contains(&ship);
+		if !sinkable {
+			return Err(ReplayError::WrongSunk(i));
+		}
+		game.sunk[enemy].push(ship);
+	}
+	if before != CellState::Hit {
+		game.board[enemy].board.insert(
+			pos,
+			Cell::shot(match hit {
+				Some(true) => CellState::Hit,
+				Some(false) => CellState::Miss,
+				None => CellState::Unknown,
+			}),
+		);
+	}
+	Ok(())
+}
+
+/// Checks the enemy's shot at us against where our ships were.
+fn their_shot(
+	game: &mut Game,
+	pos: Coord,
+	hit: Option<bool>,
+	sunk: Option<ShipKind>,
+	i: usize,
+) -> Result<(), ReplayError> {
+	let you = usize::from(game.you);
+	let board = &mut game.board[you];
+	let cell = board.cell(pos);
+	let hit_ship = cell.ship.filter(|_| cell.state == CellState::Untouched);
+	if hit != Some(hit_ship.is_some()) {
+		return Err(ReplayError::WrongResult(i));
+	}
+	if cell.ship.is_none() || hit_ship.is_some() {
+		let state = if hit_ship.is_some() {
+			CellState::Hit
+		} else {
+			CellState::Miss
+		};
+		board.board.insert(pos, Cell { state, ..cell });
+	}
+	if sunk != hit_ship.filter(|&ship| !board.contains(ship)) {
+		return Err(ReplayError::WrongSunk(i));
+	}
+	game.sunk[you].extend(sunk);
+	Ok(())
+}
+
+/// Moves our unhit submarine, as [`GameFlow::move_submarine`](crate::flow::GameFlow::move_submarine) does.
+fn move_submarine(game: &mut Game, pos: Coord, vertical: bool) -> Option<()> {
+	let board = &mut game.board[usize::from(game.you)];
+	board
+		.board
+		.retain(|_, cell| cell.ship != Some(ShipKind::Submarine));
+	ShipKind::Submarine
+		.place(board, pos, vertical)
+		.then_some(())
+}
@@ -0,0 +1,84 @@
+//! Trying out rules before playing by them: the files they're read from are
+//! watched, and whenever one changes the fleet laid out so far is carried
+//! over to the new rules, as much of it as still fits.
+//!
+//! Files are watched by checking when they were last modified, every
+//! [`POLL`], which is all an editor saving them needs.
+
+use std::{
+	fs,
+	path::{Path, PathBuf},
+	time::{Duration, SystemTime},
+};
+
+use crate::{board::Board, coord::Coord, rules::Rules, ship::ShipKind};
+
+/// How often watched files are checked for changes.
+pub const POLL: Duration = Duration::from_millis(500);
+
+/// Files to notice changes to.
+pub struct Watched {
+	files: Vec<(PathBuf, Option<SystemTime>)>,
+}
+
+impl Watched {
+	/// Watches `paths`, noticing changes from now on. Files that don't exist
+	/// yet count as changed once they do.
+	pub fn new(paths: impl IntoIterator<Item = PathBuf>) -> Watched {
+		Watched {
+			files: paths
+				.into_iter()
+				.map(|path| {
+					let modified = modified(&path);
+					(path, modified)
+				})
+				.collect(),
+		}
+	}
+
+	/// Whether any of the files changed since this last asked, or since
+	/// they started being watched.
+	pub fn changed(&mut self) -> bool {
+		let mut changed = false;
+		for (path, seen) in &mut self.files {
+			let modified = modified(path);
+			if modified != *seen {
+				*seen = modified;
+				changed = true;
+			}
+		}
+		changed
+	}
+
+	/// Waits until any of the files change.
+	#[cfg(feature = "async")]
+	pub async fn next_change(&mut self) {
+		while !self.changed() {
+			tokio::time::sleep(POLL).await;
+		}
+	}
+}
+
+/// When the file at `path` was last modified, or `None` if it can't be told.
+fn modified(path: &Path) -> Option<SystemTime> {
+	fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+/// The ships of `placed` to keep under `rules`: those still in the fleet,
+/// in the order it places them, up to the first that's missing or no longer
+/// fits on the new board. The rest have to be placed again.
+#[must_use]
+pub fn refit(placed: &[(ShipKind, Coord, bool)], rules: &Rules) -> Vec<(ShipKind, Coord, bool)> {
+	let mut board = Board::new(rules);
+	let mut kept = Vec::new();
+	for &ship in &rules.fleet {
+		let Some(&(_, pos, v)) = placed.iter().find(|&&(placed, ..)| placed == ship) else {
+			break;
+		};
+		if !ship.place(&mut board, pos, v) {
+			break;
+		}
+		kept.push((ship, pos, v));
+	}
+	kept
+}
@@ -0,0 +1,161 @@
+use std::{cmp::Reverse, fs, io, net::SocketAddr, path::PathBuf};
+#[cfg(feature = "async")]
+use std::{
+	panic,
+	sync::{Mutex, Once},
+};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+#[cfg(feature = "async")]
+use tokio::sync::watch;
+
+use crate::Game;
+#[cfg(feature = "async")]
+use crate::{flow::GameSnapshot, Phase};
+
+/// The slot games are autosaved to unless the player picks another.
+pub const AUTOSAVE: &str = "autosave";
+
+/// A game in progress written to disk, with what's needed to reconnect.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Save {
+	pub game: Game,
+	/// Where we were hosting, or the host we joined.
+	pub addr: SocketAddr,
+	/// Whether we were hosting.
+	pub serve: bool,
+}
+
+#[derive(Error, Debug)]
+pub enum SaveError {
+	#[error("there's no config directory on this system")]
+	NoConfigDir,
+	#[error("save slot names only contain letters, digits, dashes and underscores")]
+	BadSlot,
+	#[error("couldn't access the save: {0}")]
+	Io(#[from] io::Error),
+	#[error("couldn't read the save: {0}")]
+	Format(#[from] serde_cbor::Error),
+}
+
+impl Save {
+	/// The directory holding the save slots, `netbattleship/saves` under the
+	/// user's config directory.
+	#[must_use]
+	pub fn dir() -> Option<PathBuf> {
+		Some(dirs::config_dir()?.join("netbattleship").join("saves"))
+	}
+
+	fn path(slot: &str) -> Result<PathBuf, SaveError> {
+		let valid = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+		if slot.is_empty() || !slot.chars().all(valid) {
+			return Err(SaveError::BadSlot);
+		}
+		Ok(Self::dir()
+			.ok_or(SaveError::NoConfigDir)?
+			.join(format!("{slot}.cbor")))
+	}
+
+	/// Reads the game saved in `slot`.
+	///
+	/// # Errors
+	/// Fails if the slot is empty or its file can't be read.
+	pub fn load(slot: &str) -> Result<Save, SaveError> {
+		let bytes = fs::read(Self::path(slot)?)?;
+		Ok(serde_cbor::from_slice(&bytes)?)
+	}
+
+	/// Writes the game to `slot`, replacing whatever was there. The file is
+	/// written beside it first, so a crash halfway through can't break it.
+	///
+	/// # Errors
+	/// Fails if the file can't be written.
+	pub fn store(&self, slot: &str) -> Result<(), SaveError> {
+		let path = Self::path(slot)?;
+		if let Some(dir) = path.parent() {
+			fs::create_dir_all(dir)?;
+		}
+		let partial = path.with_extension("partial");
+		fs::write(&partial, serde_cbor::to_vec(self)?)?;
+		fs::rename(partial, path)?;
+		Ok(())
+	}
+
+	/// Empties `slot`, if there was anything in it.
+	///
+	/// # Errors
+	/// Fails if the file exists but can't be deleted.
+	pub fn remove(slot: &str) -> Result<(), SaveError> {
+		match fs::remove_file(Self::path(slot)?) {
+			Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+			_ => Ok(()),
+		}
+	}
+
+	/// The names of the slots holding games, most recently saved first.
+	///
+	/// # Errors
+	/// Fails if the save directory exists but can't be read.
+	pub fn slots() -> Result<Vec<String>, SaveError> {
+		let dir = Self::dir().ok_or(SaveError::NoConfigDir)?;
+		let entries = match fs::read_dir(dir) {
+			Ok(entries) => entries,
+			Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+			Err(e) => return Err(e.into()),
+		};
+		let mut slots = Vec::new();
+		for entry in entries {
+			let path = entry?.path();
+			if path.extension().is_some_and(|ext| ext == "cbor") {
+				if let Some(slot) = path.file_stem().and_then(|stem| stem.to_str()) {
+					slots.push((fs::metadata(&path)?.modified()?, slot.to_string()));
+				}
+			}
+		}
+		slots.sort_by_key(|(saved, _)| Reverse(*saved));
+		Ok(slots.into_iter().map(|(_, slot)| slot).collect())
+	}
+}
+
+/// What to write if the program panics: the slot, the game and how to
+/// reconnect to it.
+#[cfg(feature = "async")]
+type PanicSave = (String, watch::Receiver<GameSnapshot>, SocketAddr, bool);
+
+#[cfg(feature = "async")]
+static PANIC_SAVE: Mutex<Option<PanicSave>> = Mutex::new(None);
+
+/// Saves the latest state of the game to `slot` if the program panics,
+/// before the usual panic message. Replaces any game set up earlier.
+#[cfg(feature = "async")]
+pub fn save_on_panic(
+	slot: String,
+	snapshots: watch::Receiver<GameSnapshot>,
+	addr: SocketAddr,
+	serve: bool,
+) {
+	static HOOK: Once = Once::new();
+	HOOK.call_once(|| {
+		let previous = panic::take_hook();
+		panic::set_hook(Box::new(move |info| {
+			if let Ok(Some((slot, snapshots, addr, serve))) = PANIC_SAVE.try_lock().as_deref() {
+				let game = (**snapshots.borrow()).clone();
+				if !matches!(game.phase, Phase::Done(_)) {
+					let save = Save {
+						game,
+						addr: *addr,
+						serve: *serve,
+					};
+					if let Err(e) = save.store(slot) {
+						eprintln!("Couldn't save the game: {e}");
+					}
+				}
+			}
+			previous(info);
+		}));
+	});
+	if let Ok(mut saved) = PANIC_SAVE.lock() {
+		*saved = Some((slot, snapshots, addr, serve));
+	}
+}
@@ -0,0 +1,72 @@
+//! How a player's fleet looks: renamed ships, and the letters and colours
+//! their own are drawn in. Set in the `[skin]` table of the config file:
+//!
+//! ```toml
+//! [skin.names]
+//! Destroyer = "Rubber Duck"
+//!
+//! [skin.glyphs]
+//! Destroyer = "Q"
+//!
+//! [skin.colors]
+//! Destroyer = "#ffd700"
+//! ```
+//!
+//! Names are sent to the other player when a game starts, so they read about
+//! sinking the Rubber Duck too. Letters and colours only change our own
+//! boards.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	board::{Cell, CellState},
+	ship::{ShipKind, ShipNames},
+};
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Skin {
+	/// What to call each ship instead of its class.
+	pub names: ShipNames,
+	/// The letter each of our ships is drawn with on text boards. Damaged
+	/// squares show it in lowercase.
+	pub glyphs: BTreeMap<ShipKind, char>,
+	/// The colour each of our ships is drawn in, as `#rrggbb`.
+	pub colors: BTreeMap<ShipKind, String>,
+}
+
+impl Skin {
+	/// What we call `ship`.
+	#[must_use]
+	pub fn name(&self, ship: ShipKind) -> String {
+		ship.called(&self.names)
+	}
+
+	/// How `cell` is drawn on a text board, with our own ships in their
+	/// letters; see [`Cell::symbol`].
+	#[must_use]
+	pub fn symbol(&self, cell: Cell, own: bool) -> char {
+		match (
+			own,
+			cell.state,
+			cell.ship.and_then(|ship| self.glyphs.get(&ship)),
+		) {
+			(true, CellState::Untouched, Some(&glyph)) => glyph,
+			(true, CellState::Hit, Some(&glyph)) => glyph.to_ascii_lowercase(),
+			_ => cell.symbol(own),
+		}
+	}
+
+	/// The colour to draw `ship` in, if it has one that reads as `#rrggbb`.
+	#[must_use]
+	pub fn color(&self, ship: ShipKind) -> Option<[u8; 3]> {
+		let hex = self.colors.get(&ship)?.strip_prefix('#')?;
+		if hex.len() != 6 {
+			return None;
+		}
+		let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+		Some([channel(0)?, channel(2)?, channel(4)?])
+	}
+}
@@ -0,0 +1,54 @@
+use tokio::io::{AsyncRead, AsyncWrite};
+
+/// A bidirectional byte stream a game can be played over.
+///
+/// Anything tokio can read from and write to qualifies, so TCP, Unix domain
+/// sockets, Windows named pipes and in-memory `tokio::io::duplex` pairs all
+/// work without wrapping.
+pub trait Transport: AsyncRead + AsyncWrite + Unpin + Send + Sync {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send + Sync> Transport for T {}
+
+/// Waits for a peer on a Unix domain socket at `path`, or connects to one.
+///
+/// The server removes the socket file once a peer has connected, since nobody
+/// else can join the game anyway.
+///
+/// # Errors
+/// Fails if the socket can't be bound or connected to.
+#[cfg(unix)]
+pub async fn unix(
+	path: &std::path::Path,
+	serve: bool,
+) -> Result<tokio::net::UnixStream, tokio::io::Error> {
+	use tokio::net::{UnixListener, UnixStream};
+
+	if serve {
+		let listen = UnixListener::bind(path)?;
+		let stream = listen.accept().await?.0;
+		std::fs::remove_file(path)?;
+		Ok(stream)
+	} else {
+		UnixStream::connect(path).await
+	}
+}
+
+/// Waits for a peer on a named pipe called `name` (like
+/// `\\.\pipe\netbattleship`), or connects to one.
+///
+/// # Errors
+/// Fails if the pipe can't be created or opened.
+#[cfg(windows)]
+pub async fn named_pipe(name: &str, serve: bool) -> Result<Box<dyn Transport>, tokio::io::Error> {
+	use tokio::net::windows::named_pipe::{ClientOptions, ServerOptions};
+
+	if serve {
+		let server = ServerOptions::new()
+			.first_pipe_instance(true)
+			.create(name)?;
+		server.connect().await?;
+		Ok(Box::new(server))
+	} else {
+		Ok(Box::new(ClientOptions::new().open(name)?))
+	}
+}
@@ -1,20 +1,29 @@
 use std::io::{stdout, Write};
 
+use crate::coord::Coord;
+
+pub mod cast;
+#[cfg(feature = "async")]
+pub mod command;
+pub mod earcon;
+#[cfg(feature = "async")]
+pub mod error;
+#[cfg(feature = "async")]
+pub mod fx;
+#[cfg(feature = "async")]
+pub mod input;
+pub mod screen;
+#[cfg(feature = "speech")]
+pub mod speech;
+
+/// Reads a coordinate in any notation, ignoring spaces around it.
 #[must_use]
-pub fn parse_coord(c: &str) -> Option<(u8, u8)> {
-	if let [y, x] = &c.chars().take(2).collect::<Vec<_>>()[..] {
-		let y = y.to_ascii_uppercase();
-		if !('A'..='J').contains(&y) || !('0'..='9').contains(x) {
-			return None;
-		}
-		let y = y as u8 - b'A';
-		let x = *x as u8 - b'0';
-		Some((x, y))
-	} else {
-		None
-	}
+pub fn parse_coord(c: &str) -> Option<Coord> {
+	c.trim().parse().ok()
 }
 
+/// # Panics
+/// Panics if stdout is closed.
 pub fn flush() {
 	stdout().lock().flush().expect("Broken pipe");
 }
@@ -0,0 +1,126 @@
+//! Records the terminal session as an asciinema cast (version 2), so a game
+//! can be played back, or embedded in a web page with the asciinema player.
+//!
+//! Everything written through [`stdout`] is recorded once [`start`] has been
+//! called, escape sequences and all, with when it was written.
+
+use std::{
+	fs::File,
+	io::{self, LineWriter, StdoutLock, Write},
+	path::Path,
+	sync::Mutex,
+	time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use serde_json::json;
+
+/// The size of terminal a cast claims when it can't tell the real one.
+const DEFAULT_SIZE: (u16, u16) = (80, 24);
+
+/// The recording started by [`start`], if there is one.
+static RECORDING: Mutex<Option<Cast<LineWriter<File>>>> = Mutex::new(None);
+
+/// An asciinema cast being written to `out`: a header line, then a line
+/// for each piece of output.
+pub struct Cast<W> {
+	out: W,
+	start: Instant,
+}
+
+impl<W: Write> Cast<W> {
+	/// Starts a cast of a `width` by `height` terminal, writing its header.
+	///
+	/// # Errors
+	/// Fails if the header can't be written.
+	pub fn new(mut out: W, width: u16, height: u16) -> io::Result<Self> {
+		let timestamp = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.map_or(0, |since| since.as_secs());
+		let header = json!({
+			"version": 2,
+			"width": width,
+			"height": height,
+			"timestamp": timestamp,
+		});
+		writeln!(out, "{header}")?;
+		Ok(Cast {
+			out,
+			start: Instant::now(),
+		})
+	}
+
+	/// Records `text` as written to the terminal now. Lines that end in a
+	/// bare `\n` end in `\r\n` in the cast, as the terminal would have shown
+	/// them outside raw mode.
+	///
+	/// # Errors
+	/// Fails if the cast can't be written.
+	pub fn output(&mut self, text: &str) -> io::Result<()> {
+		let mut shown = String::with_capacity(text.len());
+		let mut last = None;
+		for c in text.chars() {
+			if c == '\n' && last != Some('\r') {
+				shown.push('\r');
+			}
+			shown.push(c);
+			last = Some(c);
+		}
+		let event = json!([self.start.elapsed().as_secs_f64(), "o", shown]);
+		writeln!(self.out, "{event}")
+	}
+
+	/// What the cast was written to.
+	pub fn into_inner(self) -> W {
+		self.out
+	}
+}
+
+/// Starts recording everything written through [`stdout`] to a cast at
+/// `path`, sized like the terminal.
+///
+/// # Errors
+/// Fails if the file can't be created.
+///
+/// # Panics
+/// Panics if a thread panicked while recording.
+pub fn start(path: &Path) -> io::Result<()> {
+	let (width, height) = crossterm::terminal::size().unwrap_or(DEFAULT_SIZE);
+	let cast = Cast::new(LineWriter::new(File::create(path)?), width, height)?;
+	*RECORDING.lock().expect("poisoned") = Some(cast);
+	Ok(())
+}
+
+/// Records `text` in the cast, if one was started. A cast that can't be
+/// written any more is stopped rather than getting in the way of the game.
+///
+/// # Panics
+/// Panics if a thread panicked while recording.
+pub fn record(text: &str) {
+	let mut recording = RECORDING.lock().expect("poisoned");
+	if let Some(cast) = recording.as_mut() {
+		if cast.output(text).is_err() {
+			*recording = None;
+		}
+	}
+}
+
+/// Stdout, recording what's written to it in the cast, if there is one.
+pub struct Stdout(StdoutLock<'static>);
+
+/// Locks stdout to write to it, and to the cast.
+#[must_use]
+pub fn stdout() -> Stdout {
+	Stdout(io::stdout().lock())
+}
+
+impl Write for Stdout {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		let written = self.0.write(buf)?;
+		record(&String::from_utf8_lossy(&buf[..written]));
+		Ok(written)
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		self.0.flush()
+	}
+}
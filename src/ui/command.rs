@@ -0,0 +1,148 @@
+use std::{path::PathBuf, str::FromStr};
+
+use thiserror::Error;
+
+use crate::{
+	coord::{Coord, ParseCoordError},
+	ui::input::ESCAPE,
+};
+
+/// Something the player typed, understood the same way by every frontend.
+///
+/// Letters are case-insensitive and spaces are ignored, so `p e5 v` and
+/// `PE5V` both place a ship. Coordinates can be written in any
+/// [`Notation`](crate::coord::Notation), so `p 5e v` and `p 4,5 v` do too. The only exception is querying, where an
+/// uppercase `Q` asks about your own board and a lowercase `q` about the
+/// enemy's.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Command {
+	/// Nothing was typed, or escape was pressed; repeat the prompt.
+	Nothing,
+	/// A bare coordinate like `E5`, whose meaning depends on the phase.
+	Cell(Coord),
+	/// `p` and a coordinate, then `v` to point the ship downwards.
+	Place { pos: Coord, vertical: bool },
+	/// `m` and a coordinate, then `v` to point downwards, to move the submarine there.
+	Move { pos: Coord, vertical: bool },
+	/// `f` and a coordinate.
+	Fire(Coord),
+	/// `r` and a coordinate, for a radar sweep around it.
+	Scan(Coord),
+	/// `q` and a coordinate.
+	Query { pos: Coord, own: bool },
+	/// `auto`, to place the rest of the fleet randomly.
+	Auto,
+	/// `help`, `h` or `?`.
+	Help,
+	/// `quit` or `exit`.
+	Quit,
+	/// `repeat`, to hear the last announcement again.
+	Repeat,
+	/// `where`, to ask where the enemy last fired.
+	Where,
+	/// `confirm`, to turn reading shots back before firing on or off.
+	Confirm,
+	/// `time`, to see how long each side has taken.
+	Time,
+	/// `sync`, to check that both players see the same game.
+	Sync,
+	/// `grid`, to print the boards for reading on a braille display.
+	Grid,
+	/// `say` followed by a message for the enemy.
+	Chat(String),
+	/// `export` followed by a `.png` or `.svg` file, to save a picture of
+	/// the battle.
+	Export(PathBuf),
+	/// `layout` followed by the name of a layout, to place the rest of the
+	/// fleet like it.
+	Layout(String),
+	/// `layout save` followed by a name, to keep the fleet's layout under it.
+	SaveLayout(String),
+}
+
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseCommandError {
+	#[error("unknown command")]
+	Unknown,
+	#[error("bad coordinates: {0}")]
+	Coord(#[from] ParseCoordError),
+}
+
+impl FromStr for Command {
+	type Err = ParseCommandError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let s = s.trim();
+		if let Some((word, text)) = s.split_once(char::is_whitespace) {
+			if word.eq_ignore_ascii_case("say") {
+				return Ok(Command::Chat(text.trim().to_string()));
+			}
+			if word.eq_ignore_ascii_case("export") {
+				return Ok(Command::Export(text.trim().into()));
+			}
+			if word.eq_ignore_ascii_case("layout") {
+				let text = text.trim();
+				return Ok(match text.split_once(char::is_whitespace) {
+					Some((save, name)) if save.eq_ignore_ascii_case("save") => {
+						Command::SaveLayout(name.trim().to_string())
+					}
+					_ => Command::Layout(text.to_string()),
+				});
+			}
+		}
+
+		let word = s.chars().filter(|c| !c.is_whitespace()).collect::<String>();
+		match word.to_lowercase().as_str() {
+			"" | ESCAPE => return Ok(Command::Nothing),
+			"help" | "h" | "?" => return Ok(Command::Help),
+			"quit" | "exit" => return Ok(Command::Quit),
+			"auto" => return Ok(Command::Auto),
+			"repeat" => return Ok(Command::Repeat),
+			"where" => return Ok(Command::Where),
+			"confirm" => return Ok(Command::Confirm),
+			"time" => return Ok(Command::Time),
+			"sync" => return Ok(Command::Sync),
+			"grid" => return Ok(Command::Grid),
+			_ => {}
+		}
+
+		// Row F would otherwise be mistaken for the fire command
+		if let Ok(pos) = word.parse() {
+			return Ok(Command::Cell(pos));
+		}
+
+		let mut chars = word.chars();
+		let first = chars.next().ok_or(ParseCommandError::Unknown)?;
+		let rest = chars.as_str();
+		match first.to_ascii_lowercase() {
+			'p' => {
+				let (pos, vertical) = placement(rest)?;
+				Ok(Command::Place { pos, vertical })
+			}
+			'm' => {
+				let (pos, vertical) = placement(rest)?;
+				Ok(Command::Move { pos, vertical })
+			}
+			'f' => Ok(Command::Fire(rest.parse()?)),
+			'r' => Ok(Command::Scan(rest.parse()?)),
+			'q' => Ok(Command::Query {
+				pos: rest.parse()?,
+				own: first.is_uppercase(),
+			}),
+			// Anything else with a number in it was probably meant as a square
+			_ if word.contains(|c: char| c.is_ascii_digit()) => Ok(Command::Cell(word.parse()?)),
+			_ => Err(ParseCommandError::Unknown),
+		}
+	}
+}
+
+/// A coordinate optionally followed by `v`, for where a ship should go.
+fn placement(s: &str) -> Result<(Coord, bool), ParseCommandError> {
+	match (s.parse(), s.strip_suffix(['v', 'V'])) {
+		(Ok(pos), _) => Ok((pos, false)),
+		(Err(_), Some(pos)) => Ok((pos.parse()?, true)),
+		// Only a coordinate can end in a number
+		(Err(e), None) if s.ends_with(|c: char| c.is_ascii_digit()) => Err(e.into()),
+		(Err(_), None) => Err(ParseCommandError::Unknown),
+	}
+}
@@ -0,0 +1,164 @@
+//! Earcons: short tones for what happens in a game, which are quicker to take
+//! in than a spoken sentence. Each cue can be retuned or turned off in the
+//! `[earcons]` table of the config file:
+//!
+//! ```toml
+//! [earcons.hit]
+//! notes = [440, 880]
+//! length = 50
+//!
+//! [earcons.miss]
+//! enabled = false
+//! ```
+//!
+//! Notes are in hertz and played one after another, each for `length`
+//! milliseconds. Cues left out keep their usual tones.
+
+#[cfg(feature = "speech")]
+use std::{sync::mpsc, thread, time::Duration};
+
+#[cfg(feature = "speech")]
+use rodio::{
+	source::{SineWave, Source},
+	OutputStream, Sink, StreamError,
+};
+use serde::{Deserialize, Serialize};
+
+/// Something that happened that has a tone.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cue {
+	Hit,
+	Miss,
+	Sunk,
+	YourTurn,
+}
+
+/// The notes a cue plays.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Tone {
+	pub enabled: bool,
+	/// The pitch of each note, in hertz.
+	pub notes: Vec<u16>,
+	/// How long each note lasts, in milliseconds.
+	pub length: u16,
+}
+
+impl Default for Tone {
+	fn default() -> Self {
+		Tone {
+			enabled: true,
+			notes: Vec::new(),
+			length: 80,
+		}
+	}
+}
+
+impl Tone {
+	fn new(notes: &[u16], length: u16) -> Tone {
+		Tone {
+			notes: notes.to_vec(),
+			length,
+			..Tone::default()
+		}
+	}
+}
+
+/// The tone for each cue.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Earcons {
+	/// Rising, for one of our shots or the enemy's that hit.
+	pub hit: Tone,
+	/// A single low note, for a shot into the water.
+	pub miss: Tone,
+	/// Falling, for a ship going down.
+	pub sunk: Tone,
+	/// Two quick notes, when it's our turn to fire.
+	pub your_turn: Tone,
+}
+
+impl Default for Earcons {
+	fn default() -> Self {
+		Earcons {
+			hit: Tone::new(&[660, 990], 70),
+			miss: Tone::new(&[220], 120),
+			sunk: Tone::new(&[990, 660, 440], 90),
+			your_turn: Tone::new(&[523, 784], 60),
+		}
+	}
+}
+
+impl Earcons {
+	/// The tone `cue` plays.
+	#[must_use]
+	pub fn tone(&self, cue: Cue) -> &Tone {
+		match cue {
+			Cue::Hit => &self.hit,
+			Cue::Miss => &self.miss,
+			Cue::Sunk => &self.sunk,
+			Cue::YourTurn => &self.your_turn,
+		}
+	}
+}
+
+/// How loud tones are, from silent at 0 to full volume at 1, so they
+/// don't drown out speech.
+#[cfg(feature = "speech")]
+const VOLUME: f32 = 0.2;
+
+/// Plays earcons on the default sound device. The device can't be moved
+/// between threads, so it's kept open on a thread of its own.
+#[cfg(feature = "speech")]
+pub struct Chime {
+	earcons: Earcons,
+	tones: mpsc::Sender<Tone>,
+}
+
+#[cfg(feature = "speech")]
+impl Chime {
+	/// Opens the default sound device to play `earcons` on.
+	///
+	/// # Errors
+	/// Fails if there's no sound device, or it can't be opened.
+	pub fn new(earcons: Earcons) -> Result<Chime, StreamError> {
+		let (tones, queue) = mpsc::channel::<Tone>();
+		let (opened, open) = mpsc::sync_channel(1);
+		thread::spawn(move || {
+			let (_stream, handle) = match OutputStream::try_default() {
+				Ok(output) => {
+					let _ = opened.send(Ok(()));
+					output
+				}
+				Err(e) => {
+					let _ = opened.send(Err(e));
+					return;
+				}
+			};
+			for tone in queue {
+				let Ok(sink) = Sink::try_new(&handle) else {
+					continue;
+				};
+				for &note in &tone.notes {
+					sink.append(
+						SineWave::new(f32::from(note))
+							.take_duration(Duration::from_millis(tone.length.into()))
+							.amplify(VOLUME),
+					);
+				}
+				sink.detach();
+			}
+		});
+		open.recv().unwrap_or(Err(StreamError::NoDevice))?;
+		Ok(Chime { earcons, tones })
+	}
+
+	/// Starts playing `cue`'s tone, unless it's been turned off.
+	pub fn play(&self, cue: Cue) {
+		let tone = self.earcons.tone(cue);
+		if tone.enabled && !tone.notes.is_empty() {
+			// The thread only stops if the device does
+			let _ = self.tones.send(tone.clone());
+		}
+	}
+}
@@ -0,0 +1,204 @@
+use std::{
+	any::Any,
+	fmt,
+	future::Future,
+	net::SocketAddr,
+	panic::{self, AssertUnwindSafe},
+	pin::Pin,
+	str::FromStr,
+	task::{Context, Poll},
+};
+
+use tokio::{net::TcpStream, task::JoinError};
+
+use crate::{
+	flow::{GameFlow, GameFlowError, Host},
+	net::NetError,
+	save::Save,
+	Game,
+};
+
+/// Why a game stopped when it shouldn't have, in words for the player.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Failure(pub String);
+
+impl fmt::Display for Failure {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(&self.0)
+	}
+}
+
+impl From<GameFlowError> for Failure {
+	fn from(e: GameFlowError) -> Self {
+		Failure(match e {
+			GameFlowError::Network(e) | GameFlowError::Net(NetError::Io(e)) => {
+				format!("Lost the connection to your enemy ({e}).")
+			}
+			GameFlowError::Net(e) => format!("Your enemy's connection broke down ({e})."),
+			GameFlowError::BadMessage(_)
+			| GameFlowError::MalformedMessage(_)
+			| GameFlowError::Mismatch(..) => {
+				"Your enemy sent something that doesn't make sense for this game.".to_string()
+			}
+			GameFlowError::TimedOut => "Your enemy stopped answering.".to_string(),
+			GameFlowError::Left => "Your enemy left the game.".to_string(),
+			GameFlowError::Resigned => "Your enemy resigned, so you win.".to_string(),
+			GameFlowError::Cancelled => "You left the game.".to_string(),
+			GameFlowError::ResumeMismatch(ours, theirs) => {
+				format!(
+					"You saved this game on turn {ours}, but your enemy saved it on turn {theirs}."
+				)
+			}
+			GameFlowError::WrongPassword => {
+				"Your enemy's password doesn't match yours.".to_string()
+			}
+			GameFlowError::Desync { turn, .. } => {
+				format!("Your game and your enemy's stopped matching on turn {turn}.")
+			}
+			GameFlowError::OutOfSync(ours, theirs) => {
+				format!("You're on turn {ours}, but your enemy thinks it's turn {theirs}.")
+			}
+			GameFlowError::OutOfTurn(ours, theirs) => {
+				format!(
+					"Your enemy is on turn {theirs}, not turn {ours}, so they ignored your shot."
+				)
+			}
+			e => format!("Something went wrong with the game ({e})."),
+		})
+	}
+}
+
+impl From<JoinError> for Failure {
+	fn from(e: JoinError) -> Self {
+		match e.try_into_panic() {
+			Ok(payload) => Failure::panic(&*payload),
+			Err(_) => Failure("The game was stopped before it finished.".to_string()),
+		}
+	}
+}
+
+impl Failure {
+	/// Explains a panic, given what it was raised with.
+	#[must_use]
+	pub fn panic(payload: &(dyn Any + Send)) -> Self {
+		let reason = payload
+			.downcast_ref::<&str>()
+			.copied()
+			.or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+			.unwrap_or("no reason given");
+		Failure(format!("The game crashed ({reason})."))
+	}
+}
+
+/// What the player can do once a game has failed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Recovery {
+	/// Connect to the other player again and carry on, if they do the same.
+	Reconnect,
+	/// Write the game to a save slot to continue another time, and leave.
+	Save,
+	Quit,
+}
+
+impl Recovery {
+	pub const ALL: [Recovery; 3] = [Recovery::Reconnect, Recovery::Save, Recovery::Quit];
+}
+
+impl fmt::Display for Recovery {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			Recovery::Reconnect => "Reconnect",
+			Recovery::Save => "Save and quit",
+			Recovery::Quit => "Quit",
+		})
+	}
+}
+
+impl FromStr for Recovery {
+	type Err = ();
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.trim().to_lowercase().as_str() {
+			"r" | "reconnect" => Ok(Recovery::Reconnect),
+			"s" | "save" => Ok(Recovery::Save),
+			"q" | "quit" => Ok(Recovery::Quit),
+			_ => Err(()),
+		}
+	}
+}
+
+/// Runs `future`, turning a panic inside it into a [`Failure`] instead of
+/// letting it take the whole program down.
+///
+/// # Errors
+/// Fails if `future` panics.
+pub async fn catch<F: Future>(future: F) -> Result<F::Output, Failure> {
+	CatchUnwind(Box::pin(future)).await
+}
+
+struct CatchUnwind<F>(Pin<Box<F>>);
+
+impl<F: Future> Future for CatchUnwind<F> {
+	type Output = Result<F::Output, Failure>;
+
+	fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+		let future = self.0.as_mut();
+		match panic::catch_unwind(AssertUnwindSafe(|| future.poll(cx))) {
+			Ok(Poll::Ready(output)) => Poll::Ready(Ok(output)),
+			Ok(Poll::Pending) => Poll::Pending,
+			Err(payload) => Poll::Ready(Err(Failure::panic(&*payload))),
+		}
+	}
+}
+
+/// Where a game is being played, so it can be saved or picked up again
+/// after it fails.
+#[derive(Clone, Debug)]
+pub struct Session {
+	/// Where we were hosting, or the host we joined.
+	pub addr: SocketAddr,
+	/// Whether we were hosting.
+	pub serve: bool,
+	/// The save slot the game is autosaved to.
+	pub slot: String,
+}
+
+impl Session {
+	/// The latest state of `game`, ready to write to a save slot.
+	#[must_use]
+	pub fn save(&self, game: &GameFlow) -> Save {
+		Save {
+			game: Game::clone(&game.subscribe().borrow()),
+			addr: self.addr,
+			serve: self.serve,
+		}
+	}
+
+	/// Keeps `game` saved in our slot as it goes.
+	pub fn autosave(&self, game: &GameFlow) {
+		game.autosave(self.slot.clone(), self.addr, self.serve);
+	}
+
+	/// Connects to the other player again and carries on from where `game`
+	/// left off. They have to reconnect too.
+	///
+	/// # Errors
+	/// Fails if we can't reach each other or are on different turns.
+	pub async fn reconnect(&self, game: &GameFlow) -> Result<GameFlow, GameFlowError> {
+		let game = resume(self.save(game)).await?;
+		self.autosave(&game);
+		Ok(game)
+	}
+}
+
+/// Reconnects to the other player of a saved game, on the same address.
+///
+/// # Errors
+/// Fails if we can't reach each other or are on different turns.
+pub async fn resume(save: Save) -> Result<GameFlow, GameFlowError> {
+	if save.serve {
+		Host::bind(Some(save.addr)).await?.resume(save.game).await
+	} else {
+		GameFlow::resume(TcpStream::connect(save.addr).await?, save.game).await
+	}
+}
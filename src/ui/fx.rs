@@ -0,0 +1,217 @@
+//! Short ASCII-art animations for the terminal: a ship sailing in as the
+//! battle starts, shells coming in, explosions, splashes and sinking ships.
+//!
+//! Each animation is a list of frames drawn over each other in place, so
+//! they only make sense on a real terminal; see [`enabled`].
+
+use std::{
+	future::Future,
+	io::{stdout, IsTerminal, Write},
+	time::Duration,
+};
+
+use tokio::time::sleep;
+
+use super::cast;
+
+/// How long each frame stays up unless it says otherwise.
+const FRAME: Duration = Duration::from_millis(110);
+
+/// How wide the sea is, in characters.
+const WIDTH: usize = 32;
+
+#[rustfmt::skip]
+const SHIP: [&str; 4] = [
+	r"    |\    ",
+	r"    | \   ",
+	r" ___|__\_ ",
+	r" \______/ ",
+];
+
+/// Whether animations should play: only if asked for, and only when stdout
+/// is a terminal that can redraw them in place.
+#[must_use]
+pub fn enabled(requested: bool) -> bool {
+	requested && stdout().is_terminal()
+}
+
+/// One picture of an animation, with how long to show it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Frame {
+	pub lines: Vec<String>,
+	pub hold: Duration,
+}
+
+impl Frame {
+	fn new(lines: impl IntoIterator<Item = impl Into<String>>) -> Self {
+		Frame {
+			lines: lines.into_iter().map(Into::into).collect(),
+			hold: FRAME,
+		}
+	}
+
+	/// This frame with the sea drawn underneath.
+	fn at_sea(mut self) -> Self {
+		self.lines.push("~".repeat(WIDTH));
+		self
+	}
+}
+
+/// Frames played one after another in the same spot.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Animation {
+	pub frames: Vec<Frame>,
+}
+
+impl Animation {
+	/// A ship sailing across the screen, for the start of the battle.
+	#[must_use]
+	pub fn intro() -> Self {
+		let frames = (0..=WIDTH - SHIP[0].len())
+			.step_by(2)
+			.map(|offset| Frame::new(SHIP.map(|line| format!("{:offset$}{line}", ""))).at_sea())
+			.collect();
+		Animation { frames }
+	}
+
+	/// A shell arcing down towards the water.
+	#[must_use]
+	pub fn incoming() -> Self {
+		let frames = (0..SHIP.len())
+			.map(|row| {
+				let shell = format!("{:1$}*", "", 6 + row * 5);
+				let lines = (0..SHIP.len()).map(|i| if i == row { shell.as_str() } else { "" });
+				Frame::new(lines).at_sea()
+			})
+			.collect();
+		Animation { frames }
+	}
+
+	/// A shell bursting on a ship.
+	#[must_use]
+	pub fn explosion() -> Self {
+		Self::from_art(&[
+			&["", "", "          *", "       ___|__\\_"],
+			&["", "        \\ | /", "       -- * --", "        / | \\"],
+			&[
+				"     .  \\ | /  .",
+				"      ---###---",
+				"     '  / | \\  '",
+				"       ___|__\\_",
+			],
+			&[
+				"      (  )  ( )",
+				"     (   )(   )",
+				"       ( )  )",
+				"       ___|__\\_",
+			],
+			&["       (  )", "        ( )", "", "       ___|__\\_"],
+		])
+	}
+
+	/// A shell landing in open water.
+	#[must_use]
+	pub fn splash() -> Self {
+		Self::from_art(&[
+			&["", "", "", "          ."],
+			&["", "", "          .", "          |"],
+			&["", "        . | .", "       .  |  .", "         \\|/"],
+			&["        '   '", "       .  '  .", "", "          o"],
+		])
+	}
+
+	/// A ship slipping under the waves.
+	#[must_use]
+	pub fn sinking() -> Self {
+		let mut frames = (0..=SHIP.len())
+			.map(|sunk| {
+				let lines = (0..SHIP.len()).map(|row| {
+					row.checked_sub(sunk)
+						.map_or(String::new(), |row| format!("{:8}{}", "", SHIP[row]))
+				});
+				Frame::new(lines).at_sea()
+			})
+			.collect::<Vec<_>>();
+		frames.push(Frame::new(["", "", "             o", "            o  O"]).at_sea());
+		frames.push(Frame::new(["", "", "", "              o"]).at_sea());
+		Animation { frames }
+	}
+
+	/// What to play for one shot: the shell coming in, then an explosion or
+	/// a splash (unless the result is secret), then the ship going down if
+	/// it sank.
+	#[must_use]
+	pub fn shot(hit: Option<bool>, sunk: bool) -> Vec<Self> {
+		let mut animations = vec![Self::incoming()];
+		match hit {
+			Some(true) => animations.push(Self::explosion()),
+			Some(false) => animations.push(Self::splash()),
+			None => {}
+		}
+		if sunk {
+			animations.push(Self::sinking());
+		}
+		animations
+	}
+
+	fn from_art(art: &[&[&str]]) -> Self {
+		let frames = art
+			.iter()
+			.map(|lines| Frame::new(lines.iter().copied()).at_sea())
+			.collect();
+		Animation { frames }
+	}
+
+	/// Draws the animation on stdout, then rubs it out again. Stops early
+	/// once `skip` finishes.
+	///
+	/// # Panics
+	/// Panics if stdout is closed.
+	pub async fn play(&self, skip: impl Future<Output = ()>) {
+		tokio::pin!(skip);
+		let mut drawn = 0;
+		for frame in &self.frames {
+			erase(drawn);
+			{
+				let mut out = cast::stdout();
+				for line in &frame.lines {
+					writeln!(out, "{line}").expect("Broken pipe");
+				}
+				out.flush().expect("Broken pipe");
+			}
+			drawn = frame.lines.len();
+			tokio::select! {
+				() = sleep(frame.hold) => {}
+				() = &mut skip => break,
+			}
+		}
+		erase(drawn);
+	}
+
+	/// Plays `animations` one after another, stopping them all once `skip`
+	/// finishes.
+	pub async fn play_all(animations: &[Animation], skip: impl Future<Output = ()>) {
+		tokio::pin!(skip);
+		let mut skipped = false;
+		for animation in animations {
+			animation
+				.play(async {
+					(&mut skip).await;
+					skipped = true;
+				})
+				.await;
+			if skipped {
+				break;
+			}
+		}
+	}
+}
+
+/// Moves back up over the last `lines` lines and clears them.
+fn erase(lines: usize) {
+	if lines > 0 {
+		let mut out = cast::stdout();
+		write!(out, "\x1b[{lines}A\x1b[J").expect("Broken pipe");
+		out.flush().expect("Broken pipe");
+	}
+}
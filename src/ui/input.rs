@@ -0,0 +1,279 @@
+use std::{
+	io::{stdin, BufRead},
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc,
+	},
+	thread,
+	time::Duration,
+};
+
+use crossterm::{
+	event::{self as term, KeyEventKind},
+	terminal,
+};
+
+use rustyline::{
+	error::ReadlineError, Cmd, ConditionalEventHandler, Editor, Event, EventContext, EventHandler,
+	KeyCode, KeyEvent, Modifiers, RepeatCount,
+};
+use tokio::sync::{
+	mpsc::{self, UnboundedReceiver, UnboundedSender},
+	watch,
+};
+
+use super::cast;
+
+/// The line sent when the player presses escape in the line editor, which
+/// throws away whatever they had typed.
+pub const ESCAPE: &str = "\u{1b}";
+
+/// How often the terminal reader checks whether to hand out single keys
+/// instead of lines.
+const KEY_POLL: Duration = Duration::from_millis(50);
+
+/// Lines typed by the player, read on their own thread so that waiting for
+/// the enemy never stops the player from typing.
+///
+/// Lines typed while the game can't use them stay queued, in order, until
+/// they are asked for.
+pub struct Input {
+	lines: UnboundedReceiver<String>,
+	/// A line that [`Input::typed`] saw arrive, not yet handled.
+	peeked: Option<String>,
+	/// Single key presses, if this input reads from a terminal.
+	keys: Option<Keys>,
+}
+
+/// Key presses read while the terminal is in raw mode, from
+/// [`Input::terminal`].
+struct Keys {
+	/// Whether the terminal is in raw mode, so key presses go to `events`
+	/// rather than building up lines.
+	raw: Arc<AtomicBool>,
+	events: UnboundedReceiver<term::KeyEvent>,
+	/// Changes whenever a key is pressed while typing a line.
+	typing: watch::Receiver<()>,
+}
+
+impl Input {
+	/// Reads plain lines from stdin.
+	#[must_use]
+	pub fn stdin() -> Input {
+		Self::spawn(|lines| {
+			for line in stdin().lock().lines().map_while(Result::ok) {
+				if lines.send(line).is_err() {
+					break;
+				}
+			}
+		})
+	}
+
+	/// Plays back the commands in `script`, one per line, then closes.
+	/// Blank lines and lines starting with `#` are skipped.
+	#[must_use]
+	pub fn script(script: &str) -> Input {
+		let lines = script
+			.lines()
+			.map(str::trim)
+			.filter(|line| !line.is_empty() && !line.starts_with('#'))
+			.map(str::to_string)
+			.collect::<Vec<_>>();
+		Self::spawn(move |tx| {
+			for line in lines {
+				if tx.send(line).is_err() {
+					break;
+				}
+			}
+		})
+	}
+
+	/// Reads lines with a line editor, so typing mistakes can be fixed
+	/// before pressing enter. Pressing escape sends [`ESCAPE`]. The editor
+	/// takes Ctrl-C as a key, so on Unix it's raised as the interrupt it
+	/// would have been.
+	///
+	/// # Panics
+	/// Panics if the terminal can't be opened.
+	#[must_use]
+	pub fn editor() -> Input {
+		let mut rl = Editor::<()>::new().expect("Failed to open prompt");
+		let escaped = Arc::new(AtomicBool::new(false));
+		rl.bind_sequence(
+			KeyEvent(KeyCode::Esc, Modifiers::NONE),
+			EventHandler::Conditional(Box::new(Escape(escaped.clone()))),
+		);
+		Self::spawn(move |lines| loop {
+			let line = match rl.readline("") {
+				Ok(line) => line,
+				Err(ReadlineError::Interrupted) if escaped.swap(false, Ordering::Relaxed) => {
+					ESCAPE.to_string()
+				}
+				#[cfg(unix)]
+				Err(ReadlineError::Interrupted) => {
+					if signal_hook::low_level::raise(signal_hook::consts::SIGINT).is_err() {
+						break;
+					}
+					continue;
+				}
+				Err(_) => break,
+			};
+			if lines.send(line).is_err() {
+				break;
+			}
+		})
+	}
+
+	/// Reads lines from the terminal like [`Input::stdin`], but can also
+	/// read single key presses with [`Input::key`].
+	#[must_use]
+	pub fn terminal() -> Input {
+		let raw = Arc::new(AtomicBool::new(false));
+		let (keys, events) = mpsc::unbounded_channel();
+		let (pressed, typing) = watch::channel(());
+		let mut input = Self::spawn({
+			let raw = raw.clone();
+			move |lines| {
+				let mut line = String::new();
+				loop {
+					// Polling lets a switch to raw mode take effect before the
+					// next key is read
+					match term::poll(KEY_POLL) {
+						Ok(false) => continue,
+						Ok(true) => {}
+						Err(_) => break,
+					}
+					let Ok(term::Event::Key(key)) = term::read() else {
+						continue;
+					};
+					if key.kind == KeyEventKind::Release {
+						continue;
+					}
+					if raw.load(Ordering::Relaxed) {
+						if keys.send(key).is_err() {
+							break;
+						}
+						continue;
+					}
+					pressed.send_replace(());
+					match key.code {
+						term::KeyCode::Enter => {
+							let typed = std::mem::take(&mut line);
+							if lines.send(typed).is_err() {
+								break;
+							}
+						}
+						term::KeyCode::Char(c) => line.push(c),
+						term::KeyCode::Backspace => {
+							line.pop();
+						}
+						_ => {}
+					}
+				}
+			}
+		});
+		input.keys = Some(Keys {
+			raw,
+			events,
+			typing,
+		});
+		input
+	}
+
+	fn spawn(read: impl FnOnce(UnboundedSender<String>) + Send + 'static) -> Input {
+		let (tx, lines) = mpsc::unbounded_channel();
+		thread::spawn(move || read(tx));
+		Input {
+			lines,
+			peeked: None,
+			keys: None,
+		}
+	}
+
+	/// The line [`Input::typed`] saw arrive, if it hasn't been handled yet.
+	#[must_use]
+	pub fn peek(&self) -> Option<&str> {
+		self.peeked.as_deref()
+	}
+
+	/// Whether [`Input::key`] can read single key presses.
+	#[must_use]
+	pub fn has_keys(&self) -> bool {
+		self.keys.is_some()
+	}
+
+	/// Watches for keys pressed from now on while a line is being typed,
+	/// before it's finished, if this input can tell.
+	#[must_use]
+	pub fn typing(&self) -> Option<watch::Receiver<()>> {
+		self.keys.as_ref().map(|keys| {
+			let mut typing = keys.typing.clone();
+			typing.mark_unchanged();
+			typing
+		})
+	}
+
+	/// Puts the terminal in raw mode if it isn't already, and waits for the
+	/// next key to be pressed. Returns `None` if this input can't read keys,
+	/// or once input has been closed. Call [`Input::end_keys`] to go back to
+	/// reading lines.
+	pub async fn key(&mut self) -> Option<term::KeyEvent> {
+		let keys = self.keys.as_mut()?;
+		if !keys.raw.swap(true, Ordering::Relaxed) {
+			// Keys pressed since the last time were meant for something else
+			while keys.events.try_recv().is_ok() {}
+			terminal::enable_raw_mode().ok()?;
+		}
+		keys.events.recv().await
+	}
+
+	/// Takes the terminal out of raw mode after [`Input::key`], so lines can
+	/// be typed again.
+	pub fn end_keys(&mut self) {
+		if let Some(keys) = &self.keys {
+			if keys.raw.swap(false, Ordering::Relaxed) {
+				let _ = terminal::disable_raw_mode();
+			}
+		}
+	}
+
+	/// The oldest line not yet handled, waiting for one to be typed if
+	/// necessary. Returns `None` once input has been closed.
+	pub async fn next(&mut self) -> Option<String> {
+		let line = match self.peeked.take() {
+			Some(line) => Some(line),
+			None => self.lines.recv().await,
+		};
+		// The terminal echoes what's typed, so the cast shows it too
+		if let Some(line) = &line {
+			cast::record(&format!("{line}\n"));
+		}
+		line
+	}
+
+	/// Waits until the player has typed a line, leaving it for
+	/// [`Input::next`]. Returns straight away if one is already waiting or
+	/// input has been closed.
+	pub async fn typed(&mut self) {
+		if self.peeked.is_none() {
+			self.peeked = self.lines.recv().await;
+		}
+	}
+}
+
+impl Drop for Input {
+	fn drop(&mut self) {
+		self.end_keys();
+	}
+}
+
+/// Ends the line being edited when escape is pressed, noting that it was
+/// escape rather than Ctrl-C, which still closes the input.
+struct Escape(Arc<AtomicBool>);
+
+impl ConditionalEventHandler for Escape {
+	fn handle(&self, _: &Event, _: RepeatCount, _: bool, _: &EventContext) -> Option<Cmd> {
+		self.0.store(true, Ordering::Relaxed);
+		Some(Cmd::Interrupt)
+	}
+}
@@ -0,0 +1,67 @@
+//! Keeps track of what's on the terminal so a screen that changes a little at
+//! a time, like the one for steering a ship into place, only has the lines
+//! that changed drawn again. Clearing and redrawing the whole screen on every
+//! key flickers, and over a slow SSH connection it can be watched line by
+//! line.
+
+use std::mem;
+
+use crate::Game;
+
+/// The lines last drawn, and the game they showed.
+#[derive(Default)]
+pub struct Screen {
+	shown: Option<Game>,
+	lines: Vec<String>,
+}
+
+impl Screen {
+	/// Takes `boards`, drawn from `game`, with `footer` under them as what's
+	/// on screen now, and returns which lines have to be drawn again, counted
+	/// from the top. The boards' lines are only looked at if a square of
+	/// `game` changed, or the phase or turn did, since some styles draw
+	/// different boards depending on those.
+	///
+	/// Lines past the end of a shorter screen than the last are returned as
+	/// well, and are empty, so drawing them clears what was there.
+	pub fn update(&mut self, game: &Game, boards: &str, footer: &str) -> Vec<usize> {
+		let changed = self.shown.as_ref().is_none_or(|shown| {
+			shown.phase != game.phase
+				|| shown.turn != game.turn
+				|| shown.diff(game).iter().any(|squares| !squares.is_empty())
+		});
+		let board_lines = boards.lines().count();
+		let old = mem::replace(
+			&mut self.lines,
+			boards
+				.lines()
+				.chain(footer.lines())
+				.map(String::from)
+				.collect(),
+		);
+		self.shown = Some(game.clone());
+		(0..old.len().max(self.lines.len()))
+			.filter(|&row| {
+				(changed || row >= board_lines || row >= old.len())
+					&& old.get(row) != self.lines.get(row)
+			})
+			.collect()
+	}
+
+	/// Line `row` as last drawn, or nothing if the screen is shorter than that.
+	#[must_use]
+	pub fn line(&self, row: usize) -> &str {
+		self.lines.get(row).map_or("", String::as_str)
+	}
+
+	/// How many lines are on screen.
+	#[must_use]
+	pub fn len(&self) -> usize {
+		self.lines.len()
+	}
+
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.lines.is_empty()
+	}
+}
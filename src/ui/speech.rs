@@ -0,0 +1,64 @@
+//! Reading announcements aloud through the system's text-to-speech, for the
+//! speech program and the GUI alike.
+
+use std::time::Duration;
+
+use tokio::{task::yield_now, time::sleep};
+use tts::Tts;
+
+/// How long [`Voice::wait`] gives speech to start before deciding there's
+/// nothing to wait for.
+const START_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// The system's text-to-speech voice.
+pub struct Voice {
+	tts: Tts,
+}
+
+impl Voice {
+	/// Connects to the system's text-to-speech, talking `speed` times as fast
+	/// as it normally does.
+	///
+	/// # Errors
+	/// Fails if there's no text-to-speech to connect to, or it won't change
+	/// speed.
+	pub fn new(speed: f32) -> Result<Voice, tts::Error> {
+		let mut tts = Tts::default()?;
+		tts.set_rate(speed * tts.normal_rate())?;
+		Ok(Voice { tts })
+	}
+
+	/// Speaks `volume` times as loud as the voice normally does, or as loud
+	/// as it goes if that's louder.
+	///
+	/// # Errors
+	/// Fails if the text-to-speech won't change volume.
+	pub fn set_volume(&mut self, volume: f32) -> Result<(), tts::Error> {
+		let volume = (volume * self.tts.normal_volume()).min(self.tts.max_volume());
+		self.tts.set_volume(volume).map(drop)
+	}
+
+	/// Starts saying `text`, cutting off whatever was being said before if
+	/// `interrupt` is set, or after it otherwise.
+	///
+	/// # Errors
+	/// Fails if the text-to-speech does.
+	pub fn say(&mut self, text: &str, interrupt: bool) -> Result<(), tts::Error> {
+		self.tts.speak(text, interrupt).map(drop)
+	}
+
+	/// Waits until whatever was just said has been said.
+	pub async fn wait(&mut self) {
+		let mut waited = Duration::ZERO;
+		while let Ok(false) = self.tts.is_speaking() {
+			if waited > START_TIMEOUT {
+				return;
+			}
+			sleep(Duration::from_millis(1)).await;
+			waited += Duration::from_millis(1);
+		}
+		while let Ok(true) = self.tts.is_speaking() {
+			yield_now().await;
+		}
+	}
+}
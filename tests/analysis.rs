@@ -0,0 +1,37 @@
+use netbattleship::{analysis, coord::Coord, history::Move, rules::Rules, ship::ShipKind, Game};
+
+#[test]
+fn shots_are_graded_by_what_was_known() {
+	let rules = Rules {
+		board_size: 3,
+		fleet: vec![ShipKind::Destroyer],
+		..Rules::default()
+	};
+	let mut game = Game::new(rules, true);
+	let shot = |by, x, y, hit| Move::Shot {
+		by,
+		pos: Coord::new(x, y),
+		hit: Some(hit),
+		sunk: None,
+	};
+	game.log = vec![
+		shot(true, 0, 0, false),
+		shot(false, 2, 2, false),
+		shot(true, 1, 1, true),
+	];
+	let grades = analysis::review(&game);
+	assert_eq!(grades.len(), 2);
+
+	// A Destroyer fits over the middle of an empty board four ways, and over
+	// a corner only two
+	assert!(!grades[0].is_best());
+	assert_eq!(grades[0].best, Coord::new(1, 1));
+	assert!(grades[0].best_chance > grades[0].chance);
+	assert_eq!(
+		grades[0].to_string(),
+		"turn 1: you fired A0, best was B1 with 17% vs 8%"
+	);
+
+	assert!(grades[1].is_best());
+	assert_eq!(grades[1].turn, 3);
+}
@@ -0,0 +1,183 @@
+#![cfg(feature = "async")]
+
+use std::{net::SocketAddr, time::Duration};
+
+use netbattleship::api;
+use serde_json::{json, Value};
+use tokio::{
+	io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+	net::{TcpListener, TcpStream},
+};
+
+/// Sends one request to the API at `api`, returning the status and the
+/// JSON answered, if any.
+async fn request(api: SocketAddr, method: &str, path: &str, body: Option<Value>) -> (u16, Value) {
+	let headers = format!("Host: localhost:{}\r\n", api.port());
+	request_with(api, &headers, method, path, body).await
+}
+
+/// Sends one request to the API at `api` with `headers`, each ending in
+/// CRLF, returning the status and the JSON answered, if any.
+async fn request_with(
+	api: SocketAddr,
+	headers: &str,
+	method: &str,
+	path: &str,
+	body: Option<Value>,
+) -> (u16, Value) {
+	let body = body.map(|body| body.to_string()).unwrap_or_default();
+	let mut stream = TcpStream::connect(api).await.unwrap();
+	let head = format!(
+		"{method} {path} HTTP/1.1\r\n{headers}Connection: close\r\n\
+		 Content-Type: application/json\r\nContent-Length: {}\r\n\r\n",
+		body.len()
+	);
+	stream.write_all((head + &body).as_bytes()).await.unwrap();
+	let mut answer = String::new();
+	stream.read_to_string(&mut answer).await.unwrap();
+	let status = answer[9..12].parse().unwrap();
+	let (_, json) = answer.split_once("\r\n\r\n").unwrap();
+	(status, serde_json::from_str(json).unwrap_or(Value::Null))
+}
+
+/// Fires at `pos` in game `id`.
+async fn fire(api: SocketAddr, id: &Value, pos: &str) -> (u16, Value) {
+	let path = format!("/games/{id}/fire");
+	request(api, "POST", &path, Some(json!({ "pos": pos }))).await
+}
+
+/// Waits until game `id` is on our turn.
+async fn our_turn(api: SocketAddr, id: &Value) {
+	for _ in 0..100 {
+		let (_, view) = request(api, "GET", &format!("/games/{id}"), None).await;
+		if view["phase"] == "Playing" && view["your_turn"] == true {
+			return;
+		}
+		tokio::time::sleep(Duration::from_millis(20)).await;
+	}
+	panic!("game {id} never got to our turn");
+}
+
+#[tokio::test]
+async fn only_this_computer_can_use_the_api() {
+	let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+	let api = listener.local_addr().unwrap();
+	tokio::spawn(api::serve(listener));
+	let port = api.port();
+	for host in [
+		format!("localhost:{port}"),
+		format!("127.0.0.1:{port}"),
+		format!("[::1]:{port}"),
+	] {
+		let headers = format!("Host: {host}\r\nOrigin: http://{host}\r\n");
+		let (status, _) = request_with(api, &headers, "GET", "/games/1", None).await;
+		assert_eq!(status, 404, "{host} was turned away");
+	}
+	for headers in [
+		// A page whose hostname was pointed at this computer
+		format!("Host: evil.example:{port}\r\n"),
+		format!("Host: localhost.evil.example:{port}\r\n"),
+		format!("Host: localhost:{}\r\n", port.wrapping_add(1)),
+		// A page elsewhere asking for the API by its own name
+		format!("Host: localhost:{port}\r\nOrigin: http://evil.example\r\n"),
+		format!("Host: localhost:{port}\r\nOrigin: null\r\n"),
+		String::new(),
+	] {
+		let (status, error) = request_with(api, &headers, "GET", "/games/1", None).await;
+		assert_eq!(status, 403, "{headers:?} was let in");
+		assert_eq!(error["error"], "only this computer can use the API");
+	}
+	let (status, _) = request_with(
+		api,
+		&format!("Host: evil.example:{port}\r\n"),
+		"POST",
+		"/games",
+		Some(json!({ "address": "127.0.0.1:1" })),
+	)
+	.await;
+	assert_eq!(status, 403);
+}
+
+#[tokio::test]
+async fn games_are_played_through_the_api() {
+	let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+	let api = listener.local_addr().unwrap();
+	tokio::spawn(api::serve(listener));
+	let port = TcpListener::bind("127.0.0.1:0")
+		.await
+		.unwrap()
+		.local_addr()
+		.unwrap()
+		.port();
+	let rules = json!({ "board_size": 5, "fleet": ["Destroyer"] });
+	let host = json!({ "address": format!("127.0.0.1:{port}"), "serve": true, "rules": rules });
+	let join = json!({ "address": format!("127.0.0.1:{port}"), "rules": rules, "name": "Ahab" });
+	let ((hosted, host), (joined, join)) =
+		tokio::join!(request(api, "POST", "/games", Some(host)), async {
+			tokio::time::sleep(Duration::from_millis(100)).await;
+			request(api, "POST", "/games", Some(join)).await
+		});
+	assert_eq!((hosted, joined), (200, 200));
+	assert_eq!(host["enemy"], "Ahab");
+	let (host, join) = (&host["id"], &join["id"]);
+
+	let mut events = TcpStream::connect(api).await.unwrap();
+	let head = format!(
+		"GET /games/{host}/events HTTP/1.1\r\nHost: localhost:{}\r\n\r\n",
+		api.port()
+	);
+	events.write_all(head.as_bytes()).await.unwrap();
+	let mut events = BufReader::new(events).lines();
+
+	for id in [host, join] {
+		let place = json!({ "ship": "Destroyer", "pos": "A0" });
+		let (status, _) = request(api, "POST", &format!("/games/{id}/place"), Some(place)).await;
+		assert_eq!(status, 204);
+	}
+	let (_, view) = request(api, "GET", &format!("/games/{join}"), None).await;
+	assert_eq!(view["yours"][0], "DD...");
+
+	// The host fires first, and nobody else can while they do
+	our_turn(api, host).await;
+	let (status, error) = fire(api, join, "E4").await;
+	assert_eq!(status, 409, "{error}");
+	let (status, shot) = fire(api, host, "A0").await;
+	assert_eq!((status, &shot["hit"]), (200, &json!(true)));
+	our_turn(api, join).await;
+	assert_eq!(fire(api, join, "E4").await.1["hit"], false);
+	our_turn(api, host).await;
+	let (_, shot) = fire(api, host, "A1").await;
+	assert_eq!(
+		(&shot["sunk"], &shot["won"]),
+		(&json!("Destroyer"), &json!(true))
+	);
+
+	let (_, view) = request(api, "GET", &format!("/games/{host}"), None).await;
+	assert_eq!(view["phase"], json!({ "Done": true }));
+	assert_eq!(view["theirs"][0], "XX...");
+	let mut fired = false;
+	while let Some(line) = events.next_line().await.unwrap() {
+		if let Some(data) = line.strip_prefix("data:") {
+			let event: Value = serde_json::from_str(data.trim()).unwrap();
+			if event["event"] == "done" {
+				break;
+			}
+			fired |= event["event"] == "fired";
+		}
+	}
+	assert!(fired);
+
+	assert_eq!(request(api, "GET", "/games/99", None).await.0, 404);
+	let (status, _) = fire(api, host, "Z99").await;
+	assert_eq!(status, 400);
+	assert_eq!(
+		request(api, "DELETE", &format!("/games/{join}"), None)
+			.await
+			.0,
+		204
+	);
+	assert_eq!(
+		request(api, "GET", &format!("/games/{join}"), None).await.0,
+		404
+	);
+}
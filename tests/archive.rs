@@ -0,0 +1,94 @@
+use std::{env, fs, path::PathBuf};
+
+use netbattleship::{
+	archive::{Archive, Entry, Filter, Record},
+	config::Outcome,
+	rules::Rules,
+};
+
+/// Opens a fresh history called `name` in the temp directory.
+fn archive(name: &str) -> (Archive, PathBuf) {
+	let dir = env::temp_dir().join(format!("netbattleship-{name}-{}", std::process::id()));
+	let _ = fs::remove_dir_all(&dir);
+	(Archive::open_at(&dir).unwrap(), dir)
+}
+
+#[test]
+fn finished_games_are_found_by_opponent_outcome_and_date() {
+	let (archive, dir) = archive("archive");
+	let game = |opponent: &str, outcome, ended| Entry {
+		ended,
+		replay: Some(format!("{opponent}.replay").into()),
+		..Entry::new(opponent.to_string(), outcome, Rules::default(), 40)
+	};
+	// 2024-03-05, 2024-03-20 and 2024-04-01
+	let first = archive
+		.add(&game("Bob", Outcome::Won, 1_709_640_000))
+		.unwrap();
+	archive
+		.add(&game("Bob", Outcome::Lost, 1_710_936_000))
+		.unwrap();
+	let last = archive
+		.add(&game("Bob", Outcome::Won, 1_711_972_800))
+		.unwrap();
+	archive
+		.add(&game("Ahab", Outcome::Won, 1_709_640_000))
+		.unwrap();
+	assert!(first < last);
+
+	let wins_against_bob_in_march = Filter {
+		opponent: Some("bob".to_string()),
+		won: Some(true),
+		date: Some("2024-03".to_string()),
+	};
+	let found = archive.find(&wins_against_bob_in_march).unwrap();
+	assert_eq!(found.len(), 1);
+	assert_eq!(found[0].0, first);
+	assert_eq!(found[0].1.replay.as_deref(), Some("Bob.replay".as_ref()));
+
+	let losses = Filter {
+		won: Some(false),
+		..Filter::default()
+	};
+	assert_eq!(archive.find(&losses).unwrap().len(), 1);
+	assert_eq!(archive.find(&Filter::default()).unwrap().len(), 4);
+
+	// The history outlasts the program that wrote it
+	drop(archive);
+	let archive = Archive::open_at(&dir).unwrap();
+	assert_eq!(archive.find(&Filter::default()).unwrap().len(), 4);
+	drop(archive);
+	fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn records_against_an_opponent_count_resignations_as_losses() {
+	let (archive, dir) = archive("record");
+	for outcome in [Outcome::Won, Outcome::Won, Outcome::Resigned, Outcome::Won] {
+		let entry = Entry::new("Bob".to_string(), outcome, Rules::default(), 30);
+		archive.add(&entry).unwrap();
+	}
+	archive
+		.add(&Entry::new(
+			"Ahab".to_string(),
+			Outcome::Lost,
+			Rules::default(),
+			30,
+		))
+		.unwrap();
+
+	let bob = archive.against("BOB").unwrap();
+	assert_eq!((bob.won, bob.lost), (3, 1));
+	assert_eq!(bob.to_string(), "You lead BOB 3\u{2013}1");
+	let ahab = archive.against("Ahab").unwrap();
+	assert_eq!(ahab.to_string(), "Ahab leads you 1\u{2013}0");
+	let level = Record {
+		opponent: "Ishmael".to_string(),
+		won: 2,
+		lost: 2,
+	};
+	assert_eq!(level.to_string(), "You and Ishmael are level at 2\u{2013}2");
+	assert!(!archive.against("Queequeg").unwrap().played());
+	drop(archive);
+	fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,88 @@
+use std::{
+	io::Write,
+	net::{TcpListener, TcpStream},
+	thread,
+};
+
+use netbattleship::{
+	coord::Coord,
+	event::{Event, Player},
+	flow::{blocking::GameFlow, GameFlowError},
+	rules::Rules,
+	ship::ShipKind,
+	Phase,
+};
+
+/// Lays the fleet out along column 0, one ship per row from A down.
+fn place_fleet(game: &mut GameFlow, rules: &Rules) {
+	for (row, &ship) in (0..).zip(&rules.fleet) {
+		game.place_ship(ship, Coord::new(0, row), false).unwrap();
+	}
+}
+
+#[test]
+fn blocking_games_play_each_other() {
+	let rules = Rules::default();
+	let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+	let addr = listener.local_addr().unwrap();
+	let guest_rules = rules.clone();
+	let guest = thread::spawn(move || {
+		let mut guest = GameFlow::join(addr, guest_rules.clone()).unwrap();
+		place_fleet(&mut guest, &guest_rules);
+		let theirs = guest.receive().unwrap();
+		guest.chat("Missed me").unwrap();
+		let ours = guest.fire(Coord::new(5, 5)).unwrap();
+		(theirs.ship, ours.hit)
+	});
+
+	let mut host = GameFlow::host(&listener, &rules).unwrap();
+	place_fleet(&mut host, &rules);
+	host.wait_for_fleet().unwrap();
+	assert!(host.fire(Coord::new(0, 0)).unwrap().hit);
+	assert!(!host.receive().unwrap().hit);
+	assert_eq!(guest.join().unwrap(), (Some(ShipKind::Carrier), false));
+	assert!(host.phase() == Phase::Playing);
+	let events = std::iter::from_fn(|| host.poll_event()).collect::<Vec<_>>();
+	assert!(events.contains(&Event::Chat {
+		by: Player::Enemy,
+		text: "Missed me".to_string(),
+	}));
+}
+
+#[test]
+fn blocking_hosts_ignore_strangers() {
+	let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+	let addr = listener.local_addr().unwrap();
+	let host = thread::spawn(move || GameFlow::host(&listener, &Rules::default()));
+	let mut stranger = TcpStream::connect(addr).unwrap();
+	stranger
+		.write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")
+		.unwrap();
+	let guest = GameFlow::join(addr, Rules::default()).unwrap();
+	host.join().unwrap().unwrap();
+	assert!(guest.phase() == Phase::Placing(ShipKind::Carrier));
+	drop(stranger);
+}
+
+#[test]
+fn blocking_games_check_the_password() {
+	let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+	let addr = listener.local_addr().unwrap();
+	let host = thread::spawn(move || {
+		(0..2)
+			.map(|_| {
+				let (stream, _) = listener.accept().unwrap();
+				GameFlow::with_password(stream, true, Rules::default(), Some("swordfish"))
+					.map(|_| ())
+			})
+			.collect::<Vec<_>>()
+	});
+	for guess in ["marlin", "swordfish"] {
+		let stream = TcpStream::connect(addr).unwrap();
+		let guest = GameFlow::with_password(stream, false, Rules::default(), Some(guess));
+		assert_eq!(guest.is_ok(), guess == "swordfish");
+	}
+	let hosted = host.join().unwrap();
+	assert!(matches!(hosted[0], Err(GameFlowError::WrongPassword)));
+	assert!(hosted[1].is_ok());
+}
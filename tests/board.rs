@@ -0,0 +1,178 @@
+use netbattleship::{
+	board::{Board, Cell, CellState, LayoutError, Topology},
+	coord::Coord,
+	rules::{Rules, RulesError},
+	ship::ShipKind,
+};
+
+fn with_cruiser(rules: &Rules) -> Board {
+	let mut board = Board::new(rules);
+	// C3 to C5
+	assert!(ShipKind::Cruiser.place(&mut board, Coord::new(3, 2), false));
+	board
+}
+
+#[test]
+fn overlapping_cells_conflict() {
+	let board = with_cruiser(&Rules::default());
+	assert_eq!(
+		board.placement_conflicts(ShipKind::Destroyer, Coord::new(4, 1), true),
+		vec![Coord::new(4, 2)]
+	);
+	assert_eq!(
+		board.span(ShipKind::Cruiser),
+		Some((Coord::new(3, 2), Coord::new(5, 2)))
+	);
+	assert_eq!(board.span(ShipKind::Carrier), None);
+}
+
+#[test]
+fn touching_only_conflicts_with_adjacency() {
+	let destroyer = (ShipKind::Destroyer, Coord::new(6, 3), false);
+	let board = with_cruiser(&Rules::default());
+	assert!(board
+		.placement_conflicts(destroyer.0, destroyer.1, destroyer.2)
+		.is_empty());
+
+	let board = with_cruiser(&Rules {
+		adjacency: true,
+		..Rules::default()
+	});
+	assert_eq!(
+		board.placement_conflicts(destroyer.0, destroyer.1, destroyer.2),
+		vec![Coord::new(5, 2)]
+	);
+}
+
+#[test]
+fn running_off_the_board_is_no_conflict() {
+	let board = with_cruiser(&Rules::default());
+	assert!(board
+		.placement_conflicts(ShipKind::Carrier, Coord::new(7, 9), false)
+		.is_empty());
+	assert!(!ShipKind::Carrier.can_place(&board, Coord::new(7, 9), false));
+}
+
+#[test]
+fn hexes_touch_six_others() {
+	let hex = Rules {
+		topology: Topology::Hex,
+		adjacency: true,
+		..Rules::default()
+	};
+	let board = Board::new(&hex);
+	let mut around = board.neighbours(Coord::new(4, 4)).collect::<Vec<_>>();
+	around.sort_by_key(|c| (c.y, c.x));
+	assert_eq!(
+		around,
+		[(4, 3), (5, 3), (3, 4), (5, 4), (3, 5), (4, 5)].map(|(x, y)| Coord::new(x, y))
+	);
+
+	// The diagonal down and right isn't adjacent, but down and left is
+	let board = with_cruiser(&hex);
+	assert!(board
+		.placement_conflicts(ShipKind::Destroyer, Coord::new(6, 3), false)
+		.is_empty());
+	assert_eq!(
+		board.placement_conflicts(ShipKind::Destroyer, Coord::new(1, 3), false),
+		vec![Coord::new(3, 2)]
+	);
+}
+
+#[test]
+fn ships_run_aground_on_land() {
+	let rules = Rules {
+		fleet: vec![ShipKind::Destroyer],
+		..Rules::default()
+	}
+	.with_map(".#..\n....\n....\n...#\n")
+	.unwrap();
+	assert_eq!(rules.board_size, 4);
+	let board = Board::new(&rules);
+	assert!(!ShipKind::Destroyer.can_place(&board, Coord::new(0, 0), false));
+	assert!(ShipKind::Destroyer.can_place(&board, Coord::new(0, 0), true));
+	assert!(!board.is_water(Coord::new(3, 3)));
+	assert!(board.is_water(Coord::new(2, 3)));
+	assert!(matches!(
+		Rules::default().with_map("..\n.\n"),
+		Err(RulesError::BadMap(2))
+	));
+}
+
+#[test]
+fn diffs_list_every_square_that_changed() {
+	let before = with_cruiser(&Rules::default());
+	let mut after = before.clone();
+	let hit = Cell {
+		state: CellState::Hit,
+		..after.cell(Coord::new(4, 2))
+	};
+	after.board.insert(Coord::new(4, 2), hit);
+	after
+		.board
+		.insert(Coord::new(0, 0), Cell::shot(CellState::Miss));
+	assert_eq!(
+		before.diff(&after),
+		vec![
+			(
+				Coord::new(0, 0),
+				Cell::default(),
+				Cell::shot(CellState::Miss)
+			),
+			(Coord::new(4, 2), Cell::ship(ShipKind::Cruiser), hit),
+		]
+	);
+	assert!(after.diff(&after.clone()).is_empty());
+}
+
+#[test]
+fn layouts_read_back_as_written() {
+	let layout = "\
+		CCCCC.....\n\
+		..........\n\
+		..BBBB...d\n\
+		.........D\n\
+		..........\n\
+		.S........\n\
+		.S...#....\n\
+		.S........\n\
+		....RRR...\n\
+		..........\n";
+	let board = layout.parse::<Board>().unwrap();
+	assert_eq!(board.size(), 10);
+	assert!(board.land().contains(&Coord::new(5, 6)));
+	assert_eq!(
+		board.placements(),
+		[
+			(ShipKind::Carrier, Coord::new(0, 0), false),
+			(ShipKind::Battleship, Coord::new(2, 2), false),
+			(ShipKind::Cruiser, Coord::new(4, 8), false),
+			(ShipKind::Submarine, Coord::new(1, 5), true),
+			(ShipKind::Destroyer, Coord::new(9, 2), true),
+		]
+	);
+	assert_eq!(board.to_string(), layout.to_uppercase());
+}
+
+#[test]
+fn malformed_layouts_are_refused() {
+	assert_eq!(
+		"...\n..\n...".parse::<Board>().err(),
+		Some(LayoutError::BadLine(2))
+	);
+	assert_eq!(
+		"...\n.x.\n...".parse::<Board>().err(),
+		Some(LayoutError::BadSquare {
+			line: 2,
+			square: 'x'
+		})
+	);
+	assert_eq!(
+		"DD.\n...\n..D".parse::<Board>().err(),
+		Some(LayoutError::BadShip(ShipKind::Destroyer))
+	);
+	assert_eq!(
+		"RR.\n.R.\n...".parse::<Board>().err(),
+		Some(LayoutError::BadShip(ShipKind::Cruiser))
+	);
+}
@@ -0,0 +1,268 @@
+#![cfg(feature = "async")]
+
+use std::{env, time::Duration};
+
+use netbattleship::{
+	board::{Board, Cell, CellState},
+	bot::{
+		self,
+		past::PastYou,
+		taunt::{Taunting, Taunts},
+		twitch::{Login, Twitch},
+		Difficulty, Engine,
+	},
+	coord::Coord,
+	event::{Event, Player},
+	history::Move,
+	replay::Replay,
+	rules::Rules,
+	ship::ShipKind,
+	Game, Phase,
+};
+
+/// Lets `bot` fire at a fleet `placer` laid out until it's all sunk,
+/// returning how many shots that took.
+fn hunt(mut bot: Difficulty, mut placer: Difficulty) -> usize {
+	let rules = Rules::default();
+	let mut target = Board::new(&rules);
+	for &ship in &rules.fleet {
+		let (pos, v) = placer.placement(&target, ship).unwrap();
+		assert!(ship.place(&mut target, pos, v));
+	}
+	let mut game = Game::new(rules.clone(), true);
+	let enemy = usize::from(!game.you);
+	let mut shots = 0;
+	while game.sunk[enemy].len() < rules.fleet.len() {
+		let aim = bot.aim(&game);
+		assert_eq!(
+			game.board[enemy].cell(aim).state,
+			CellState::Untouched,
+			"{bot} bot fired at {aim} twice"
+		);
+		let cell = target.cell(aim);
+		let state = if cell.ship.is_some() {
+			CellState::Hit
+		} else {
+			CellState::Miss
+		};
+		target.board.insert(aim, Cell { state, ..cell });
+		game.board[enemy].board.insert(aim, Cell::shot(state));
+		if let Some(ship) = cell.ship.filter(|&ship| !target.contains(ship)) {
+			game.sunk[enemy].push(ship);
+		}
+		shots += 1;
+	}
+	shots
+}
+
+#[test]
+fn difficulties_parse() {
+	assert_eq!("Hard".parse(), Ok(Difficulty::Hard));
+	assert_eq!(Difficulty::Medium.to_string(), "medium");
+	assert!("impossible".parse::<Difficulty>().is_err());
+}
+
+#[test]
+fn harder_bots_sink_fleets_sooner() {
+	const GAMES: usize = 40;
+	let average = |bot| {
+		(0..GAMES)
+			.map(|_| hunt(bot, Difficulty::Hard))
+			.sum::<usize>()
+			/ GAMES
+	};
+	let (easy, medium, hard) = (
+		average(Difficulty::Easy),
+		average(Difficulty::Medium),
+		average(Difficulty::Hard),
+	);
+	// Against fleets placed to dodge heatmaps, firing at random takes about
+	// 95 shots on average, hunting and targeting about 70, and the heatmap
+	// with parity about 60
+	assert!(medium < easy, "medium took {medium} shots, easy {easy}");
+	assert!(hard < medium, "hard took {hard} shots, medium {medium}");
+}
+
+#[test]
+fn hunts_sink_the_whole_fleet() {
+	let rules = Rules::default();
+	let cells = rules.fleet.iter().map(|ship| usize::from(ship.len())).sum();
+	for mut bot in [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard] {
+		let shots = bot::hunt(&mut bot, &mut Difficulty::Easy, &rules);
+		assert!(
+			(cells..=100).contains(&shots),
+			"{bot} bot took {shots} shots"
+		);
+	}
+}
+
+#[test]
+fn taunts_fill_in_the_ship() {
+	let taunts: Taunts = toml::from_str(
+		r#"
+		chance = 1.0
+		sunk = ["Bye, {ship}!"]
+		miss = []
+		"#,
+	)
+	.unwrap();
+	let shot = |hit, sunk| Event::Result {
+		by: Player::You,
+		pos: Coord::new(0, 0),
+		hit,
+		ship: None,
+		sunk,
+	};
+	assert_eq!(
+		taunts.line(&shot(Some(true), Some(ShipKind::Submarine))),
+		Some("Bye, Submarine!".to_string())
+	);
+	// Left out keeps the built-in lines, empty keeps quiet
+	assert!(taunts.line(&shot(Some(true), None)).is_some());
+	assert_eq!(taunts.line(&shot(Some(false), None)), None);
+	// Shots hidden by the blind rule say nothing
+	assert_eq!(taunts.line(&shot(None, None)), None);
+	assert!(toml::from_str::<Taunts>("hits = []").is_err());
+}
+
+#[test]
+fn taunting_plays_the_same() {
+	let mut bot = Taunting {
+		engine: Difficulty::Easy,
+		taunts: Taunts {
+			chance: 1.0,
+			enemy_miss: vec!["Missed me!".to_string()],
+			..Taunts::default()
+		},
+	};
+	let rules = Rules::default();
+	let board = Board::new(&rules);
+	assert!(bot.placement(&board, ShipKind::Carrier).is_some());
+	let game = Game::new(rules, true);
+	assert!(board.in_bounds(bot.aim(&game)));
+	let missed = Event::Result {
+		by: Player::Enemy,
+		pos: Coord::new(3, 3),
+		hit: Some(false),
+		ship: None,
+		sunk: None,
+	};
+	assert_eq!(bot.react(&missed), Some("Missed me!".to_string()));
+	assert_eq!(bot.react(&Event::Playing), None);
+}
+
+#[tokio::test]
+async fn practice_games_play_to_the_end() {
+	let rules = Rules {
+		board_size: 5,
+		fleet: vec![ShipKind::Cruiser, ShipKind::Destroyer],
+		..Rules::default()
+	};
+	let game = bot::practice(rules, Difficulty::Easy).await.unwrap();
+	game.play_with(Difficulty::Hard).await.unwrap();
+	assert!(matches!(game.phase().await, Phase::Done(_)));
+}
+
+#[test]
+fn past_you_places_ships_like_you_did() {
+	let rules = Rules::default();
+	let mut game = Game::new(rules.clone(), true);
+	game.log = (0..)
+		.zip(&rules.fleet)
+		.map(|(row, &ship)| Move::Place {
+			ship,
+			pos: Coord::new(0, row),
+			vertical: false,
+		})
+		.collect();
+	let path = env::temp_dir().join(format!("netbattleship-past-{}.nbr", std::process::id()));
+	Replay::new(&game, String::new(), String::new())
+		.store(&path)
+		.unwrap();
+	let other_rules = Rules {
+		board_size: 8,
+		..Rules::default()
+	};
+	let past = PastYou::load(Difficulty::Easy, &rules, [&path]);
+	let other = PastYou::load(Difficulty::Easy, &other_rules, [&path]);
+	let _ = std::fs::remove_file(&path);
+	let mut past = past.unwrap();
+	assert_eq!(past.layouts(), 1);
+	assert_eq!(other.unwrap().layouts(), 0);
+
+	let mut board = Board::new(&rules);
+	for (row, &ship) in (0..).zip(&rules.fleet) {
+		let (pos, v) = past.placement(&board, ship).unwrap();
+		assert_eq!((pos, v), (Coord::new(0, row), false));
+		assert!(ship.place(&mut board, pos, v));
+	}
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn chat_fires_where_most_viewers_vote() {
+	use tokio::{
+		io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+		net::TcpListener,
+	};
+
+	let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+	let addr = listener.local_addr().unwrap();
+	let login = Login {
+		nick: "Host".to_string(),
+		token: "secret".to_string(),
+	};
+	let countdown = Duration::from_millis(300);
+	let connecting = Twitch::connect(addr, "#Stream", Some(login), Difficulty::Easy, countdown);
+	let (twitch, accepted) = tokio::join!(connecting, listener.accept());
+	let mut twitch = twitch.unwrap();
+	let (stream, _) = accepted.unwrap();
+	let (read, mut write) = stream.into_split();
+	let mut lines = BufReader::new(read).lines();
+	let mut next = async || lines.next_line().await.unwrap().unwrap();
+	assert_eq!(next().await, "PASS oauth:secret");
+	assert_eq!(next().await, "NICK host");
+	assert_eq!(next().await, "JOIN #stream");
+
+	let mut game = Game::new(Rules::default(), true);
+	let enemy = usize::from(!game.you);
+	let a0 = "A0".parse().unwrap();
+	game.board[enemy]
+		.board
+		.insert(a0, Cell::shot(CellState::Miss));
+	let aiming = tokio::task::spawn_blocking(move || (twitch.aim(&game), twitch, game));
+	assert!(next()
+		.await
+		.starts_with("PRIVMSG #stream :Where should we fire?"));
+	// Alice changes her mind, and nobody can vote for a square already shot
+	let chat = [
+		"PING :tmi.twitch.tv",
+		":alice!alice@alice.tmi.twitch.tv PRIVMSG #stream :B2",
+		":bob!bob@bob.tmi.twitch.tv PRIVMSG #stream :b2",
+		":carol!carol@carol.tmi.twitch.tv PRIVMSG #stream :C3",
+		":alice!alice@alice.tmi.twitch.tv PRIVMSG #stream :c3",
+		":dave!dave@dave.tmi.twitch.tv PRIVMSG #stream :A0",
+		":erin!erin@erin.tmi.twitch.tv PRIVMSG #stream :A0",
+		":erin!erin@erin.tmi.twitch.tv PRIVMSG #stream :hello everyone",
+	];
+	for line in chat {
+		write
+			.write_all(format!("{line}\r\n").as_bytes())
+			.await
+			.unwrap();
+	}
+	assert_eq!(next().await, "PONG :tmi.twitch.tv");
+	let (aim, mut twitch, game) = aiming.await.unwrap();
+	assert_eq!(aim, "C3".parse().unwrap());
+	assert_eq!(
+		next().await,
+		"PRIVMSG #stream :Firing at C3, with 2 of 3 votes."
+	);
+
+	// Without chat the bot fires straight away
+	drop((write, lines));
+	tokio::time::sleep(Duration::from_millis(50)).await;
+	let aim = tokio::task::spawn_blocking(move || twitch.aim(&game))
+		.await
+		.unwrap();
+	assert_ne!(aim, a0);
+}
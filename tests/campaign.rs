@@ -0,0 +1,98 @@
+use std::{collections::BTreeSet, env, fs, path::PathBuf};
+
+use netbattleship::{
+	board::Board,
+	bot::Engine,
+	campaign::{Campaign, CampaignError},
+	coord::Coord,
+	rules::Rules,
+	ship::ShipKind,
+};
+
+/// Writes `missions`, as file names and contents, to a fresh campaign
+/// directory called `name`.
+fn campaign(name: &str, missions: &[(&str, &str)]) -> PathBuf {
+	let dir = env::temp_dir().join(format!("netbattleship-{name}-{}", std::process::id()));
+	let _ = fs::remove_dir_all(&dir);
+	fs::create_dir_all(&dir).unwrap();
+	for (file, text) in missions {
+		fs::write(dir.join(file), text).unwrap();
+	}
+	dir
+}
+
+#[test]
+fn missions_unlock_in_order() {
+	let dir = campaign(
+		"order",
+		&[
+			("01-first.toml", "name = \"First\""),
+			("02-second.toml", "name = \"Second\"\nafter = \"01-first\""),
+			("notes.txt", "not a mission"),
+		],
+	);
+	let campaign = Campaign::load(&dir).unwrap();
+	assert_eq!(campaign.missions.len(), 2);
+	let mut won = BTreeSet::new();
+	assert_eq!(campaign.next(&won).unwrap().id, "01-first");
+	assert!(!Campaign::unlocked(
+		campaign.mission("02-second").unwrap(),
+		&won
+	));
+	won.insert("01-first".to_string());
+	assert_eq!(campaign.next(&won).unwrap().name, "Second");
+	won.insert("02-second".to_string());
+	assert!(campaign.next(&won).is_none());
+	fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn the_enemy_places_its_fleet_as_the_mission_says() {
+	let dir = campaign(
+		"fleet",
+		&[(
+			"harbour.toml",
+			"name = \"Harbour\"\nbot = \"hard\"\narea = [\"A0\", \"D9\"]\n\
+			 fleet = [{ ship = \"Carrier\", at = \"E0\", vertical = true }]",
+		)],
+	);
+	let campaign = Campaign::load(&dir).unwrap();
+	let mission = &campaign.missions[0];
+	let mut enemy = mission.enemy();
+	let mut board = Board::new(&Rules::default());
+	for &ship in &mission.rules.fleet {
+		let (pos, v) = enemy.placement(&board, ship).unwrap();
+		assert!(ship.place(&mut board, pos, v));
+		if ship == ShipKind::Carrier {
+			assert_eq!((pos, v), (Coord::new(0, 4), true));
+		} else {
+			assert!(ship.footprint(pos, v).all(|cell| cell.y <= 3));
+		}
+	}
+	fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn broken_missions_are_refused() {
+	for (name, text) in [
+		("after", "name = \"Lost\"\nafter = \"nowhere\""),
+		("square", "name = \"Off\"\nfleet = [{ ship = \"Carrier\", at = \"Z0\" }]"),
+		(
+			"stray",
+			"name = \"Stray\"\nfleet = [{ ship = \"Carrier\", at = \"A0\" }]\n[rules]\nfleet = [\"Destroyer\"]",
+		),
+	] {
+		let dir = campaign(name, &[("mission.toml", text)]);
+		let loaded = Campaign::load(&dir);
+		assert!(
+			matches!(
+				(name, &loaded),
+				("after", Err(CampaignError::After(..)))
+					| ("square", Err(CampaignError::Coord(..)))
+					| ("stray", Err(CampaignError::Placement(..)))
+			),
+			"{name}: {loaded:?}"
+		);
+		fs::remove_dir_all(dir).unwrap();
+	}
+}
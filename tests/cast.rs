@@ -0,0 +1,23 @@
+use netbattleship::ui::cast::Cast;
+use serde_json::Value;
+
+#[test]
+fn casts_are_asciinema_v2() {
+	let mut cast = Cast::new(Vec::new(), 100, 30).unwrap();
+	cast.output("Your turn.\nFire at: ").unwrap();
+	cast.output("\x1b[2J\r\n").unwrap();
+	let written = String::from_utf8(cast.into_inner()).unwrap();
+	let lines = written
+		.lines()
+		.map(|line| serde_json::from_str::<Value>(line).unwrap())
+		.collect::<Vec<_>>();
+	assert_eq!(lines.len(), 3);
+	assert_eq!(lines[0]["version"], 2);
+	assert_eq!(lines[0]["width"], 100);
+	assert_eq!(lines[0]["height"], 30);
+	assert_eq!(lines[1][1], "o");
+	assert_eq!(lines[1][2], "Your turn.\r\nFire at: ");
+	// Output already meant for raw mode is left alone
+	assert_eq!(lines[2][2], "\x1b[2J\r\n");
+	assert!(lines[1][0].as_f64().unwrap() <= lines[2][0].as_f64().unwrap());
+}
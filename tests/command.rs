@@ -0,0 +1,169 @@
+#![cfg(feature = "async")]
+
+use netbattleship::{
+	coord::{Coord, Notation, ParseCoordError},
+	ui::command::{Command, ParseCommandError},
+};
+
+fn parse(s: &str) -> Result<Command, ParseCommandError> {
+	s.parse()
+}
+
+#[test]
+fn bare_coordinates() {
+	assert_eq!(parse("E5"), Ok(Command::Cell(Coord::new(5, 4))));
+	assert_eq!(parse(" a0 "), Ok(Command::Cell(Coord::new(0, 0))));
+	assert_eq!(parse("F5"), Ok(Command::Cell(Coord::new(5, 5))));
+}
+
+#[test]
+fn any_notation() {
+	let e5 = Ok(Command::Cell(Coord::new(5, 4)));
+	assert_eq!(parse("5e"), e5);
+	assert_eq!(parse("E-5"), e5);
+	assert_eq!(parse("5-E"), e5);
+	assert_eq!(parse("4,5"), e5);
+	assert_eq!(parse("4, 5"), e5);
+	assert_eq!(parse("q5r4"), e5);
+	assert_eq!(parse("Q5 R4"), e5);
+	assert_eq!(
+		parse("p 4,5 v"),
+		Ok(Command::Place {
+			pos: Coord::new(5, 4),
+			vertical: true
+		})
+	);
+	assert_eq!(
+		parse("e10"),
+		Err(ParseCommandError::Coord(ParseCoordError::BadColumn))
+	);
+	assert_eq!(
+		parse("4,10"),
+		Err(ParseCommandError::Coord(ParseCoordError::BadColumn))
+	);
+	assert_eq!("E--5".parse::<Coord>(), Err(ParseCoordError::BadColumn));
+
+	let written = |notation| Coord::new(5, 4).written(notation).to_string();
+	assert_eq!(written(Notation::LetterFirst), "E5");
+	assert_eq!(written(Notation::NumberFirst), "5E");
+	assert_eq!(written(Notation::Numeric), "4,5");
+	assert_eq!(written(Notation::Axial), "q5r4");
+	let past_z = Coord::new(5, 200);
+	assert_eq!(past_z.row(), '?');
+	assert_eq!(past_z.to_string(), "200,5");
+	assert_eq!(past_z.written(Notation::NumberFirst).to_string(), "200,5");
+	assert_eq!("1A".parse(), Ok(Notation::NumberFirst));
+	assert_eq!("q1r0".parse(), Ok(Notation::Axial));
+	assert_eq!(
+		"hex".parse::<Notation>().unwrap_err().to_string(),
+		"unknown notation \"hex\", expected letter-first, number-first, numeric or axial"
+	);
+}
+
+#[test]
+fn placing() {
+	assert_eq!(
+		parse("pe5v"),
+		Ok(Command::Place {
+			pos: Coord::new(5, 4),
+			vertical: true
+		})
+	);
+	assert_eq!(
+		parse("P J9"),
+		Ok(Command::Place {
+			pos: Coord::new(9, 9),
+			vertical: false
+		})
+	);
+	assert_eq!(parse("p e5 x"), Err(ParseCommandError::Unknown));
+}
+
+#[test]
+fn moving() {
+	assert_eq!(
+		parse("m c7 v"),
+		Ok(Command::Move {
+			pos: Coord::new(7, 2),
+			vertical: true
+		})
+	);
+}
+
+#[test]
+fn firing() {
+	assert_eq!(parse("f b3"), Ok(Command::Fire(Coord::new(3, 1))));
+	assert_eq!(
+		parse("fz3"),
+		Err(ParseCommandError::Coord(ParseCoordError::BadRow))
+	);
+}
+
+#[test]
+fn scanning() {
+	assert_eq!(parse("r d4"), Ok(Command::Scan(Coord::new(4, 3))));
+}
+
+#[test]
+fn querying_depends_on_case() {
+	assert_eq!(
+		parse("qc2"),
+		Ok(Command::Query {
+			pos: Coord::new(2, 2),
+			own: false
+		})
+	);
+	assert_eq!(
+		parse("Qc2"),
+		Ok(Command::Query {
+			pos: Coord::new(2, 2),
+			own: true
+		})
+	);
+}
+
+#[test]
+fn words() {
+	assert_eq!(parse(""), Ok(Command::Nothing));
+	assert_eq!(parse("HELP"), Ok(Command::Help));
+	assert_eq!(parse("?"), Ok(Command::Help));
+	assert_eq!(parse("quit"), Ok(Command::Quit));
+	assert_eq!(parse("Auto"), Ok(Command::Auto));
+	assert_eq!(parse("repeat"), Ok(Command::Repeat));
+	assert_eq!(parse("Where"), Ok(Command::Where));
+	assert_eq!(parse("confirm"), Ok(Command::Confirm));
+	assert_eq!(parse("TIME"), Ok(Command::Time));
+	assert_eq!(parse("grid"), Ok(Command::Grid));
+	assert_eq!(parse("\u{1b}"), Ok(Command::Nothing));
+	assert_eq!(parse("hello"), Err(ParseCommandError::Unknown));
+}
+
+#[test]
+fn chat_keeps_the_message() {
+	assert_eq!(
+		parse("say  Nice Shot! "),
+		Ok(Command::Chat("Nice Shot!".into()))
+	);
+}
+
+#[test]
+fn exporting_keeps_the_file_name() {
+	assert_eq!(
+		parse("export  my battle.png "),
+		Ok(Command::Export("my battle.png".into()))
+	);
+	assert_eq!(parse("export"), Err(ParseCommandError::Unknown));
+}
+
+#[test]
+fn layouts_by_name() {
+	assert_eq!(
+		parse("layout corners"),
+		Ok(Command::Layout("corners".into()))
+	);
+	assert_eq!(
+		parse("Layout  save  my-fleet "),
+		Ok(Command::SaveLayout("my-fleet".into()))
+	);
+	assert_eq!(parse("layout save"), Ok(Command::Layout("save".into())));
+}
@@ -0,0 +1,24 @@
+use netbattleship::{
+	config::Config,
+	ui::earcon::{Cue, Earcons},
+};
+
+#[test]
+fn earcons_can_be_retuned_or_turned_off() {
+	let config: Config = toml::from_str(
+		"[earcons.hit]\nnotes = [440, 880]\nlength = 50\n\
+		 [earcons.miss]\nenabled = false",
+	)
+	.unwrap();
+	let earcons = &config.earcons;
+	let hit = earcons.tone(Cue::Hit);
+	assert!(hit.enabled);
+	assert_eq!(
+		(hit.notes.as_slice(), hit.length),
+		([440, 880].as_slice(), 50)
+	);
+	assert!(!earcons.tone(Cue::Miss).enabled);
+	// Cues left out keep their usual tones
+	assert_eq!(earcons.tone(Cue::Sunk), Earcons::default().tone(Cue::Sunk));
+	assert!(!earcons.tone(Cue::YourTurn).notes.is_empty());
+}
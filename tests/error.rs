@@ -0,0 +1,46 @@
+#![cfg(feature = "async")]
+
+use std::io;
+
+use netbattleship::{
+	flow::GameFlowError,
+	net::NetError,
+	ui::error::{self, Failure, Recovery},
+};
+
+#[test]
+fn recoveries_by_letter_or_word() {
+	assert_eq!("r".parse(), Ok(Recovery::Reconnect));
+	assert_eq!(" Save ".parse(), Ok(Recovery::Save));
+	assert_eq!("QUIT".parse(), Ok(Recovery::Quit));
+	assert_eq!("x".parse::<Recovery>(), Err(()));
+}
+
+#[test]
+fn lost_connections_are_explained() {
+	let e = GameFlowError::Network(io::Error::from(io::ErrorKind::ConnectionReset));
+	assert!(Failure::from(e).0.starts_with("Lost the connection"));
+	let e = NetError::Io(io::Error::from(io::ErrorKind::UnexpectedEof));
+	assert!(Failure::from(GameFlowError::Net(e))
+		.0
+		.starts_with("Lost the connection"));
+	assert_eq!(
+		Failure::from(GameFlowError::TimedOut),
+		Failure("Your enemy stopped answering.".to_string())
+	);
+}
+
+#[tokio::test]
+async fn panics_become_failures() {
+	assert_eq!(error::catch(async { 7 }).await, Ok(7));
+	let caught = error::catch(async { panic!("out of torpedoes") }).await;
+	assert_eq!(
+		caught,
+		Err::<(), _>(Failure("The game crashed (out of torpedoes).".to_string()))
+	);
+	let task = tokio::spawn(async { panic!("{} torpedoes", 0) });
+	assert_eq!(
+		Failure::from(task.await.unwrap_err()),
+		Failure("The game crashed (0 torpedoes).".to_string())
+	);
+}
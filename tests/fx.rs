@@ -0,0 +1,32 @@
+#![cfg(feature = "async")]
+
+use netbattleship::ui::fx::Animation;
+
+#[test]
+fn frames_line_up() {
+	let all = [
+		Animation::intro(),
+		Animation::incoming(),
+		Animation::explosion(),
+		Animation::splash(),
+		Animation::sinking(),
+	];
+	for animation in all {
+		assert!(!animation.frames.is_empty());
+		let height = animation.frames[0].lines.len();
+		assert!(animation.frames.iter().all(|f| f.lines.len() == height));
+	}
+}
+
+#[test]
+fn secret_shots_skip_the_result() {
+	assert_eq!(Animation::shot(None, false), vec![Animation::incoming()]);
+	assert_eq!(
+		Animation::shot(Some(true), true),
+		vec![
+			Animation::incoming(),
+			Animation::explosion(),
+			Animation::sinking()
+		]
+	);
+}
@@ -0,0 +1,103 @@
+#![cfg(feature = "async")]
+
+use netbattleship::{
+	config::Config,
+	coord::Coord,
+	event::{Event, Player},
+	hooks::{Hook, HookError, Trigger},
+	ship::ShipKind,
+};
+use tokio::{
+	io::{AsyncReadExt, AsyncWriteExt},
+	net::TcpListener,
+};
+
+fn sinking() -> Event {
+	Event::Result {
+		by: Player::Enemy,
+		pos: Coord::new(4, 2),
+		hit: Some(true),
+		ship: Some(ShipKind::Cruiser),
+		sunk: Some(ShipKind::Cruiser),
+	}
+}
+
+#[test]
+fn sinking_is_hitting_too() {
+	assert_eq!(
+		Trigger::of(&sinking()),
+		[Trigger::EnemyHit, Trigger::EnemySunk]
+	);
+	let hidden = Event::Result {
+		by: Player::You,
+		pos: Coord::new(0, 0),
+		hit: None,
+		ship: None,
+		sunk: None,
+	};
+	assert!(Trigger::of(&hidden).is_empty());
+	assert_eq!(Trigger::of(&Event::Done { won: false }), [Trigger::Lose]);
+}
+
+#[test]
+fn hooks_are_read_from_the_config() {
+	let config: Config = toml::from_str(
+		r#"
+		[[hooks]]
+		on = "enemy_sunk"
+		run = ["flash", "{ship}"]
+
+		[[hooks]]
+		on = "win"
+		url = "http://127.0.0.1:8123/won"
+		"#,
+	)
+	.unwrap();
+	assert_eq!(config.hooks[0].on, Trigger::EnemySunk);
+	assert_eq!(config.hooks[0].run, ["flash", "{ship}"]);
+	assert_eq!(
+		config.hooks[1].url.as_deref(),
+		Some("http://127.0.0.1:8123/won")
+	);
+	let again: Config = toml::from_str(&toml::to_string(&config).unwrap()).unwrap();
+	assert_eq!(again, config);
+}
+
+#[tokio::test]
+async fn webhooks_get_the_event() {
+	let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+	let addr = listener.local_addr().unwrap();
+	let webhook = tokio::spawn(async move {
+		let (mut socket, _) = listener.accept().await.unwrap();
+		let mut request = vec![0; 4096];
+		let read = socket.read(&mut request).await.unwrap();
+		socket
+			.write_all(b"HTTP/1.1 204 No Content\r\n\r\n")
+			.await
+			.unwrap();
+		String::from_utf8_lossy(&request[..read]).into_owned()
+	});
+	let hook = Hook {
+		on: Trigger::EnemySunk,
+		run: Vec::new(),
+		url: Some(format!("http://{addr}/sunk/{{ship}}")),
+	};
+	hook.fire(Trigger::EnemySunk, &sinking()).await.unwrap();
+	let request = webhook.await.unwrap();
+	assert!(request.starts_with("POST /sunk/Cruiser HTTP/1.1\r\n"));
+	assert!(request.contains(r#""on":"enemy_sunk""#));
+	assert!(request.contains(&format!(r#""pos":"{}""#, Coord::new(4, 2))));
+}
+
+#[tokio::test]
+async fn only_plain_http_is_posted_to() {
+	let hook = Hook {
+		on: Trigger::Win,
+		run: Vec::new(),
+		url: Some("https://example.com/won".to_string()),
+	};
+	assert!(matches!(
+		hook.fire(Trigger::Win, &Event::Done { won: true }).await,
+		Err(HookError::NotHttp)
+	));
+}
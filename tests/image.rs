@@ -0,0 +1,49 @@
+use std::path::Path;
+
+use netbattleship::{
+	board::{Cell, CellState},
+	coord::Coord,
+	render::image::{self, ExportError, Format},
+	rules::Rules,
+	ship::ShipKind,
+	Game,
+};
+
+/// A game where we've hit a ship and they've missed once.
+fn battle() -> Game {
+	let mut game = Game::new(Rules::default(), true);
+	let (ours, theirs) = (usize::from(game.you), usize::from(!game.you));
+	assert!(ShipKind::Destroyer.place(&mut game.board[ours], Coord::new(0, 0), false));
+	game.board[ours]
+		.board
+		.insert(Coord::new(9, 9), Cell::shot(CellState::Miss));
+	game.board[theirs]
+		.board
+		.insert(Coord::new(4, 4), Cell::shot(CellState::Hit));
+	game
+}
+
+#[test]
+fn formats_follow_the_extension() {
+	assert_eq!(Format::of(Path::new("battle.PNG")), Some(Format::Png));
+	assert_eq!(Format::of(Path::new("maps/battle.svg")), Some(Format::Svg));
+	assert_eq!(Format::of(Path::new("battle.txt")), None);
+	assert!(matches!(
+		image::export(&battle(), Path::new("battle")),
+		Err(ExportError::UnknownFormat(_))
+	));
+}
+
+#[test]
+fn both_formats_draw_the_boards() {
+	let svg = image::svg(&battle());
+	assert!(svg.starts_with("<svg"));
+	assert!(svg.contains(">Them</text>"));
+	// 200 squares of water, two of ship, and one hit
+	assert_eq!(svg.matches("<rect").count(), 1 + 200 + 2 + 1);
+	// Their miss on our board
+	assert_eq!(svg.matches("<circle").count(), 1);
+
+	let png = image::png(&battle()).unwrap();
+	assert!(png.starts_with(b"\x89PNG"));
+}
@@ -0,0 +1,11 @@
+#![cfg(feature = "async")]
+
+use netbattleship::ui::input::Input;
+
+#[tokio::test]
+async fn scripts_skip_blanks_and_comments() {
+	let mut input = Input::script("# place the carrier\np A0\n\n  f J9  \n");
+	assert_eq!(input.next().await.as_deref(), Some("p A0"));
+	assert_eq!(input.next().await.as_deref(), Some("f J9"));
+	assert_eq!(input.next().await, None);
+}
@@ -0,0 +1,37 @@
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+
+use netbattleship::{
+	address::Address,
+	invite::{self, Invite},
+};
+
+const HOST: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 20), 7777);
+
+#[test]
+fn links_join_like_codes() {
+	let link = Invite(HOST).link();
+	assert_eq!(link.parse::<Invite>().unwrap(), Invite(HOST));
+	assert_eq!(link.parse::<Address>().unwrap(), Address::Direct(HOST));
+}
+
+#[test]
+fn qr_codes_are_square() {
+	let rows = Invite(HOST).qr();
+	assert!(rows.iter().all(|row| row.len() == rows.len()));
+	assert!(!Invite(HOST).qr_text().is_empty());
+}
+
+#[test]
+fn qr_codes_are_for_the_local_network() {
+	let addrs = [
+		SocketAddr::from((Ipv4Addr::new(203, 0, 113, 5), 7777)),
+		SocketAddr::from((Ipv4Addr::LOCALHOST, 7777)),
+		SocketAddr::from(HOST),
+	];
+	assert_eq!(invite::lan_addr(&addrs), Some(HOST));
+	assert_eq!(
+		invite::lan_addr(&addrs[..2]),
+		Some(SocketAddrV4::new(Ipv4Addr::new(203, 0, 113, 5), 7777))
+	);
+	assert_eq!(invite::lan_addr(&addrs[1..2]), None);
+}
@@ -0,0 +1,94 @@
+use netbattleship::{
+	board::Board,
+	coord::Coord,
+	layouts::{self, LayoutsError},
+	rules::Rules,
+	ship::ShipKind,
+};
+
+/// Places `placements` on a fresh board for `rules`, checking each fits.
+fn laid_out(rules: &Rules, placements: &[(ShipKind, Coord, bool)]) -> Board {
+	let mut board = Board::new(rules);
+	for &(ship, pos, v) in placements {
+		assert!(ship.place(&mut board, pos, v), "{ship:?} doesn't fit");
+	}
+	board
+}
+
+#[test]
+fn presets_place_the_whole_fleet() {
+	for adjacency in [false, true] {
+		let rules = Rules {
+			adjacency,
+			..Rules::default()
+		};
+		for name in layouts::PRESETS {
+			let placements = layouts::placements(name, &Board::new(&rules), &rules.fleet).unwrap();
+			let board = laid_out(&rules, &placements);
+			assert!(
+				rules.fleet.iter().all(|&ship| board.span(ship).is_some()),
+				"{name} left ships out"
+			);
+		}
+	}
+}
+
+#[test]
+fn corners_starts_in_a_corner() {
+	let rules = Rules::default();
+	let placements = layouts::placements("corners", &Board::new(&rules), &rules.fleet).unwrap();
+	assert_eq!(placements[0], (ShipKind::Carrier, Coord::new(0, 0), false));
+}
+
+#[test]
+fn layouts_only_place_whats_left() {
+	let rules = Rules::default();
+	let layout = laid_out(
+		&rules,
+		&layouts::placements("diagonal", &Board::new(&rules), &rules.fleet).unwrap(),
+	);
+	let mut board = Board::new(&rules);
+	assert!(ShipKind::Destroyer.place(&mut board, Coord::new(8, 0), true));
+	let placements = layouts::fit(&layout, &board, &rules.fleet).unwrap();
+	assert_eq!(placements.len(), rules.fleet.len() - 1);
+	assert!(placements
+		.iter()
+		.all(|&(ship, _, _)| ship != ShipKind::Destroyer));
+}
+
+#[test]
+fn layouts_for_other_boards_are_refused() {
+	let rules = Rules::default();
+	let small = Rules {
+		board_size: 8,
+		..Rules::default()
+	};
+	let layout = laid_out(
+		&small,
+		&layouts::placements("corners", &Board::new(&small), &small.fleet).unwrap(),
+	);
+	assert!(matches!(
+		layouts::fit(&layout, &Board::new(&rules), &rules.fleet),
+		Err(LayoutsError::DifferentBoard)
+	));
+	assert!(matches!(
+		layouts::fit(&layout, &Board::new(&small), &[ShipKind::Carrier]),
+		Err(LayoutsError::NotInFleet(_))
+	));
+}
+
+#[test]
+fn presets_and_paths_cant_be_saved_over() {
+	let rules = Rules::default();
+	let board = laid_out(&rules, &[(ShipKind::Carrier, Coord::new(0, 0), false)]);
+	for name in ["corners", "../fleet", "my fleet", ""] {
+		assert!(matches!(
+			layouts::save(name, &board),
+			Err(LayoutsError::BadName)
+		));
+	}
+	assert!(matches!(
+		layouts::save("mine", &Board::new(&rules)),
+		Err(LayoutsError::Empty)
+	));
+}
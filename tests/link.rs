@@ -0,0 +1,16 @@
+#![cfg(feature = "async")]
+
+use std::time::Duration;
+
+use netbattleship::link::{Latency, Quality};
+
+#[test]
+fn latency_is_rated() {
+	assert_eq!(Quality::of(Duration::from_millis(20)), Quality::Good);
+	assert_eq!(Quality::of(Duration::from_millis(150)), Quality::Fair);
+	assert_eq!(Quality::of(Duration::from_secs(1)), Quality::Poor);
+	assert_eq!(
+		Latency(Duration::from_micros(42_700)).to_string(),
+		"42 ms (good)"
+	);
+}
@@ -0,0 +1,143 @@
+#![cfg(feature = "async")]
+
+use std::{
+	net::{SocketAddr, SocketAddrV4},
+	time::Duration,
+};
+
+use netbattleship::{
+	lobby::{self, Identity, LobbyError, Presence, Registration},
+	rules::Rules,
+};
+use tokio::net::TcpListener;
+
+/// Starts a lobby server on a free local port.
+async fn lobby() -> SocketAddr {
+	let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+	let addr = listener.local_addr().unwrap();
+	tokio::spawn(lobby::serve(listener));
+	addr
+}
+
+/// Has `from`, calling themselves Ahab, invite `to` to `game`.
+async fn invite(
+	lobby: SocketAddr,
+	from: &Identity,
+	to: &str,
+	game: SocketAddrV4,
+) -> Result<(), LobbyError> {
+	lobby::invite(lobby, from, "Ahab".to_string(), to, Rules::default(), game).await
+}
+
+#[tokio::test]
+async fn announced_games_are_listed_until_joined() {
+	let lobby = lobby().await;
+	let rules = Rules {
+		salvo: true,
+		..Rules::default()
+	};
+	let unlisted = Registration::new(lobby, 7000).await.unwrap();
+	let listed = Registration::listed(lobby, 7001, "Ahab".to_string(), rules.clone())
+		.await
+		.unwrap();
+
+	let games = lobby::browse(lobby).await.unwrap();
+	assert_eq!(games.len(), 1);
+	assert_eq!(games[0].host, listed.public());
+	assert_eq!(games[0].name, "Ahab");
+	assert_eq!(games[0].rules, rules);
+
+	let (joined, paired) = tokio::join!(lobby::join(lobby, listed.public()), listed.paired());
+	joined.unwrap();
+	paired.unwrap();
+	assert!(lobby::browse(lobby).await.unwrap().is_empty());
+	drop(unlisted);
+}
+
+#[tokio::test]
+async fn hosts_that_hang_up_are_forgotten() {
+	let lobby = lobby().await;
+	let stays = Registration::listed(lobby, 7010, "Ahab".to_string(), Rules::default())
+		.await
+		.unwrap();
+	let leaves = Registration::listed(lobby, 7011, "Ishmael".to_string(), Rules::default())
+		.await
+		.unwrap();
+	let gone = leaves.public();
+	assert_eq!(lobby::browse(lobby).await.unwrap().len(), 2);
+	drop(leaves);
+
+	let mut games = lobby::browse(lobby).await.unwrap();
+	for _ in 0..100 {
+		if games.len() == 1 {
+			break;
+		}
+		tokio::time::sleep(Duration::from_millis(20)).await;
+		games = lobby::browse(lobby).await.unwrap();
+	}
+	assert_eq!(games.len(), 1);
+	assert_eq!(games[0].host, stays.public());
+	assert!(matches!(
+		lobby::join(lobby, gone).await,
+		Err(LobbyError::NoSuchGame)
+	));
+}
+
+#[tokio::test]
+async fn each_address_only_gets_so_many_games() {
+	let lobby = lobby().await;
+	let mut waiting = Vec::new();
+	for port in 0..lobby::MAX_GAMES_PER_IP {
+		let port = 7100 + u16::try_from(port).unwrap();
+		waiting.push(Registration::new(lobby, port).await.unwrap());
+	}
+	assert!(matches!(
+		Registration::new(lobby, 7099).await,
+		Err(LobbyError::TooManyGames)
+	));
+	// Registering the same port again replaces the game already there
+	waiting.push(Registration::new(lobby, 7100).await.unwrap());
+}
+
+#[tokio::test]
+async fn friends_are_invited_to_relayed_games() {
+	let lobby = lobby().await;
+	let mut friend = Presence::new(lobby, None).await.unwrap();
+	let code = friend.identity().code.clone();
+	let us = Presence::new(lobby, None).await.unwrap();
+	let game = Registration::new(lobby, 7002).await.unwrap();
+	let rules = Rules::default();
+
+	invite(lobby, us.identity(), &code.to_lowercase(), game.public())
+		.await
+		.unwrap();
+	let invitation = friend.invitation().await.unwrap();
+	assert_eq!(invitation.from, us.identity().code);
+	assert_eq!(invitation.name, "Ahab");
+	assert_eq!(invitation.rules, rules);
+	assert_eq!(invitation.game, game.public());
+
+	let mut stolen = us.identity().clone();
+	stolen.code.clone_from(&code);
+	assert!(matches!(
+		invite(lobby, &stolen, &us.identity().code, game.public()).await,
+		Err(LobbyError::NotYourCode)
+	));
+	let elsewhere = "127.0.0.1:1".parse().unwrap();
+	assert!(matches!(
+		invite(lobby, us.identity(), &code, elsewhere).await,
+		Err(LobbyError::NoSuchGame)
+	));
+
+	// Coming back keeps the same code, and leaving takes us offline
+	let again = Presence::new(lobby, Some(friend.identity().clone()))
+		.await
+		.unwrap();
+	assert_eq!(again.identity().code, code);
+	drop((friend, again));
+	tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+	assert!(matches!(
+		invite(lobby, us.identity(), &code, game.public()).await,
+		Err(LobbyError::NotOnline)
+	));
+}
@@ -0,0 +1,53 @@
+#![cfg(feature = "async")]
+
+use netbattleship::{
+	bot::Difficulty,
+	flow::GameFlow,
+	manager::{Finished, Manager},
+	rules::Rules,
+};
+use tokio::net::{TcpListener, TcpStream};
+
+#[tokio::test]
+async fn games_run_side_by_side() {
+	let rules = Rules::default();
+	let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+	let addr = listener.local_addr().unwrap();
+	let mut manager = Manager::new(2);
+	let mut results = manager.results().unwrap();
+	assert!(manager.results().is_none());
+
+	let mut challengers = Vec::new();
+	for expected in 1..=2 {
+		// One at a time, as a handshake still going when a game starts is dropped
+		let theirs = rules.clone();
+		challengers.push(tokio::spawn(async move {
+			let stream = TcpStream::connect(addr).await.unwrap();
+			let game = GameFlow::join(stream, theirs).await.unwrap();
+			game.play_with(Difficulty::Easy).await.unwrap();
+		}));
+		let id = manager
+			.host(
+				&listener,
+				GameFlow::builder().rules(rules.clone()),
+				Difficulty::Easy,
+			)
+			.await
+			.unwrap();
+		assert_eq!(id, expected);
+	}
+	assert!(manager.running() <= manager.max());
+
+	let mut ids = Vec::new();
+	for _ in 0..2 {
+		let Finished { id, outcome, .. } = results.recv().await.unwrap();
+		outcome.unwrap();
+		ids.push(id);
+	}
+	ids.sort_unstable();
+	assert_eq!(ids, [1, 2]);
+	for challenger in challengers {
+		challenger.await.unwrap();
+	}
+	assert_eq!(manager.running(), 0);
+}
@@ -0,0 +1,252 @@
+//! Plays the state machine against an honest opponent while an adversary
+//! slips messages of its own onto the wire, checking that whatever turns up,
+//! the game goes on turn by turn and ends the same way for both sides.
+
+use std::collections::VecDeque;
+
+use netbattleship::{
+	board::CellState,
+	coord::Coord,
+	history::Move,
+	net::Msg,
+	protocol::{Done, StateMachine},
+	rules::Rules,
+	ship::ShipKind,
+	sync::PublicState,
+	Game, Phase,
+};
+use proptest::prelude::*;
+
+/// How many steps a game gets to finish in once the adversary is done. Every
+/// shot takes a few, and there are only 50 squares to shoot at.
+const FINISH_STEPS: usize = 1000;
+
+#[derive(Clone, Debug)]
+enum Action {
+	/// Whoever's turn it is fires at one of the squares they haven't yet,
+	/// picked by the number.
+	Fire(usize),
+	/// The next message on its way to the host, or from it, arrives.
+	Deliver { to_host: bool },
+	/// The adversary sends the host a message of its own.
+	Inject(Msg),
+}
+
+fn rules() -> Rules {
+	Rules {
+		board_size: 5,
+		fleet: vec![ShipKind::Cruiser, ShipKind::Destroyer],
+		..Rules::default()
+	}
+}
+
+fn msg() -> impl Strategy<Value = Msg> {
+	// Close to the real turn numbers and squares, so they're often taken up
+	let turn = 0..12_u32;
+	let coord = (0..6_u8, 0..6_u8).prop_map(|(x, y)| Coord::new(x, y));
+	let ship = prop_oneof![Just(ShipKind::Cruiser), Just(ShipKind::Destroyer)];
+	let state = (turn.clone(), any::<bool>()).prop_map(|(turn_number, turn)| PublicState {
+		turn_number,
+		turn,
+		..PublicState::default()
+	});
+	prop_oneof![
+		(coord.clone(), turn.clone()).prop_map(|(aim, turn)| Msg::Fire(aim, turn)),
+		(any::<bool>(), turn.clone()).prop_map(|(hit, turn)| Msg::DidHit(hit, turn)),
+		(proptest::option::of(ship), turn.clone()).prop_map(|(ship, turn)| Msg::Sunk(ship, turn)),
+		any::<u64>().prop_map(Msg::Finished),
+		any::<u64>().prop_map(Msg::NotFinished),
+		turn.prop_map(Msg::WrongTurn),
+		coord.prop_map(Msg::Scan),
+		any::<u8>().prop_map(Msg::ScanResult),
+		any::<u64>().prop_map(Msg::Move),
+		state.clone().prop_map(Msg::SyncRequest),
+		state.prop_map(Msg::SyncState),
+		Just(Msg::Ready),
+		Just(Msg::Ping),
+		Just(Msg::Resign),
+		Just(Msg::Goodbye),
+	]
+}
+
+fn action() -> impl Strategy<Value = Action> {
+	prop_oneof![
+		3 => any::<usize>().prop_map(Action::Fire),
+		3 => any::<bool>().prop_map(|to_host| Action::Deliver { to_host }),
+		1 => msg().prop_map(Action::Inject),
+	]
+}
+
+/// A host and a guest with their fleets out, and what's on the wire between
+/// them.
+struct Model {
+	host: StateMachine,
+	guest: StateMachine,
+	to_host: VecDeque<Msg>,
+	to_guest: VecDeque<Msg>,
+	/// Whether either side has given up on the game over something it was
+	/// sent.
+	broken: bool,
+}
+
+impl Model {
+	fn new() -> Model {
+		let rules = rules();
+		let [host, guest] = [true, false].map(|serve| {
+			let mut side = StateMachine::new(Game::new(rules.clone(), serve));
+			for (row, &ship) in (0..).zip(&rules.fleet) {
+				side.place(ship, Coord::new(0, row * 2), false).unwrap();
+			}
+			side
+		});
+		let mut model = Model {
+			host,
+			guest,
+			to_host: VecDeque::new(),
+			to_guest: VecDeque::new(),
+			broken: false,
+		};
+		model.post();
+		while model.deliver(true) || model.deliver(false) {}
+		model
+	}
+
+	fn over(&self) -> bool {
+		matches!(self.host.phase, Phase::Done(_)) && matches!(self.guest.phase, Phase::Done(_))
+	}
+
+	/// Puts whatever either side has to send on the wire.
+	fn post(&mut self) {
+		self.to_guest
+			.extend(std::iter::from_fn(|| self.host.poll_transmit()));
+		self.to_host
+			.extend(std::iter::from_fn(|| self.guest.poll_transmit()));
+	}
+
+	/// Has whoever's turn it is fire, with the other side waiting for it.
+	/// Returns whether anyone could.
+	fn fire(&mut self, pick: usize) -> bool {
+		let (shooter, target) = if self.host.turn == self.host.you {
+			(&mut self.host, &mut self.guest)
+		} else {
+			(&mut self.guest, &mut self.host)
+		};
+		let _ = target.wait_for_enemy();
+		let board = &shooter.board[usize::from(!shooter.you)];
+		let open = (0..5)
+			.flat_map(|y| (0..5).map(move |x| Coord::new(x, y)))
+			.filter(|&pos| board.cell(pos).state == CellState::Untouched)
+			.collect::<Vec<_>>();
+		let fired = !open.is_empty() && shooter.fire(open[pick % open.len()]).is_ok();
+		self.post();
+		fired
+	}
+
+	/// Hands over the next message on its way to the host or the guest.
+	/// Returns whether there was one.
+	fn deliver(&mut self, to_host: bool) -> bool {
+		let (queue, side) = if to_host {
+			(&mut self.to_host, &mut self.host)
+		} else {
+			(&mut self.to_guest, &mut self.guest)
+		};
+		let Some(msg) = queue.pop_front() else {
+			return false;
+		};
+		self.broken |= handle(side, msg).is_err();
+		self.post();
+		true
+	}
+
+	fn inject(&mut self, msg: Msg) {
+		self.broken |= handle(&mut self.host, msg).is_err();
+		self.post();
+	}
+
+	fn act(&mut self, action: Action) {
+		match action {
+			Action::Fire(pick) => {
+				self.fire(pick);
+			}
+			Action::Deliver { to_host } => {
+				self.deliver(to_host);
+			}
+			Action::Inject(msg) => self.inject(msg),
+		}
+	}
+
+	/// Plays on honestly until the game is over, or until one side gives up,
+	/// in which case the host resigns as the frontends do. Returns whether it
+	/// ended in time.
+	fn finish(&mut self) -> bool {
+		for pick in 0..FINISH_STEPS {
+			if self.broken {
+				self.host.resign();
+				self.post();
+				while self.deliver(false) {}
+				return true;
+			}
+			if self.over() {
+				return true;
+			}
+			let delivered = self.deliver(true) || self.deliver(false);
+			if !delivered && !self.fire(pick) {
+				// Both sides are waiting on each other, which only a turn timer
+				// would get out of
+				self.broken = true;
+			}
+		}
+		false
+	}
+}
+
+/// Hands `msg` to `side`, checking that it never passes the turn without a
+/// turn being taken, or turns back the count of turns.
+fn handle(side: &mut StateMachine, msg: Msg) -> Result<Option<Done>, String> {
+	let (turn, turn_number, logged) = (side.turn, side.turn_number, side.log.len());
+	let result = side.handle(msg.clone());
+	assert!(
+		side.turn_number >= turn_number,
+		"{msg:?} took the turn number back from {turn_number} to {}",
+		side.turn_number
+	);
+	if side.turn != turn {
+		// Even a turn the sides disagree on is taken, to be looked at
+		let taken = side.log[logged..]
+			.iter()
+			.any(|step| matches!(step, Move::Shot { .. } | Move::Submarine { .. }));
+		assert!(taken, "{msg:?} passed the turn without one being taken");
+	}
+	result.map_err(|error| error.to_string())
+}
+
+proptest! {
+	#![proptest_config(ProptestConfig::with_cases(512))]
+
+	#[test]
+	fn games_end_the_same_way_for_both_sides(
+		actions in proptest::collection::vec(action(), 0..80)
+	) {
+		let mut model = Model::new();
+		prop_assert!(model.host.phase == Phase::Playing && model.guest.phase == Phase::Playing);
+		for action in actions {
+			model.act(action);
+		}
+		prop_assert!(model.finish(), "the game never ended");
+
+		let Phase::Done(won) = model.host.phase else {
+			panic!("the host isn't done");
+		};
+		if model.broken {
+			// They can't both have won, or both lost
+			prop_assert!(model.guest.phase != Phase::Done(won));
+		} else {
+			prop_assert!(model.guest.phase == Phase::Done(!won));
+			let enemy = usize::from(!model.host.you);
+			prop_assert_eq!(won, model.host.sunk[enemy].len() == rules().fleet.len());
+		}
+		// A finished game takes no more shots
+		prop_assert!(model.host.fire(Coord::new(4, 4)).is_err());
+		prop_assert!(model.host.wait_for_enemy().is_err());
+	}
+}
@@ -0,0 +1,293 @@
+#![cfg(feature = "async")]
+
+use std::time::Duration;
+
+use netbattleship::{
+	board::CellState,
+	coord::Coord,
+	flow,
+	net::{
+		read_from, read_from_async, read_from_async_with, write_to, Codec, Compression, Limits,
+		Msg, NetError, Profile, MAX_FRAME_SIZE,
+	},
+	ship::ShipKind,
+	sync::PublicState,
+};
+use proptest::prelude::*;
+use serde::Serialize;
+use tokio::io::AsyncWriteExt;
+
+fn ship() -> impl Strategy<Value = ShipKind> {
+	prop_oneof![
+		Just(ShipKind::Carrier),
+		Just(ShipKind::Battleship),
+		Just(ShipKind::Cruiser),
+		Just(ShipKind::Submarine),
+		Just(ShipKind::Destroyer),
+	]
+}
+
+fn compression() -> impl Strategy<Value = Compression> {
+	prop_oneof![Just(Compression::None), Just(Compression::Deflate)]
+}
+
+fn coord() -> impl Strategy<Value = Coord> {
+	(any::<u8>(), any::<u8>()).prop_map(|(x, y)| Coord::new(x, y))
+}
+
+fn public_state() -> impl Strategy<Value = PublicState> {
+	let shots = || {
+		let state = prop_oneof![
+			Just(CellState::Hit),
+			Just(CellState::Miss),
+			Just(CellState::Unknown),
+		];
+		proptest::collection::vec((coord(), state), 0..8)
+	};
+	let sunk = || proptest::collection::vec(ship(), 0..5);
+	(
+		any::<u32>(),
+		any::<bool>(),
+		[shots(), shots()],
+		[sunk(), sunk()],
+		any::<[u32; 2]>(),
+		any::<[bool; 2]>(),
+	)
+		.prop_map(
+			|(turn_number, turn, shots, sunk, scans, moved)| PublicState {
+				turn_number,
+				turn,
+				shots,
+				sunk,
+				scans,
+				moved,
+			},
+		)
+}
+
+fn msg() -> impl Strategy<Value = Msg> {
+	prop_oneof![
+		any::<u64>().prop_map(Msg::Hello),
+		any::<u64>().prop_map(Msg::NotFinished),
+		any::<u64>().prop_map(Msg::Finished),
+		(any::<bool>(), any::<u32>()).prop_map(|(hit, turn)| Msg::DidHit(hit, turn)),
+		(coord(), any::<u32>()).prop_map(|(aim, turn)| Msg::Fire(aim, turn)),
+		(proptest::option::of(ship()), any::<u32>()).prop_map(|(ship, turn)| Msg::Sunk(ship, turn)),
+		coord().prop_map(Msg::Scan),
+		any::<u8>().prop_map(Msg::ScanResult),
+		any::<u64>().prop_map(Msg::Move),
+		any::<u32>().prop_map(Msg::Resume),
+		Just(Msg::Ping),
+		Just(Msg::Pong),
+		proptest::collection::vec(compression(), 0..4).prop_map(Msg::Compression),
+		public_state().prop_map(Msg::SyncRequest),
+		public_state().prop_map(Msg::SyncState),
+		".*".prop_map(Msg::Chat),
+		Just(Msg::Goodbye),
+		Just(Msg::Resign),
+		Just(Msg::Ready),
+		any::<u32>().prop_map(Msg::WrongTurn),
+		(
+			proptest::option::of(".*"),
+			proptest::collection::btree_map(ship(), ".*", 0..3)
+		)
+			.prop_map(|(name, ships)| Msg::Profile(Profile { name, ships })),
+	]
+}
+
+fn encode(msg: &Msg) -> Vec<u8> {
+	let mut bytes = Vec::new();
+	write_to(msg, &mut bytes);
+	bytes
+}
+
+proptest! {
+	#[test]
+	fn messages_survive_the_trip(msg in msg()) {
+		let bytes = encode(&msg);
+		prop_assert_eq!(read_from::<Msg, _>(&mut bytes.as_slice()).unwrap(), msg);
+	}
+
+	#[test]
+	fn the_schema_covers_every_message(msg in msg()) {
+		let schema = serde_json::to_value(flow::schema()).unwrap();
+		let name = match serde_json::to_value(&msg).unwrap() {
+			serde_json::Value::Object(map) => map.keys().next().unwrap().clone(),
+			value => value.as_str().unwrap().to_string(),
+		};
+		let described = schema["oneOf"].as_array().unwrap().iter().any(|variant| {
+			variant["required"][0] == name.as_str() || variant["enum"][0] == name.as_str()
+		});
+		prop_assert!(described, "{} isn't in the schema", name);
+	}
+
+	#[test]
+	fn arbitrary_bytes_never_panic(bytes in proptest::collection::vec(any::<u8>(), 0..512)) {
+		let _ = read_from::<Msg, _>(&mut bytes.as_slice());
+	}
+
+	#[test]
+	fn mutated_messages_never_panic(
+		msg in msg(),
+		flips in proptest::collection::vec((any::<prop::sample::Index>(), any::<u8>()), 1..8),
+	) {
+		let mut bytes = encode(&msg);
+		// Leave the length alone so the mutated CBOR actually gets decoded
+		for (at, value) in flips {
+			let at = 4 + at.index(bytes.len() - 4);
+			bytes[at] = value;
+		}
+		let _ = read_from::<Msg, _>(&mut bytes.as_slice());
+	}
+
+	#[test]
+	fn oversized_frames_are_refused(len in MAX_FRAME_SIZE + 1..=u32::MAX) {
+		// Only the length is sent; reading the body would fail differently
+		let bytes = len.to_be_bytes();
+		let result = read_from::<Msg, _>(&mut bytes.as_slice());
+		prop_assert!(matches!(result, Err(NetError::FrameTooLarge(l)) if l == len));
+	}
+}
+
+#[tokio::test]
+async fn async_reads_are_bounded_too() {
+	let bytes = (MAX_FRAME_SIZE + 1).to_be_bytes();
+	let result = read_from_async::<Msg, _>(&mut bytes.as_slice()).await;
+	assert!(matches!(result, Err(NetError::FrameTooLarge(_))));
+}
+
+#[tokio::test]
+async fn truncated_frames_are_errors() {
+	let bytes = encode(&Msg::Fire(Coord::new(3, 4), 1));
+	let result = read_from_async::<Msg, _>(&mut &bytes[..bytes.len() - 1]).await;
+	assert!(matches!(result, Err(NetError::Io(_))));
+}
+
+#[tokio::test]
+async fn frame_size_is_configurable() {
+	let bytes = encode(&Msg::Hello(u64::MAX));
+	let limits = Limits {
+		max_frame_size: 4,
+		..Limits::default()
+	};
+	let result = read_from_async_with::<Msg, _>(&mut bytes.as_slice(), limits).await;
+	assert!(matches!(result, Err(NetError::FrameTooLarge(_))));
+}
+
+#[tokio::test]
+async fn stalled_frames_time_out() {
+	let (mut ours, mut theirs) = tokio::io::duplex(64);
+	let bytes = encode(&Msg::Fire(Coord::new(3, 4), 1));
+	// Send the length and half the body, then go quiet without hanging up
+	theirs.write_all(&bytes[..bytes.len() / 2]).await.unwrap();
+	let limits = Limits {
+		frame_timeout: Duration::from_millis(50),
+		..Limits::default()
+	};
+	let result = read_from_async_with::<Msg, _>(&mut ours, limits).await;
+	assert!(matches!(result, Err(NetError::TimedOut)));
+}
+
+/// [`Msg::Sunk`] as older versions sent it, when "no ship" was a kind of
+/// ship too.
+#[derive(Serialize)]
+enum OldMsg {
+	Sunk(&'static str, u32),
+}
+
+#[test]
+fn old_sunk_messages_still_read() {
+	for (old, new) in [("None", None), ("Carrier", Some(ShipKind::Carrier))] {
+		let mut bytes = Vec::new();
+		write_to(&OldMsg::Sunk(old, 3), &mut bytes);
+		assert_eq!(
+			read_from::<Msg, _>(&mut bytes.as_slice()).unwrap(),
+			Msg::Sunk(new, 3)
+		);
+	}
+}
+
+const DEFLATE: Codec = Codec {
+	limits: Limits {
+		max_frame_size: MAX_FRAME_SIZE,
+		frame_timeout: Duration::from_secs(30),
+	},
+	compression: Compression::Deflate,
+};
+
+/// A message big and repetitive enough to be worth compressing.
+fn big() -> Msg {
+	Msg::Compression(vec![Compression::Deflate; 500])
+}
+
+#[test]
+fn compression_falls_back_to_none() {
+	let all = Compression::SUPPORTED;
+	assert_eq!(Compression::agree(all, all), Compression::Deflate);
+	assert_eq!(
+		Compression::agree(all, &[Compression::None]),
+		Compression::None
+	);
+	assert_eq!(
+		Compression::agree(&[Compression::None], all),
+		Compression::None
+	);
+	assert_eq!(Compression::agree(all, &[]), Compression::None);
+}
+
+#[tokio::test]
+async fn big_messages_are_compressed() {
+	let packed = DEFLATE.encode(&big());
+	assert!(packed.len() < encode(&big()).len());
+	assert_eq!(
+		DEFLATE
+			.read::<Msg, _>(&mut packed.as_slice())
+			.await
+			.unwrap(),
+		big()
+	);
+}
+
+#[tokio::test]
+async fn small_messages_reach_uncompressed_peers() {
+	let msg = Msg::Fire(Coord::new(3, 4), 1);
+	let bytes = DEFLATE.encode(&msg);
+	assert_eq!(bytes, encode(&msg));
+	assert_eq!(
+		read_from_async::<Msg, _>(&mut bytes.as_slice())
+			.await
+			.unwrap(),
+		msg
+	);
+}
+
+#[tokio::test]
+async fn uncompressed_peers_refuse_compressed_frames() {
+	let packed = DEFLATE.encode(&big());
+	let result = read_from_async::<Msg, _>(&mut packed.as_slice()).await;
+	assert!(matches!(result, Err(NetError::FrameTooLarge(_))));
+}
+
+#[tokio::test]
+async fn compressed_peers_read_plain_frames() {
+	let bytes = encode(&big());
+	assert_eq!(
+		DEFLATE.read::<Msg, _>(&mut bytes.as_slice()).await.unwrap(),
+		big()
+	);
+}
+
+#[tokio::test]
+async fn decompression_is_bounded() {
+	let packed = DEFLATE.encode(&Msg::Compression(vec![Compression::Deflate; 5000]));
+	let small = Codec {
+		limits: Limits {
+			max_frame_size: 1024,
+			..Limits::default()
+		},
+		..DEFLATE
+	};
+	assert!(packed.len() < 1024);
+	let result = small.read::<Msg, _>(&mut packed.as_slice()).await;
+	assert!(matches!(result, Err(NetError::FrameTooLarge(_))));
+}
@@ -0,0 +1,118 @@
+use netbattleship::{
+	board::CellState,
+	coord::Coord,
+	flow::GameFlowError,
+	pbm::{Correspondence, Letter, PbmError},
+	rules::Rules,
+	ship::ShipKind,
+	Phase,
+};
+
+fn rules() -> Rules {
+	Rules {
+		board_size: 5,
+		fleet: vec![ShipKind::Cruiser, ShipKind::Destroyer],
+		..Rules::default()
+	}
+}
+
+/// The first square `side` hasn't fired at yet.
+fn aim(side: &Correspondence) -> Coord {
+	let enemy = &side.game.board[usize::from(!side.game.you)];
+	(0..5)
+		.flat_map(|y| (0..5).map(move |x| Coord::new(x, y)))
+		.find(|&pos| enemy.cell(pos).state == CellState::Untouched)
+		.unwrap()
+}
+
+#[test]
+fn games_are_played_out_by_letter() {
+	let mut first = Correspondence::start(rules()).unwrap();
+	let mut letter = first.write(Some(aim(&first)), "hunter2").unwrap();
+	let turn = letter.clone().open("hunter2").unwrap();
+	let mut second = Correspondence::accept(&turn).unwrap();
+	let mut sides = [&mut second, &mut first];
+	for _ in 0..100 {
+		let [to, from] = &mut sides;
+		if !to.to_write() {
+			break;
+		}
+		let aim = to.to_fire().then(|| aim(to));
+		letter = to.write(aim, "hunter2").unwrap();
+		from.read(&letter.clone().open("hunter2").unwrap()).unwrap();
+		sides.swap(0, 1);
+	}
+	let [a, b] = sides;
+	let Phase::Done(won) = a.game.phase else {
+		panic!("the game never ended");
+	};
+	assert!(b.game.phase == Phase::Done(!won));
+}
+
+#[test]
+fn letters_that_dont_fit_are_turned_away() {
+	let mut first = Correspondence::start(rules()).unwrap();
+	let letter = first.write(Some(Coord::new(0, 0)), "hunter2").unwrap();
+	assert!(matches!(
+		letter.clone().open("letmein"),
+		Err(PbmError::BadSignature)
+	));
+
+	let mut tampered = letter.clone();
+	tampered.turn.shot = Some(Coord::new(1, 1));
+	assert!(matches!(
+		tampered.open("hunter2"),
+		Err(PbmError::BadSignature)
+	));
+
+	let turn = letter.open("hunter2").unwrap();
+	let mut second = Correspondence::accept(&turn).unwrap();
+	// The same letter twice
+	assert!(matches!(
+		second.read(&turn),
+		Err(PbmError::OutOfOrder { .. })
+	));
+	// Firing at a square already fired at
+	let reply = second.write(Some(Coord::new(2, 2)), "hunter2").unwrap();
+	first.read(&reply.open("hunter2").unwrap()).unwrap();
+	let again = first.write(Some(Coord::new(0, 0)), "hunter2");
+	assert!(matches!(again, Err(PbmError::BadShot(_))));
+}
+
+#[test]
+fn first_letters_with_impossible_rules_are_turned_away() {
+	let mut first = Correspondence::start(rules()).unwrap();
+	let mut turn = first
+		.write(Some(Coord::new(0, 0)), "hunter2")
+		.unwrap()
+		.open("hunter2")
+		.unwrap();
+	turn.rules = Some(Rules {
+		fleet: Vec::new(),
+		..rules()
+	});
+	let turn = Letter::sign(turn, "hunter2").open("hunter2").unwrap();
+	assert!(matches!(
+		Correspondence::accept(&turn),
+		Err(PbmError::Flow(GameFlowError::InvalidRules(_)))
+	));
+}
+
+#[test]
+fn shots_off_the_board_are_turned_away() {
+	let mut first = Correspondence::start(rules()).unwrap();
+	let letter = first.write(Some(Coord::new(0, 0)), "hunter2").unwrap();
+	let mut second = Correspondence::accept(&letter.open("hunter2").unwrap()).unwrap();
+	let mut turn = second
+		.write(Some(Coord::new(0, 0)), "hunter2")
+		.unwrap()
+		.open("hunter2")
+		.unwrap();
+	turn.shot = Some(Coord::new(3, 200));
+	let err = first.read(&turn).unwrap_err();
+	assert!(matches!(err, PbmError::BadShot(_)));
+	assert_eq!(
+		err.to_string(),
+		"the shot at 200,3 isn't one that can be fired"
+	);
+}
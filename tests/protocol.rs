@@ -0,0 +1,252 @@
+use netbattleship::{
+	board::CellState,
+	coord::Coord,
+	event::{Event, Player},
+	flow::GameFlowError,
+	net::Msg,
+	protocol::{Done, StateMachine},
+	rules::Rules,
+	ship::ShipKind,
+	Game, Phase,
+};
+
+/// A host and a guest with their fleets laid out along column 0, each
+/// having heard that the other is ready.
+fn ready(rules: &Rules) -> (StateMachine, StateMachine) {
+	let [mut host, mut guest] = [true, false].map(|serve| {
+		let mut side = StateMachine::new(Game::new(rules.clone(), serve));
+		for (row, &ship) in (0..).zip(&rules.fleet) {
+			side.place(ship, Coord::new(0, row), false).unwrap();
+		}
+		assert!(side.phase == Phase::Ready);
+		side
+	});
+	deliver(&mut host, &mut guest);
+	deliver(&mut guest, &mut host);
+	assert!(host.phase == Phase::Playing && guest.phase == Phase::Playing);
+	(host, guest)
+}
+
+/// Passes everything `from` has to send on to `to`, returning the last
+/// thing that finished on `to`.
+fn deliver(from: &mut StateMachine, to: &mut StateMachine) -> Option<Done> {
+	let mut done = None;
+	while let Some(msg) = from.poll_transmit() {
+		done = to.handle(msg).unwrap().or(done);
+	}
+	done
+}
+
+#[test]
+fn fleets_can_be_placed_before_anyone_is_listening() {
+	let rules = Rules::default();
+	let mut host = StateMachine::new(Game::new(rules.clone(), true));
+	let mut guest = StateMachine::new(Game::new(rules.clone(), false));
+	assert!(guest.wait_for_fleet().unwrap());
+	for (row, &ship) in (0..).zip(&rules.fleet) {
+		host.place(ship, Coord::new(0, row), false).unwrap();
+	}
+	assert!(matches!(deliver(&mut host, &mut guest), Some(Done::Placed)));
+	assert!(guest.enemy_placed());
+	assert!(!guest.wait_for_fleet().unwrap());
+}
+
+#[test]
+fn each_ship_placed_is_counted_for_the_enemy() {
+	let rules = Rules::default();
+	let mut host = StateMachine::new(Game::new(rules.clone(), true));
+	let mut guest = StateMachine::new(Game::new(rules.clone(), false));
+	host.place(rules.fleet[0], Coord::new(0, 0), false).unwrap();
+	host.place(rules.fleet[1], Coord::new(0, 1), false).unwrap();
+	assert_eq!(
+		std::iter::from_fn(|| host.poll_transmit()).collect::<Vec<_>>(),
+		[Msg::PlacingProgress(1), Msg::PlacingProgress(2)]
+	);
+
+	guest.handle(Msg::PlacingProgress(2)).unwrap();
+	let fleet = u8::try_from(rules.fleet.len()).unwrap();
+	assert_eq!(
+		guest.poll_event(),
+		Some(Event::EnemyPlacing { placed: 2, fleet })
+	);
+	assert!(!guest.enemy_placed());
+}
+
+#[test]
+fn nobody_fires_until_both_are_ready() {
+	let rules = Rules::default();
+	let mut host = StateMachine::new(Game::new(rules.clone(), true));
+	let mut guest = StateMachine::new(Game::new(rules.clone(), false));
+	host.place_remaining().unwrap();
+	deliver(&mut host, &mut guest);
+	assert!(host.phase == Phase::Ready);
+	assert!(matches!(
+		host.fire(Coord::new(0, 0)),
+		Err(GameFlowError::OutOfOrder)
+	));
+
+	guest.place_remaining().unwrap();
+	assert!(guest.phase == Phase::Playing);
+	deliver(&mut guest, &mut host);
+	assert!(host.phase == Phase::Playing);
+	host.fire(Coord::new(0, 0)).unwrap();
+}
+
+#[test]
+fn a_shot_takes_one_round_trip() {
+	let rules = Rules::default();
+	let (mut host, mut guest) = ready(&rules);
+	guest.wait_for_enemy().unwrap();
+	host.fire(Coord::new(0, 0)).unwrap();
+	assert!(host.is_waiting());
+
+	let Some(Done::Turn(theirs)) = deliver(&mut host, &mut guest) else {
+		panic!("the guest should have taken the shot");
+	};
+	assert_eq!(theirs.ship, Some(ShipKind::Carrier));
+	let Some(Done::Turn(ours)) = deliver(&mut guest, &mut host) else {
+		panic!("the host should have heard how it went");
+	};
+	assert!(ours.hit);
+	assert!(!host.is_waiting());
+	assert_eq!(
+		host.board[usize::from(!host.you)]
+			.cell(Coord::new(0, 0))
+			.state,
+		CellState::Hit
+	);
+	assert!(guest.turn == guest.you);
+}
+
+#[test]
+fn moves_are_made_in_turn() {
+	let rules = Rules::default();
+	let (mut host, mut guest) = ready(&rules);
+	assert!(matches!(
+		guest.fire(Coord::new(0, 0)),
+		Err(GameFlowError::OutOfOrder)
+	));
+	assert!(matches!(
+		host.wait_for_enemy(),
+		Err(GameFlowError::OutOfOrder)
+	));
+	host.fire(Coord::new(0, 0)).unwrap();
+	assert!(matches!(
+		host.fire(Coord::new(1, 0)),
+		Err(GameFlowError::OutOfOrder)
+	));
+}
+
+#[test]
+fn shots_on_the_wrong_turn_are_ignored() {
+	let rules = Rules::default();
+	let (mut host, mut guest) = ready(&rules);
+	guest.turn_number += 2;
+	guest.wait_for_enemy().unwrap();
+	host.fire(Coord::new(0, 0)).unwrap();
+	assert!(deliver(&mut host, &mut guest).is_none());
+	assert!(guest.is_waiting());
+	assert!(matches!(
+		host.handle(guest.poll_transmit().unwrap()),
+		Err(GameFlowError::OutOfTurn(1, 3))
+	));
+	assert!(!host.is_waiting());
+	assert_eq!(
+		guest.board[usize::from(guest.you)]
+			.cell(Coord::new(0, 0))
+			.state,
+		CellState::Untouched
+	);
+}
+
+/// Everything `side` has reported since this last asked.
+fn events(side: &mut StateMachine) -> Vec<Event> {
+	std::iter::from_fn(|| side.poll_event()).collect()
+}
+
+#[test]
+fn shots_turned_away_are_never_reported_as_fired() {
+	let fired = Event::Fired {
+		by: Player::You,
+		pos: Coord::new(0, 0),
+	};
+	for blind in [false, true] {
+		let rules = Rules {
+			blind,
+			..Rules::default()
+		};
+		let (mut host, mut guest) = ready(&rules);
+		events(&mut host);
+		guest.turn_number += 2;
+		guest.wait_for_enemy().unwrap();
+		host.fire(Coord::new(0, 0)).unwrap();
+		assert!(events(&mut host).is_empty());
+		deliver(&mut host, &mut guest);
+		assert!(host.handle(guest.poll_transmit().unwrap()).is_err());
+		assert!(!events(&mut host).contains(&fired));
+
+		let (mut host, mut guest) = ready(&rules);
+		events(&mut host);
+		guest.wait_for_enemy().unwrap();
+		host.fire(Coord::new(0, 0)).unwrap();
+		deliver(&mut host, &mut guest);
+		deliver(&mut guest, &mut host);
+		let reported = events(&mut host);
+		assert_eq!(reported.first(), Some(&fired));
+		assert_eq!(reported.iter().filter(|&e| *e == fired).count(), 1);
+	}
+}
+
+#[test]
+fn land_cant_be_fired_at() {
+	let map = "..........\n".repeat(9) + ".........#";
+	let rules = Rules::default().with_map(&map).unwrap();
+	let (mut host, _) = ready(&rules);
+	assert!(matches!(
+		host.fire(Coord::new(9, 9)),
+		Err(GameFlowError::Land(_))
+	));
+	host.fire(Coord::new(8, 9)).unwrap();
+}
+
+#[test]
+fn unexpected_messages_are_refused() {
+	let rules = Rules::default();
+	let (mut host, mut guest) = ready(&rules);
+	host.fire(Coord::new(0, 0)).unwrap();
+	// The guest never started waiting on the host's turn
+	let fire = host.poll_transmit().unwrap();
+	assert!(matches!(
+		guest.handle(fire),
+		Err(GameFlowError::BadMessage(_))
+	));
+}
+
+#[test]
+fn games_can_be_worked_out_from_their_logs() {
+	let rules = Rules {
+		board_size: 5,
+		fleet: vec![ShipKind::Destroyer],
+		..Rules::default()
+	};
+	let (mut host, mut guest) = ready(&rules);
+	for (shooter, aim) in [(true, (0, 0)), (false, (4, 4)), (true, (1, 0))] {
+		let (from, to) = if shooter {
+			(&mut host, &mut guest)
+		} else {
+			(&mut guest, &mut host)
+		};
+		to.wait_for_enemy().unwrap();
+		from.fire(Coord::new(aim.0, aim.1)).unwrap();
+		deliver(from, to);
+		deliver(to, from);
+	}
+	assert!(host.phase == Phase::Done(true));
+	for side in [&host, &guest] {
+		let game = Game::from_log(rules.clone(), side.you, &side.log).unwrap();
+		assert_eq!(game.public_state(), side.public_state());
+		assert!(game.phase == side.phase);
+		let ours = |game: &Game| game.board[usize::from(game.you)].board.clone();
+		assert_eq!(ours(&game), ours(side));
+	}
+}
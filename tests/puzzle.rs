@@ -0,0 +1,64 @@
+use netbattleship::{
+	coord::Coord,
+	puzzle::{self, Puzzle, PuzzleError, SHOTS},
+};
+
+/// Every square of a default board.
+fn squares() -> impl Iterator<Item = Coord> {
+	(0..10).flat_map(|y| (0..10).map(move |x| Coord::new(x, y)))
+}
+
+#[test]
+fn everyone_gets_the_same_fleet_on_the_same_day() {
+	let shoot = |date: &str| {
+		let mut puzzle = Puzzle::new(date);
+		squares()
+			.take(SHOTS as usize)
+			.map(|aim| puzzle.fire(aim).unwrap().hit)
+			.collect::<Vec<_>>()
+	};
+	assert_eq!(shoot("2026-10-17"), shoot("2026-10-17"));
+	assert_ne!(shoot("2026-10-17"), shoot("2026-10-18"));
+}
+
+#[test]
+fn running_out_of_shots_scores_nothing() {
+	let mut puzzle = Puzzle::new("2026-10-17");
+	for _ in 0..SHOTS {
+		puzzle.fire(Coord::new(0, 0)).unwrap();
+	}
+	assert!(puzzle.over() && !puzzle.solved());
+	assert_eq!(puzzle.score(), 0);
+	assert!(matches!(
+		puzzle.fire(Coord::new(1, 1)),
+		Err(PuzzleError::Over)
+	));
+	assert!(puzzle
+		.summary()
+		.starts_with("Netbattleship 2026-10-17 X/50\n"));
+	assert_eq!(puzzle.summary().lines().count(), 6);
+}
+
+#[test]
+fn the_score_is_the_shots_left_over() {
+	// Find the ships a shot at a time on fresh copies, then sink them all
+	let ships = squares()
+		.filter(|&aim| Puzzle::new("2026-10-17").fire(aim).unwrap().hit)
+		.collect::<Vec<_>>();
+	assert_eq!(ships.len(), 17);
+	let mut puzzle = Puzzle::new("2026-10-17");
+	for &aim in &ships {
+		puzzle.fire(aim).unwrap();
+	}
+	assert!(puzzle.solved());
+	assert_eq!(puzzle.score(), SHOTS - 17);
+	assert!(puzzle.summary().contains('💥'));
+	assert!(!puzzle.summary().contains('🟦'));
+}
+
+#[test]
+fn days_are_counted_from_the_epoch_in_utc() {
+	assert_eq!(puzzle::day(0), "1970-01-01");
+	assert_eq!(puzzle::day(951_782_400), "2000-02-29");
+	assert_eq!(puzzle::day(1_792_195_199), "2026-10-16");
+}
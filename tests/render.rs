@@ -0,0 +1,39 @@
+use netbattleship::{
+	board::{Cell, CellState},
+	coord::Coord,
+	render::{BoardRenderer, Grid, Style},
+	rules::Rules,
+	ship::ShipKind,
+	Game,
+};
+
+#[test]
+fn the_grid_is_a_character_per_square() {
+	let map = format!(".........#\n{}", "..........\n".repeat(9));
+	let mut game = Game::new(Rules::default().with_map(&map).unwrap(), true);
+	let [theirs, ours] = &mut game.board;
+	assert!(ShipKind::Destroyer.place(ours, Coord::new(1, 0), false));
+	theirs
+		.board
+		.insert(Coord::new(0, 9), Cell::shot(CellState::Miss));
+	let grid = Grid.render(&game);
+	let lines = grid.lines().collect::<Vec<_>>();
+	assert_eq!(lines.len(), 22);
+	assert_eq!(lines[0], "YOU");
+	assert_eq!(lines[1], ".DD......#");
+	assert_eq!(lines[2], "..........");
+	assert_eq!(lines[11], "THEM");
+	assert_eq!(lines[21], "?.........");
+}
+
+#[test]
+fn every_style_but_the_grid_has_a_legend() {
+	for style in [Style::Ascii, Style::Unicode, Style::Compact] {
+		assert!(style
+			.renderer()
+			.legend()
+			.is_some_and(|legend| legend.contains("hit")));
+	}
+	// The grid is kept bare for braille displays
+	assert_eq!(Grid.legend(), None);
+}
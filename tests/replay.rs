@@ -0,0 +1,87 @@
+use std::env;
+
+use netbattleship::{
+	coord::Coord,
+	history::Move,
+	replay::{Header, Replay, ReplayError, VERSION},
+	rules::Rules,
+	ship::ShipKind,
+};
+
+/// A quick game on a tiny board: we place our Destroyer along the top, hit
+/// theirs, they miss, and we sink it.
+fn quick_game() -> Replay {
+	let shot = |by, x, y, hit, sunk| Move::Shot {
+		by,
+		pos: Coord::new(x, y),
+		hit: Some(hit),
+		sunk,
+	};
+	Replay {
+		header: Header {
+			version: VERSION,
+			rules: Rules {
+				board_size: 3,
+				fleet: vec![ShipKind::Destroyer],
+				..Rules::default()
+			},
+			players: ["Bo".to_string(), "Ann".to_string()],
+			you: true,
+			date: 0,
+			winner: Some(true),
+		},
+		moves: vec![
+			Move::Place {
+				ship: ShipKind::Destroyer,
+				pos: Coord::new(0, 0),
+				vertical: false,
+			},
+			shot(true, 1, 1, true, None),
+			shot(false, 2, 2, false, None),
+			shot(true, 2, 1, true, Some(ShipKind::Destroyer)),
+		],
+	}
+}
+
+#[test]
+fn a_fair_game_adds_up_and_survives_a_round_trip() {
+	let replay = quick_game();
+	replay.verify().unwrap();
+
+	let path = env::temp_dir().join(format!("netbattleship-{}.nbr", std::process::id()));
+	replay.store(&path).unwrap();
+	let loaded = Replay::load(&path);
+	let _ = std::fs::remove_file(&path);
+	assert_eq!(loaded.unwrap(), replay);
+}
+
+#[test]
+fn tampered_replays_are_caught() {
+	// They fired at our Destroyer, not the water
+	let mut replay = quick_game();
+	replay.moves[2] = Move::Shot {
+		by: false,
+		pos: Coord::new(0, 0),
+		hit: Some(false),
+		sunk: None,
+	};
+	assert!(matches!(replay.verify(), Err(ReplayError::WrongResult(3))));
+
+	// We can't fire twice in a row without salvos
+	let mut replay = quick_game();
+	replay.moves.swap(2, 3);
+	assert!(matches!(replay.verify(), Err(ReplayError::OutOfTurn(3))));
+
+	// Nothing happens once the last ship sinks
+	let mut replay = quick_game();
+	replay.moves.push(replay.moves[2]);
+	assert!(matches!(replay.verify(), Err(ReplayError::AfterEnd(5))));
+
+	let mut replay = quick_game();
+	replay.header.winner = Some(false);
+	assert!(matches!(replay.verify(), Err(ReplayError::WrongOutcome)));
+
+	let mut replay = quick_game();
+	replay.header.version = VERSION + 1;
+	assert!(matches!(replay.verify(), Err(ReplayError::Version(_))));
+}
@@ -0,0 +1,48 @@
+#![cfg(feature = "async")]
+
+use std::{env, fs, time::Duration};
+
+use netbattleship::{
+	coord::Coord,
+	rules::Rules,
+	sandbox::{self, Watched},
+	ship::ShipKind,
+};
+
+#[test]
+fn refitting_keeps_what_still_fits_in_order() {
+	let placed = [
+		(ShipKind::Carrier, Coord::new(0, 0), false),
+		(ShipKind::Battleship, Coord::new(6, 2), false),
+		(ShipKind::Cruiser, Coord::new(0, 4), false),
+	];
+	assert_eq!(sandbox::refit(&placed, &Rules::default()), placed);
+	let small = Rules {
+		board_size: 8,
+		..Rules::default()
+	};
+	// The battleship runs off the smaller board, so the cruiser after it goes too
+	assert_eq!(sandbox::refit(&placed, &small), placed[..1]);
+	let fewer = Rules {
+		fleet: vec![ShipKind::Cruiser, ShipKind::Carrier],
+		..Rules::default()
+	};
+	assert_eq!(sandbox::refit(&placed, &fewer), [placed[2], placed[0]]);
+}
+
+#[tokio::test]
+async fn watching_notices_changes() {
+	let path = env::temp_dir().join(format!("netbattleship-sandbox-{}.toml", std::process::id()));
+	fs::write(&path, "board_size = 10").unwrap();
+	let mut watched = Watched::new([path.clone()]);
+	assert!(!watched.changed());
+	// Some filesystems only keep modification times to the second
+	tokio::time::sleep(Duration::from_millis(1100)).await;
+	fs::write(&path, "board_size = 8").unwrap();
+	tokio::time::timeout(Duration::from_secs(5), watched.next_change())
+		.await
+		.unwrap();
+	assert!(!watched.changed());
+	fs::remove_file(&path).unwrap();
+	assert!(watched.changed());
+}
@@ -0,0 +1,33 @@
+use netbattleship::{
+	board::{Cell, CellState},
+	coord::Coord,
+	render::{BoardRenderer, Grid},
+	rules::Rules,
+	Game,
+};
+
+#[test]
+fn only_changed_lines_are_drawn_again() {
+	let mut game = Game::new(Rules::default(), true);
+	let mut screen = netbattleship::ui::screen::Screen::default();
+	let rows = screen.update(&game, &Grid.render(&game), "Your turn.");
+	assert_eq!(rows.len(), screen.len());
+
+	// Nothing changed
+	assert!(screen
+		.update(&game, &Grid.render(&game), "Your turn.")
+		.is_empty());
+
+	let enemy = usize::from(!game.you);
+	game.board[enemy]
+		.board
+		.insert(Coord::new(3, 2), Cell::shot(CellState::Miss));
+	let rows = screen.update(&game, &Grid.render(&game), "Missed.");
+	assert_eq!(rows.len(), 2);
+	assert_eq!(screen.line(rows[1]), "Missed.");
+
+	// A shorter screen clears the lines it no longer has
+	let rows = screen.update(&game, &Grid.render(&game), "");
+	assert_eq!(rows, [screen.len()]);
+	assert_eq!(screen.line(rows[0]), "");
+}
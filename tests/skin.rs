@@ -0,0 +1,33 @@
+use netbattleship::{
+	board::{Cell, CellState},
+	config::Config,
+	ship::ShipKind,
+};
+
+#[test]
+fn skins_are_read_from_the_config_file() {
+	let config: Config = toml::from_str(
+		"[skin.names]\nDestroyer = \"Rubber Duck\"\n\
+		 [skin.glyphs]\nDestroyer = \"Q\"\n\
+		 [skin.colors]\nDestroyer = \"#ffd700\"\nCarrier = \"gold\"",
+	)
+	.unwrap();
+	let skin = &config.skin;
+	assert_eq!(skin.name(ShipKind::Destroyer), "Rubber Duck");
+	assert_eq!(skin.name(ShipKind::Carrier), "Carrier");
+	assert_eq!(skin.color(ShipKind::Destroyer), Some([0xff, 0xd7, 0x00]));
+	assert_eq!(skin.color(ShipKind::Carrier), None);
+
+	let duck = Cell::ship(ShipKind::Destroyer);
+	assert_eq!(skin.symbol(duck, true), 'Q');
+	let hit = Cell {
+		state: CellState::Hit,
+		..duck
+	};
+	assert_eq!(skin.symbol(hit, true), 'q');
+	// Only our own board is drawn in our skin
+	assert_eq!(skin.symbol(hit, false), 'd');
+
+	let saved = toml::to_string(&config).unwrap();
+	assert_eq!(toml::from_str::<Config>(&saved).unwrap(), config);
+}